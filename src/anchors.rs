@@ -0,0 +1,338 @@
+//! Heading and block targets of links: `[[note#Heading]]`, `[[note^id]]`
+//! and `[[note#^id]]`.
+
+use regex::Regex;
+use std::ops::Range;
+use std::path::Path;
+use std::sync::OnceLock;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Anchor {
+    Heading(String),
+    Block(String),
+}
+
+/// Splits a link target into the note and the anchor after `#`, `#^` or
+/// `^`. With nested headings (`note#A#B`) only the last one is kept.
+///
+/// Note names can't contain `#` but can contain `^`, so without `#` only
+/// a trailing `^id` made of letters, digits and `-` is taken as a block;
+/// [`resolve_target`] falls back to the whole name if that note is missing.
+pub fn split_target(target: &str) -> (&str, Option<Anchor>) {
+    if let Some(pos) = target.find('#') {
+        let (note, rest) = target.split_at(pos);
+        let anchor = match rest.strip_prefix("#^") {
+            Some(id) => Anchor::Block(id.trim().to_string()),
+            None => {
+                let heading = rest.rsplit('#').find(|h| !h.trim().is_empty()).unwrap_or("");
+                Anchor::Heading(heading.trim().to_string())
+            }
+        };
+        return (note.trim(), Some(anchor));
+    }
+    match target.rsplit_once('^') {
+        Some((note, id)) if !id.is_empty() && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') => {
+            (note.trim(), Some(Anchor::Block(id.to_string())))
+        }
+        _ => (target, None),
+    }
+}
+
+/// Path and anchor of a link target. An empty note (`[[#Heading]]`)
+/// points to `current_path`.
+pub fn resolve_target(vault: &str, current_path: &str, target: &str) -> Option<(String, Option<Anchor>)> {
+    let (note, anchor) = split_target(target);
+    if note.is_empty() {
+        return Some((current_path.to_string(), anchor));
+    }
+    if let Some(path) = crate::files::resolve_path(vault, current_path, note) {
+        return Some((path, anchor));
+    }
+    // "[[x^2]]" puede ser una nota con "^" en el nombre
+    if anchor.is_some() && !target.contains('#') {
+        return crate::files::resolve_path(vault, current_path, target).map(|path| (path, None));
+    }
+    None
+}
+
+fn block_id_regex() -> &'static Regex {
+    static BLOCK_ID: OnceLock<Regex> = OnceLock::new();
+    BLOCK_ID.get_or_init(|| Regex::new(r"(?:^|\s)\^([A-Za-z0-9-]+)\s*$").unwrap())
+}
+
+// Obsidian ignora mayúsculas y los signos al comparar encabezados
+fn normalize(text: &str) -> String {
+    text.chars()
+        .filter(|c| c.is_alphanumeric() || c.is_whitespace())
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+fn heading_text(line: &str) -> Option<&str> {
+    let trimmed = line.trim_start();
+    let level = trimmed.chars().take_while(|c| *c == '#').count();
+    if (1..=6).contains(&level) && trimmed[level..].starts_with(' ') {
+        Some(trimmed[level..].trim().trim_end_matches('#').trim_end())
+    } else {
+        None
+    }
+}
+
+fn is_list_item(line: &str) -> bool {
+    let trimmed = line.trim_start();
+    if trimmed.starts_with("- ") || trimmed.starts_with("* ") || trimmed.starts_with("+ ") {
+        return true;
+    }
+    let digits = trimmed.chars().take_while(|c| c.is_ascii_digit()).count();
+    digits > 0 && (trimmed[digits..].starts_with(". ") || trimmed[digits..].starts_with(") "))
+}
+
+/// Lines of `content` with their byte ranges and whether they are fenced code.
+fn lines(content: &str) -> Vec<(Range<usize>, &str, bool)> {
+    let mut result = vec![];
+    let mut offset = 0;
+    let mut in_code = false;
+    for raw in content.split_inclusive('\n') {
+        let line = raw.trim_end_matches(['\n', '\r']);
+        let trimmed = line.trim_start();
+        let fence = trimmed.starts_with("```") || trimmed.starts_with("~~~");
+        if fence {
+            in_code = !in_code;
+        }
+        result.push((offset..offset + line.len(), line, in_code || fence));
+        offset += raw.len();
+    }
+    result
+}
+
+/// Byte range of what the anchor points to: the heading line, or the list
+/// item or paragraph that ends with the block id.
+pub fn find_range(content: &str, anchor: &Anchor) -> Option<Range<usize>> {
+    let lines = lines(content);
+    match anchor {
+        Anchor::Heading(heading) => {
+            let wanted = normalize(heading);
+            lines
+                .iter()
+                .filter(|(_, _, code)| !code)
+                .find(|(_, line, _)| heading_text(line).map(|h| normalize(h) == wanted).unwrap_or(false))
+                .map(|(range, _, _)| range.clone())
+        }
+        Anchor::Block(id) => {
+            let index = lines.iter().position(|(_, line, code)| {
+                !code
+                    && block_id_regex()
+                        .captures(line)
+                        .map(|c| c[1].eq_ignore_ascii_case(id))
+                        .unwrap_or(false)
+            })?;
+            let end = lines[index].0.end;
+            if is_list_item(lines[index].1) {
+                return Some(lines[index].0.start..end);
+            }
+            // Un id en su propia línea o al final de un párrafo marca el párrafo entero
+            let mut start = index;
+            while start > 0 {
+                let (_, prev, code) = &lines[start - 1];
+                if *code || prev.trim().is_empty() || heading_text(prev).is_some() || is_list_item(prev) {
+                    break;
+                }
+                start -= 1;
+            }
+            Some(lines[start].0.start..end)
+        }
+    }
+}
+
+/// Text the anchor refers to: a heading with everything under it until
+/// the next heading of the same or higher level, or the block.
+pub fn section<'a>(content: &'a str, anchor: &Anchor) -> Option<&'a str> {
+    let range = find_range(content, anchor)?;
+    let Anchor::Heading(_) = anchor else {
+        return content.get(range);
+    };
+    let level = |line: &str| line.trim_start().chars().take_while(|c| *c == '#').count();
+    let lines = lines(content);
+    let index = lines.iter().position(|(r, _, _)| r.start == range.start)?;
+    let own_level = level(lines[index].1);
+    let end = lines[index + 1..]
+        .iter()
+        .find(|(_, line, code)| !code && heading_text(line).is_some() && level(*line) <= own_level)
+        .map(|(r, _, _)| r.start)
+        .unwrap_or(content.len());
+    content.get(range.start..end)
+}
+
+/// Char range of the anchor, for selecting it in the editor.
+pub fn find_char_range(content: &str, anchor: &Anchor) -> Option<Range<usize>> {
+    let range = find_range(content, anchor)?;
+    let start = content[..range.start].chars().count();
+    Some(start..start + content[range].chars().count())
+}
+
+/// Link to the heading or block on `line` (starting at 0) of the note at
+/// `path`. A block without id gets a new `^id` at its end; the updated
+/// content is returned in that case.
+pub fn link_to_line(content: &str, line: usize, path: &str) -> Option<(String, Option<String>)> {
+    let file_name = Path::new(path).file_name()?.to_str()?;
+    let note = file_name.strip_suffix(".md").unwrap_or(file_name);
+    let lines = lines(content);
+    let (_, text, code) = lines.get(line)?;
+    if text.trim().is_empty() {
+        return None;
+    }
+    if !code {
+        if let Some(heading) = heading_text(text) {
+            return Some((format!("[[{}#{}]]", note, heading), None));
+        }
+    }
+
+    // El id va en la última línea del bloque
+    let mut last = line;
+    if *code {
+        let mut open = None;
+        for (i, (_, l, _)) in lines.iter().enumerate() {
+            let t = l.trim_start();
+            if t.starts_with("```") || t.starts_with("~~~") {
+                match open {
+                    None => open = Some(i),
+                    Some(o) if (o..=i).contains(&line) => {
+                        last = i;
+                        break;
+                    }
+                    Some(_) => open = None,
+                }
+            }
+        }
+        // Tras un bloque de código el id va en la línea siguiente
+        if let Some((_, next, _)) = lines.get(last + 1) {
+            if let Some(caps) = block_id_regex().captures(next).filter(|_| next.trim_start().starts_with('^')) {
+                return Some((format!("[[{}#^{}]]", note, &caps[1]), None));
+            }
+        }
+    } else if !is_list_item(text) {
+        while last + 1 < lines.len() {
+            let (_, next, code) = &lines[last + 1];
+            if *code || next.trim().is_empty() || heading_text(next).is_some() || is_list_item(next) {
+                break;
+            }
+            last += 1;
+        }
+    }
+    let (range, last_text, _) = &lines[last];
+    if !code {
+        if let Some(caps) = block_id_regex().captures(last_text) {
+            return Some((format!("[[{}#^{}]]", note, &caps[1]), None));
+        }
+    }
+
+    let id = new_block_id(content);
+    let mut updated = content.to_string();
+    if *code {
+        updated.insert_str(range.end, &format!("\n^{}", id));
+    } else {
+        updated.insert_str(range.end, &format!(" ^{}", id));
+    }
+    Some((format!("[[{}#^{}]]", note, id), Some(updated)))
+}
+
+fn new_block_id(content: &str) -> String {
+    loop {
+        let id = format!("{:06x}", rand::random::<u32>() & 0xff_ffff);
+        if !content.contains(&format!("^{}", id)) {
+            return id;
+        }
+    }
+}
+
+/// Opens a link target, with its anchor if it has one. An empty note
+/// (`[[#Heading]]`) points to `current_path`.
+pub fn follow_link(ctx: &egui::Context, vault: &str, current_path: &str, target: &str) {
+    let target = target.split('|').next().unwrap_or(target).trim();
+    let Some((path, anchor)) = resolve_target(vault, current_path, target) else {
+        println!("No se encontró el enlace: {}", target);
+        return;
+    };
+    ctx.data_mut(|d| {
+        d.insert_temp(egui::Id::new("global_nav_request"), Some(path.clone()));
+        d.insert_temp(egui::Id::new("nav_anchor_request"), anchor.map(|a| (path, a)));
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOTE: &str = "# Title\nIntro\n\n## Risks & Costs\nFirst line\nsecond line ^para\n\n- item ^item-1\n- other\n\n### Detail\nText\n\n## Next\n```\n## Not a heading ^code\n```\n";
+
+    fn heading(text: &str) -> Anchor {
+        Anchor::Heading(text.to_string())
+    }
+
+    fn block(id: &str) -> Anchor {
+        Anchor::Block(id.to_string())
+    }
+
+    #[test]
+    fn splits_targets() {
+        assert_eq!(split_target("Note"), ("Note", None));
+        assert_eq!(split_target("Note#Risks"), ("Note", Some(heading("Risks"))));
+        assert_eq!(split_target("Note#A#B"), ("Note", Some(heading("B"))));
+        assert_eq!(split_target("#Risks"), ("", Some(heading("Risks"))));
+        assert_eq!(split_target("Note#^abc"), ("Note", Some(block("abc"))));
+        assert_eq!(split_target("Note^abc"), ("Note", Some(block("abc"))));
+        // "^" puede ser parte del nombre de la nota
+        assert_eq!(split_target("a^b#^id"), ("a^b", Some(block("id"))));
+        assert_eq!(split_target("a^b#Heading"), ("a^b", Some(heading("Heading"))));
+        assert_eq!(split_target("x ^ y"), ("x ^ y", None));
+        assert_eq!(split_target("x^"), ("x^", None));
+    }
+
+    #[test]
+    fn finds_headings_and_blocks() {
+        let text = |anchor: &Anchor| find_range(NOTE, anchor).map(|r| &NOTE[r]);
+        assert_eq!(text(&heading("risks costs")), Some("## Risks & Costs"));
+        assert_eq!(text(&block("para")), Some("First line\nsecond line ^para"));
+        assert_eq!(text(&block("ITEM-1")), Some("- item ^item-1"));
+        // Dentro de bloques de código no se busca
+        assert_eq!(text(&heading("Not a heading ^code")), None);
+        assert_eq!(text(&block("code")), None);
+        assert_eq!(text(&heading("Missing")), None);
+    }
+
+    #[test]
+    fn sections_end_at_the_next_heading_of_their_level() {
+        assert_eq!(
+            section(NOTE, &heading("Risks & Costs")),
+            Some("## Risks & Costs\nFirst line\nsecond line ^para\n\n- item ^item-1\n- other\n\n### Detail\nText\n\n")
+        );
+        assert_eq!(section(NOTE, &heading("Detail")), Some("### Detail\nText\n\n"));
+        assert_eq!(section(NOTE, &heading("Next")), Some("## Next\n```\n## Not a heading ^code\n```\n"));
+        assert_eq!(section(NOTE, &block("item-1")), Some("- item ^item-1"));
+    }
+
+    #[test]
+    fn links_to_lines() {
+        let path = "/vault/folder/Note.md";
+        assert_eq!(link_to_line(NOTE, 3, path), Some(("[[Note#Risks & Costs]]".to_string(), None)));
+        // Cualquier línea del párrafo usa el id de su última línea
+        assert_eq!(link_to_line(NOTE, 4, path), Some(("[[Note#^para]]".to_string(), None)));
+        assert_eq!(link_to_line(NOTE, 7, path), Some(("[[Note#^item-1]]".to_string(), None)));
+        assert_eq!(link_to_line(NOTE, 2, path), None);
+
+        let (link, updated) = link_to_line(NOTE, 8, path).unwrap();
+        let id = link.strip_prefix("[[Note#^").and_then(|l| l.strip_suffix("]]")).unwrap();
+        assert_eq!(id.len(), 6);
+        let updated = updated.unwrap();
+        assert!(updated.contains(&format!("- other ^{}\n", id)));
+        assert_eq!(find_range(&updated, &block(id)).map(|r| &updated[r]), Some(format!("- other ^{}", id).as_str()));
+
+        // Tras un bloque de código el id va en una línea aparte
+        let (link, updated) = link_to_line(NOTE, 16, path).unwrap();
+        let id = link.strip_prefix("[[Note#^").and_then(|l| l.strip_suffix("]]")).unwrap();
+        assert!(updated.unwrap().ends_with(&format!("```\n^{}\n", id)));
+    }
+}
@@ -0,0 +1,39 @@
+//! Small sync server for Marmol vaults.
+//!
+//! Usage: `marmol-sync-server <storage dir> [--host 127.0.0.1] [--port 8484] [--token SECRET]`
+//!
+//! It only listens on this machine by default. Serving other machines
+//! (`--host 0.0.0.0`) requires a `--token`.
+
+fn main() {
+    let mut root = None;
+    let mut host = "127.0.0.1".to_string();
+    let mut port = "8484".to_string();
+    let mut token = None;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--host" => host = args.next().unwrap_or(host),
+            "--port" | "-p" => port = args.next().unwrap_or(port),
+            "--token" => token = args.next(),
+            _ => root = Some(arg),
+        }
+    }
+
+    let Some(root) = root else {
+        eprintln!("Usage: marmol-sync-server <storage dir> [--host HOST] [--port PORT] [--token TOKEN]");
+        std::process::exit(1);
+    };
+
+    let addr = format!("{}:{}", host, port);
+    if token.is_none() && !marmol::server::sync_server::is_loopback(&addr) {
+        eprintln!("Listening on {} exposes the vault to other machines: pass --token TOKEN", addr);
+        std::process::exit(1);
+    }
+    println!("Serving {} on http://{}", root, addr);
+    if let Err(e) = marmol::server::sync_server::run(&root, &addr, token) {
+        eprintln!("Server error: {}", e);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,415 @@
+//! Bookmarks stored in `.obsidian/bookmarks.json`, in the same format
+//! Obsidian uses: files (with an optional heading or block), folders,
+//! searches, graph views and groups holding other bookmarks.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+use std::time::SystemTime;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Bookmark {
+    File {
+        #[serde(default)]
+        ctime: u64,
+        /// Path relative to the vault.
+        path: String,
+        /// `#Heading` or `#^block` inside the note.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        subpath: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        title: Option<String>,
+    },
+    Folder {
+        #[serde(default)]
+        ctime: u64,
+        path: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        title: Option<String>,
+    },
+    Search {
+        #[serde(default)]
+        ctime: u64,
+        query: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        title: Option<String>,
+    },
+    Graph {
+        #[serde(default)]
+        ctime: u64,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        title: Option<String>,
+        /// Obsidian's graph settings, kept as they are.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        options: Option<serde_json::Value>,
+    },
+    Url {
+        #[serde(default)]
+        ctime: u64,
+        url: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        title: Option<String>,
+    },
+    Group {
+        #[serde(default)]
+        ctime: u64,
+        #[serde(default)]
+        title: String,
+        #[serde(default)]
+        items: Vec<Bookmark>,
+    },
+    /// A type marmol doesn't know, kept as it was so saving doesn't lose it.
+    #[serde(untagged)]
+    Unknown(serde_json::Value),
+}
+
+fn now() -> u64 {
+    chrono::Local::now().timestamp_millis() as u64
+}
+
+fn relative(vault: &str, path: &str) -> String {
+    path.strip_prefix(vault).unwrap_or(path).trim_start_matches('/').to_string()
+}
+
+impl Bookmark {
+    /// Bookmark of the file at the absolute `path`, or of one of its headings
+    /// or blocks with `subpath`.
+    pub fn file(vault: &str, path: &str, subpath: Option<String>) -> Self {
+        Bookmark::File {
+            ctime: now(),
+            path: relative(vault, path),
+            subpath,
+            title: None,
+        }
+    }
+
+    pub fn folder(vault: &str, path: &str) -> Self {
+        Bookmark::Folder {
+            ctime: now(),
+            path: relative(vault, path),
+            title: None,
+        }
+    }
+
+    pub fn search(query: &str) -> Self {
+        Bookmark::Search {
+            ctime: now(),
+            query: query.to_string(),
+            title: None,
+        }
+    }
+
+    pub fn graph() -> Self {
+        Bookmark::Graph {
+            ctime: now(),
+            title: Some("Graph view".to_string()),
+            options: None,
+        }
+    }
+
+    pub fn group(title: &str) -> Self {
+        Bookmark::Group {
+            ctime: now(),
+            title: title.to_string(),
+            items: vec![],
+        }
+    }
+
+    /// Name shown in the list: the custom title or one made from the target.
+    pub fn title(&self) -> String {
+        let custom = match self {
+            Bookmark::File { title, .. }
+            | Bookmark::Folder { title, .. }
+            | Bookmark::Search { title, .. }
+            | Bookmark::Graph { title, .. }
+            | Bookmark::Url { title, .. } => title.clone(),
+            Bookmark::Group { title, .. } => Some(title.clone()),
+            Bookmark::Unknown(value) => value["title"].as_str().map(|t| t.to_string()),
+        };
+        if let Some(title) = custom.filter(|t| !t.is_empty()) {
+            return title;
+        }
+        match self {
+            Bookmark::File { path, subpath, .. } => {
+                let name = Path::new(path).file_stem().and_then(|n| n.to_str()).unwrap_or(path);
+                format!("{}{}", name, subpath.as_deref().unwrap_or(""))
+            }
+            Bookmark::Folder { path, .. } => Path::new(path)
+                .file_name()
+                .and_then(|n| n.to_str())
+                .unwrap_or(path)
+                .to_string(),
+            Bookmark::Search { query, .. } => query.clone(),
+            Bookmark::Graph { .. } => "Graph view".to_string(),
+            Bookmark::Url { url, .. } => url.clone(),
+            Bookmark::Group { .. } => "Group".to_string(),
+            Bookmark::Unknown(value) => value["type"].as_str().unwrap_or("Unknown").to_string(),
+        }
+    }
+
+    pub fn set_title(&mut self, new: &str) {
+        let new = new.trim().to_string();
+        match self {
+            Bookmark::File { title, .. }
+            | Bookmark::Folder { title, .. }
+            | Bookmark::Search { title, .. }
+            | Bookmark::Graph { title, .. }
+            | Bookmark::Url { title, .. } => *title = (!new.is_empty()).then_some(new),
+            Bookmark::Group { title, .. } => *title = new,
+            Bookmark::Unknown(value) => {
+                if let Some(object) = value.as_object_mut() {
+                    object.insert("title".to_string(), serde_json::Value::String(new));
+                }
+            }
+        }
+    }
+
+    pub fn icon(&self) -> &'static str {
+        match self {
+            Bookmark::File { subpath: Some(_), .. } => "#",
+            Bookmark::File { .. } => "🗋",
+            Bookmark::Folder { .. } => "🗀",
+            Bookmark::Search { .. } => "🔍",
+            Bookmark::Graph { .. } => "🕸",
+            Bookmark::Url { .. } => "🔗",
+            Bookmark::Group { .. } => "📂",
+            Bookmark::Unknown(_) => "?",
+        }
+    }
+
+    /// Whether both point to the same thing, ignoring titles and dates.
+    fn same_target(&self, other: &Bookmark) -> bool {
+        match (self, other) {
+            (Bookmark::File { path: a, subpath: sa, .. }, Bookmark::File { path: b, subpath: sb, .. }) => a == b && sa == sb,
+            (Bookmark::Folder { path: a, .. }, Bookmark::Folder { path: b, .. }) => a == b,
+            (Bookmark::Search { query: a, .. }, Bookmark::Search { query: b, .. }) => a == b,
+            (Bookmark::Url { url: a, .. }, Bookmark::Url { url: b, .. }) => a == b,
+            (Bookmark::Graph { .. }, Bookmark::Graph { .. }) => true,
+            _ => false,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct Bookmarks {
+    #[serde(default)]
+    pub items: Vec<Bookmark>,
+    /// `bookmarks.json` couldn't be read: saving would overwrite it.
+    #[serde(skip)]
+    pub broken: bool,
+}
+
+fn bookmarks_path(vault: &str) -> String {
+    format!("{}/.obsidian/bookmarks.json", vault)
+}
+
+/// Last bookmarks read, with the vault and the modification time of the file.
+fn cache() -> &'static Mutex<Option<(String, Option<SystemTime>, Bookmarks)>> {
+    static CACHE: OnceLock<Mutex<Option<(String, Option<SystemTime>, Bookmarks)>>> = OnceLock::new();
+    CACHE.get_or_init(|| Mutex::new(None))
+}
+
+fn modified(vault: &str) -> Option<SystemTime> {
+    fs::metadata(bookmarks_path(vault)).and_then(|m| m.modified()).ok()
+}
+
+impl Bookmarks {
+    /// Bookmarks of the vault, read again only when the file changed.
+    pub fn load(vault: &str) -> Self {
+        let mtime = modified(vault);
+        if let Some((cached_vault, cached_mtime, bookmarks)) = &*cache().lock().unwrap() {
+            if cached_vault == vault && *cached_mtime == mtime {
+                return bookmarks.clone();
+            }
+        }
+        let bookmarks = Self::read(vault);
+        *cache().lock().unwrap() = Some((vault.to_string(), mtime, bookmarks.clone()));
+        bookmarks
+    }
+
+    /// Without `bookmarks.json` the old `starred.json` is imported.
+    fn read(vault: &str) -> Self {
+        if let Ok(content) = fs::read_to_string(bookmarks_path(vault)) {
+            return match serde_json::from_str(&content) {
+                Ok(bookmarks) => bookmarks,
+                Err(e) => {
+                    println!("Error leyendo bookmarks.json: {}", e);
+                    Self {
+                        broken: true,
+                        ..Self::default()
+                    }
+                }
+            };
+        }
+        let mut bookmarks = Self::default();
+        let starred = fs::read_to_string(format!("{}/.obsidian/starred.json", vault)).unwrap_or_default();
+        if let Ok(json) = serde_json::from_str::<serde_json::Value>(&starred) {
+            for item in json["items"].as_array().into_iter().flatten() {
+                if let Some(path) = item["path"].as_str() {
+                    bookmarks.items.push(Bookmark::File {
+                        ctime: now(),
+                        path: path.to_string(),
+                        subpath: None,
+                        title: item["title"].as_str().map(|t| t.to_string()),
+                    });
+                }
+            }
+        }
+        bookmarks
+    }
+
+    pub fn save(&self, vault: &str) {
+        if self.broken {
+            println!("No se guarda bookmarks.json: no se pudo leer y se perderían los marcadores");
+            return;
+        }
+        let _ = fs::create_dir_all(format!("{}/.obsidian", vault));
+        match serde_json::to_string_pretty(self) {
+            Ok(json) => {
+                if let Err(e) = fs::write(bookmarks_path(vault), json) {
+                    println!("Error guardando bookmarks.json: {}", e);
+                    return;
+                }
+                *cache().lock().unwrap() = Some((vault.to_string(), modified(vault), self.clone()));
+            }
+            Err(e) => println!("Error guardando bookmarks.json: {}", e),
+        }
+    }
+
+    pub fn contains(&self, bookmark: &Bookmark) -> bool {
+        fn find(items: &[Bookmark], bookmark: &Bookmark) -> bool {
+            items.iter().any(|item| match item {
+                Bookmark::Group { items, .. } => find(items, bookmark),
+                item => item.same_target(bookmark),
+            })
+        }
+        find(&self.items, bookmark)
+    }
+
+    /// Removes every bookmark pointing to the same target as `bookmark`.
+    pub fn remove_target(&mut self, bookmark: &Bookmark) {
+        fn remove(items: &mut Vec<Bookmark>, bookmark: &Bookmark) {
+            items.retain(|item| !item.same_target(bookmark));
+            for item in items {
+                if let Bookmark::Group { items, .. } = item {
+                    remove(items, bookmark);
+                }
+            }
+        }
+        remove(&mut self.items, bookmark);
+    }
+
+    /// List holding the item at `at` (the indexes from the top level down).
+    fn parent_mut(&mut self, at: &[usize]) -> Option<&mut Vec<Bookmark>> {
+        let mut items = &mut self.items;
+        for &i in at.get(..at.len().checked_sub(1)?)? {
+            match items.get_mut(i)? {
+                Bookmark::Group { items: inner, .. } => items = inner,
+                _ => return None,
+            }
+        }
+        Some(items)
+    }
+
+    pub fn get_mut(&mut self, at: &[usize]) -> Option<&mut Bookmark> {
+        let last = *at.last()?;
+        self.parent_mut(at)?.get_mut(last)
+    }
+
+    pub fn take(&mut self, at: &[usize]) -> Option<Bookmark> {
+        let last = *at.last()?;
+        let parent = self.parent_mut(at)?;
+        (last < parent.len()).then(|| parent.remove(last))
+    }
+
+    /// Moves the item at `from` before the one at `to`, or to the end of the
+    /// group at `to` with `into_group`.
+    pub fn move_item(&mut self, from: &[usize], to: &[usize], into_group: bool) {
+        // Un grupo no puede ir dentro de sí mismo
+        if to.starts_with(from) {
+            return;
+        }
+        let Some(item) = self.take(from) else {
+            return;
+        };
+        // Quitar el elemento desplaza a los que iban después en la misma lista
+        let mut to = to.to_vec();
+        let depth = from.len() - 1;
+        if to.len() > depth && to[..depth] == from[..depth] && to[depth] > from[depth] {
+            to[depth] -= 1;
+        }
+        if into_group {
+            if let Some(Bookmark::Group { items, .. }) = self.get_mut(&to) {
+                items.push(item);
+                return;
+            }
+        } else if let Some(parent) = self.parent_mut(&to) {
+            let index = to.last().copied().unwrap_or(0).min(parent.len());
+            parent.insert(index, item);
+            return;
+        }
+        self.items.push(item);
+    }
+
+    /// Updates the file and folder bookmarks after a rename or move, with
+    /// paths relative to the vault.
+    pub fn rename_path(&mut self, old: &str, new: &str) -> bool {
+        fn rename(items: &mut [Bookmark], old: &str, new: &str) -> bool {
+            let mut changed = false;
+            for item in items {
+                match item {
+                    Bookmark::File { path, .. } | Bookmark::Folder { path, .. } => {
+                        if path == old {
+                            *path = new.to_string();
+                            changed = true;
+                        } else if let Some(rest) = path.strip_prefix(&format!("{}/", old)) {
+                            *path = format!("{}/{}", new, rest);
+                            changed = true;
+                        }
+                    }
+                    Bookmark::Group { items, .. } => changed |= rename(items, old, new),
+                    _ => {}
+                }
+            }
+            changed
+        }
+        rename(&mut self.items, old, new)
+    }
+}
+
+/// Adds `bookmark` at the end unless the vault already has it. Returns
+/// false if it was already bookmarked.
+pub fn add(vault: &str, bookmark: Bookmark) -> bool {
+    let mut bookmarks = Bookmarks::load(vault);
+    if bookmarks.contains(&bookmark) {
+        return false;
+    }
+    bookmarks.items.push(bookmark);
+    bookmarks.save(vault);
+    true
+}
+
+/// Adds the bookmark, or removes it if it was already there.
+pub fn toggle(vault: &str, bookmark: Bookmark) {
+    let mut bookmarks = Bookmarks::load(vault);
+    if bookmarks.contains(&bookmark) {
+        bookmarks.remove_target(&bookmark);
+    } else {
+        bookmarks.items.push(bookmark);
+    }
+    bookmarks.save(vault);
+}
+
+pub fn is_bookmarked(vault: &str, path: &str) -> bool {
+    Bookmarks::load(vault).contains(&Bookmark::file(vault, path, None))
+}
+
+/// Updates the bookmarks after renaming the file or folder at `old_path`.
+pub fn rename_path(vault: &str, old_path: &str, new_path: &str) {
+    let mut bookmarks = Bookmarks::load(vault);
+    if bookmarks.rename_path(&relative(vault, old_path), &relative(vault, new_path)) {
+        bookmarks.save(vault);
+    }
+}
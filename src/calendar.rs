@@ -0,0 +1,317 @@
+use chrono::prelude::*;
+use chrono::Duration;
+use egui::*;
+use std::collections::HashMap;
+use std::fs;
+
+use crate::kanban;
+use crate::periodic::{self, Period};
+use crate::tasks;
+use crate::vault_index;
+
+const WEEKDAYS: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+
+#[derive(Default, PartialEq, Clone, Copy)]
+enum CalendarMode {
+    #[default]
+    Month,
+    Week,
+}
+
+/// Everything the vault has dated on a given day.
+#[derive(Default, Clone)]
+pub struct DayInfo {
+    pub daily_note: Option<String>,
+    pub tasks_completed: usize,
+    /// (card text, board path)
+    pub kanban_due: Vec<(String, String)>,
+}
+
+#[derive(Default)]
+pub struct CalendarGui {
+    vault: String,
+    mode: CalendarMode,
+    anchor: Option<NaiveDate>,
+    days: HashMap<NaiveDate, DayInfo>,
+    loaded: bool,
+}
+
+impl CalendarGui {
+    /// Forces a rescan of the vault on the next frame.
+    pub fn invalidate(&mut self) {
+        self.loaded = false;
+    }
+
+    fn load(&mut self, ctx: &Context, vault: &str) {
+        self.vault = vault.to_string();
+        self.days = scan_vault(ctx, vault);
+        self.loaded = true;
+    }
+
+    /// Returns the file the user wants to open (a daily note or a kanban board).
+    pub fn show(&mut self, ui: &mut Ui, vault: &str) -> Option<String> {
+        if !self.loaded || self.vault != vault {
+            self.load(ui.ctx(), vault);
+        }
+        let today = Local::now().date_naive();
+        let anchor = *self.anchor.get_or_insert(today);
+        let mut open = None;
+
+        ui.horizontal(|ui| {
+            let step = |date: NaiveDate, forward: bool, mode: CalendarMode| match mode {
+                CalendarMode::Week => date + Duration::days(if forward { 7 } else { -7 }),
+                CalendarMode::Month => shift_month(date, forward),
+            };
+            if ui.button("◀").clicked() {
+                self.anchor = Some(step(anchor, false, self.mode));
+            }
+            if ui.button("Today").clicked() {
+                self.anchor = Some(today);
+            }
+            if ui.button("▶").clicked() {
+                self.anchor = Some(step(anchor, true, self.mode));
+            }
+            let title = match self.mode {
+                CalendarMode::Month => anchor.format("%B %Y").to_string(),
+                CalendarMode::Week => format!("Week {} of {}", anchor.iso_week().week(), anchor.iso_week().year()),
+            };
+            ui.heading(title);
+            ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                if ui.button("⟳").on_hover_text("Rescan vault").clicked() {
+                    self.invalidate();
+                }
+                ui.selectable_value(&mut self.mode, CalendarMode::Week, "Week");
+                ui.selectable_value(&mut self.mode, CalendarMode::Month, "Month");
+            });
+        });
+        ui.separator();
+
+        let anchor = self.anchor.unwrap_or(today);
+        let clicked = match self.mode {
+            CalendarMode::Month => self.month_ui(ui, anchor, today),
+            CalendarMode::Week => self.week_ui(ui, anchor, today),
+        };
+
+        match clicked {
+            Some(Click::Day(date)) => {
+                if let Some(path) = self.days.get(&date).and_then(|d| d.daily_note.clone()) {
+                    open = Some(path);
+                } else {
+                    let settings = periodic::settings(ui.ctx(), vault);
+                    open = settings.open_or_create(vault, Period::Daily, date);
+                    self.invalidate();
+                }
+            }
+            Some(Click::File(path)) => open = Some(path),
+            None => {}
+        }
+        open
+    }
+
+    fn month_ui(&self, ui: &mut Ui, anchor: NaiveDate, today: NaiveDate) -> Option<Click> {
+        let first = anchor.with_day(1).unwrap_or(anchor);
+        let start = first - Duration::days(first.weekday().num_days_from_monday() as i64);
+        let width = (ui.available_width() / 7.0 - 6.0).max(40.0);
+        let height = (width * 0.7).clamp(40.0, 90.0);
+        let mut clicked = None;
+
+        Grid::new("calendar_month")
+            .num_columns(7)
+            .spacing([4.0, 4.0])
+            .show(ui, |ui| {
+                for name in WEEKDAYS {
+                    ui.vertical_centered(|ui| ui.strong(name));
+                }
+                ui.end_row();
+                for week in 0..6 {
+                    for weekday in 0..7 {
+                        let date = start + Duration::days(week * 7 + weekday);
+                        let faded = date.month() != first.month();
+                        if let Some(c) = self.day_cell(ui, date, today, faded, vec2(width, height)) {
+                            clicked = Some(c);
+                        }
+                    }
+                    ui.end_row();
+                }
+            });
+        clicked
+    }
+
+    fn week_ui(&self, ui: &mut Ui, anchor: NaiveDate, today: NaiveDate) -> Option<Click> {
+        let start = anchor - Duration::days(anchor.weekday().num_days_from_monday() as i64);
+        let mut clicked = None;
+        for offset in 0..7 {
+            let date = start + Duration::days(offset);
+            let info = self.days.get(&date).cloned().unwrap_or_default();
+            let frame = Frame::group(ui.style()).stroke(day_stroke(ui, date == today));
+            frame.show(ui, |ui| {
+                ui.set_width(ui.available_width());
+                ui.horizontal(|ui| {
+                    let label = format!("{} {}", WEEKDAYS[offset as usize], date.format("%d %b"));
+                    if ui.link(RichText::new(label).strong()).clicked() {
+                        clicked = Some(Click::Day(date));
+                    }
+                    if info.daily_note.is_some() {
+                        ui.weak("● daily note");
+                    }
+                    if info.tasks_completed > 0 {
+                        ui.weak(format!("✔ {} tasks completed", info.tasks_completed));
+                    }
+                });
+                for (card, board) in &info.kanban_due {
+                    let board_name = board.rsplit('/').next().unwrap_or(board);
+                    if ui
+                        .link(format!("📌 {}  ({})", card, board_name.trim_end_matches(".md")))
+                        .clicked()
+                    {
+                        clicked = Some(Click::File(board.clone()));
+                    }
+                }
+            });
+        }
+        clicked
+    }
+
+    fn day_cell(&self, ui: &mut Ui, date: NaiveDate, today: NaiveDate, faded: bool, size: Vec2) -> Option<Click> {
+        let info = self.days.get(&date);
+        let (rect, response) = ui.allocate_exact_size(size, Sense::click());
+        let visuals = ui.style().interact(&response);
+        let painter = ui.painter_at(rect);
+
+        let fill = if response.hovered() {
+            visuals.bg_fill
+        } else {
+            ui.visuals().faint_bg_color
+        };
+        painter.rect(rect, 4.0, fill, day_stroke(ui, date == today), StrokeKind::Inside);
+
+        let mut text_color = ui.visuals().text_color();
+        if faded {
+            text_color = text_color.gamma_multiply(0.4);
+        }
+        painter.text(
+            rect.left_top() + vec2(5.0, 3.0),
+            Align2::LEFT_TOP,
+            date.day().to_string(),
+            TextStyle::Body.resolve(ui.style()),
+            text_color,
+        );
+
+        if let Some(info) = info {
+            let small = TextStyle::Small.resolve(ui.style());
+            if info.daily_note.is_some() {
+                painter.circle_filled(rect.right_top() + vec2(-8.0, 8.0), 3.5, ui.visuals().selection.bg_fill);
+            }
+            let mut badges = vec![];
+            if info.tasks_completed > 0 {
+                badges.push(format!("✔{}", info.tasks_completed));
+            }
+            if !info.kanban_due.is_empty() {
+                badges.push(format!("📌{}", info.kanban_due.len()));
+            }
+            painter.text(
+                rect.left_bottom() + vec2(5.0, -3.0),
+                Align2::LEFT_BOTTOM,
+                badges.join(" "),
+                small,
+                text_color,
+            );
+        }
+
+        let response = response.on_hover_ui(|ui| {
+            ui.strong(date.format("%A %d %B %Y").to_string());
+            match info {
+                Some(info) => {
+                    ui.label(if info.daily_note.is_some() { "Daily note" } else { "No daily note" });
+                    if info.tasks_completed > 0 {
+                        ui.label(format!("{} tasks completed", info.tasks_completed));
+                    }
+                    for (card, _) in &info.kanban_due {
+                        ui.label(format!("📌 {}", card));
+                    }
+                }
+                None => {
+                    ui.weak("Click to create the daily note");
+                }
+            }
+        });
+        response.clicked().then_some(Click::Day(date))
+    }
+}
+
+enum Click {
+    Day(NaiveDate),
+    File(String),
+}
+
+fn day_stroke(ui: &Ui, today: bool) -> Stroke {
+    if today {
+        Stroke::new(2.0, ui.visuals().selection.stroke.color)
+    } else {
+        ui.visuals().widgets.noninteractive.bg_stroke
+    }
+}
+
+fn shift_month(date: NaiveDate, forward: bool) -> NaiveDate {
+    let (year, month) = match (date.month(), forward) {
+        (12, true) => (date.year() + 1, 1),
+        (1, false) => (date.year() - 1, 12),
+        (m, true) => (date.year(), m + 1),
+        (m, false) => (date.year(), m - 1),
+    };
+    NaiveDate::from_ymd_opt(year, month, 1).unwrap_or(date)
+}
+
+fn parse_date(date: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d").ok()
+}
+
+// Notas diarias, días del archivo de tareas (.graph) y tarjetas con @{fecha}
+fn scan_vault(ctx: &Context, vault: &str) -> HashMap<NaiveDate, DayInfo> {
+    let settings = periodic::settings(ctx, vault);
+    let mut days: HashMap<NaiveDate, DayInfo> = HashMap::new();
+
+    // Solo se leen del disco los archivos de tareas y los tableros kanban
+    let (graphs, boards) = vault_index::with_index(vault, |index| {
+        let mut graphs = vec![];
+        let mut boards = vec![];
+        for file in index.files() {
+            if file.path.ends_with(".graph") {
+                graphs.push(file.path.clone());
+            }
+            let Some(note) = &file.note else {
+                continue;
+            };
+            if let Some((Period::Daily, date)) = settings.note_date(vault, &file.path) {
+                days.entry(date).or_default().daily_note = Some(file.path.clone());
+            }
+            if note.frontmatter.contains("kanban-plugin: board") {
+                boards.push(file.path.clone());
+            }
+        }
+        (graphs, boards)
+    });
+
+    for path in graphs {
+        for (date, completed) in tasks::load_tasks(&path).completed_by_date() {
+            if let Some(date) = parse_date(&date) {
+                days.entry(date).or_default().tasks_completed += completed;
+            }
+        }
+    }
+    for path in boards {
+        let Ok(content) = fs::read_to_string(&path) else {
+            continue;
+        };
+        let board = kanban::parse_kanban(&content);
+        for task in board.columns.iter().flat_map(|c| c.tasks.iter()) {
+            if let Some(date) = task.date.as_deref().and_then(parse_date) {
+                days.entry(date)
+                    .or_default()
+                    .kanban_due
+                    .push((task.content.clone(), path.clone()));
+            }
+        }
+    }
+    days
+}
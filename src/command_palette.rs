@@ -1,5 +1,7 @@
 use eframe::egui;
 
+use crate::periodic::Period;
+
 pub enum CommandAction {
     None,
     OpenIconInstaller,
@@ -10,6 +12,10 @@ pub enum CommandAction {
     ToggleLeftMenu,
     Quit,
     CreateFile(String),
+    OpenPeriodicNote(Period),
+    InsertTemplate,
+    OpenCalendar,
+    OpenTasks,
 }
 
 pub struct Command {
@@ -58,6 +64,30 @@ impl Default for CommandPalette {
                     name: "New File".to_string(),
                     action: CommandAction::None, // Will be handled specially to enter Prompt state
                 },
+                Command {
+                    name: "Open today's daily note".to_string(),
+                    action: CommandAction::OpenPeriodicNote(Period::Daily),
+                },
+                Command {
+                    name: "Open this week's note".to_string(),
+                    action: CommandAction::OpenPeriodicNote(Period::Weekly),
+                },
+                Command {
+                    name: "Open this month's note".to_string(),
+                    action: CommandAction::OpenPeriodicNote(Period::Monthly),
+                },
+                Command {
+                    name: "Open calendar".to_string(),
+                    action: CommandAction::OpenCalendar,
+                },
+                Command {
+                    name: "Open tasks".to_string(),
+                    action: CommandAction::OpenTasks,
+                },
+                Command {
+                    name: "Insert template".to_string(),
+                    action: CommandAction::InsertTemplate,
+                },
                 Command {
                     name: "Close Tab".to_string(),
                     action: CommandAction::CloseTab,
@@ -171,6 +201,10 @@ impl CommandPalette {
                                 CommandAction::CloseTab => CommandAction::CloseTab,
                                 CommandAction::ToggleLeftMenu => CommandAction::ToggleLeftMenu,
                                 CommandAction::Quit => CommandAction::Quit,
+                                CommandAction::OpenPeriodicNote(p) => CommandAction::OpenPeriodicNote(*p),
+                                CommandAction::InsertTemplate => CommandAction::InsertTemplate,
+                                CommandAction::OpenCalendar => CommandAction::OpenCalendar,
+                                CommandAction::OpenTasks => CommandAction::OpenTasks,
                                 _ => CommandAction::None,
                             };
 
@@ -214,6 +248,10 @@ impl CommandPalette {
                                         CommandAction::CloseTab => CommandAction::CloseTab,
                                         CommandAction::ToggleLeftMenu => CommandAction::ToggleLeftMenu,
                                         CommandAction::Quit => CommandAction::Quit,
+                                        CommandAction::OpenPeriodicNote(p) => CommandAction::OpenPeriodicNote(p),
+                                        CommandAction::InsertTemplate => CommandAction::InsertTemplate,
+                                        CommandAction::OpenCalendar => CommandAction::OpenCalendar,
+                                        CommandAction::OpenTasks => CommandAction::OpenTasks,
                                         _ => CommandAction::None,
                                     };
                                     should_close = true;
@@ -0,0 +1,569 @@
+//! `.table` files: every note matching a source and a filter shown as a
+//! spreadsheet whose columns are frontmatter properties.
+
+use egui::{Align, Id, Layout, RichText, Ui};
+use egui_extras::{Column, TableBuilder};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::fs;
+
+use crate::main_area::metadata_renderer::value_cell;
+use crate::properties::{self, Property};
+use crate::vault_index;
+
+const NAME_COLUMN: &str = "file.name";
+
+fn default_width() -> f32 {
+    160.0
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct TableColumn {
+    pub key: String,
+    #[serde(default = "default_width")]
+    pub width: f32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct TableView {
+    pub name: String,
+    #[serde(default)]
+    pub columns: Vec<TableColumn>,
+    #[serde(default = "default_width")]
+    pub name_width: f32,
+    /// Property used to sort, `file.name` for the note name.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sort_by: Option<String>,
+    #[serde(default)]
+    pub descending: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group_by: Option<String>,
+}
+
+impl TableView {
+    fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            columns: vec![],
+            name_width: default_width(),
+            sort_by: None,
+            descending: false,
+            group_by: None,
+        }
+    }
+}
+
+/// Contents of a `.table` file.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TableData {
+    /// Notes to show, as the `FROM` of a query: `#tag`, `"folder"`...
+    #[serde(default)]
+    pub source: String,
+    /// Condition the notes must meet, as the `WHERE` of a query.
+    #[serde(default)]
+    pub filter: String,
+    #[serde(default)]
+    pub views: Vec<TableView>,
+    #[serde(default)]
+    pub active_view: usize,
+}
+
+impl Default for TableData {
+    fn default() -> Self {
+        Self {
+            source: String::new(),
+            filter: String::new(),
+            views: vec![TableView::new("Table")],
+            active_view: 0,
+        }
+    }
+}
+
+struct Row {
+    path: String,
+    name: String,
+    properties: Vec<Property>,
+}
+
+impl Row {
+    fn value(&self, key: &str) -> String {
+        if key == NAME_COLUMN {
+            return self.name.clone();
+        }
+        self.properties
+            .iter()
+            .find(|p| p.key == key)
+            .map(|p| p.value.as_text())
+            .unwrap_or_default()
+    }
+}
+
+fn compare_values(a: &str, b: &str) -> Ordering {
+    // Los vacíos van al final
+    match (a.trim().is_empty(), b.trim().is_empty()) {
+        (true, true) => return Ordering::Equal,
+        (true, false) => return Ordering::Greater,
+        (false, true) => return Ordering::Less,
+        _ => {}
+    }
+    match (a.trim().parse::<f64>(), b.trim().parse::<f64>()) {
+        (Ok(x), Ok(y)) => x.partial_cmp(&y).unwrap_or(Ordering::Equal),
+        // Las fechas ISO se ordenan bien como texto
+        _ => a.to_lowercase().cmp(&b.to_lowercase()),
+    }
+}
+
+#[derive(Default)]
+pub struct DatabaseGui {
+    pub path: String,
+    pub data: TableData,
+    rows: Vec<Row>,
+    error: Option<String>,
+    /// Generation of the vault index the rows were built from.
+    loaded_generation: Option<u64>,
+    /// View being renamed, its new name and whether the field has the focus.
+    renaming_view: Option<(usize, String, bool)>,
+    new_column: String,
+    /// The `.table` file couldn't be read: saving would overwrite it.
+    broken: bool,
+}
+
+impl DatabaseGui {
+    pub fn set_path(&mut self, path: &str) {
+        if self.path != path {
+            self.path = path.to_string();
+            self.reload();
+        }
+    }
+
+    pub fn reload(&mut self) {
+        if let Ok(content) = fs::read_to_string(&self.path) {
+            match serde_json::from_str::<TableData>(&content) {
+                Ok(data) => {
+                    self.data = data;
+                    self.broken = false;
+                }
+                Err(e) => {
+                    println!("Error leyendo la tabla {}: {}", self.path, e);
+                    self.broken = true;
+                }
+            }
+        }
+        if self.data.views.is_empty() {
+            self.data.views.push(TableView::new("Table"));
+        }
+        self.loaded_generation = None;
+    }
+
+    pub fn save(&self) {
+        if self.broken {
+            println!("No se guarda la tabla {}: no se pudo leer y se perderían sus vistas", self.path);
+            return;
+        }
+        if let Ok(json) = serde_json::to_string_pretty(&self.data) {
+            if let Err(e) = fs::write(&self.path, json) {
+                println!("Error guardando la tabla {}: {}", self.path, e);
+            }
+        }
+    }
+
+    fn view(&mut self) -> &mut TableView {
+        let active = self.data.active_view.min(self.data.views.len() - 1);
+        &mut self.data.views[active]
+    }
+
+    fn load_rows(&mut self, vault: &str) {
+        self.rows.clear();
+        match crate::query::matching_notes(vault, &self.data.source, &self.data.filter) {
+            Ok(paths) => {
+                self.error = None;
+                // El frontmatter sale del índice, sin volver a leer las notas
+                self.rows = vault_index::with_index(vault, |index| {
+                    paths
+                        .into_iter()
+                        .filter_map(|path| {
+                            let file = index.get(&path)?;
+                            let frontmatter = file.note.as_ref().map(|n| n.frontmatter.as_str()).unwrap_or("");
+                            let properties = if frontmatter.trim().is_empty() {
+                                vec![]
+                            } else {
+                                properties::read(&format!("---\n{}---\n", frontmatter)).unwrap_or_default()
+                            };
+                            Some(Row {
+                                name: file.name.clone(),
+                                properties,
+                                path,
+                            })
+                        })
+                        .collect()
+                });
+            }
+            Err(e) => self.error = Some(e),
+        }
+    }
+
+    /// Writes `value` to the property `key` of the note at `path`.
+    /// The watcher picks up the write, so the open tabs of the note reload it.
+    fn write_property(&mut self, path: &str, key: &str, value: &properties::Value) {
+        let Ok(content) = fs::read_to_string(path) else {
+            return;
+        };
+        let updated = properties::set(&content, key, value);
+        match fs::write(path, &updated) {
+            Ok(_) => {
+                if let Some(row) = self.rows.iter_mut().find(|r| r.path == path) {
+                    row.properties = properties::read(&updated).unwrap_or_default();
+                }
+            }
+            Err(e) => println!("Error guardando {}: {}", path, e),
+        }
+    }
+
+    pub fn show(&mut self, ui: &mut Ui, vault: &str, seed_id: Id) {
+        // Las filas se reconstruyen cuando cambia el índice, salvo mientras se edita una celda
+        let generation = vault_index::generation();
+        let editing = ui.ctx().memory(|m| m.focused().is_some());
+        if self.loaded_generation.map(|g| g != generation && !editing).unwrap_or(true) {
+            self.load_rows(vault);
+            self.loaded_generation = Some(vault_index::generation());
+        }
+
+        let mut changed = false;
+        changed |= self.views_bar(ui);
+        changed |= self.options_bar(ui, vault);
+        if let Some(error) = &self.error {
+            ui.colored_label(ui.visuals().error_fg_color, format!("Query error: {}", error));
+        }
+        if self.broken {
+            ui.label(RichText::new("This table file couldn't be read; it won't be changed until it's fixed").weak().small());
+        }
+        ui.separator();
+        changed |= self.table(ui, vault, seed_id);
+        if changed {
+            self.save();
+        }
+    }
+
+    fn views_bar(&mut self, ui: &mut Ui) -> bool {
+        let mut changed = false;
+        ui.horizontal(|ui| {
+            let mut remove = None;
+            for i in 0..self.data.views.len() {
+                if let Some((index, buffer, focused)) = &mut self.renaming_view {
+                    if *index == i {
+                        let response = ui.add(egui::TextEdit::singleline(buffer).desired_width(100.0));
+                        if !*focused {
+                            response.request_focus();
+                            *focused = true;
+                        }
+                        if response.lost_focus() {
+                            if !buffer.trim().is_empty() {
+                                self.data.views[i].name = buffer.trim().to_string();
+                                changed = true;
+                            }
+                            self.renaming_view = None;
+                        }
+                        continue;
+                    }
+                }
+                let response = ui.selectable_label(self.data.active_view == i, &self.data.views[i].name);
+                if response.clicked() && self.data.active_view != i {
+                    self.data.active_view = i;
+                    changed = true;
+                }
+                response.context_menu(|ui| {
+                    if ui.button("Rename view").clicked() {
+                        self.renaming_view = Some((i, self.data.views[i].name.clone(), false));
+                        ui.close();
+                    }
+                    if ui.button("Duplicate view").clicked() {
+                        let mut copy = self.data.views[i].clone();
+                        copy.name = format!("{} copy", copy.name);
+                        self.data.views.insert(i + 1, copy);
+                        self.data.active_view = i + 1;
+                        changed = true;
+                        ui.close();
+                    }
+                    if ui.add_enabled(self.data.views.len() > 1, egui::Button::new("🗑 Delete view")).clicked() {
+                        remove = Some(i);
+                        ui.close();
+                    }
+                });
+            }
+            if let Some(i) = remove {
+                self.data.views.remove(i);
+                self.data.active_view = self.data.active_view.min(self.data.views.len() - 1);
+                changed = true;
+            }
+            if ui.small_button("＋").on_hover_text("New view").clicked() {
+                let mut view = TableView::new(&format!("View {}", self.data.views.len() + 1));
+                view.columns = self.view().columns.clone();
+                self.data.views.push(view);
+                self.data.active_view = self.data.views.len() - 1;
+                changed = true;
+            }
+        });
+        changed
+    }
+
+    fn options_bar(&mut self, ui: &mut Ui, vault: &str) -> bool {
+        let mut changed = false;
+        ui.horizontal_wrapped(|ui| {
+            ui.label("From");
+            let source = ui.add(
+                egui::TextEdit::singleline(&mut self.data.source)
+                    .hint_text("#tag or \"folder\"")
+                    .desired_width(160.0),
+            );
+            ui.label("Where");
+            let filter = ui.add(
+                egui::TextEdit::singleline(&mut self.data.filter)
+                    .hint_text("status != \"done\"")
+                    .desired_width(200.0),
+            );
+            if source.lost_focus() || filter.lost_focus() {
+                self.loaded_generation = None;
+                changed = true;
+            }
+
+            ui.separator();
+            let columns: Vec<String> = self.view().columns.iter().map(|c| c.key.clone()).collect();
+            let view = self.view();
+            let selected = view.group_by.clone().unwrap_or_else(|| "None".to_string());
+            egui::ComboBox::from_label("Group by")
+                .selected_text(selected)
+                .show_ui(ui, |ui| {
+                    changed |= ui.selectable_value(&mut view.group_by, None, "None").changed();
+                    for key in &columns {
+                        changed |= ui.selectable_value(&mut view.group_by, Some(key.clone()), key).changed();
+                    }
+                });
+
+            ui.menu_button("＋ Column", |ui| {
+                let response = ui.add(
+                    egui::TextEdit::singleline(&mut self.new_column)
+                        .hint_text("Property name")
+                        .desired_width(160.0),
+                );
+                let key = self.new_column.trim().to_string();
+                let enter = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                if (ui.button("Add").clicked() || enter) && !key.is_empty() && !columns.contains(&key) {
+                    self.view().columns.push(TableColumn { key, width: default_width() });
+                    self.new_column.clear();
+                    changed = true;
+                    ui.close();
+                }
+                ui.separator();
+                let cache_id = Id::new("database_keys").with(vault);
+                let keys: Vec<String> = match ui.ctx().data(|d| d.get_temp::<(u64, Vec<String>)>(cache_id)) {
+                    Some((generation, keys)) if generation == vault_index::generation() => keys,
+                    _ => {
+                        let keys = properties::vault_keys(vault);
+                        ui.ctx().data_mut(|d| d.insert_temp(cache_id, (vault_index::generation(), keys.clone())));
+                        keys
+                    }
+                };
+                egui::ScrollArea::vertical().max_height(240.0).show(ui, |ui| {
+                    for key in keys {
+                        if columns.contains(&key) {
+                            continue;
+                        }
+                        if ui.button(&key).clicked() {
+                            self.view().columns.push(TableColumn { key, width: default_width() });
+                            changed = true;
+                            ui.close();
+                        }
+                    }
+                });
+            });
+            ui.weak(format!("{} notes", self.rows.len()));
+        });
+        changed
+    }
+
+    fn table(&mut self, ui: &mut Ui, vault: &str, seed_id: Id) -> bool {
+        let mut changed = false;
+        let view_index = self.data.active_view.min(self.data.views.len() - 1);
+        let view = self.data.views[view_index].clone();
+        let types = properties::types(ui.ctx(), vault);
+
+        // Orden y grupos de las filas
+        let mut order: Vec<usize> = (0..self.rows.len()).collect();
+        if let Some(key) = &view.sort_by {
+            order.sort_by(|&a, &b| {
+                let ordering = compare_values(&self.rows[a].value(key), &self.rows[b].value(key));
+                if view.descending { ordering.reverse() } else { ordering }
+            });
+        }
+        let mut groups: Vec<(String, Vec<usize>)> = vec![];
+        match &view.group_by {
+            Some(key) => {
+                for i in order {
+                    let value = self.rows[i].value(key);
+                    match groups.iter_mut().find(|(g, _)| *g == value) {
+                        Some((_, rows)) => rows.push(i),
+                        None => groups.push((value, vec![i])),
+                    }
+                }
+                groups.sort_by(|a, b| compare_values(&a.0, &b.0));
+            }
+            None => groups.push((String::new(), order)),
+        }
+
+        let table_id = seed_id.with("database").with(view_index);
+        let mut edits: Vec<(String, String, properties::Value)> = vec![];
+        let mut new_view = view.clone();
+
+        egui::ScrollArea::horizontal().id_salt(table_id.with("scroll")).show(ui, |ui| {
+            let mut builder = TableBuilder::new(ui)
+                .id_salt(table_id)
+                .striped(true)
+                .resizable(true)
+                .cell_layout(Layout::left_to_right(Align::Center))
+                .column(Column::initial(view.name_width).at_least(60.0).clip(true));
+            for column in &view.columns {
+                builder = builder.column(Column::initial(column.width).at_least(60.0).clip(true));
+            }
+            let released = ui.input(|i| i.pointer.any_released());
+            let ctx = ui.ctx().clone();
+
+            builder
+                .header(24.0, |mut header| {
+                    let keys = std::iter::once(NAME_COLUMN.to_string()).chain(view.columns.iter().map(|c| c.key.clone()));
+                    for (i, key) in keys.enumerate() {
+                        header.col(|ui| {
+                            // El ancho se guarda al soltar el borde de la columna
+                            let width = ui.max_rect().width();
+                            let saved = if i == 0 {
+                                Some(&mut new_view.name_width)
+                            } else {
+                                new_view.columns.iter_mut().find(|c| c.key == key).map(|c| &mut c.width)
+                            };
+                            if let Some(saved) = saved.filter(|s| released && (width - **s).abs() > 0.5) {
+                                *saved = width;
+                            }
+
+                            let arrow = match (&view.sort_by, view.descending) {
+                                (Some(k), false) if *k == key => " ⏶",
+                                (Some(k), true) if *k == key => " ⏷",
+                                _ => "",
+                            };
+                            let title = if i == 0 {
+                                "Name".to_string()
+                            } else {
+                                let property_type = types.get(&key).unwrap_or(properties::PropertyType::Text);
+                                format!("{} {}", property_type.icon(), key)
+                            };
+                            let response = ui.add(
+                                egui::Label::new(RichText::new(format!("{}{}", title, arrow)).strong())
+                                    .sense(egui::Sense::click()),
+                            );
+                            // Ascendente, descendente y sin orden
+                            if response.clicked() {
+                                match (&new_view.sort_by, new_view.descending) {
+                                    (Some(k), false) if *k == key => new_view.descending = true,
+                                    (Some(k), true) if *k == key => {
+                                        new_view.sort_by = None;
+                                        new_view.descending = false;
+                                    }
+                                    _ => {
+                                        new_view.sort_by = Some(key.clone());
+                                        new_view.descending = false;
+                                    }
+                                }
+                            }
+                            if i > 0 {
+                                response.context_menu(|ui| {
+                                    let index = i - 1;
+                                    if ui.add_enabled(index > 0, egui::Button::new("⬅ Move left")).clicked() {
+                                        new_view.columns.swap(index, index - 1);
+                                        ui.close();
+                                    }
+                                    if ui.add_enabled(index + 1 < view.columns.len(), egui::Button::new("➡ Move right")).clicked() {
+                                        new_view.columns.swap(index, index + 1);
+                                        ui.close();
+                                    }
+                                    if ui.button("Group by this").clicked() {
+                                        new_view.group_by = Some(key.clone());
+                                        ui.close();
+                                    }
+                                    if ui.button("🗑 Hide column").clicked() {
+                                        new_view.columns.remove(index);
+                                        if new_view.group_by.as_ref() == Some(&key) {
+                                            new_view.group_by = None;
+                                        }
+                                        ui.close();
+                                    }
+                                });
+                            }
+                        });
+                    }
+                })
+                .body(|mut body| {
+                    for (group, rows) in &groups {
+                        let collapsed_id = table_id.with("collapsed").with(group);
+                        let mut collapsed: bool = ctx.data(|d| d.get_temp(collapsed_id).unwrap_or(false));
+                        if view.group_by.is_some() {
+                            body.row(26.0, |mut row| {
+                                row.col(|ui| {
+                                    let label = if group.is_empty() { "(empty)" } else { group.as_str() };
+                                    let icon = if collapsed { "▶" } else { "▼" };
+                                    let text = RichText::new(format!("{} {} ({})", icon, label, rows.len())).strong();
+                                    if ui.add(egui::Label::new(text).sense(egui::Sense::click())).clicked() {
+                                        collapsed = !collapsed;
+                                        ui.ctx().data_mut(|d| d.insert_temp(collapsed_id, collapsed));
+                                    }
+                                });
+                                for _ in &view.columns {
+                                    row.col(|_| {});
+                                }
+                            });
+                        }
+                        if collapsed {
+                            continue;
+                        }
+                        for &index in rows {
+                            let note = &self.rows[index];
+                            body.row(28.0, |mut row| {
+                                row.col(|ui| {
+                                    if ui.link(&note.name).clicked() {
+                                        ui.ctx().data_mut(|d| d.insert_temp(Id::new("global_nav_request"), Some(note.path.clone())));
+                                    }
+                                });
+                                for column in &view.columns {
+                                    row.col(|ui| {
+                                        let property = note
+                                            .properties
+                                            .iter()
+                                            .find(|p| p.key == column.key)
+                                            .cloned();
+                                        let property_type = match &property {
+                                            Some(p) => types.type_of(p),
+                                            None => types.get(&column.key).unwrap_or(properties::PropertyType::Text),
+                                        };
+                                        let property = property.unwrap_or_else(|| Property {
+                                            key: column.key.clone(),
+                                            value: property_type.empty_value(),
+                                        });
+                                        let id = table_id.with(&note.path).with(&column.key);
+                                        if let Some(value) = value_cell(ui, id, vault, &note.path, &property, property_type) {
+                                            edits.push((note.path.clone(), column.key.clone(), value));
+                                        }
+                                    });
+                                }
+                            });
+                        }
+                    }
+                });
+        });
+
+        for (path, key, value) in edits {
+            self.write_property(&path, &key, &value);
+        }
+        if new_view != view {
+            self.data.views[view_index] = new_view;
+            changed = true;
+        }
+        changed
+    }
+}
@@ -1,3 +1,4 @@
+use super::link_completion::{self, CompletionItem, CompletionKind};
 use egui::{
     Key,
     KeyboardShortcut,
@@ -19,6 +20,33 @@ pub struct EasyMarkEditor {
     pub shortcut_code: KeyboardShortcut,
     pub shortcut_italics: KeyboardShortcut,
 
+    /// Template to insert at the cursor on the next frame.
+    #[serde(skip)]
+    pub pending_insert: Option<crate::templates::Rendered>,
+    /// Char index where the cursor is placed on the next frame.
+    #[serde(skip)]
+    pub pending_cursor: Option<usize>,
+    /// Char range selected and scrolled into view on the next frame.
+    #[serde(skip)]
+    pub pending_selection: Option<std::ops::Range<usize>>,
+
+    /// Char index of the cursor in the last frame, if the editor has one.
+    #[serde(skip)]
+    pub cursor: Option<usize>,
+
+    /// Vault and file being edited, used by the `[[` autocompletion.
+    #[serde(skip)]
+    pub vault: String,
+    #[serde(skip)]
+    pub path: String,
+    #[serde(skip)]
+    completion_selected: usize,
+    // Posición del "[[" cuya ventana se cerró con Escape
+    #[serde(skip)]
+    completion_dismissed: Option<usize>,
+    #[serde(skip)]
+    completion_cache: Option<(String, Vec<CompletionItem>)>,
+
     #[serde(skip)]
     highlighter: super::MemoizedEasymarkHighlighter,
 }
@@ -42,6 +70,15 @@ impl Default for EasyMarkEditor {
             shortcut_bold: SHORTCUT_BOLD,
             shortcut_code: SHORTCUT_CODE,
             shortcut_italics: SHORTCUT_ITALICS,
+            pending_insert: None,
+            pending_cursor: None,
+            pending_selection: None,
+            cursor: None,
+            vault: String::new(),
+            path: String::new(),
+            completion_selected: 0,
+            completion_dismissed: None,
+            completion_cache: None,
             highlighter: Default::default(),
         }
     }
@@ -79,6 +116,63 @@ impl EasyMarkEditor {
         let cursor_index = TextEdit::load_state(ui.ctx(), editor_id)
             .and_then(|state| state.cursor.char_range())
             .map(|range| range.primary.index);
+        self.cursor = cursor_index;
+
+        let mut inserted = false;
+        if let Some(rendered) = self.pending_insert.take() {
+            let at = cursor_index.unwrap_or_else(|| self.code.chars().count());
+            let (code, cursor) = crate::templates::insert_into(&self.code, at, &rendered);
+            self.code = code;
+            self.pending_cursor = Some(cursor);
+            inserted = true;
+        }
+
+        // Autocompletado de [[enlaces]]: las teclas se consumen antes que el TextEdit
+        let has_focus = ui.memory(|m| m.has_focus(editor_id));
+        let completion = cursor_index
+            .filter(|_| has_focus && !inserted)
+            .and_then(|cursor| link_completion::link_context(&self.code, cursor).map(|c| (cursor, c)));
+        match &completion {
+            Some((_, context)) if self.completion_dismissed == Some(context.start) => {}
+            Some(_) => self.completion_dismissed = None,
+            None => {
+                self.completion_dismissed = None;
+                self.completion_selected = 0;
+            }
+        }
+        let completion = completion.filter(|(_, c)| self.completion_dismissed != Some(c.start));
+        let items = match &completion {
+            Some((_, context)) => self.completion_items(&context.query),
+            None => vec![],
+        };
+        if let Some((cursor, context)) = &completion {
+            if !items.is_empty() {
+                self.completion_selected = self.completion_selected.min(items.len() - 1);
+                let (down, up, escape, enter, tab) = ui.input_mut(|i| {
+                    (
+                        i.consume_key(Modifiers::NONE, Key::ArrowDown),
+                        i.consume_key(Modifiers::NONE, Key::ArrowUp),
+                        i.consume_key(Modifiers::NONE, Key::Escape),
+                        i.consume_key(Modifiers::NONE, Key::Enter),
+                        i.consume_key(Modifiers::NONE, Key::Tab),
+                    )
+                });
+                if down {
+                    self.completion_selected = (self.completion_selected + 1) % items.len();
+                }
+                if up {
+                    self.completion_selected = (self.completion_selected + items.len() - 1) % items.len();
+                }
+                if escape {
+                    self.completion_dismissed = Some(context.start);
+                }
+                if enter || tab {
+                    let item = items[self.completion_selected].clone();
+                    self.accept_completion(context, *cursor, &item, tab);
+                    inserted = true;
+                }
+            }
+        }
 
         let mut layouter = |ui: &egui::Ui, easymark: &dyn TextBuffer, wrap_width: f32| {
             let mut layout_job = self.highlighter.highlight(ui.style(), easymark.as_str(), cursor_index);
@@ -86,14 +180,72 @@ impl EasyMarkEditor {
             ui.fonts(|f| f.layout_job(layout_job))
         };
 
-        let mut response = ui.add(
-            egui::TextEdit::multiline(&mut self.code)
-                .id(editor_id)
-                .desired_width(f32::INFINITY)
-                .font(egui::TextStyle::Monospace) // for cursor height
-                .layouter(&mut layouter)
-                .frame(false),
-        );
+        let output = egui::TextEdit::multiline(&mut self.code)
+            .id(editor_id)
+            .desired_width(f32::INFINITY)
+            .font(egui::TextStyle::Monospace) // for cursor height
+            .layouter(&mut layouter)
+            .frame(false)
+            .show(ui);
+        let mut response = output.response;
+
+        if let Some((cursor, context)) = completion.filter(|_| !inserted && !items.is_empty()) {
+            let cursor_rect = output.galley.pos_from_cursor(egui::text::CCursor::new(cursor));
+            let pos = output.galley_pos + cursor_rect.left_bottom().to_vec2();
+            if let Some(i) = self.completion_popup(ui, editor_id, pos, &items) {
+                let item = items[i].clone();
+                self.accept_completion(&context, cursor, &item, false);
+                inserted = true;
+            }
+        }
+
+        if let Some(cursor) = self.pending_cursor.take() {
+            let mut state = TextEdit::load_state(ui.ctx(), response.id).unwrap_or_default();
+            let ccursor = egui::text::CCursor::new(cursor);
+            state.cursor.set_char_range(Some(CCursorRange::one(ccursor)));
+            state.store(ui.ctx(), response.id);
+            response.request_focus();
+            if inserted {
+                response.mark_changed();
+            }
+        }
+
+        if let Some(range) = self.pending_selection.take() {
+            let mut state = TextEdit::load_state(ui.ctx(), response.id).unwrap_or_default();
+            let (start, end) = (egui::text::CCursor::new(range.start), egui::text::CCursor::new(range.end));
+            state.cursor.set_char_range(Some(CCursorRange::two(start, end)));
+            state.store(ui.ctx(), response.id);
+            let rect = output.galley.pos_from_cursor(start).union(output.galley.pos_from_cursor(end));
+            ui.scroll_to_rect(rect.translate(output.galley_pos.to_vec2()), Some(egui::Align::Center));
+            response.request_focus();
+        }
+
+        // Ctrl+clic sobre un [[enlace]] lo abre
+        if response.clicked() && ui.input(|i| i.modifiers.command) {
+            let clicked_at = output.cursor_range.map(|r| r.primary.index);
+            if let Some(target) = clicked_at.and_then(|cursor| link_at(&self.code, cursor)) {
+                crate::anchors::follow_link(ui.ctx(), &self.vault, &self.path, &target);
+            }
+        }
+
+        let mut changed_by_menu = false;
+        response.context_menu(|ui| {
+            if ui.button("Copy link to heading/block").clicked() {
+                if let Some(line) = self.cursor_line() {
+                    if let Some((link, updated)) = crate::anchors::link_to_line(&self.code, line, &self.path) {
+                        ui.ctx().copy_text(link);
+                        if let Some(updated) = updated {
+                            self.code = updated;
+                            changed_by_menu = true;
+                        }
+                    }
+                }
+                ui.close();
+            }
+        });
+        if changed_by_menu {
+            response.mark_changed();
+        }
 
         if let Some(mut state) = TextEdit::load_state(ui.ctx(), response.id) {
             if let Some(mut ccursor_range) = state.cursor.char_range() {
@@ -107,6 +259,95 @@ impl EasyMarkEditor {
         }
         response
     }
+
+    /// Line (starting at 0) where the cursor is.
+    pub fn cursor_line(&self) -> Option<usize> {
+        let cursor = self.cursor?;
+        Some(self.code.chars().take(cursor).filter(|c| *c == '\n').count())
+    }
+
+    fn completion_items(&mut self, query: &str) -> Vec<CompletionItem> {
+        if let Some((cached, items)) = &self.completion_cache {
+            if cached == query {
+                return items.clone();
+            }
+        }
+        let items = link_completion::suggestions(&self.vault, &self.path, query);
+        self.completion_cache = Some((query.to_string(), items.clone()));
+        items
+    }
+
+    fn accept_completion(&mut self, context: &link_completion::LinkContext, cursor: usize, item: &CompletionItem, edit_display: bool) {
+        if item.kind == CompletionKind::Create {
+            link_completion::create_note(&self.vault, &item.target);
+        }
+        let (code, cursor) = link_completion::apply(&self.code, context, cursor, item, edit_display);
+        self.code = code;
+        self.pending_cursor = Some(cursor);
+        self.completion_selected = 0;
+        self.completion_cache = None;
+    }
+
+    fn completion_popup(&mut self, ui: &Ui, editor_id: egui::Id, pos: egui::Pos2, items: &[CompletionItem]) -> Option<usize> {
+        let mut clicked = None;
+        egui::Area::new(editor_id.with("link_completion"))
+            .order(egui::Order::Foreground)
+            .fixed_pos(pos)
+            .show(ui.ctx(), |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.set_max_width(360.0);
+                    egui::ScrollArea::vertical().max_height(240.0).show(ui, |ui| {
+                        for (i, item) in items.iter().enumerate() {
+                            let icon = match item.kind {
+                                CompletionKind::Note => "📄",
+                                CompletionKind::Alias => "↪",
+                                CompletionKind::Heading => "#",
+                                CompletionKind::Block => "^",
+                                CompletionKind::Create => "➕",
+                            };
+                            let selected = i == self.completion_selected;
+                            let response = ui.horizontal(|ui| {
+                                let label = ui.selectable_label(selected, format!("{} {}", icon, item.label));
+                                if !item.detail.is_empty() {
+                                    ui.weak(&item.detail);
+                                }
+                                label
+                            }).inner;
+                            if selected {
+                                response.scroll_to_me(None);
+                            }
+                            if response.clicked() {
+                                clicked = Some(i);
+                            }
+                        }
+                    });
+                    ui.separator();
+                    ui.weak("Enter: insert · Tab: add display text · Esc: close");
+                });
+            });
+        clicked
+    }
+}
+
+/// Target of the `[[link]]` around the char index `cursor`.
+fn link_at(code: &str, cursor: usize) -> Option<String> {
+    let chars: Vec<char> = code.chars().collect();
+    let cursor = cursor.min(chars.len());
+    let line_start = chars[..cursor].iter().rposition(|c| *c == '\n').map(|i| i + 1).unwrap_or(0);
+    let line_end = chars[cursor..].iter().position(|c| *c == '\n').map(|i| cursor + i).unwrap_or(chars.len());
+    let line: String = chars[line_start..line_end].iter().collect();
+    let col = cursor - line_start;
+
+    let mut search = 0;
+    while let Some(open) = line[search..].find("[[").map(|i| search + i) {
+        let close = line[open..].find("]]").map(|i| open + i)?;
+        let (open_col, close_col) = (line[..open].chars().count(), line[..close].chars().count() + 2);
+        if (open_col..=close_col).contains(&col) {
+            return Some(line[open + 2..close].to_string());
+        }
+        search = close + 2;
+    }
+    None
 }
 
 fn shortcut_ui(ui: &mut egui::Ui, shortcut: &mut KeyboardShortcut) {
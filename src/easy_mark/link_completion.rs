@@ -0,0 +1,349 @@
+//! `[[` autocompletion for the editor.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::server::sync;
+use crate::vault_index;
+
+// Máximo de sugerencias mostradas
+const MAX_ITEMS: usize = 20;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum CompletionKind {
+    Note,
+    Alias,
+    Heading,
+    Block,
+    Create,
+}
+
+#[derive(Clone, Debug)]
+pub struct CompletionItem {
+    pub kind: CompletionKind,
+    /// Text shown in the popup.
+    pub label: String,
+    /// Secondary text (folder, note of the alias...).
+    pub detail: String,
+    /// Link target inserted between `[[` and `]]`.
+    pub target: String,
+    /// Display text inserted after `|`, for aliases.
+    pub display: Option<String>,
+}
+
+/// The `[[...` being typed before the cursor.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LinkContext {
+    /// Char index right after `[[`.
+    pub start: usize,
+    /// What was typed after `[[`.
+    pub query: String,
+}
+
+/// Finds an unclosed `[[` on the cursor line.
+pub fn link_context(code: &str, cursor: usize) -> Option<LinkContext> {
+    let before: Vec<char> = code.chars().take(cursor).collect();
+    let mut i = before.len();
+    while i >= 2 {
+        let c = before[i - 1];
+        if c == '\n' || (c == ']' && before[i - 2] == ']') {
+            return None;
+        }
+        if c == '[' && before[i - 2] == '[' {
+            let query: String = before[i..].iter().collect();
+            if query.contains('|') {
+                return None;
+            }
+            return Some(LinkContext { start: i, query });
+        }
+        i -= 1;
+    }
+    None
+}
+
+/// Subsequence match; higher is better. Consecutive characters and matches at
+/// the start of words score more.
+pub fn fuzzy_score(query: &str, candidate: &str) -> Option<i32> {
+    if query.is_empty() {
+        return Some(0);
+    }
+    let candidate: Vec<char> = candidate.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut pos = 0;
+    let mut prev_match: Option<usize> = None;
+    for q in query.to_lowercase().chars() {
+        let found = (pos..candidate.len()).find(|&i| candidate[i] == q)?;
+        score += 1;
+        if prev_match.map(|p| p + 1 == found).unwrap_or(false) {
+            score += 5;
+        }
+        if found == 0 || !candidate[found - 1].is_alphanumeric() {
+            score += 3;
+        }
+        score -= (found - pos).min(5) as i32;
+        prev_match = Some(found);
+        pos = found + 1;
+    }
+    // A igual puntuación, los nombres más cortos primero
+    Some(score * 100 - candidate.len() as i32)
+}
+
+/// Suggestions for `query` (the text after `[[`).
+pub fn suggestions(vault: &str, current_file: &str, query: &str) -> Vec<CompletionItem> {
+    if vault.is_empty() {
+        return vec![];
+    }
+    if let Some(pos) = query.find(['#', '^']) {
+        let (note, rest) = query.split_at(pos);
+        return section_suggestions(vault, current_file, note, rest);
+    }
+
+    vault_index::with_index(vault, |index| {
+        let mut name_count: HashMap<String, usize> = HashMap::new();
+        for file in index.files() {
+            *name_count.entry(link_name(&file.rel_path, file.note.is_some()).to_lowercase()).or_default() += 1;
+        }
+
+        let mut scored: Vec<(i32, CompletionItem)> = vec![];
+        for file in index.files() {
+            let name = link_name(&file.rel_path, file.note.is_some());
+            // Con nombres repetidos se usa la ruta para que el enlace no sea ambiguo
+            let target = if name_count.get(&name.to_lowercase()).copied().unwrap_or(0) > 1 {
+                file.rel_path.trim_end_matches(".md").to_string()
+            } else {
+                name.clone()
+            };
+            let folder = Path::new(&file.rel_path)
+                .parent()
+                .and_then(|p| p.to_str())
+                .unwrap_or("")
+                .to_string();
+            if let Some(score) = fuzzy_score(query, &name).or_else(|| fuzzy_score(query, &file.rel_path).map(|s| s - 1000)) {
+                scored.push((score, CompletionItem {
+                    kind: CompletionKind::Note,
+                    label: name.clone(),
+                    detail: folder.clone(),
+                    target: target.clone(),
+                    display: None,
+                }));
+            }
+            for alias in file.note.iter().flat_map(|n| n.aliases.iter()) {
+                if let Some(score) = fuzzy_score(query, alias) {
+                    scored.push((score - 50, CompletionItem {
+                        kind: CompletionKind::Alias,
+                        label: alias.clone(),
+                        detail: name.clone(),
+                        target: target.clone(),
+                        display: Some(alias.clone()),
+                    }));
+                }
+            }
+        }
+        scored.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.label.cmp(&b.1.label)));
+
+        let mut items: Vec<CompletionItem> = scored.into_iter().take(MAX_ITEMS).map(|(_, i)| i).collect();
+        let query = query.trim();
+        let exists = items.iter().any(|i| i.label.eq_ignore_ascii_case(query));
+        if !query.is_empty() && !exists {
+            items.push(CompletionItem {
+                kind: CompletionKind::Create,
+                label: format!("Create new note \"{}\"", query),
+                detail: String::new(),
+                target: query.to_string(),
+                display: None,
+            });
+        }
+        items
+    })
+}
+
+fn section_suggestions(vault: &str, current_file: &str, note: &str, rest: &str) -> Vec<CompletionItem> {
+    // Obsidian escribe los bloques como "#^id"; "^id" también se acepta
+    let block_prefix = if rest.starts_with("#^") { "#^" } else { "^" };
+    let is_block = rest.starts_with('^') || rest.starts_with("#^");
+    let query = if is_block { &rest[block_prefix.len()..] } else { rest[1..].trim_start_matches('#') };
+    vault_index::with_index(vault, |index| {
+        let path = if note.trim().is_empty() {
+            current_file.to_string()
+        } else {
+            match index.resolve(current_file, note) {
+                Some(path) => path,
+                None => return vec![],
+            }
+        };
+        let Some(meta) = index.get(&path).and_then(|f| f.note.as_ref()) else {
+            return vec![];
+        };
+
+        let mut scored: Vec<(i32, CompletionItem)> = if is_block {
+            meta.block_ids
+                .iter()
+                .filter_map(|id| {
+                    let score = fuzzy_score(query, id)?;
+                    Some((score, CompletionItem {
+                        kind: CompletionKind::Block,
+                        label: format!("^{}", id),
+                        detail: String::new(),
+                        target: format!("{}{}{}", note, block_prefix, id),
+                        display: None,
+                    }))
+                })
+                .collect()
+        } else {
+            meta.headings
+                .iter()
+                .enumerate()
+                .filter_map(|(i, (level, text))| {
+                    let score = fuzzy_score(query, text)?;
+                    // Sin texto se conserva el orden del documento
+                    let score = if query.is_empty() { -(i as i32) } else { score };
+                    Some((score, CompletionItem {
+                        kind: CompletionKind::Heading,
+                        label: text.clone(),
+                        detail: "#".repeat(*level),
+                        target: format!("{}#{}", note, text),
+                        display: None,
+                    }))
+                })
+                .collect()
+        };
+        scored.sort_by(|a, b| b.0.cmp(&a.0));
+        scored.into_iter().take(MAX_ITEMS).map(|(_, i)| i).collect()
+    })
+}
+
+// Obsidian omite la extensión solo en las notas
+fn link_name(rel_path: &str, is_note: bool) -> String {
+    let file_name = Path::new(rel_path)
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or(rel_path);
+    if is_note {
+        file_name.strip_suffix(".md").unwrap_or(file_name).to_string()
+    } else {
+        file_name.to_string()
+    }
+}
+
+/// Replaces the typed `[[query` with the chosen link. With `edit_display`
+/// the cursor is left after `|` to type the display text. Returns the new
+/// text and the char index for the cursor.
+pub fn apply(code: &str, context: &LinkContext, cursor: usize, item: &CompletionItem, edit_display: bool) -> (String, usize) {
+    let chars: Vec<char> = code.chars().collect();
+    let before: String = chars[..context.start].iter().collect();
+    let mut after: String = chars[cursor.min(chars.len())..].iter().collect();
+    // Si el editor ya cerró el enlace no se duplica el "]]"
+    if after.starts_with("]]") {
+        after = after[2..].to_string();
+    }
+
+    let mut link = item.target.clone();
+    if let Some(display) = &item.display {
+        link.push('|');
+        link.push_str(display);
+    } else if edit_display {
+        link.push('|');
+    }
+    let cursor = if edit_display {
+        context.start + link.chars().count()
+    } else {
+        context.start + link.chars().count() + 2
+    };
+    (format!("{}{}]]{}", before, link, after), cursor)
+}
+
+/// Creates the note of a "Create new note" item in the vault root (or the
+/// folder written in the link) if it doesn't exist yet.
+pub fn create_note(vault: &str, target: &str) -> Option<String> {
+    let rel = target.trim_start_matches('/');
+    // Un enlace con ".." no puede crear notas fuera del vault
+    if !sync::is_safe_path(rel) {
+        println!("Ruta fuera del vault, no se crea la nota: {}", target);
+        return None;
+    }
+    let mut path = Path::new(vault).join(rel);
+    if path.extension().map(|e| e != "md").unwrap_or(true) {
+        path = Path::new(vault).join(format!("{}.md", rel));
+    }
+    if !path.exists() {
+        if let Some(parent) = path.parent() {
+            if let Err(e) = std::fs::create_dir_all(parent) {
+                println!("Error creando {}: {}", parent.display(), e);
+                return None;
+            }
+        }
+        if let Err(e) = std::fs::write(&path, "") {
+            println!("Error creando {}: {}", path.display(), e);
+            return None;
+        }
+    }
+    let path = path.to_str()?.to_string();
+    vault_index::update_file(vault, &path);
+    Some(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(target: &str, display: Option<&str>) -> CompletionItem {
+        CompletionItem {
+            kind: CompletionKind::Note,
+            label: target.to_string(),
+            detail: String::new(),
+            target: target.to_string(),
+            display: display.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn finds_the_link_being_typed() {
+        let code = "See [[Not";
+        assert_eq!(
+            link_context(code, code.chars().count()),
+            Some(LinkContext { start: 6, query: "Not".to_string() })
+        );
+        assert_eq!(link_context("[[", 2), Some(LinkContext { start: 2, query: String::new() }));
+        assert_eq!(link_context("ñ [[é", 5), Some(LinkContext { start: 4, query: "é".to_string() }));
+        // Enlaces cerrados, con texto visible o en otra línea
+        assert_eq!(link_context("[[Note]] more", 13), None);
+        assert_eq!(link_context("[[Note|sho", 10), None);
+        assert_eq!(link_context("[[Note\nnext", 11), None);
+        assert_eq!(link_context("no link", 7), None);
+    }
+
+    #[test]
+    fn fuzzy_scores_prefer_tight_matches() {
+        assert_eq!(fuzzy_score("", "anything"), Some(0));
+        assert_eq!(fuzzy_score("xyz", "Note"), None);
+        assert_eq!(fuzzy_score("ba", "abc"), None);
+        assert!(fuzzy_score("NOT", "note").is_some());
+        // Seguidas y al principio de palabra puntúan más
+        assert!(fuzzy_score("note", "Note") > fuzzy_score("note", "n-o-t-e"));
+        assert!(fuzzy_score("pl", "Project log") > fuzzy_score("pl", "Apple"));
+        // A igual coincidencia, el nombre más corto primero
+        assert!(fuzzy_score("note", "Note") > fuzzy_score("note", "Notes"));
+    }
+
+    #[test]
+    fn apply_inserts_the_link() {
+        let code = "See [[No";
+        let context = link_context(code, 8).unwrap();
+        assert_eq!(apply(code, &context, 8, &item("Note", None), false), ("See [[Note]]".to_string(), 12));
+        assert_eq!(apply(code, &context, 8, &item("Note", None), true), ("See [[Note|]]".to_string(), 11));
+        assert_eq!(
+            apply(code, &context, 8, &item("Note", Some("alias")), false),
+            ("See [[Note|alias]]".to_string(), 18)
+        );
+        // El "]]" que ya cerró el editor no se duplica
+        let code = "See [[No]] end";
+        assert_eq!(apply(code, &context, 8, &item("Note", None), false), ("See [[Note]] end".to_string(), 12));
+    }
+
+    #[test]
+    fn notes_are_not_created_outside_the_vault() {
+        assert_eq!(create_note("/vault", "../outside"), None);
+        assert_eq!(create_note("/vault", "folder/../../outside"), None);
+        assert_eq!(create_note("/vault", "/"), None);
+    }
+}
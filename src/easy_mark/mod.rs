@@ -4,6 +4,7 @@ mod easy_mark_editor;
 mod easy_mark_highlighter;
 pub mod easy_mark_parser;
 mod easy_mark_viewer;
+mod link_completion;
 
 pub use easy_mark_editor::EasyMarkEditor;
 pub use easy_mark_highlighter::MemoizedEasymarkHighlighter;
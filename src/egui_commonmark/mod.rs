@@ -232,6 +232,23 @@ impl<'f> CommonMarkViewer<'f> {
         self
     }
 
+    pub fn render_embed_fn(mut self, func: Option<&'f crate::egui_commonmark_backend::misc::RenderEmbedFn>) -> Self {
+        self.options.render_embed_fn = func;
+        self
+    }
+
+    pub fn render_query_fn(mut self, func: Option<&'f crate::egui_commonmark_backend::misc::RenderQueryFn>) -> Self {
+        self.options.render_query_fn = func;
+        self
+    }
+
+    /// Highlights the blocks in `range` of the source text, scrolling to
+    /// them if `scroll` is set.
+    pub fn highlight(mut self, range: Option<(std::ops::Range<usize>, bool)>) -> Self {
+        self.options.highlight = range;
+        self
+    }
+
     /// Shows rendered markdown
     pub fn show(
         self,
@@ -252,6 +269,29 @@ impl<'f> CommonMarkViewer<'f> {
         response
     }
 
+    /// Shows rendered markdown with checkboxes that can be clicked, without
+    /// touching the text. Returns the source span of each clicked checkbox
+    /// and its new state so the caller can write it where it belongs.
+    pub fn show_checkboxes(
+        mut self,
+        ui: &mut egui::Ui,
+        cache: &mut CommonMarkCache,
+        text: &str,
+    ) -> (egui::InnerResponse<()>, Vec<(std::ops::Range<usize>, bool)>) {
+        self.options.mutable = true;
+        crate::egui_commonmark_backend::prepare_show(cache, ui.ctx());
+
+        let (response, checkmark_events) = parsers::pulldown::CommonMarkViewerInternal::new().show(
+            ui,
+            cache,
+            &self.options,
+            text,
+            None,
+        );
+        let clicked = checkmark_events.into_iter().map(|ev| (ev.span, ev.checked)).collect();
+        (response, clicked)
+    }
+
     /// Shows rendered markdown, and allows the rendered ui to mutate the source text.
     ///
     /// The only currently implemented mutation is allowing checkboxes to be toggled through the ui.
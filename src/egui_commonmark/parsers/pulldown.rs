@@ -133,6 +133,12 @@ impl CommonMarkViewerInternal {
             let height = ui.text_style_height(&TextStyle::Body);
             ui.set_row_height(height);
 
+            // El resaltado se pinta debajo del texto
+            let highlight_shape = options.highlight.as_ref().map(|_| ui.painter().add(egui::Shape::Noop));
+            let mut highlight_top: Option<f32> = None;
+            let mut highlight_bottom: Option<f32> = None;
+            cache.block_tops.clear();
+
             let mut events = pulldown_cmark::Parser::new_ext(
                 text,
                 parser_options_math(options.math_fn.is_some()),
@@ -146,6 +152,24 @@ impl CommonMarkViewerInternal {
                 let is_element_end = matches!(e, pulldown_cmark::Event::End(_));
                 let should_add_split_point = self.list.is_inside_a_list() && is_element_end;
 
+                if let pulldown_cmark::Event::Start(
+                    pulldown_cmark::Tag::Heading { .. }
+                    | pulldown_cmark::Tag::Paragraph
+                    | pulldown_cmark::Tag::Item
+                    | pulldown_cmark::Tag::CodeBlock(_)
+                    | pulldown_cmark::Tag::Table(_),
+                ) = &e
+                {
+                    cache.block_tops.push((src_span.start, start_position.y));
+                }
+                if let Some((range, _)) = &options.highlight {
+                    if highlight_top.is_none() && src_span.start >= range.start {
+                        highlight_top = Some(start_position.y);
+                    } else if highlight_top.is_some() && highlight_bottom.is_none() && src_span.start >= range.end {
+                        highlight_bottom = Some(start_position.y);
+                    }
+                }
+
                 if events.peek().is_none() {
                     self.line.should_end_newline_forced = false;
                 }
@@ -179,6 +203,16 @@ impl CommonMarkViewerInternal {
                 scroll_cache(cache, &source_id).page_size =
                     Some(ui.next_widget_position().to_vec2());
             }
+
+            if let (Some(shape), Some(top), Some((_, scroll))) = (highlight_shape, highlight_top, &options.highlight) {
+                let bottom = highlight_bottom.unwrap_or_else(|| ui.min_rect().bottom());
+                let rect = egui::Rect::from_x_y_ranges(ui.max_rect().x_range(), top..=bottom.max(top + height));
+                let color = ui.visuals().selection.bg_fill.gamma_multiply(0.35);
+                ui.painter().set(shape, egui::Shape::rect_filled(rect.expand(2.0), 3.0, color));
+                if *scroll {
+                    ui.scroll_to_rect(rect, Some(egui::Align::TOP));
+                }
+            }
         });
 
         (re, std::mem::take(&mut self.checkbox_events))
@@ -828,7 +862,13 @@ impl CommonMarkViewerInternal {
             }
             pulldown_cmark::TagEnd::Image => {
                 if let Some(image) = self.image.take() {
-                    image.end(ui, options);
+                    let embedded = options
+                        .render_embed_fn
+                        .map(|embed_fn| embed_fn(ui, cache, &image.source))
+                        .unwrap_or(false);
+                    if !embedded {
+                        image.end(ui, options);
+                    }
                 }
             }
             pulldown_cmark::TagEnd::HtmlBlock => {
@@ -45,6 +45,10 @@ const DEFAULT_THEME_DARK: &str = "base16-ocean.dark";
 
 pub type ProcessLinkFn = dyn Fn(&mut Ui, &str, egui::text::LayoutJob) -> bool;
 pub type RenderExcalidrawFn = dyn Fn(&mut Ui, &str, Option<f32>);
+/// Renders a `![[target]]` embed. Returns false to show it as an image.
+pub type RenderEmbedFn = dyn Fn(&mut Ui, &mut CommonMarkCache, &str) -> bool;
+/// Renders the result of a ` ```query ` block from its source.
+pub type RenderQueryFn = dyn Fn(&mut Ui, &str);
 
 pub struct CommonMarkOptions<'f> {
     pub indentation_spaces: usize,
@@ -64,6 +68,11 @@ pub struct CommonMarkOptions<'f> {
     pub html_fn: Option<&'f crate::egui_commonmark_backend::RenderHtmlFn>,
     pub process_link: Option<&'f ProcessLinkFn>,
     pub render_excalidraw_fn: Option<&'f RenderExcalidrawFn>,
+    pub render_embed_fn: Option<&'f RenderEmbedFn>,
+    pub render_query_fn: Option<&'f RenderQueryFn>,
+    /// Source range drawn with a highlighted background. With `true` it's
+    /// also scrolled into view.
+    pub highlight: Option<(std::ops::Range<usize>, bool)>,
 }
 
 impl std::fmt::Debug for CommonMarkOptions<'_> {
@@ -88,6 +97,9 @@ impl std::fmt::Debug for CommonMarkOptions<'_> {
             .field("mutable", &self.mutable)
             .field("process_link", &self.process_link.map(|_| "ProcessLinkFn"))
             .field("render_excalidraw_fn", &self.render_excalidraw_fn.map(|_| "RenderExcalidrawFn"))
+            .field("render_embed_fn", &self.render_embed_fn.map(|_| "RenderEmbedFn"))
+            .field("render_query_fn", &self.render_query_fn.map(|_| "RenderQueryFn"))
+            .field("highlight", &self.highlight)
             .finish()
     }
 }
@@ -111,6 +123,9 @@ impl Default for CommonMarkOptions<'_> {
             html_fn: None,
             process_link: None,
             render_excalidraw_fn: None,
+            render_embed_fn: None,
+            render_query_fn: None,
+            highlight: None,
         }
     }
 }
@@ -262,12 +277,15 @@ impl Link {
 
 pub struct Image {
     pub uri: String,
+    /// Destination as written, before adding the uri scheme.
+    pub source: String,
     pub alt_text: Vec<RichText>,
 }
 
 impl Image {
     // FIXME: string conversion
     pub fn new(uri: &str, options: &CommonMarkOptions) -> Self {
+        let source = uri.to_string();
         let uri = if uri.starts_with("![[") && uri.ends_with("]]") {
             uri[3..uri.len() - 2].to_string()
         } else {
@@ -284,6 +302,7 @@ impl Image {
 
         Self {
             uri,
+            source,
             alt_text: Vec::new(),
         }
     }
@@ -319,6 +338,12 @@ impl CodeBlock {
         max_width: f32,
     ) {
         if let Some(lang) = &self.lang {
+            if lang == "query" {
+                if let Some(render_query) = options.render_query_fn {
+                    render_query(ui, &self.content);
+                    return;
+                }
+            }
             if lang == "mermaid" || lang == "vega" || lang == "vega-lite" {
                 let cache_map = if lang == "mermaid" {
                     &mut cache.mermaid_cache
@@ -636,6 +661,9 @@ pub struct CommonMarkCache {
     pub mermaid_cache: HashMap<String, String>,
     pub vega_cache: HashMap<String, String>,
     pub latex_cache: HashMap<String, String>,
+    /// Source offset and top (in screen coordinates) of every block drawn by
+    /// the last `show`.
+    pub block_tops: Vec<(usize, f32)>,
 }
 
 #[allow(clippy::derivable_impls)]
@@ -652,6 +680,7 @@ impl Default for CommonMarkCache {
             mermaid_cache: HashMap::new(),
             vega_cache: HashMap::new(),
             latex_cache: HashMap::new(),
+            block_tops: Vec::new(),
         }
     }
 }
@@ -1,10 +1,7 @@
-use json::{object, JsonValue};
 use std::fs;
 use std::fs::File;
 use std::io::Read;
-use std::io::Write;
 use std::path::Path;
-use walkdir::WalkDir;
 
 pub fn delete_file(trgt: &str) -> bool {
     //let path_to_delete = trgt.to_string();
@@ -49,39 +46,9 @@ pub fn copy_file(origin: &str, dest: &str) {
     }
 }
 
+/// Bookmarks `file` (an absolute path) in `.obsidian/bookmarks.json`.
 pub fn add_starred(vault: &str, file: &str) {
-    let stared_path = format!("{}/.obsidian/starred.json", vault);
-    let nw_json = object! {
-        "type":"file",
-        "title":Path::new(file).file_stem().unwrap().to_str().unwrap(),
-        "path":"testi" //TODO Cambiar esto al valor correcto
-    };
-    if Path::new(&stared_path).exists() {
-        let contents =
-            fs::read_to_string(&stared_path).expect("Should have been able to read the file");
-        let mut parsed = json::parse(&contents).unwrap();
-        let json_arr: &mut JsonValue = &mut parsed["items"];
-        json_arr.push(nw_json).unwrap();
-        let mut f = std::fs::OpenOptions::new()
-            .write(true)
-            .truncate(true)
-            .open(stared_path)
-            .unwrap();
-        f.write_all(parsed.pretty(2).as_bytes()).unwrap();
-        f.flush().unwrap();
-    } else {
-        let n_file = File::create(stared_path);
-        match n_file {
-            Ok(mut w) => {
-                let text = format!("{{ items:[{}] }}", nw_json.dump());
-                match w.write(text.as_bytes()) {
-                    Ok(_) => {}
-                    Err(r) => println!("Error Star {}: {}", file, r),
-                }
-            }
-            Err(r) => println!("Error Star {}: {}", file, r),
-        }
-    }
+    crate::bookmarks::add(vault, crate::bookmarks::Bookmark::file(vault, file, None));
 }
 
 pub fn read_file(file_name: &str) -> String {
@@ -99,82 +66,6 @@ pub fn read_file(file_name: &str) -> String {
     contents
 }
 
-pub fn contents(contents: &String) -> (String, String) {
-    let metadata = String::new();
-    if contents.starts_with("---") {
-        let test = contents.splitn(3, "---");
-        let test: Vec<&str> = test.collect();
-        return (test[2].to_string(), test[1].to_string());
-    } else {
-        return (contents.to_string(), metadata);
-    }
-}
-
 pub fn resolve_path(vault: &str, current_file_path: &str, target: &str) -> Option<String> {
-    let clean_target = target.trim();
-    if clean_target.is_empty() {
-        return None;
-    }
-
-    // 1. Ruta absoluta dentro del vault (si empieza con /)
-    if clean_target.starts_with('/') {
-        let p = format!("{}{}", vault, clean_target);
-        if Path::new(&p).exists() {
-            return Some(p);
-        }
-    }
-
-    // 2. Ruta relativa al archivo actual
-    if let Some(current_dir) = Path::new(current_file_path).parent() {
-        let joined = current_dir.join(clean_target);
-        if joined.exists() && joined.is_file() {
-            return Some(joined.to_string_lossy().to_string());
-        }
-
-        // Probar con extensiones comunes relativas
-        for ext in &[".md", ".excalidraw.md"] {
-            if !clean_target.to_lowercase().ends_with(ext) {
-                let joined_ext = current_dir.join(format!("{}{}", clean_target, ext));
-                if joined_ext.exists() && joined_ext.is_file() {
-                    return Some(joined_ext.to_string_lossy().to_string());
-                }
-            }
-        }
-    }
-
-    // 3. Búsqueda global en el vault (por nombre de archivo, case-insensitive)
-    let mut targets = vec![clean_target.to_lowercase()];
-    if !clean_target.to_lowercase().ends_with(".md") {
-        targets.push(format!("{}.md", clean_target.to_lowercase()));
-    }
-    if !clean_target.to_lowercase().ends_with(".excalidraw.md") {
-        targets.push(format!("{}.excalidraw.md", clean_target.to_lowercase()));
-    }
-
-    // Normalizar targets para comparación (solo nombre de archivo en minúsculas)
-    let targets_names: Vec<String> = targets
-        .iter()
-        .map(|t| {
-            Path::new(t)
-                .file_name()
-                .unwrap_or_default()
-                .to_string_lossy()
-                .to_string()
-        })
-        .collect();
-
-    for entry in WalkDir::new(vault).into_iter().filter_map(|e| e.ok()) {
-        if entry.file_type().is_file() {
-            if let Some(fname) = entry.file_name().to_str() {
-                let fname_lower = fname.to_lowercase();
-                for t in &targets_names {
-                    if fname_lower == *t {
-                        return Some(entry.path().to_string_lossy().to_string());
-                    }
-                }
-            }
-        }
-    }
-
-    None
+    crate::vault_index::with_index(vault, |index| index.resolve(current_file_path, target))
 }
@@ -37,7 +37,7 @@ impl MarmolPoint {
     }
 }
 
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, PartialEq, Debug)]
 pub enum MatchType {
     Filename,
     Tag,
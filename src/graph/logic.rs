@@ -1,5 +1,4 @@
 use super::data::{CustomGroup, Graph, MarmolPoint, MatchType};
-use crate::files;
 use egui::{Color32, Vec2};
 use std::collections::hash_map::DefaultHasher;
 use std::collections::HashMap;
@@ -7,7 +6,6 @@ use std::collections::HashSet;
 use std::fs;
 use std::hash::{Hash, Hasher};
 use std::path::Path;
-use yaml_rust::YamlLoader;
 
 impl Graph {
     pub fn new(vault: &str, ctx: &egui::Context) -> Self {
@@ -95,37 +93,9 @@ impl Graph {
         if val.is_empty() {
             return false;
         }
-        let search = val.to_lowercase();
-
-        match m_type {
-            MatchType::Tag => point
-                .tags
-                .iter()
-                .any(|t| t.to_lowercase().contains(&search)),
-            MatchType::Filename => point.text.to_lowercase().contains(&search),
-            MatchType::Path => point.rel_path.to_lowercase().contains(&search),
-            MatchType::Content => {
-                if point.is_attachment || point.is_tag {
-                    return false;
-                }
-                if let Ok(c) = fs::read_to_string(&point.abs_path) {
-                    c.to_lowercase().contains(&search)
-                } else {
-                    false
-                }
-            }
-            MatchType::Section => {
-                if point.is_attachment || point.is_tag {
-                    return false;
-                }
-                if let Ok(c) = fs::read_to_string(&point.abs_path) {
-                    c.lines()
-                        .any(|l| l.trim().starts_with('#') && l.to_lowercase().contains(&search))
-                } else {
-                    false
-                }
-            }
-        }
+        match_point(m_type, &val.to_lowercase(), point, || {
+            fs::read_to_string(&point.abs_path).ok()
+        })
     }
 
     pub fn update_vault(&mut self, vault: &Path) {
@@ -507,6 +477,60 @@ impl Graph {
     }
 }
 
+/// Matching shared by the graph groups and the search panel operators.
+/// `search` must already be lowercase; `content` is only read for the
+/// `Content` and `Section` match types.
+pub fn match_point<F: FnOnce() -> Option<String>>(
+    m_type: &MatchType,
+    search: &str,
+    point: &MarmolPoint,
+    content: F,
+) -> bool {
+    match m_type {
+        MatchType::Tag => {
+            // Un tag encuentra el mismo tag y sus subtags: #project encuentra #project/web
+            let search = search.trim_start_matches('#');
+            point.tags.iter().any(|t| {
+                let t = t.to_lowercase();
+                t == search || t.starts_with(&format!("{}/", search))
+            })
+        }
+        MatchType::Filename => point.text.to_lowercase().contains(search),
+        MatchType::Path => point.rel_path.to_lowercase().contains(search),
+        MatchType::Content => {
+            if point.is_attachment || point.is_tag {
+                return false;
+            }
+            content()
+                .map(|c| c.to_lowercase().contains(search))
+                .unwrap_or(false)
+        }
+        MatchType::Section => {
+            if point.is_attachment || point.is_tag {
+                return false;
+            }
+            content()
+                .map(|c| {
+                    c.lines()
+                        .any(|l| l.trim().starts_with('#') && l.to_lowercase().contains(search))
+                })
+                .unwrap_or(false)
+        }
+    }
+}
+
+/// Targets of the `[[wikilinks]]` in `content`, without the `|alias` part.
+pub fn extract_links(content: &str) -> Vec<String> {
+    let mut links_vec = vec![];
+    for part in content.split("[[").skip(1) {
+        if let Some(end) = part.find("]]") {
+            let target = part[..end].split('|').next().unwrap_or(&part[..end]);
+            links_vec.push(target.trim().to_string());
+        }
+    }
+    links_vec
+}
+
 // ---------------- Helpers Internos ----------------
 
 fn mix_colors(colors: &[Color32]) -> Color32 {
@@ -571,85 +595,46 @@ fn get_data(dir: &Path, marmol_vec: &mut Vec<MarmolPoint>, total_entries: &mut i
     if !Path::new(vault).exists() {
         return;
     }
-    if let Ok(entries) = fs::read_dir(dir) {
-        for entry in entries {
-            let entry = entry.unwrap();
-            let path = entry.path();
-            if path.is_dir() {
-                get_data(&path, marmol_vec, total_entries, vault);
-            } else {
-                if let Some(ext) = path
-                    .extension()
-                    .and_then(|s| s.to_str())
-                    .map(|s| s.to_lowercase())
-                {
-                    let abs_path = path.to_str().unwrap().to_string();
-                    let rel_path = abs_path.replace(vault, "");
-                    let node_name = path
-                        .file_stem()
-                        .and_then(|s| s.to_str())
-                        .unwrap_or("Unknown")
-                        .to_string();
-
-                    if ext == "md" {
-                        *total_entries += 1;
-                        let raw_content = files::read_file(&abs_path);
-                        let mut tag_vecs = vec![];
-                        if let Some(yaml_str) = extract_frontmatter(&raw_content) {
-                            if let Ok(docs) = YamlLoader::load_from_str(&yaml_str) {
-                                if !docs.is_empty() {
-                                    if let Some(tags) =
-                                        docs[0]["tags"].as_vec().or(docs[0]["Tags"].as_vec())
-                                    {
-                                        for tag in tags {
-                                            if let Some(s) = tag.as_str() {
-                                                tag_vecs.push(s.to_owned());
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                        if tag_vecs.is_empty() {
-                            tag_vecs.push("Orphan".to_owned());
-                        }
-
-                        let mut links_vec = vec![];
-                        for part in raw_content.split("[[").skip(1) {
-                            if let Some(end) = part.find("]]") {
-                                let target = part[..end].split('|').next().unwrap_or(&part[..end]);
-                                links_vec.push(target.trim().to_string());
-                            }
-                        }
-                        marmol_vec.push(MarmolPoint::new(
-                            &node_name, tag_vecs, links_vec, rel_path, abs_path, false, false, true,
-                        ));
-                    } else if ["png", "jpg", "jpeg", "pdf", "gif"].contains(&ext.as_str()) {
-                        *total_entries += 1;
-                        marmol_vec.push(MarmolPoint::new(
-                            &node_name,
-                            vec!["Attachment".to_string()],
-                            vec![],
-                            rel_path,
-                            abs_path,
-                            true,
-                            false,
-                            true,
-                        ));
-                    }
+    let dir = dir.to_str().unwrap_or(vault);
+    crate::vault_index::with_index(vault, |index| {
+        for file in index.files().filter(|f| f.path.starts_with(dir)) {
+            let ext = Path::new(&file.path)
+                .extension()
+                .and_then(|s| s.to_str())
+                .map(|s| s.to_lowercase())
+                .unwrap_or_default();
+            let rel_path = file.path.replace(vault, "");
+            if let Some(note) = &file.note {
+                *total_entries += 1;
+                let mut tag_vecs = note.tags();
+                if tag_vecs.is_empty() {
+                    tag_vecs.push("Orphan".to_owned());
                 }
+                marmol_vec.push(MarmolPoint::new(
+                    &file.name,
+                    tag_vecs,
+                    note.links.clone(),
+                    rel_path,
+                    file.path.clone(),
+                    false,
+                    false,
+                    true,
+                ));
+            } else if ["png", "jpg", "jpeg", "pdf", "gif"].contains(&ext.as_str()) {
+                *total_entries += 1;
+                marmol_vec.push(MarmolPoint::new(
+                    &file.name,
+                    vec!["Attachment".to_string()],
+                    vec![],
+                    rel_path,
+                    file.path.clone(),
+                    true,
+                    false,
+                    true,
+                ));
             }
         }
-    }
-}
-
-fn extract_frontmatter(content: &str) -> Option<String> {
-    if content.starts_with("---") {
-        if let Some(end_idx) = content[3..].find("---") {
-            return Some(content[0..end_idx + 6].to_string());
-        }
-    }
-    None
+    });
 }
 
 fn get_coords(coords_vec: &mut Vec<(f32, f32)>, total_entries: i32) {
@@ -2,14 +2,278 @@ use egui::*;
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::io::Write;
-use regex::Regex;
-use walkdir::WalkDir;
+use std::sync::OnceLock;
+use regex::{Captures, Regex};
+
+use crate::tags;
+use crate::vault_tasks::Priority;
+
+// Sangría de las líneas nuevas bajo una tarjeta
+const BODY_INDENT: &str = "    ";
+// Línea que el plugin pone bajo el título de las columnas que completan tarjetas
+const COMPLETE_MARK: &str = "**Complete**";
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct KanbanTask {
+    /// First line of the card, with its `#tags`, `@assignees` and priority
+    /// but without the date and time.
     pub content: String,
     pub completed: bool,
+    /// `@{date}`
     pub date: Option<String>,
+    /// `@@{time}`
+    #[serde(default)]
+    pub time: Option<String>,
+    /// Indented lines under the card, without the indentation. Lines like
+    /// `- [ ] item` are its checklist.
+    #[serde(default)]
+    pub body: Vec<String>,
+    /// Indentation the body lines were written with.
+    #[serde(default)]
+    pub indent: String,
+    /// Date written as `@[[date]]`, linked to the daily note.
+    #[serde(default)]
+    pub date_link: bool,
+    #[serde(skip)]
+    source: Option<Source>,
+    /// Blank or unknown lines after the card, which move with it.
+    #[serde(skip)]
+    after: String,
+}
+
+/// Text of a card or column as it was in the file. It is written back
+/// unchanged while the element renders the same as when it was read.
+#[derive(Debug, Clone, Default)]
+struct Source {
+    raw: String,
+    key: String,
+}
+
+/// Parts of the file that aren't columns or cards.
+#[derive(Debug, Clone, Default)]
+struct BoardSource {
+    /// Frontmatter with its `---` lines.
+    frontmatter_raw: String,
+    frontmatter: String,
+    /// Text between the frontmatter and the first column.
+    preamble: String,
+    /// `***` and `## Archive` lines with the blank lines around them.
+    archive_head: Option<String>,
+    archive_tail: String,
+    /// `%% kanban:settings` block and the settings it had.
+    settings: Option<Source>,
+    /// Anything after the settings block.
+    epilogue: String,
+    crlf: bool,
+}
+
+fn assignee_regex() -> &'static Regex {
+    static ASSIGNEE: OnceLock<Regex> = OnceLock::new();
+    ASSIGNEE.get_or_init(|| Regex::new(r"(^|\s)@([\p{L}\p{N}_.-]+)").unwrap())
+}
+
+fn checklist_regex() -> &'static Regex {
+    static ITEM: OnceLock<Regex> = OnceLock::new();
+    ITEM.get_or_init(|| Regex::new(r"^\s*[-*+] \[(.)\] ?(.*)$").unwrap())
+}
+
+fn link_regex() -> &'static Regex {
+    static LINK: OnceLock<Regex> = OnceLock::new();
+    LINK.get_or_init(|| Regex::new(r"\[\[(.*?)\]\]").unwrap())
+}
+
+impl KanbanTask {
+    pub fn new(content: &str) -> Self {
+        Self {
+            content: content.to_string(),
+            completed: false,
+            date: None,
+            time: None,
+            body: vec![],
+            indent: String::new(),
+            date_link: false,
+            source: None,
+            after: String::new(),
+        }
+    }
+
+    /// The first line and the body, which is where the tags and links can be.
+    fn lines(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(self.content.as_str()).chain(self.body.iter().map(String::as_str))
+    }
+
+    pub fn tags(&self) -> Vec<String> {
+        let mut tags: Vec<String> = vec![];
+        for line in self.lines() {
+            for caps in tags::inline_tags(line) {
+                if !tags.iter().any(|t| t.eq_ignore_ascii_case(&caps[2])) {
+                    tags.push(caps[2].to_string());
+                }
+            }
+        }
+        tags
+    }
+
+    pub fn assignees(&self) -> Vec<String> {
+        let mut assignees: Vec<String> = vec![];
+        for caps in assignee_regex().captures_iter(&self.content) {
+            if !assignees.contains(&caps[2].to_string()) {
+                assignees.push(caps[2].to_string());
+            }
+        }
+        assignees
+    }
+
+    pub fn priority(&self) -> Priority {
+        Priority::ALL
+            .into_iter()
+            .filter(|p| *p != Priority::None)
+            .find(|p| self.content.contains(p.emoji()))
+            .unwrap_or_default()
+    }
+
+    /// Notes linked from the body; the ones of the title are shown in it.
+    pub fn body_links(&self) -> Vec<String> {
+        self.body
+            .iter()
+            .flat_map(|line| link_regex().captures_iter(line).map(|c| c[1].to_string()))
+            .collect()
+    }
+
+    /// Items of the checklist and whether they are done.
+    pub fn checklist(&self) -> Vec<(bool, String)> {
+        self.body
+            .iter()
+            .filter_map(|line| checklist_regex().captures(line))
+            .map(|c| (&c[1] != " ", c[2].to_string()))
+            .collect()
+    }
+
+    /// First line without the tags, assignees and priority, which are shown
+    /// as chips.
+    pub fn title(&self) -> String {
+        let title = tags::inline_tag_regex().replace_all(&self.content, |caps: &Captures| {
+            if tags::is_tag(&caps[2]) {
+                caps[1].to_string()
+            } else {
+                caps[0].to_string()
+            }
+        });
+        let mut title = assignee_regex().replace_all(&title, "$1").to_string();
+        for priority in Priority::ALL.iter().filter(|p| **p != Priority::None) {
+            title = title.replace(priority.emoji(), "");
+        }
+        title.split_whitespace().collect::<Vec<_>>().join(" ")
+    }
+
+    /// Whether the card matches every term of `filter`: `#tag`, `@person`
+    /// and words of its text.
+    fn matches(&self, filter: &str, priority: Option<Priority>) -> bool {
+        if priority.is_some_and(|p| p != self.priority()) {
+            return false;
+        }
+        let tags = self.tags();
+        let assignees = self.assignees();
+        filter.split_whitespace().all(|term| {
+            let term = term.to_lowercase();
+            if let Some(tag) = term.strip_prefix('#') {
+                tags.iter().any(|t| {
+                    let t = t.to_lowercase();
+                    t == tag || t.starts_with(&format!("{}/", tag))
+                })
+            } else if let Some(name) = term.strip_prefix('@') {
+                assignees.iter().any(|a| a.to_lowercase() == name)
+            } else {
+                self.lines().any(|l| l.to_lowercase().contains(&term))
+                    || self.date.as_deref().is_some_and(|d| d.contains(&term))
+            }
+        })
+    }
+}
+
+/// Fields of a card being edited in the detail dialog.
+struct CardDraft {
+    col: usize,
+    row: usize,
+    title: String,
+    /// Body lines that aren't checklist items.
+    description: String,
+    checklist: Vec<(bool, String)>,
+    new_item: String,
+    tags: String,
+    assignees: String,
+    priority: Priority,
+    date: String,
+    time: String,
+}
+
+impl CardDraft {
+    fn new(task: &KanbanTask, col: usize, row: usize) -> Self {
+        let description: Vec<&str> = task
+            .body
+            .iter()
+            .map(String::as_str)
+            .filter(|line| !checklist_regex().is_match(line))
+            .collect();
+        Self {
+            col,
+            row,
+            title: task.title(),
+            description: description.join("\n"),
+            checklist: task.checklist(),
+            new_item: String::new(),
+            tags: task.tags().iter().map(|t| format!("#{}", t)).collect::<Vec<_>>().join(" "),
+            assignees: task.assignees().iter().map(|a| format!("@{}", a)).collect::<Vec<_>>().join(" "),
+            priority: task.priority(),
+            date: task.date.clone().unwrap_or_default(),
+            time: task.time.clone().unwrap_or_default(),
+        }
+    }
+
+    /// Writes the draft into `task`. Tags found in the description stay there.
+    fn apply(&self, task: &mut KanbanTask) {
+        let description_tags: Vec<String> = self
+            .description
+            .lines()
+            .flat_map(|l| tags::inline_tags(l).map(|c| c[2].to_lowercase()))
+            .collect();
+        let mut content = self.title.trim().to_string();
+        let words = |text: &str, prefix: char| -> Vec<String> {
+            text.split([' ', ','])
+                .map(|w| w.trim().trim_start_matches(prefix))
+                .filter(|w| !w.is_empty())
+                .map(|w| format!("{}{}", prefix, w))
+                .collect()
+        };
+        for tag in words(&self.tags, '#') {
+            if !description_tags.contains(&tag[1..].to_lowercase()) {
+                content.push(' ');
+                content.push_str(&tag);
+            }
+        }
+        for assignee in words(&self.assignees, '@') {
+            content.push(' ');
+            content.push_str(&assignee);
+        }
+        if self.priority != Priority::None {
+            content.push(' ');
+            content.push_str(self.priority.emoji());
+        }
+        task.content = content;
+
+        let mut body: Vec<String> = if self.description.trim().is_empty() {
+            vec![]
+        } else {
+            self.description.lines().map(|l| l.to_string()).collect()
+        };
+        for (done, text) in &self.checklist {
+            body.push(format!("- [{}] {}", if *done { "x" } else { " " }, text));
+        }
+        task.body = body;
+        let non_empty = |s: &str| (!s.trim().is_empty()).then(|| s.trim().to_string());
+        task.date = non_empty(&self.date);
+        task.time = non_empty(&self.time);
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -18,6 +282,28 @@ pub struct KanbanColumn {
     pub tasks: Vec<KanbanTask>,
     #[serde(default)]
     pub collapsed: bool,
+    /// Lane marked with `**Complete**`: the cards dropped in it are done.
+    #[serde(default)]
+    pub complete: bool,
+    /// Heading and the lines before the first card.
+    #[serde(skip)]
+    head: Option<Source>,
+    /// Blank or unknown lines after the last card.
+    #[serde(skip)]
+    tail: Option<String>,
+}
+
+impl KanbanColumn {
+    pub fn new(title: &str) -> Self {
+        Self {
+            title: title.to_string(),
+            tasks: Vec::new(),
+            collapsed: false,
+            complete: false,
+            head: None,
+            tail: None,
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
@@ -26,6 +312,8 @@ pub struct KanbanBoard {
     pub archive: Vec<KanbanTask>,
     pub settings: String,
     pub frontmatter: String,
+    #[serde(skip)]
+    source: Option<BoardSource>,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -45,9 +333,12 @@ pub struct KanbanGui {
     pub path: String,
     pub new_task_str: String,
     pub adding_task_to: Option<usize>,
-    pub editing_task: Option<(usize, usize)>,
     pub adding_column: bool,
     pub new_column_name: String,
+    /// Text, `#tag` and `@person` terms the cards must match.
+    pub filter: String,
+    pub priority_filter: Option<Priority>,
+    card_dialog: Option<CardDraft>,
 }
 
 impl KanbanGui {
@@ -86,19 +377,45 @@ impl KanbanGui {
         let mut to_task: Option<Location> = None;
         
         let mut adding_task_to = self.adding_task_to;
-        let mut editing_task = self.editing_task;
+        let mut open_card = None;
         let mut new_task_str = self.new_task_str.clone();
         let mut remove_task = None;
         let mut archive_task = None;
 
-        let re_link = Regex::new(r"\[\[(.*?)\]\]").unwrap();
+        let re_link = link_regex();
 
         if ui.input_mut(|i| i.consume_key(Modifiers::NONE, Key::Escape)) {
             adding_task_to = None;
-            editing_task = None;
+            self.card_dialog = None;
         }
 
         ui.add_space(10.0);
+        ui.horizontal(|ui| {
+            ui.add(
+                TextEdit::singleline(&mut self.filter)
+                    .hint_text("Filter cards: text #tag @person")
+                    .desired_width(220.0),
+            );
+            ComboBox::from_id_salt(seed_id.with("priority_filter"))
+                .selected_text(match self.priority_filter {
+                    Some(p) => format!("{} {}", p.emoji(), p.name()).trim().to_string(),
+                    None => "Any priority".to_string(),
+                })
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.priority_filter, None, "Any priority");
+                    for priority in Priority::ALL {
+                        let label = format!("{} {}", priority.emoji(), priority.name());
+                        ui.selectable_value(&mut self.priority_filter, Some(priority), label.trim());
+                    }
+                });
+            if (!self.filter.is_empty() || self.priority_filter.is_some()) && ui.small_button("✖").clicked() {
+                self.filter.clear();
+                self.priority_filter = None;
+            }
+        });
+        ui.add_space(6.0);
+        let filter = self.filter.clone();
+        let priority_filter = self.priority_filter;
         
         ScrollArea::both().auto_shrink([false, true]).id_salt(seed_id.with("scroll")).show(ui, |ui| {
             ui.horizontal_top(|ui| {
@@ -166,6 +483,11 @@ impl KanbanGui {
                                     });
 
                                     let response = header_res.response;
+                                    response.context_menu(|ui| {
+                                        if ui.checkbox(&mut self.board.columns[col_idx].complete, "Mark cards as complete").changed() {
+                                            needs_save = true;
+                                        }
+                                    });
                                     if let (Some(pointer), Some(_payload)) = (
                                         ui.input(|i| i.pointer.interact_pos()),
                                         response.dnd_hover_payload::<ColumnLocation>(),
@@ -207,6 +529,9 @@ impl KanbanGui {
                                 }
 
                                 for task_idx in 0..self.board.columns[col_idx].tasks.len() {
+                                    if !self.board.columns[col_idx].tasks[task_idx].matches(&filter, priority_filter) {
+                                        continue;
+                                    }
                                     let item_id = seed_id.with("kanban_item").with(col_idx).with(task_idx);
                                     let item_location = Location { col: col_idx, row: task_idx };
                                     
@@ -224,37 +549,30 @@ impl KanbanGui {
                                                         let check_res = ui.checkbox(&mut task.completed, "");
                                                         if check_res.changed() { needs_save = true; }
                                                         
-                                                        if editing_task == Some((col_idx, task_idx)) {
-                                                            let res = ui.text_edit_singleline(&mut task.content);
-                                                            if res.lost_focus() || ui.input(|i| i.key_pressed(Key::Enter)) {
-                                                                editing_task = None;
-                                                                needs_save = true;
-                                                            }
-                                                        } else {
-                                                            ui.horizontal_wrapped(|ui| {
-                                                                ui.spacing_mut().item_spacing.x = 0.0;
-                                                                let mut last_end = 0;
-                                                                for cap in re_link.captures_iter(&task.content) {
-                                                                    let m = cap.get(0).unwrap();
-                                                                    let start = m.start();
-                                                                    let end = m.end();
-                                                                    let link_text = cap.get(1).unwrap().as_str();
-                                                                    if start > last_end { ui.label(&task.content[last_end..start]); }
-                                                                    let accent = ui.visuals().selection.bg_fill;
-                                                                    let link_label = Label::new(RichText::new(link_text).color(accent)).sense(Sense::click());
-                                                                    if ui.add(link_label).clicked() {
-                                                                        if let Some(path) = find_file(vault, link_text) {
-                                                                            open_file = Some(path);
-                                                                        }
+                                                        let title = task.title();
+                                                        ui.horizontal_wrapped(|ui| {
+                                                            ui.spacing_mut().item_spacing.x = 0.0;
+                                                            let mut last_end = 0;
+                                                            for cap in re_link.captures_iter(&title) {
+                                                                let m = cap.get(0).unwrap();
+                                                                let start = m.start();
+                                                                let end = m.end();
+                                                                let link_text = cap.get(1).unwrap().as_str();
+                                                                if start > last_end { ui.label(&title[last_end..start]); }
+                                                                let accent = ui.visuals().selection.bg_fill;
+                                                                let link_label = Label::new(RichText::new(link_text).color(accent)).sense(Sense::click());
+                                                                if ui.add(link_label).clicked() {
+                                                                    if let Some(path) = find_file(vault, link_text) {
+                                                                        open_file = Some(path);
                                                                     }
-                                                                    last_end = end;
                                                                 }
-                                                                if last_end < task.content.len() { ui.label(&task.content[last_end..]); }
-                                                            });
-                                                        }
+                                                                last_end = end;
+                                                            }
+                                                            if last_end < title.len() { ui.label(&title[last_end..]); }
+                                                        });
                                                     });
-                                                    if let Some(date) = &task.date {
-                                                        ui.weak(format!("📅 {}", date));
+                                                    if let Some(path) = card_chips(ui, task, vault) {
+                                                        open_file = Some(path);
                                                     }
                                                 });
                                             });
@@ -263,11 +581,12 @@ impl KanbanGui {
                                     let response = dnd_res.response;
                                     
                                     response.context_menu(|ui| {
+                                        if ui.button("✏ Editar").clicked() { open_card = Some((col_idx, task_idx)); ui.close(); }
                                         if ui.button("📁 Archivar").clicked() { archive_task = Some((col_idx, task_idx)); ui.close(); }
                                         if ui.button("🗑 Eliminar").clicked() { remove_task = Some((col_idx, task_idx)); ui.close(); }
                                     });
 
-                                    if response.double_clicked() { editing_task = Some((col_idx, task_idx)); }
+                                    if response.double_clicked() { open_card = Some((col_idx, task_idx)); }
 
                                     if let (Some(pointer), Some(_payload)) = (
                                         ui.input(|i| i.pointer.interact_pos()),
@@ -323,11 +642,7 @@ impl KanbanGui {
                             ui.horizontal(|ui| {
                                 if ui.button("Add").clicked() || (res.lost_focus() && ui.input(|i| i.key_pressed(Key::Enter))) {
                                     if !self.new_column_name.is_empty() {
-                                        self.board.columns.push(KanbanColumn {
-                                            title: self.new_column_name.clone(),
-                                            tasks: Vec::new(),
-                                            collapsed: false,
-                                        });
+                                        self.board.columns.push(KanbanColumn::new(&self.new_column_name));
                                         self.new_column_name.clear();
                                         self.adding_column = false;
                                         needs_save = true;
@@ -368,7 +683,12 @@ impl KanbanGui {
                 }
                 
                 if f.col != t.col || f.row != t.row {
-                    let task = self.board.columns[f.col].tasks.remove(f.row);
+                    let mut task = self.board.columns[f.col].tasks.remove(f.row);
+                    if self.board.columns[t.col].complete && f.col != t.col {
+                        task.completed = true;
+                    } else if self.board.columns[f.col].complete && f.col != t.col {
+                        task.completed = false;
+                    }
                     let target_row = t.row.min(self.board.columns[t.col].tasks.len());
                     self.board.columns[t.col].tasks.insert(target_row, task);
                     needs_save = true;
@@ -394,11 +714,7 @@ impl KanbanGui {
         if let Some(c_idx) = adding_task_to {
             if self.adding_task_to == Some(c_idx) && ui.input(|i| i.key_pressed(Key::Enter)) && !new_task_str.is_empty() {
                 if c_idx < self.board.columns.len() {
-                    self.board.columns[c_idx].tasks.push(KanbanTask {
-                        content: new_task_str.clone(),
-                        completed: false,
-                        date: None,
-                    });
+                    self.board.columns[c_idx].tasks.push(KanbanTask::new(&new_task_str));
                     new_task_str = String::new();
                     adding_task_to = None;
                     needs_save = true;
@@ -406,8 +722,16 @@ impl KanbanGui {
             }
         }
 
+        if let Some((c, t)) = open_card {
+            if let Some(task) = self.board.columns.get(c).and_then(|col| col.tasks.get(t)) {
+                self.card_dialog = Some(CardDraft::new(task, c, t));
+            }
+        }
+        if self.card_dialog_ui(ui.ctx(), seed_id) {
+            needs_save = true;
+        }
+
         self.adding_task_to = adding_task_to;
-        self.editing_task = editing_task;
         self.new_task_str = new_task_str;
 
         if needs_save {
@@ -415,152 +739,762 @@ impl KanbanGui {
         }
         open_file
     }
+
+    /// Detail dialog of the card being edited. Returns true if it was saved.
+    fn card_dialog_ui(&mut self, ctx: &Context, seed_id: Id) -> bool {
+        let Some(draft) = &mut self.card_dialog else {
+            return false;
+        };
+        let mut open = true;
+        let mut save = false;
+        let mut close = false;
+        Window::new("Card")
+            .id(seed_id.with("card_dialog"))
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(true)
+            .default_width(360.0)
+            .show(ctx, |ui| {
+                Grid::new(seed_id.with("card_fields")).num_columns(2).spacing([8.0, 6.0]).show(ui, |ui| {
+                    ui.label("Title");
+                    ui.add(TextEdit::singleline(&mut draft.title).desired_width(f32::INFINITY));
+                    ui.end_row();
+                    ui.label("Tags");
+                    ui.add(TextEdit::singleline(&mut draft.tags).hint_text("#design #urgent"));
+                    ui.end_row();
+                    ui.label("Assignees");
+                    ui.add(TextEdit::singleline(&mut draft.assignees).hint_text("@ana @luis"));
+                    ui.end_row();
+                    ui.label("Priority");
+                    ComboBox::from_id_salt(seed_id.with("card_priority"))
+                        .selected_text(format!("{} {}", draft.priority.emoji(), draft.priority.name()).trim().to_string())
+                        .show_ui(ui, |ui| {
+                            for priority in Priority::ALL {
+                                let label = format!("{} {}", priority.emoji(), priority.name());
+                                ui.selectable_value(&mut draft.priority, priority, label.trim());
+                            }
+                        });
+                    ui.end_row();
+                    ui.label("Date");
+                    ui.add(TextEdit::singleline(&mut draft.date).hint_text("YYYY-MM-DD"));
+                    ui.end_row();
+                    ui.label("Time");
+                    ui.add(TextEdit::singleline(&mut draft.time).hint_text("HH:mm"));
+                    ui.end_row();
+                });
+                ui.separator();
+                ui.label("Description");
+                ui.add(TextEdit::multiline(&mut draft.description).desired_rows(4).desired_width(f32::INFINITY));
+                ui.separator();
+                ui.label("Checklist");
+                let mut remove = None;
+                for (i, (done, text)) in draft.checklist.iter_mut().enumerate() {
+                    ui.horizontal(|ui| {
+                        ui.checkbox(done, "");
+                        ui.add(TextEdit::singleline(text).desired_width(260.0));
+                        if ui.small_button("🗑").clicked() {
+                            remove = Some(i);
+                        }
+                    });
+                }
+                if let Some(i) = remove {
+                    draft.checklist.remove(i);
+                }
+                ui.horizontal(|ui| {
+                    let res = ui.add(TextEdit::singleline(&mut draft.new_item).hint_text("New item").desired_width(260.0));
+                    let enter = res.lost_focus() && ui.input(|i| i.key_pressed(Key::Enter));
+                    if (ui.small_button("➕").clicked() || enter) && !draft.new_item.trim().is_empty() {
+                        draft.checklist.push((false, draft.new_item.trim().to_string()));
+                        draft.new_item.clear();
+                    }
+                });
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if ui.button("Save").clicked() {
+                        save = true;
+                    }
+                    if ui.button("Cancel").clicked() {
+                        close = true;
+                    }
+                });
+            });
+
+        if save {
+            if let Some(task) = self.board.columns.get_mut(draft.col).and_then(|c| c.tasks.get_mut(draft.row)) {
+                draft.apply(task);
+            }
+        }
+        if save || close || !open {
+            self.card_dialog = None;
+        }
+        save
+    }
 }
 
-fn parse_kanban(content: &str) -> KanbanBoard {
+/// Line without its `\n` or `\r\n`.
+fn line_text(raw: &str) -> &str {
+    raw.trim_end_matches(['\n', '\r'])
+}
+
+fn is_card(text: &str) -> bool {
+    (text.starts_with("- [") || text.starts_with("* [")) && text.get(4..5) == Some("]")
+}
+
+/// Takes the blank lines at the end of `text` out of it.
+fn split_trailing_blank(text: &mut String) -> String {
+    let mut end = 0;
+    let mut offset = 0;
+    for line in text.split_inclusive('\n') {
+        offset += line.len();
+        if !line.trim().is_empty() {
+            end = offset;
+        }
+    }
+    text.split_off(end)
+}
+
+/// Reads a board written by the Obsidian Kanban plugin. Everything that
+/// isn't a column or a card (blank lines, text between columns, the
+/// settings block) is kept, so `serialize_kanban` writes the file back
+/// byte for byte except for the cards and columns that changed.
+pub fn parse_kanban(content: &str) -> KanbanBoard {
+    #[derive(PartialEq)]
+    enum Section {
+        Preamble,
+        Column,
+        Archive,
+    }
+
     let mut board = KanbanBoard::default();
-    let mut current_column: Option<KanbanColumn> = None;
-    let mut in_frontmatter = false;
-    let mut frontmatter = String::new();
-    let mut settings = String::new();
-    let mut in_settings = false;
-    let mut in_archive = false;
-
-    for line in content.lines() {
-        if line.trim() == "---" {
-            if !in_frontmatter && frontmatter.is_empty() {
-                in_frontmatter = true;
-                continue;
-            } else if in_frontmatter {
-                in_frontmatter = false;
-                continue;
+    let mut source = BoardSource {
+        crlf: content.contains("\r\n"),
+        ..Default::default()
+    };
+    let lines: Vec<&str> = content.split_inclusive('\n').collect();
+    let mut i = 0;
+
+    if lines.first().map(|l| line_text(l)) == Some("---") {
+        if let Some(end) = lines.iter().skip(1).position(|l| line_text(l) == "---") {
+            let end = end + 1;
+            for line in &lines[..=end] {
+                source.frontmatter_raw.push_str(line);
             }
+            for line in &lines[1..end] {
+                board.frontmatter.push_str(line_text(line));
+                board.frontmatter.push('\n');
+            }
+            i = end + 1;
         }
+    }
 
-        if in_frontmatter {
-            frontmatter.push_str(line);
-            frontmatter.push('\n');
-            continue;
+    let mut section = Section::Preamble;
+    let mut current: Option<KanbanColumn> = None;
+    // Si las líneas sangradas que siguen son de la última tarjeta
+    let mut card_open = false;
+    // Líneas en blanco tras una tarjeta, que pueden seguir siendo de su cuerpo
+    let mut pending = String::new();
+    let mut blank_lines = 0;
+
+    fn finish_column(board: &mut KanbanBoard, current: &mut Option<KanbanColumn>) {
+        if let Some(mut col) = current.take() {
+            if let Some(last) = col.tasks.last_mut() {
+                col.tail = Some(split_trailing_blank(&mut last.after));
+            }
+            board.columns.push(col);
         }
+    }
 
-        if line.starts_with("%% kanban:settings") {
-            in_settings = true;
-            continue;
+    fn finish_archive(board: &mut KanbanBoard, source: &mut BoardSource) {
+        if let Some(last) = board.archive.last_mut() {
+            let blank = split_trailing_blank(&mut last.after);
+            source.archive_tail.push_str(&blank);
         }
-        if in_settings {
-            if line.starts_with("%% ") {
-                in_settings = false;
-            } else {
-                settings.push_str(line);
+    }
+
+    while i < lines.len() {
+        let raw = lines[i];
+        let text = line_text(raw);
+        i += 1;
+
+        let last_task = match section {
+            Section::Column => current.as_mut().and_then(|c| c.tasks.last_mut()),
+            Section::Archive => board.archive.last_mut(),
+            Section::Preamble => None,
+        };
+        if card_open {
+            if let Some(task) = last_task {
+                if text.trim().is_empty() {
+                    pending.push_str(raw);
+                    blank_lines += 1;
+                    continue;
+                }
+                if text.starts_with([' ', '\t']) {
+                    // Líneas sangradas bajo la tarjeta: su cuerpo y checklist
+                    if task.body.is_empty() {
+                        task.indent = text[..text.len() - text.trim_start().len()].to_string();
+                    }
+                    for _ in 0..blank_lines {
+                        task.body.push(String::new());
+                    }
+                    let body = text.strip_prefix(task.indent.as_str()).unwrap_or(text.trim_start());
+                    task.body.push(body.to_string());
+                    if let Some(src) = &mut task.source {
+                        src.raw.push_str(&pending);
+                        src.raw.push_str(raw);
+                    }
+                    pending.clear();
+                    blank_lines = 0;
+                    continue;
+                }
+                task.after.push_str(&pending);
+            }
+            pending.clear();
+            blank_lines = 0;
+            card_open = false;
+        }
+
+        if text.starts_with("%% kanban:settings") {
+            finish_column(&mut board, &mut current);
+            finish_archive(&mut board, &mut source);
+            let mut block = raw.to_string();
+            let mut settings = String::new();
+            while i < lines.len() {
+                let raw = lines[i];
+                i += 1;
+                block.push_str(raw);
+                if line_text(raw).trim_start().starts_with("%%") {
+                    break;
+                }
+                settings.push_str(line_text(raw));
                 settings.push('\n');
             }
-            continue;
+            source.settings = Some(Source {
+                raw: block,
+                key: settings.clone(),
+            });
+            board.settings = settings;
+            source.epilogue = lines[i..].concat();
+            break;
         }
 
-        if line.starts_with("## Archive") {
-            in_archive = true;
-            if let Some(col) = current_column.take() {
-                board.columns.push(col);
+        // El plugin separa el archivo con `***` justo antes de `## Archive`
+        let archive_rule = text.trim() == "***"
+            && lines[i..]
+                .iter()
+                .find(|l| !l.trim().is_empty())
+                .is_some_and(|l| line_text(l).trim_end() == "## Archive");
+        let archive_heading = text.trim_end() == "## Archive";
+        if archive_rule || (archive_heading && source.archive_head.is_none()) {
+            finish_column(&mut board, &mut current);
+            section = Section::Archive;
+            source.archive_head = Some(raw.to_string());
+            continue;
+        }
+        if archive_heading && section == Section::Archive && board.archive.is_empty() {
+            if let Some(head) = &mut source.archive_head {
+                head.push_str(raw);
             }
             continue;
         }
 
-        if line.starts_with("## ") {
-            in_archive = false;
-            if let Some(col) = current_column.take() {
-                board.columns.push(col);
-            }
-            current_column = Some(KanbanColumn {
-                title: line[3..].to_string(),
-                tasks: Vec::new(),
-                collapsed: false,
+        if text.starts_with("## ") && !archive_heading {
+            finish_column(&mut board, &mut current);
+            finish_archive(&mut board, &mut source);
+            section = Section::Column;
+            current = Some(KanbanColumn {
+                head: Some(Source {
+                    raw: raw.to_string(),
+                    key: String::new(),
+                }),
+                tail: Some(String::new()),
+                ..KanbanColumn::new(&text[3..])
             });
-        } else if line.trim().starts_with("- [") {
-            let completed = line.contains("- [x]");
-            let rest = &line[6..];
-            
-            let mut content_str = rest.to_string();
-            let mut date = None;
-            
-            if let Some(idx) = content_str.find("@{ ") {
-                if let Some(end_idx) = content_str[idx..].find('}') {
-                    date = Some(content_str[idx+2..idx+end_idx].to_string());
-                    content_str = content_str[..idx].trim().to_string();
-                }
-            }
+            continue;
+        }
 
+        if is_card(text) && section != Section::Preamble {
+            let completed = matches!(text.get(3..4), Some("x") | Some("X"));
+            let rest = text.get(6..).unwrap_or("");
+            let (content, date, time, date_link) = split_date_time(rest);
             let task = KanbanTask {
-                content: content_str,
-                completed,
                 date,
+                time,
+                completed,
+                date_link,
+                source: Some(Source {
+                    raw: raw.to_string(),
+                    key: String::new(),
+                }),
+                ..KanbanTask::new(&content)
             };
-
-            if in_archive {
-                board.archive.push(task);
-            } else if let Some(ref mut col) = current_column {
-                col.tasks.push(task);
+            match section {
+                Section::Archive => board.archive.push(task),
+                _ => {
+                    if let Some(col) = &mut current {
+                        col.tasks.push(task);
+                    }
+                }
             }
+            card_open = true;
+            continue;
         }
+
+        // Texto que no es una tarjeta: se queda donde estaba
+        let sink = match section {
+            Section::Preamble => &mut source.preamble,
+            Section::Column => match current.as_mut() {
+                Some(col) if col.tasks.is_empty() => {
+                    if text.trim() == COMPLETE_MARK {
+                        col.complete = true;
+                    }
+                    &mut col.head.get_or_insert_with(Source::default).raw
+                }
+                Some(col) => &mut col.tasks.last_mut().unwrap().after,
+                None => &mut source.preamble,
+            },
+            Section::Archive => match board.archive.last_mut() {
+                Some(task) => &mut task.after,
+                None => source.archive_head.get_or_insert_with(String::new),
+            },
+        };
+        sink.push_str(raw);
     }
 
-    if let Some(col) = current_column {
-        board.columns.push(col);
+    if card_open {
+        let last_task = match section {
+            Section::Column => current.as_mut().and_then(|c| c.tasks.last_mut()),
+            Section::Archive => board.archive.last_mut(),
+            Section::Preamble => None,
+        };
+        if let Some(task) = last_task {
+            task.after.push_str(&pending);
+        }
     }
+    finish_column(&mut board, &mut current);
+    finish_archive(&mut board, &mut source);
 
-    board.frontmatter = frontmatter;
-    board.settings = settings;
+    // Cómo se escribía cada elemento al leerlo, para saber si ha cambiado
+    for col in &mut board.columns {
+        let key = render_head(col);
+        if let Some(head) = &mut col.head {
+            head.key = key;
+        }
+        for task in &mut col.tasks {
+            let key = render_task(task, task.completed);
+            if let Some(src) = &mut task.source {
+                src.key = key;
+            }
+        }
+    }
+    for task in &mut board.archive {
+        let key = render_task(task, task.completed);
+        if let Some(src) = &mut task.source {
+            src.key = key;
+        }
+    }
+    source.frontmatter = board.frontmatter.clone();
+    board.source = Some(source);
     board
 }
 
 fn serialize_kanban(board: &KanbanBoard) -> String {
+    let fallback = BoardSource::default();
+    let source = board.source.as_ref().unwrap_or(&fallback);
+    let newline = if source.crlf { "\r\n" } else { "\n" };
+    // Lo que se escribe de nuevo usa los saltos de línea del archivo
+    let rendered = |out: &mut String, text: &str| {
+        if !out.is_empty() && !out.ends_with('\n') {
+            out.push_str(newline);
+        }
+        out.push_str(&text.replace('\n', newline));
+    };
     let mut out = String::new();
-    out.push_str("---\n");
-    out.push_str(&board.frontmatter);
-    if !board.frontmatter.ends_with('\n') {
-        out.push('\n');
+
+    if board.source.is_some() && board.frontmatter == source.frontmatter {
+        out.push_str(&source.frontmatter_raw);
+    } else {
+        rendered(&mut out, &format!("---\n{}", board.frontmatter));
+        if !board.frontmatter.ends_with('\n') {
+            rendered(&mut out, "\n");
+        }
+        rendered(&mut out, if board.source.is_some() { "---\n" } else { "---\n\n" });
     }
-    out.push_str("---\n\n");
+    out.push_str(&source.preamble);
 
     for col in &board.columns {
-        out.push_str(&format!("## {}\n\n", col.title));
+        match &col.head {
+            Some(head) if head.key == render_head(col) => out.push_str(&head.raw),
+            Some(head) => rendered(&mut out, &edit_head(&head.raw, col)),
+            None => rendered(&mut out, &render_head(col)),
+        }
         for task in &col.tasks {
-            let check = if task.completed { "x" } else { " " };
-            let date_str = task.date.as_ref().map_or(String::new(), |d| format!(" @{{{}}}", d));
-            out.push_str(&format!("- [{}] {}{}\n", check, task.content, date_str));
+            write_task(&mut out, task, &rendered);
+        }
+        match &col.tail {
+            Some(tail) => out.push_str(tail),
+            None => rendered(&mut out, "\n\n"),
         }
-        out.push_str("\n\n");
     }
 
-    if !board.archive.is_empty() {
-        out.push_str("## Archive\n\n");
-        for task in &board.archive {
-            let check = if task.completed { "x" } else { " " };
-            let date_str = task.date.as_ref().map_or(String::new(), |d| format!(" @{{{}}}", d));
-            out.push_str(&format!("- [{}] {}{}\n", check, task.content, date_str));
+    match &source.archive_head {
+        Some(head) => out.push_str(head),
+        None if !board.archive.is_empty() => rendered(&mut out, "***\n\n## Archive\n\n"),
+        None => {}
+    }
+    for task in &board.archive {
+        write_task(&mut out, task, &rendered);
+    }
+    if source.archive_head.is_some() {
+        out.push_str(&source.archive_tail);
+    } else if !board.archive.is_empty() {
+        rendered(&mut out, "\n\n");
+    }
+
+    match &source.settings {
+        Some(settings) if settings.key == board.settings => out.push_str(&settings.raw),
+        _ if !board.settings.is_empty() => {
+            rendered(&mut out, &format!("%% kanban:settings\n{}%%\n", board.settings));
+        }
+        _ => {}
+    }
+    out.push_str(&source.epilogue);
+    out
+}
+
+/// Takes the `@{date}` (or `@[[date]]`) and `@@{time}` out of the text of a
+/// card. The last value says whether the date was a link.
+fn split_date_time(text: &str) -> (String, Option<String>, Option<String>, bool) {
+    static TIME: OnceLock<Regex> = OnceLock::new();
+    static DATE: OnceLock<Regex> = OnceLock::new();
+    let time_re = TIME.get_or_init(|| Regex::new(r"\s*@@\{([^}]*)\}").unwrap());
+    let date_re = DATE.get_or_init(|| Regex::new(r"\s*@(?:\{([^}]*)\}|\[\[([^\]]*)\]\])").unwrap());
+    let time = time_re.captures(text).map(|c| c[1].to_string());
+    let text = time_re.replace(text, "");
+    let date = date_re.captures(&text).map(|c| match c.get(1) {
+        Some(date) => (date.as_str().to_string(), false),
+        None => (c[2].to_string(), true),
+    });
+    let text = date_re.replace(&text, "");
+    let date_link = date.as_ref().is_some_and(|(_, link)| *link);
+    (text.trim().to_string(), date.map(|(d, _)| d), time, date_link)
+}
+
+/// Heading of a column as it is written for a new one.
+fn render_head(col: &KanbanColumn) -> String {
+    let mark = if col.complete { format!("{}\n", COMPLETE_MARK) } else { String::new() };
+    format!("## {}\n\n{}", col.title, mark)
+}
+
+/// Heading read from the file after renaming the column or changing its
+/// `**Complete**` mark, keeping the rest of its lines.
+fn edit_head(raw: &str, col: &KanbanColumn) -> String {
+    let mut out = String::new();
+    let mut has_mark = false;
+    for (i, line) in raw.split_inclusive('\n').enumerate() {
+        let ending = if line.ends_with('\n') { "\n" } else { "" };
+        if i == 0 {
+            out.push_str(&format!("## {}{}", col.title, ending));
+        } else if line_text(line).trim() == COMPLETE_MARK {
+            if col.complete {
+                out.push_str(line_text(line));
+                out.push_str(ending);
+                has_mark = true;
+            }
+        } else {
+            out.push_str(line_text(line));
+            out.push_str(ending);
         }
-        out.push_str("\n\n");
     }
+    if col.complete && !has_mark {
+        if !out.ends_with('\n') {
+            out.push('\n');
+        }
+        out.push_str(COMPLETE_MARK);
+        out.push('\n');
+    }
+    out
+}
 
-    if !board.settings.is_empty() {
-        out.push_str("%% kanban:settings\n");
-        out.push_str(&board.settings);
-        out.push_str("%%\n");
+/// Lines of a card as they are written for a new one, with its checkbox
+/// set to `completed`.
+fn render_task(task: &KanbanTask, completed: bool) -> String {
+    let check = if completed { "x" } else { " " };
+    let date_str = match &task.date {
+        Some(d) if task.date_link => format!(" @[[{}]]", d),
+        Some(d) => format!(" @{{{}}}", d),
+        None => String::new(),
+    };
+    let time_str = task.time.as_ref().map_or(String::new(), |t| format!(" @@{{{}}}", t));
+    let mut out = format!("- [{}] {}{}{}\n", check, task.content, date_str, time_str);
+    let indent = if task.indent.is_empty() { BODY_INDENT } else { &task.indent };
+    for line in &task.body {
+        if line.is_empty() {
+            out.push('\n');
+        } else {
+            out.push_str(&format!("{}{}\n", indent, line));
+        }
     }
     out
 }
 
+fn write_task(out: &mut String, task: &KanbanTask, rendered: &dyn Fn(&mut String, &str)) {
+    let new = render_task(task, task.completed);
+    match &task.source {
+        Some(src) if src.key == new => out.push_str(&src.raw),
+        // Marcar la tarjeta solo cambia la casilla
+        Some(src) if src.key == render_task(task, !task.completed) => {
+            let check = if task.completed { "x" } else { " " };
+            out.push_str(&src.raw[..3]);
+            out.push_str(check);
+            out.push_str(&src.raw[4..]);
+        }
+        _ => rendered(out, &new),
+    }
+    out.push_str(&task.after);
+}
+
 fn find_file(vault: &str, name: &str) -> Option<String> {
-    let target_name = name.to_string();
-    let target_name_md = format!("{}.md", name);
-
-    for entry in WalkDir::new(vault).into_iter().filter_map(|e| e.ok()) {
-        if entry.file_type().is_file() {
-            if let Some(fname) = entry.file_name().to_str() {
-                 if fname == target_name || fname == target_name_md {
-                     return Some(entry.path().to_str()?.to_string());
-                 }
+    crate::vault_index::with_index(vault, |index| index.find_by_name(name))
+}
+
+/// Chips under the title of a card: priority, date, time, checklist
+/// progress, tags, assignees and the notes linked from its body. Returns
+/// the note clicked.
+fn card_chips(ui: &mut Ui, task: &KanbanTask, vault: &str) -> Option<String> {
+    let mut open = None;
+    let checklist = task.checklist();
+    let tags = task.tags();
+    let assignees = task.assignees();
+    let links = task.body_links();
+    let priority = task.priority();
+    if task.date.is_none() && task.time.is_none() && checklist.is_empty() && tags.is_empty()
+        && assignees.is_empty() && links.is_empty() && priority == Priority::None
+    {
+        return None;
+    }
+    let chip = |ui: &mut Ui, text: String, color: Color32| {
+        Frame::NONE
+            .fill(color.gamma_multiply(0.2))
+            .corner_radius(4.0)
+            .inner_margin(Margin::symmetric(4, 1))
+            .show(ui, |ui| ui.label(RichText::new(text).small().color(color)))
+            .inner
+    };
+    ui.horizontal_wrapped(|ui| {
+        ui.spacing_mut().item_spacing = vec2(4.0, 4.0);
+        let text_color = ui.visuals().text_color();
+        if priority != Priority::None {
+            chip(ui, format!("{} {}", priority.emoji(), priority.name()), text_color);
+        }
+        if let Some(date) = &task.date {
+            chip(ui, format!("📅 {}", date), text_color);
+        }
+        if let Some(time) = &task.time {
+            chip(ui, format!("🕒 {}", time), text_color);
+        }
+        if !checklist.is_empty() {
+            let done = checklist.iter().filter(|(d, _)| *d).count();
+            let color = if done == checklist.len() { Color32::from_rgb(64, 160, 90) } else { text_color };
+            chip(ui, format!("☑ {}/{}", done, checklist.len()), color);
+        }
+        let accent = ui.visuals().selection.stroke.color;
+        for tag in &tags {
+            chip(ui, format!("#{}", tag), accent);
+        }
+        for assignee in &assignees {
+            chip(ui, format!("👤 {}", assignee), text_color);
+        }
+        for link in &links {
+            let name = link.split('|').next().unwrap_or(link).to_string();
+            if chip(ui, format!("🔗 {}", name), accent).interact(Sense::click()).clicked() {
+                open = find_file(vault, &name);
             }
         }
+    });
+    open
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Tableros escritos por el plugin de Obsidian y lo que debe quedar tras editarlos
+    const BASIC: &str = include_str!("../tests/kanban/basic.md");
+    const BASIC_MOVED: &str = include_str!("../tests/kanban/basic.moved.md");
+    const BASIC_EDITED: &str = include_str!("../tests/kanban/basic.edited.md");
+    const MESSY: &str = include_str!("../tests/kanban/messy.md");
+    // Guardado por el propio plugin, sin retocar a mano
+    const PLUGIN: &str = include_str!("../tests/kanban/plugin.md");
+
+    #[test]
+    fn round_trip_is_lossless() {
+        for board in [BASIC, MESSY, PLUGIN] {
+            assert_eq!(serialize_kanban(&parse_kanban(board)), board);
+            let crlf = board.replace('\n', "\r\n");
+            assert_eq!(serialize_kanban(&parse_kanban(&crlf)), crlf);
+        }
+    }
+
+    #[test]
+    fn parses_plugin_syntax() {
+        let board = parse_kanban(BASIC);
+        let titles: Vec<&str> = board.columns.iter().map(|c| c.title.as_str()).collect();
+        assert_eq!(titles, ["Todo", "Doing", "Done"]);
+        assert!(board.columns[2].complete);
+        assert_eq!(board.archive.len(), 1);
+        assert!(board.settings.contains("\"new-note-folder\":\"Kanban\""));
+
+        let todo = &board.columns[0].tasks;
+        assert_eq!(todo[0].content, "Write the report");
+        assert_eq!(todo[0].date.as_deref(), Some("2024-05-02"));
+        assert_eq!(todo[1].tags(), ["sales"]);
+        assert_eq!(todo[1].assignees(), ["ana"]);
+        assert_eq!(todo[1].priority(), Priority::High);
+        assert_eq!(todo[2].body, ["Book flights", "- [ ] Hotel", "- [x] Train"]);
+        assert_eq!(todo[2].checklist(), [(false, "Hotel".to_string()), (true, "Train".to_string())]);
+        assert_eq!(todo[3].time.as_deref(), Some("10:30"));
+
+        let messy = parse_kanban(MESSY);
+        let backlog = &messy.columns[0].tasks;
+        assert_eq!(messy.columns[0].title, "Backlog (3)");
+        assert!(backlog[0].completed);
+        assert_eq!(backlog[1].content, "Due in the middle");
+        assert_eq!(backlog[1].date.as_deref(), Some("2024-06-01"));
+        assert!(backlog[2].date_link);
+        assert_eq!(backlog[2].body, ["", "Indented after a blank line"]);
+        assert_eq!(backlog.len(), 4);
+        assert!(messy.columns[1].tasks.is_empty());
+    }
+
+    #[test]
+    fn reads_boards_saved_by_the_plugin() {
+        let mut board = parse_kanban(PLUGIN);
+        let titles: Vec<&str> = board.columns.iter().map(|c| c.title.as_str()).collect();
+        assert_eq!(titles, ["Backlog", "In progress", "Waiting", "Done"]);
+        assert_eq!(board.columns.iter().map(|c| c.complete).collect::<Vec<_>>(), [false, false, false, true]);
+        assert!(board.columns[2].tasks.is_empty());
+        assert!(board.settings.contains("\"list-collapse\":[false,false,true,false]"));
+
+        let backlog = &board.columns[0].tasks;
+        assert_eq!(backlog[1].assignees(), ["maria"]);
+        assert_eq!(backlog[1].date.as_deref(), Some("2024-07-01"));
+        assert_eq!(backlog[2].content, "Update the website<br>Check the links first");
+        let doing = &board.columns[1].tasks[0];
+        assert_eq!((doing.priority(), doing.time.as_deref()), (Priority::High, Some("09:00")));
+        assert_eq!(board.archive.len(), 2);
+        assert_eq!(board.archive[0].content, "2024-06-15 18:02 Set up the repository");
+
+        // Marcar una tarjeta solo cambia su casilla
+        board.columns[0].tasks[0].completed = true;
+        let saved = serialize_kanban(&board);
+        assert_eq!(saved, PLUGIN.replacen("- [ ] Draft", "- [x] Draft", 1));
+        assert!(saved.ends_with("```\n%%"));
+    }
+
+    #[test]
+    fn moving_a_card_keeps_its_lines() {
+        let mut board = parse_kanban(BASIC);
+        let task = board.columns[0].tasks.remove(2);
+        board.columns[1].tasks.push(task);
+        assert_eq!(serialize_kanban(&board), BASIC_MOVED);
+    }
+
+    #[test]
+    fn only_edited_parts_are_rewritten() {
+        let mut board = parse_kanban(BASIC);
+        board.columns[0].tasks[0].completed = true;
+        board.columns[0].tasks[1].content = "Call the client #sales @luis ⏫".to_string();
+        board.columns[1].title = "In progress".to_string();
+        board.columns[2].complete = false;
+        board.columns.push(KanbanColumn::new("Blocked"));
+        board.archive.push(KanbanTask::new("Archived today"));
+        assert_eq!(serialize_kanban(&board), BASIC_EDITED);
+    }
+
+    fn one_card(lines: &str) -> KanbanBoard {
+        parse_kanban(&format!("---\nkanban-plugin: board\n---\n\n## Todo\n\n{}\n\n\n", lines))
+    }
+
+    const CARD: &str = "- [ ] Call #sales the client @ana ⏫ about #Sales mail@example.com @{2024-05-02} @@{10:30}\n\tNotes #travel/eu\n\t- [ ] Hotel\n\t* [X] Train";
+
+    #[test]
+    fn card_fields() {
+        let board = one_card(CARD);
+        let task = &board.columns[0].tasks[0];
+        assert_eq!(task.content, "Call #sales the client @ana ⏫ about #Sales mail@example.com");
+        assert_eq!((task.date.as_deref(), task.time.as_deref()), (Some("2024-05-02"), Some("10:30")));
+        // Sin repetir etiquetas que solo cambian en mayúsculas, y también las del cuerpo
+        assert_eq!(task.tags(), ["sales", "travel/eu"]);
+        // Ni el correo ni la fecha son personas
+        assert_eq!(task.assignees(), ["ana"]);
+        assert_eq!(KanbanTask::new("Meet @{2024-05-02} @@{10:30} @luis").assignees(), ["luis"]);
+        assert_eq!(task.priority(), Priority::High);
+        assert_eq!(KanbanTask::new("No priority").priority(), Priority::None);
+        assert_eq!(task.checklist(), [(false, "Hotel".to_string()), (true, "Train".to_string())]);
+        assert_eq!(task.title(), "Call the client about mail@example.com");
+    }
+
+    #[test]
+    fn draft_keeps_description_tags_in_the_body() {
+        let mut board = one_card(CARD);
+        let task = &mut board.columns[0].tasks[0];
+        let mut draft = CardDraft::new(task, 0, 0);
+        assert_eq!(draft.description, "Notes #travel/eu");
+        assert_eq!(draft.tags, "#sales #travel/eu");
+        draft.tags.push_str(", urgent");
+        draft.apply(task);
+        assert_eq!(task.content, "Call the client about mail@example.com #sales #urgent @ana ⏫");
+        assert_eq!(task.body, ["Notes #travel/eu", "- [ ] Hotel", "- [x] Train"]);
+        assert_eq!(task.tags(), ["sales", "urgent", "travel/eu"]);
+    }
+
+    #[test]
+    fn draft_dates_and_assignees_dont_mix() {
+        let mut board = one_card("- [ ] Plan the launch");
+        let mut draft = CardDraft::new(&board.columns[0].tasks[0], 0, 0);
+        draft.assignees = "ana, @luis".to_string();
+        draft.date = "2024-06-01".to_string();
+        draft.time = " ".to_string();
+        draft.apply(&mut board.columns[0].tasks[0]);
+        let content = serialize_kanban(&board);
+        assert!(content.contains("- [ ] Plan the launch @ana @luis @{2024-06-01}\n"));
+
+        let task = &parse_kanban(&content).columns[0].tasks[0];
+        assert_eq!(task.assignees(), ["ana", "luis"]);
+        assert_eq!(task.date.as_deref(), Some("2024-06-01"));
+        assert_eq!(task.time, None);
+        assert_eq!(task.title(), "Plan the launch");
+    }
+
+    #[test]
+    fn draft_replaces_the_priority_emoji() {
+        let mut board = one_card(CARD);
+        let task = &mut board.columns[0].tasks[0];
+        let mut draft = CardDraft::new(task, 0, 0);
+        assert_eq!(draft.priority, Priority::High);
+        draft.priority = Priority::None;
+        draft.apply(task);
+        assert!(!task.content.contains(Priority::High.emoji()));
+        assert_eq!(task.priority(), Priority::None);
+
+        draft.priority = Priority::Low;
+        draft.apply(task);
+        assert!(task.content.ends_with(" 🔽"));
+        assert!(!task.content.contains(Priority::High.emoji()));
+        assert_eq!(task.priority(), Priority::Low);
+        assert_eq!(task.title(), "Call the client about mail@example.com");
+    }
+
+    #[test]
+    fn new_boards_use_the_plugin_layout() {
+        let mut board = KanbanBoard {
+            frontmatter: "kanban-plugin: board\n".to_string(),
+            ..Default::default()
+        };
+        board.columns.push(KanbanColumn::new("Todo"));
+        board.columns[0].tasks.push(KanbanTask::new("First"));
+        let content = serialize_kanban(&board);
+        assert_eq!(content, "---\nkanban-plugin: board\n---\n\n## Todo\n\n- [ ] First\n\n\n");
+        assert_eq!(serialize_kanban(&parse_kanban(&content)), content);
     }
-    None
 }
@@ -12,9 +12,13 @@ use std::path::Path;
 extern crate json;
 extern crate log;
 
+pub mod anchors;
+pub mod bookmarks;
+pub mod calendar;
 pub mod command_palette;
 pub mod configuraciones;
 pub mod canvas;
+pub mod database;
 pub mod easy_mark;
 pub mod egui_commonmark;
 pub mod egui_commonmark_backend;
@@ -27,22 +31,33 @@ pub mod graph;
 pub mod iconize;
 pub mod income;
 pub mod kanban;
+pub mod links;
 pub mod main_area;
+pub mod periodic;
+pub mod properties;
+pub mod query;
 pub mod screens;
 pub mod search;
 pub mod server;
 pub mod switcher;
 pub mod tabs;
+pub mod tags;
 pub mod tasks;
+pub mod templates;
 pub mod theme;
+pub mod vault_index;
+pub mod vault_tasks;
+pub mod watcher;
 
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Debug, Clone, Copy)]
 pub enum NewFileType {
     Markdown,
     Income,
     Tasks,
     Excalidraw,
     Canvas,
+    Kanban,
+    Table,
 }
 pub struct MShape {
     pub height: f32,
@@ -87,9 +102,19 @@ pub struct Marmol {
     tabs: tabs::Tabs,
 
     new_file_type: NewFileType,
+    new_file_template: Option<String>,
+    new_file_prompts: Vec<(String, String)>,
+    /// Templates listed for the type chosen in the new file dialog, read
+    /// once while it is open.
+    new_file_templates: Option<(NewFileType, Vec<(String, String)>)>,
     marker: Graph,
     enable_icon_folder: bool,
     icon_selector: IconSelector,
+    link_rewriter: links::LinkRewriter,
+    template_picker: templates::TemplatePicker,
+    backups: server::BackupManager,
+    sync: server::sync::SyncClient,
+    watcher: watcher::VaultWatcher,
     icon_pack_installer: IconPackInstaller,
     command_palette: CommandPalette,
     #[cfg(target_os = "android")]
@@ -150,12 +175,20 @@ impl Marmol {
             vault_vec: state.vault_vec,
             current_file: current_path_str,
             new_file_type: NewFileType::Markdown,
+            new_file_template: None,
+            new_file_prompts: Vec::new(),
+            new_file_templates: None,
 
             left_collpased: state.collapsed_left,
             vault_changed: false,
             sort_files: state.sort_files,
             enable_icon_folder: state.enable_icon_folder,
             icon_selector: IconSelector::default(),
+            link_rewriter: links::LinkRewriter::default(),
+            template_picker: templates::TemplatePicker::default(),
+            backups: server::BackupManager::default(),
+            sync: server::sync::SyncClient::default(),
+            watcher: watcher::VaultWatcher::default(),
             icon_pack_installer: IconPackInstaller::default(),
             command_palette: CommandPalette::default(),
             #[cfg(target_os = "android")]
@@ -201,11 +234,19 @@ impl Default for Marmol {
             vault_vec: Vec::new(),
             current_file: String::new(),
             new_file_type: NewFileType::Markdown,
+            new_file_template: None,
+            new_file_prompts: Vec::new(),
+            new_file_templates: None,
             left_collpased: true,
             vault_changed: false,
             sort_files: false,
             enable_icon_folder: false,
             icon_selector: IconSelector::default(),
+            link_rewriter: links::LinkRewriter::default(),
+            template_picker: templates::TemplatePicker::default(),
+            backups: server::BackupManager::default(),
+            sync: server::sync::SyncClient::default(),
+            watcher: watcher::VaultWatcher::default(),
             icon_pack_installer: IconPackInstaller::default(),
             command_palette: CommandPalette::default(),
             #[cfg(target_os = "android")]
@@ -276,6 +317,7 @@ impl eframe::App for Marmol {
             let deleted_file: Option<String> = ctx.data_mut(|d| d.get_temp(egui::Id::new("file_deleted_signal")).flatten());
             if let Some(path) = deleted_file {
                 self.tabs.close_tab_by_path(&path);
+                self.index_file(&path);
                 ctx.data_mut(|d| d.insert_temp(egui::Id::new("file_deleted_signal"), None::<String>));
             }
 
@@ -284,9 +326,53 @@ impl eframe::App for Marmol {
             if let Some(path) = delete_req {
                 let _ = std::fs::remove_file(&path);
                 self.tabs.close_tab_by_path(&path);
+                self.index_file(&path);
                 ctx.data_mut(|d| d.insert_temp(egui::Id::new("delete_file_request"), None::<String>));
             }
 
+            // Check for saved file signal (from the editor) to keep the search index fresh
+            let saved_file: Option<String> = ctx.data_mut(|d| d.get_temp(egui::Id::new("file_saved_signal")).flatten());
+            if let Some(path) = saved_file {
+                self.watcher.ignore_write(&path);
+                self.index_file(&path);
+                ctx.data_mut(|d| d.insert_temp(egui::Id::new("file_saved_signal"), None::<String>));
+            }
+
+            self.backups.tick(&self.vault);
+            if self.sync.tick(&self.vault) {
+                self.index_vault();
+            }
+
+            // Changes made by other programs (git, Syncthing, other editors)
+            self.watcher.watch(&self.vault, ctx);
+            let changed = self.watcher.poll();
+            if !changed.is_empty() {
+                for path in &changed {
+                    self.index_file(path);
+                }
+                self.left_controls.file_tree.invalidate();
+                self.switcher.refresh(&self.vault);
+                self.tabs.reload_changed(ctx, &changed, &self.vault);
+            }
+            self.tabs.conflicts_ui(ctx);
+
+            // Check for renamed/moved files to offer updating the links pointing to them
+            let renamed = if self.link_rewriter.is_open() { None } else { links::take_renamed(ctx) };
+            if let Some((old_path, new_path)) = renamed {
+                self.tabs.rename_tab_path(&old_path, &new_path);
+                bookmarks::rename_path(&self.vault, &old_path, &new_path);
+                if self.current_file == old_path {
+                    self.current_file = new_path.clone();
+                }
+                if std::path::Path::new(&new_path).is_dir() {
+                    self.index_vault();
+                } else {
+                    self.index_file(&old_path);
+                    self.index_file(&new_path);
+                }
+                self.link_rewriter.open(&self.vault, &old_path, &new_path);
+            }
+
             // Check for reveal in navigation signal
             let reveal_req: Option<String> = ctx.data_mut(|d| d.get_temp(egui::Id::new("reveal_in_nav_signal")).flatten());
             if let Some(path) = reveal_req {
@@ -295,6 +381,23 @@ impl eframe::App for Marmol {
                 ctx.data_mut(|d| d.insert_temp(egui::Id::new("reveal_in_nav_signal"), None::<String>));
             }
 
+            // Check for show backlinks signal (from tab menu)
+            let backlinks_req: Option<String> = ctx.data_mut(|d| d.get_temp(egui::Id::new("show_backlinks_signal")).flatten());
+            if let Some(path) = backlinks_req {
+                self.current_file = path;
+                self.left_controls.current_left_tab = main_area::left_controls::enums::LeftTab::Backlinks;
+                self.left_controls.search_index.open(&self.vault);
+                self.left_collpased = true;
+                ctx.data_mut(|d| d.insert_temp(egui::Id::new("show_backlinks_signal"), None::<String>));
+            }
+
+            // Check for open graph signal (from a graph bookmark)
+            let graph_req: Option<bool> = ctx.data_mut(|d| d.get_temp(egui::Id::new("open_graph_signal")).flatten());
+            if graph_req.is_some() {
+                self.tabs.add_graph_tab(&self.vault);
+                ctx.data_mut(|d| d.insert_temp(egui::Id::new("open_graph_signal"), None::<bool>));
+            }
+
             // Check for open icon selector signal
             let icon_req: Option<String> = ctx.data_mut(|d| d.get_temp(egui::Id::new("open_icon_selector_signal")).flatten());
             if let Some(rel_path) = icon_req {
@@ -317,6 +420,14 @@ impl eframe::App for Marmol {
             self.icon_selector
                 .ui(ctx, &self.vault, &mut self.left_controls.icon_manager);
 
+            for path in self.link_rewriter.ui(ctx, &self.vault) {
+                self.index_file(&path);
+            }
+
+            if let Some(insert) = self.template_picker.ui(ctx, &self.vault) {
+                ctx.data_mut(|d| d.insert_temp(egui::Id::new("insert_template_signal"), Some(insert)));
+            }
+
             // Render Command Palette and handle actions
             match self.command_palette.ui(ctx) {
                 CommandAction::OpenIconInstaller => {
@@ -416,6 +527,21 @@ tags: [excalidraw]
                     self.current_file = path;
                     self.tabs.file_changed(&self.current_file);
                 }
+                CommandAction::OpenPeriodicNote(period) => {
+                    if let Some(path) = periodic::open_today(ctx, &self.vault, period) {
+                        self.current_file = path;
+                        self.tabs.file_changed(&self.current_file);
+                    }
+                }
+                CommandAction::OpenTasks => {
+                    self.tabs.add_tasks_tab();
+                }
+                CommandAction::OpenCalendar => {
+                    self.tabs.add_calendar_tab();
+                }
+                CommandAction::InsertTemplate => {
+                    self.template_picker.open(&self.vault, &self.current_file);
+                }
                 CommandAction::None => {}
             }
 
@@ -459,6 +585,8 @@ tags: [excalidraw]
                     self.new_file(ui, ctx.input(|i| i.key_pressed(Key::Enter)));
                     return;
                 }
+                // La próxima vez que se abra el diálogo se vuelven a leer las plantillas
+                self.new_file_templates = None;
 
                 if let Some(file_to_open) = self.switcher.ui(ctx, &self.vault) {
                     self.current_file = file_to_open;
@@ -561,7 +689,11 @@ tags: [excalidraw]
                 self.save_to_disk();
             }
         } else if self.current_window == screens::Screen::Server {
-            screens::set_server(ctx);
+            let prev_win = self.current_window;
+            screens::set_server(ctx, &mut self.current_window, &self.vault, &mut self.backups, &mut self.sync);
+            if prev_win != self.current_window {
+                self.save_to_disk();
+            }
         };
 
         #[cfg(target_os = "android")]
@@ -589,6 +721,19 @@ tags: [excalidraw]
 }
 
 impl Marmol {
+    /// Keeps the search index and the vault metadata index in sync with a
+    /// file written, renamed or deleted.
+    fn index_file(&mut self, path: &str) {
+        self.left_controls.search_index.update_file(path);
+        vault_index::update_file(&self.vault, path);
+    }
+
+    fn index_vault(&mut self) {
+        // Un solo recorrido del disco: el índice de búsqueda usa el del vault
+        vault_index::refresh(&self.vault);
+        self.left_controls.search_index.refresh();
+    }
+
     fn save_to_disk(&mut self) {
         let state = configuraciones::MarmolProgramState {
             vault: self.vault.clone(),
@@ -603,6 +748,7 @@ impl Marmol {
             android_storage: Some(self.android_storage),
         };
         configuraciones::save_program_state(&state);
+        self.left_controls.search_index.save();
 
         let context_path = String::from(&self.config_path) + "/ContextState";
         if let Ok(mut file2) = fs::File::create(context_path) {
@@ -618,6 +764,7 @@ impl Marmol {
         ui.label("Create New File");
         ui.add(egui::TextEdit::singleline(&mut self.new_file_str));
         let new_path = format!("{}/{}", &self.vault, &self.new_file_str);
+        let prev_type = self.new_file_type;
         egui::ComboBox::from_label("Editar categoria")
             .selected_text(&self.new_file_type.to_string())
             .show_ui(ui, |ui| {
@@ -626,7 +773,55 @@ impl Marmol {
                 ui.selectable_value(&mut self.new_file_type, NewFileType::Income, "Income");
                 ui.selectable_value(&mut self.new_file_type, NewFileType::Excalidraw, "Excalidraw");
                 ui.selectable_value(&mut self.new_file_type, NewFileType::Canvas, "Canvas");
+                ui.selectable_value(&mut self.new_file_type, NewFileType::Kanban, "Kanban");
+                ui.selectable_value(&mut self.new_file_type, NewFileType::Table, "Table");
+            });
+        if prev_type != self.new_file_type {
+            self.new_file_template = None;
+            self.new_file_prompts.clear();
+        }
+
+        // Plantillas de la carpeta configurada con la misma extensión que el tipo elegido
+        let extensions: &[&str] = match self.new_file_type {
+            NewFileType::Tasks => &[".graph"],
+            NewFileType::Income => &[".inc"],
+            NewFileType::Canvas => &[".canvas"],
+            NewFileType::Table => &[".table"],
+            NewFileType::Excalidraw => &[".excalidraw.md"],
+            NewFileType::Markdown | NewFileType::Kanban => &[".md"],
+        };
+        let available = match &self.new_file_templates {
+            Some((file_type, available)) if *file_type == self.new_file_type => available.clone(),
+            _ => {
+                let available = templates::TemplateSettings::load(&self.vault).list(&self.vault, extensions);
+                self.new_file_templates = Some((self.new_file_type, available.clone()));
+                available
+            }
+        };
+        let prev_template = self.new_file_template.clone();
+        let selected_name = available
+            .iter()
+            .find(|(_, p)| Some(p) == self.new_file_template.as_ref())
+            .map(|(n, _)| n.clone())
+            .unwrap_or_else(|| "None".to_string());
+        egui::ComboBox::from_label("Template")
+            .selected_text(selected_name)
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut self.new_file_template, None, "None");
+                for (name, path) in &available {
+                    ui.selectable_value(&mut self.new_file_template, Some(path.clone()), name);
+                }
             });
+        if prev_template != self.new_file_template {
+            self.new_file_prompts = self
+                .new_file_template
+                .as_ref()
+                .and_then(|p| fs::read_to_string(p).ok())
+                .map(|c| templates::prompts(&c))
+                .unwrap_or_default();
+        }
+        templates::prompt_fields(ui, &mut self.new_file_prompts);
+
         let path = if self.new_file_type == NewFileType::Tasks {
             format!("{}.graph", new_path)
         } else if self.new_file_type == NewFileType::Income {
@@ -635,6 +830,10 @@ impl Marmol {
             format!("{}.excalidraw.md", new_path)
         } else if self.new_file_type == NewFileType::Canvas {
             format!("{}.canvas", new_path)
+        } else if self.new_file_type == NewFileType::Kanban {
+            format!("{}.md", new_path)
+        } else if self.new_file_type == NewFileType::Table {
+            format!("{}.table", new_path)
         } else {
             String::new()
         };
@@ -651,11 +850,35 @@ impl Marmol {
         } else {
             if ui.button("Create").clicked() || enter_clicked {
                 self.content = main_area::content_enum::Content::View;
+                let rendered = self.new_file_template.as_ref().map(|template| {
+                    let title = new_file
+                        .file_stem()
+                        .and_then(|n| n.to_str())
+                        .map(|n| n.trim_end_matches(".excalidraw").to_string())
+                        .unwrap_or_default();
+                    let vars = templates::TemplateVars {
+                        title,
+                        date: chrono::Local::now().date_naive(),
+                        prompts: self.new_file_prompts.iter().cloned().collect(),
+                    };
+                    let content = fs::read_to_string(template).unwrap_or_default();
+                    let rendered = templates::render(&content, &template_settings, &vars);
+                    if self.new_file_type == NewFileType::Kanban {
+                        // El tablero necesita su frontmatter aunque la plantilla no lo tenga
+                        let header = "---\nkanban-plugin: board\n---\n";
+                        let (text, cursor) = templates::insert_into(header, header.len(), &rendered);
+                        templates::Rendered { text, cursor: rendered.cursor.map(|_| cursor) }
+                    } else {
+                        rendered
+                    }
+                });
                 let res = File::create(new_file);
                 match res {
                     Ok(mut re) => {
                         self.create_file_error = String::new();
-                        if self.new_file_type == NewFileType::Tasks {
+                        if let Some(rendered) = &rendered {
+                            re.write_all(rendered.text.as_bytes()).unwrap();
+                        } else if self.new_file_type == NewFileType::Tasks {
                             let contents = String::from("{\"tasks\":[],\"days\":[],\"top_id\":0}");
                             re.write_all(contents.as_bytes()).unwrap();
                         } else if self.new_file_type == NewFileType::Income {
@@ -688,20 +911,34 @@ tags: [excalidraw]
                         } else if self.new_file_type == NewFileType::Canvas {
                             let contents = String::from("{\"nodes\":[],\"edges\":[]}");
                             re.write_all(contents.as_bytes()).unwrap();
+                        } else if self.new_file_type == NewFileType::Kanban {
+                            let contents = String::from("---\nkanban-plugin: board\n---\n");
+                            re.write_all(contents.as_bytes()).unwrap();
+                        } else if self.new_file_type == NewFileType::Table {
+                            let contents = serde_json::to_string_pretty(&database::TableData::default()).unwrap_or_default();
+                            re.write_all(contents.as_bytes()).unwrap();
                         }
                         self.current_file = String::from(new_file.to_str().unwrap());
+                        if let Some(cursor) = rendered.and_then(|r| r.cursor) {
+                            let signal = Some((self.current_file.clone(), cursor));
+                            ui.ctx().data_mut(|d| d.insert_temp(egui::Id::new("template_cursor_signal"), signal));
+                        }
                     }
                     Err(x) => {
                         self.create_file_error = x.to_string();
                     }
                 }
                 self.new_file_str = String::new();
+                self.new_file_template = None;
+                self.new_file_prompts.clear();
             }
             self.create_file_error = String::new();
         }
         if ui.button("Cancel").clicked() {
             self.content = main_area::content_enum::Content::View;
             self.new_file_str = String::new();
+            self.new_file_template = None;
+            self.new_file_prompts.clear();
         }
     }
 }
@@ -0,0 +1,510 @@
+use egui::{RichText, ScrollArea};
+use regex::{Captures, Regex};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::sync::OnceLock;
+use walkdir::WalkDir;
+
+use crate::vault_index;
+
+/// A file (or every file inside a folder) that changed its location.
+#[derive(Clone)]
+struct Move {
+    old_abs: PathBuf,
+    new_abs: PathBuf,
+    old_rel: String,
+    new_rel: String,
+}
+
+/// Files of the vault at one moment, to resolve the wikilinks the way
+/// Obsidian did before a rename.
+struct LinkTargets {
+    vault: String,
+    files: HashSet<PathBuf>,
+    // nombre de archivo en minúsculas -> rutas
+    by_name: HashMap<String, Vec<String>>,
+}
+
+impl LinkTargets {
+    fn new(vault: &str, paths: impl IntoIterator<Item = PathBuf>) -> Self {
+        let mut targets = LinkTargets {
+            vault: vault.to_string(),
+            files: HashSet::new(),
+            by_name: HashMap::new(),
+        };
+        for path in paths {
+            if !targets.files.insert(path.clone()) {
+                continue;
+            }
+            let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("").to_lowercase();
+            targets.by_name.entry(name).or_default().push(path.to_string_lossy().to_string());
+        }
+        targets
+    }
+
+    /// The vault index with the moved files back where they were. The index
+    /// may or may not have seen the move yet.
+    fn before(vault: &str, moves: &[Move]) -> Self {
+        let paths: Vec<PathBuf> =
+            vault_index::with_index(vault, |index| index.files().map(|f| PathBuf::from(&f.path)).collect());
+        let unmoved = paths
+            .into_iter()
+            .filter(|p| !moves.iter().any(|m| m.new_abs == *p || m.old_abs == *p));
+        Self::new(vault, unmoved.chain(moves.iter().map(|m| m.old_abs.clone())))
+    }
+
+    fn resolve(&self, current: &Path, target: &str) -> Option<PathBuf> {
+        let resolved = vault_index::resolve_link(
+            &self.vault,
+            &current.to_string_lossy(),
+            target,
+            &|p| self.files.contains(&normalize(p)),
+            &|name| self.by_name.get(name).cloned().unwrap_or_default(),
+        )?;
+        Some(normalize(Path::new(&resolved)))
+    }
+
+    /// Whether another file will have the name `m` gets after `moves`, so a
+    /// bare `[[name]]` would be ambiguous.
+    fn name_shared_after(&self, m: &Move, moves: &[Move]) -> bool {
+        let name = file_name(&m.new_rel).to_lowercase();
+        let staying = self
+            .by_name
+            .get(&name)
+            .map_or(0, |paths| paths.iter().filter(|p| !moves.iter().any(|other| other.old_abs == Path::new(p))).count());
+        let arriving = moves.iter().filter(|other| file_name(&other.new_rel).to_lowercase() == name).count();
+        staying + arriving > 1
+    }
+}
+
+pub struct FileEdit {
+    pub path: String,
+    // (número de línea, antes, después)
+    pub changes: Vec<(usize, String, String)>,
+    pub selected: bool,
+    new_content: String,
+}
+
+pub struct RenamePlan {
+    pub old_path: String,
+    pub new_path: String,
+    pub edits: Vec<FileEdit>,
+}
+
+/// Pushes a rename so the main loop can offer to rewrite the links pointing
+/// to `old_path`. Call it after every successful `fs::rename` in the vault.
+pub fn notify_renamed(ctx: &egui::Context, old_path: &str, new_path: &str) {
+    let id = egui::Id::new("file_renamed_signal");
+    let mut pending: Vec<(String, String)> = ctx.data_mut(|d| d.get_temp(id).unwrap_or_default());
+    pending.push((old_path.to_string(), new_path.to_string()));
+    ctx.data_mut(|d| d.insert_temp(id, pending));
+}
+
+/// Takes the oldest pending rename, if any.
+pub fn take_renamed(ctx: &egui::Context) -> Option<(String, String)> {
+    let id = egui::Id::new("file_renamed_signal");
+    let mut pending: Vec<(String, String)> = ctx.data_mut(|d| d.get_temp(id).unwrap_or_default());
+    if pending.is_empty() {
+        return None;
+    }
+    let first = pending.remove(0);
+    ctx.data_mut(|d| d.insert_temp(id, pending));
+    Some(first)
+}
+
+/// Finds every reference to `old_path` (already renamed to `new_path`) in
+/// the markdown and canvas files of the vault and prepares the new contents.
+pub fn plan_rename(vault: &str, old_path: &str, new_path: &str) -> RenamePlan {
+    let moves = collect_moves(vault, old_path, new_path);
+    let targets = LinkTargets::before(vault, &moves);
+    let mut edits = vec![];
+
+    for entry in WalkDir::new(vault)
+        .into_iter()
+        .filter_entry(|e| {
+            e.path() == Path::new(vault)
+                || !e.file_name().to_str().map(|n| n.starts_with('.')).unwrap_or(false)
+        })
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+    {
+        let path = entry.path();
+        let Some(path_str) = path.to_str() else {
+            continue;
+        };
+        let lower = path_str.to_lowercase();
+        let Ok(content) = fs::read_to_string(path) else {
+            continue;
+        };
+
+        let new_content = if lower.ends_with(".md") {
+            // Si el propio archivo se movió, sus enlaces relativos se resuelven desde su ubicación anterior
+            let before = moves
+                .iter()
+                .find(|m| m.new_abs == path)
+                .map(|m| m.old_abs.clone())
+                .unwrap_or_else(|| path.to_path_buf());
+            rewrite_markdown(&content, vault, &before, path, &moves, &targets)
+        } else if lower.ends_with(".canvas") {
+            rewrite_canvas(&content, &moves)
+        } else {
+            continue;
+        };
+
+        if new_content != content {
+            let changes = content
+                .lines()
+                .zip(new_content.lines())
+                .enumerate()
+                .filter(|(_, (a, b))| a != b)
+                .map(|(n, (a, b))| (n + 1, a.trim().to_string(), b.trim().to_string()))
+                .collect();
+            edits.push(FileEdit {
+                path: path_str.to_string(),
+                changes,
+                selected: true,
+                new_content,
+            });
+        }
+    }
+
+    edits.sort_by(|a, b| a.path.cmp(&b.path));
+    RenamePlan {
+        old_path: old_path.to_string(),
+        new_path: new_path.to_string(),
+        edits,
+    }
+}
+
+impl RenamePlan {
+    /// Writes the selected edits, returns the paths that were written.
+    pub fn apply(&self) -> Vec<String> {
+        let mut written = vec![];
+        for edit in self.edits.iter().filter(|e| e.selected) {
+            match fs::write(&edit.path, &edit.new_content) {
+                Ok(_) => written.push(edit.path.clone()),
+                Err(e) => println!("Error actualizando enlaces en {}: {}", edit.path, e),
+            }
+        }
+        written
+    }
+}
+
+/// Preview window shown before rewriting the links of a renamed file.
+#[derive(Default)]
+pub struct LinkRewriter {
+    pub plan: Option<RenamePlan>,
+}
+
+impl LinkRewriter {
+    pub fn is_open(&self) -> bool {
+        self.plan.is_some()
+    }
+
+    pub fn open(&mut self, vault: &str, old_path: &str, new_path: &str) {
+        let plan = plan_rename(vault, old_path, new_path);
+        self.plan = if plan.edits.is_empty() { None } else { Some(plan) };
+    }
+
+    /// Returns the files that were modified when the user confirms.
+    pub fn ui(&mut self, ctx: &egui::Context, vault: &str) -> Vec<String> {
+        let mut written = vec![];
+        let mut close = false;
+        let Some(plan) = &mut self.plan else {
+            return written;
+        };
+
+        let rel = |p: &str| {
+            p.strip_prefix(vault)
+                .unwrap_or(p)
+                .trim_start_matches('/')
+                .to_string()
+        };
+
+        egui::Window::new("Update links")
+            .collapsible(false)
+            .resizable(true)
+            .default_width(500.0)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.label(format!(
+                    "{} → {}",
+                    rel(&plan.old_path),
+                    rel(&plan.new_path)
+                ));
+                let total: usize = plan.edits.iter().map(|e| e.changes.len().max(1)).sum();
+                ui.label(
+                    RichText::new(format!(
+                        "{} links in {} files point to the old location",
+                        total,
+                        plan.edits.len()
+                    ))
+                    .strong(),
+                );
+                ui.separator();
+
+                ScrollArea::vertical().max_height(350.0).show(ui, |ui| {
+                    for edit in &mut plan.edits {
+                        ui.checkbox(&mut edit.selected, rel(&edit.path));
+                        ui.indent(&edit.path, |ui| {
+                            for (line, before, after) in &edit.changes {
+                                ui.label(
+                                    RichText::new(format!("{}: {}", line, before))
+                                        .small()
+                                        .color(ui.visuals().error_fg_color),
+                                );
+                                ui.label(
+                                    RichText::new(format!("{}: {}", line, after))
+                                        .small()
+                                        .color(ui.visuals().selection.stroke.color),
+                                );
+                            }
+                        });
+                    }
+                });
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if ui.button("Update links").clicked() {
+                        written = plan.apply();
+                        close = true;
+                    }
+                    if ui.button("Don't update").clicked() {
+                        close = true;
+                    }
+                });
+            });
+
+        if close {
+            self.plan = None;
+        }
+        written
+    }
+}
+
+fn collect_moves(vault: &str, old_path: &str, new_path: &str) -> Vec<Move> {
+    let make = |old_abs: PathBuf, new_abs: PathBuf| Move {
+        old_rel: vault_relative(vault, &old_abs),
+        new_rel: vault_relative(vault, &new_abs),
+        old_abs,
+        new_abs,
+    };
+
+    if Path::new(new_path).is_dir() {
+        WalkDir::new(new_path)
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file())
+            .filter_map(|e| {
+                let suffix = e.path().strip_prefix(new_path).ok()?;
+                Some(make(Path::new(old_path).join(suffix), e.path().to_path_buf()))
+            })
+            .collect()
+    } else {
+        vec![make(PathBuf::from(old_path), PathBuf::from(new_path))]
+    }
+}
+
+/// Rewrites the links of a note that pointed to a moved file. `before` is
+/// where the note was when its links were written and `targets` the files
+/// of the vault at that time, so each wikilink is resolved as Obsidian did.
+fn rewrite_markdown(content: &str, vault: &str, before: &Path, now: &Path, moves: &[Move], targets: &LinkTargets) -> String {
+    static WIKILINK: OnceLock<Regex> = OnceLock::new();
+    static MDLINK: OnceLock<Regex> = OnceLock::new();
+    let wikilink =
+        WIKILINK.get_or_init(|| Regex::new(r"(!?)\[\[([^\]|#^]+)([#^][^\]|]*)?(\|[^\]]*)?\]\]").unwrap());
+    let mdlink = MDLINK.get_or_init(|| Regex::new(r"(!?\[[^\]]*\])\(([^)\s]+)\)").unwrap());
+
+    let content = wikilink.replace_all(content, |caps: &Captures| {
+        let target = &caps[2];
+        let resolved = targets.resolve(before, target);
+        match moves.iter().find(|m| resolved.as_ref() == Some(&m.old_abs)) {
+            Some(m) => format!(
+                "{}[[{}{}{}]]",
+                &caps[1],
+                new_wikilink_target(target, m, targets.name_shared_after(m, moves)),
+                caps.get(3).map_or("", |c| c.as_str()),
+                caps.get(4).map_or("", |c| c.as_str()),
+            ),
+            None => caps[0].to_string(),
+        }
+    });
+
+    let before_dir = before.parent().unwrap_or(Path::new(vault));
+    let now_dir = now.parent().unwrap_or(Path::new(vault));
+    mdlink
+        .replace_all(&content, |caps: &Captures| {
+            let raw = &caps[2];
+            if raw.contains("://") || raw.starts_with('#') || raw.starts_with("mailto:") {
+                return caps[0].to_string();
+            }
+            let (target, fragment) = match raw.find('#') {
+                Some(i) => (&raw[..i], &raw[i..]),
+                None => (raw, ""),
+            };
+            let decoded = percent_encoding::percent_decode_str(target)
+                .decode_utf8_lossy()
+                .to_string();
+            let resolved = if let Some(stripped) = decoded.strip_prefix('/') {
+                normalize(&Path::new(vault).join(stripped))
+            } else {
+                normalize(&before_dir.join(&decoded))
+            };
+
+            let new_abs = match moves.iter().find(|m| m.old_abs == resolved) {
+                Some(m) => m.new_abs.clone(),
+                // El enlace no cambió de destino, pero el archivo que lo contiene sí
+                None if before != now && !decoded.starts_with('/') => resolved,
+                None => return caps[0].to_string(),
+            };
+
+            let new_target = if decoded.starts_with('/') {
+                format!("/{}", vault_relative(vault, &new_abs))
+            } else {
+                relative_path(now_dir, &new_abs)
+            };
+            // Un espacio sin codificar corta el enlace
+            let new_target = new_target.replace(' ', "%20");
+            format!("{}({}{})", &caps[1], new_target, fragment)
+        })
+        .to_string()
+}
+
+/// Changes only the `"file"` values that point to a moved file, so the rest of
+/// the canvas keeps the formatting it was written with.
+fn rewrite_canvas(content: &str, moves: &[Move]) -> String {
+    static FILE: OnceLock<Regex> = OnceLock::new();
+    let file = FILE.get_or_init(|| Regex::new(r#"("file"\s*:\s*)("(?:[^"\\]|\\.)*")"#).unwrap());
+    file.replace_all(content, |caps: &Captures| {
+        let new_rel = serde_json::from_str::<String>(&caps[2])
+            .ok()
+            .and_then(|old| moves.iter().find(|m| m.old_rel == old))
+            .and_then(|m| serde_json::to_string(&m.new_rel).ok());
+        match new_rel {
+            Some(new_rel) => format!("{}{}", &caps[1], new_rel),
+            None => caps[0].to_string(),
+        }
+    })
+    .to_string()
+}
+
+/// New target of a wikilink to `m`: by path if it was written with one or if
+/// the new name alone would point to another file.
+fn new_wikilink_target(target: &str, m: &Move, ambiguous: bool) -> String {
+    let target = target.trim();
+    let by_path = ambiguous || target.trim_start_matches('/').contains('/');
+    let mut new_target = if by_path {
+        m.new_rel.clone()
+    } else {
+        file_name(&m.new_rel)
+    };
+    if new_target.ends_with(".md") && !target.to_lowercase().ends_with(".md") {
+        new_target.truncate(new_target.len() - 3);
+    }
+    new_target
+}
+
+fn file_name(path: &str) -> String {
+    path.rsplit('/').next().unwrap_or(path).to_string()
+}
+
+fn vault_relative(vault: &str, path: &Path) -> String {
+    path.strip_prefix(vault)
+        .map(|p| p.to_string_lossy().replace('\\', "/"))
+        .unwrap_or_else(|_| path.to_string_lossy().to_string())
+        .trim_start_matches('/')
+        .to_string()
+}
+
+fn normalize(path: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                out.pop();
+            }
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+fn relative_path(from_dir: &Path, to: &Path) -> String {
+    let from: Vec<Component> = from_dir.components().collect();
+    let to_parts: Vec<Component> = to.components().collect();
+    let common = from
+        .iter()
+        .zip(to_parts.iter())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let mut parts: Vec<String> = vec![];
+    for _ in common..from.len() {
+        parts.push("..".to_string());
+    }
+    for c in &to_parts[common..] {
+        parts.push(c.as_os_str().to_string_lossy().to_string());
+    }
+    parts.join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn moved(old: &str, new: &str) -> Move {
+        Move {
+            old_abs: PathBuf::from(format!("/vault/{}", old)),
+            new_abs: PathBuf::from(format!("/vault/{}", new)),
+            old_rel: old.to_string(),
+            new_rel: new.to_string(),
+        }
+    }
+
+    #[test]
+    fn canvas_keeps_its_formatting() {
+        let content = "{\"nodes\":[\n\t{\"id\":\"a\",\"type\":\"file\",\"file\":\"Old note.md\",\"x\":0},\n\t{\"id\":\"b\",\"type\":\"file\", \"file\": \"Other.md\"}\n],\"edges\":[]}";
+        let moves = [moved("Old note.md", "Archive/New \"note\".md")];
+        assert_eq!(
+            rewrite_canvas(content, &moves),
+            content.replace("\"Old note.md\"", "\"Archive/New \\\"note\\\".md\"")
+        );
+        assert_eq!(rewrite_canvas(content, &[moved("Missing.md", "Gone.md")]), content);
+    }
+
+    #[test]
+    fn markdown_links_encode_spaces() {
+        let moves = [moved("Notes/plan.md", "Notes/Project plan.md")];
+        let before = Path::new("/vault/index.md");
+        let targets = LinkTargets::new("/vault", [PathBuf::from("/vault/index.md"), PathBuf::from("/vault/Notes/plan.md")]);
+        let content = "See [plan](Notes/plan.md#Goals) and [[plan|the plan]].";
+        assert_eq!(
+            rewrite_markdown(content, "/vault", before, before, &moves, &targets),
+            "See [plan](Notes/Project%20plan.md#Goals) and [[Project plan|the plan]]."
+        );
+    }
+
+    #[test]
+    fn wikilinks_follow_the_note_they_resolve_to() {
+        let files = ["a/Note.md", "a/todo.md", "b/Note.md", "b/ref.md", "index.md"];
+        let targets = LinkTargets::new("/vault", files.iter().map(|f| PathBuf::from(format!("/vault/{}", f))));
+        let moves = [moved("a/Note.md", "a/Renamed.md")];
+        let rewrite = |source: &str, content: &str| {
+            let path = PathBuf::from(format!("/vault/{}", source));
+            rewrite_markdown(content, "/vault", &path, &path, &moves, &targets)
+        };
+        // Desde b/ el nombre suelto es la nota de al lado, que no se movió
+        assert_eq!(rewrite("b/ref.md", "[[Note]] and [[a/Note#Plan]]"), "[[Note]] and [[a/Renamed#Plan]]");
+        assert_eq!(rewrite("a/todo.md", "[[Note|see]]"), "[[Renamed|see]]");
+
+        // Al mover junto a otra nota del mismo nombre el enlace lleva la ruta
+        let moves = [moved("a/Note.md", "b/Other/Note.md")];
+        let path = PathBuf::from("/vault/a/todo.md");
+        assert_eq!(
+            rewrite_markdown("[[Note]]", "/vault", &path, &path, &moves, &targets),
+            "[[b/Other/Note]]"
+        );
+    }
+}
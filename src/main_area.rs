@@ -1,5 +1,8 @@
+pub mod backlinks;
+pub mod bookmarks_pane;
 pub mod content_enum;
 pub mod file_options;
 pub mod metadata_renderer;
+pub mod tag_pane;
 pub mod left_controls;
 pub mod file_tree;
\ No newline at end of file
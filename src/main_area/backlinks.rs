@@ -0,0 +1,337 @@
+use crate::files;
+use crate::graph::logic::extract_links;
+use crate::search::SearchIndex;
+
+use egui::{CollapsingHeader, Frame, RichText, ScrollArea};
+use regex::Regex;
+use std::fs;
+use std::path::Path;
+
+pub struct BacklinkItem {
+    pub path: String,
+    // Índice de la línea (desde 0) dentro del archivo
+    pub line: usize,
+    pub text: String,
+}
+
+#[derive(Default)]
+pub struct Backlinks {
+    pub for_file: String,
+    pub linked: Vec<BacklinkItem>,
+    pub unlinked: Vec<BacklinkItem>,
+    pub outgoing: Vec<String>,
+}
+
+impl Backlinks {
+    /// Collects the notes linking to `current_file` and the plain text
+    /// mentions of its title, reading only the notes the index says contain
+    /// every word of the title.
+    pub fn refresh(&mut self, index: &SearchIndex, current_file: &str) {
+        self.for_file = current_file.to_string();
+        self.linked.clear();
+        self.unlinked.clear();
+        self.outgoing.clear();
+
+        let Some(title) = note_title(current_file) else {
+            return;
+        };
+        let vault = index.vault().to_string();
+        let resolve = |source: &str, target: &str| {
+            crate::vault_index::with_index(&vault, |vault_index| vault_index.resolve(source, target))
+        };
+        let mention_re = mention_regex(&title);
+
+        if let Ok(content) = fs::read_to_string(current_file) {
+            for line in content.lines() {
+                for link in extract_links(line) {
+                    if !self.outgoing.contains(&link) {
+                        self.outgoing.push(link);
+                    }
+                }
+            }
+        }
+
+        for path in index.files_mentioning(&title) {
+            if !path.ends_with(".md") || path == current_file {
+                continue;
+            }
+            let Ok(content) = fs::read_to_string(&path) else {
+                continue;
+            };
+            for (n, line) in content.lines().enumerate() {
+                let links_here = extract_links(line)
+                    .iter()
+                    .any(|l| link_points_to(l, &path, current_file, &resolve));
+                if links_here {
+                    self.linked.push(BacklinkItem {
+                        path: path.to_string(),
+                        line: n,
+                        text: snippet(line),
+                    });
+                } else if mention_re
+                    .as_ref()
+                    .map(|re| find_mention(re, line).is_some())
+                    .unwrap_or(false)
+                {
+                    self.unlinked.push(BacklinkItem {
+                        path: path.to_string(),
+                        line: n,
+                        text: snippet(line),
+                    });
+                }
+            }
+        }
+
+        self.linked
+            .sort_by(|a, b| a.path.cmp(&b.path).then(a.line.cmp(&b.line)));
+        self.unlinked
+            .sort_by(|a, b| a.path.cmp(&b.path).then(a.line.cmp(&b.line)));
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui, index: &mut SearchIndex, current_file: &mut String) {
+        if self.for_file != *current_file {
+            self.refresh(index, current_file);
+        }
+
+        let Some(title) = note_title(current_file) else {
+            ui.label("Open a note to see its backlinks");
+            return;
+        };
+
+        ui.horizontal(|ui| {
+            ui.label(RichText::new(&title).strong());
+            if ui.small_button("⟳").on_hover_text("Refresh").clicked() {
+                crate::vault_index::refresh(index.vault());
+                index.refresh();
+                self.for_file.clear();
+            }
+        });
+        ui.separator();
+
+        let vault = index.vault().to_string();
+        let mut open_file = None;
+        let mut link_it = None;
+
+        ScrollArea::vertical().show(ui, |ui| {
+            CollapsingHeader::new(format!("Linked mentions ({})", self.linked.len()))
+                .default_open(true)
+                .show(ui, |ui| {
+                    if self.linked.is_empty() {
+                        ui.weak("No backlinks found");
+                    }
+                    for item in &self.linked {
+                        if mention_frame(ui, item, &vault) {
+                            open_file = Some(item.path.clone());
+                        }
+                    }
+                });
+
+            CollapsingHeader::new(format!("Unlinked mentions ({})", self.unlinked.len()))
+                .default_open(false)
+                .show(ui, |ui| {
+                    if self.unlinked.is_empty() {
+                        ui.weak("No unlinked mentions found");
+                    }
+                    for (i, item) in self.unlinked.iter().enumerate() {
+                        if mention_frame(ui, item, &vault) {
+                            open_file = Some(item.path.clone());
+                        }
+                        if ui.small_button("🔗 Link it").clicked() {
+                            link_it = Some(i);
+                        }
+                    }
+                });
+
+            CollapsingHeader::new(format!("Outgoing links ({})", self.outgoing.len()))
+                .default_open(false)
+                .show(ui, |ui| {
+                    for link in &self.outgoing {
+                        match files::resolve_path(&vault, current_file, link) {
+                            Some(path) => {
+                                if ui.link(link).clicked() {
+                                    open_file = Some(path);
+                                }
+                            }
+                            None => {
+                                ui.weak(link).on_hover_text("Not created yet");
+                            }
+                        }
+                    }
+                });
+        });
+
+        if let Some(i) = link_it {
+            let item = &self.unlinked[i];
+            if link_mention(&item.path, item.line, &title) {
+                index.update_file(&item.path);
+                self.for_file.clear();
+            }
+        }
+
+        if let Some(path) = open_file {
+            *current_file = path;
+        }
+    }
+}
+
+// Marco con la ruta y el contexto de una mención. Devuelve true si se pidió abrir el archivo.
+fn mention_frame(ui: &mut egui::Ui, item: &BacklinkItem, vault: &str) -> bool {
+    let mut clicked = false;
+    Frame::group(ui.style()).show(ui, |ui| {
+        ui.set_width(ui.available_width());
+        let rel = item.path.strip_prefix(vault).unwrap_or(&item.path);
+        let rel = rel.trim_start_matches('/');
+        if ui
+            .link(RichText::new(rel).color(ui.visuals().selection.stroke.color))
+            .clicked()
+        {
+            clicked = true;
+        }
+        ui.label(RichText::new(&item.text).small());
+    });
+    clicked
+}
+
+fn note_title(path: &str) -> Option<String> {
+    if !path.ends_with(".md") {
+        return None;
+    }
+    let name = Path::new(path).file_name()?.to_str()?;
+    Some(name.trim_end_matches(".md").to_string())
+}
+
+fn snippet(line: &str) -> String {
+    line.trim().chars().take(200).collect()
+}
+
+/// Whether `link`, written in `source`, resolves to `current_file` the way
+/// Obsidian resolves it, so notes with the same name in other folders
+/// don't count.
+fn link_points_to(link: &str, source: &str, current_file: &str, resolve: &dyn Fn(&str, &str) -> Option<String>) -> bool {
+    let target = link.split(['#', '^']).next().unwrap_or(link).trim();
+    // `[[#Encabezado]]` apunta a la propia nota
+    if target.is_empty() {
+        return false;
+    }
+    resolve(source, target).as_deref() == Some(current_file)
+}
+
+fn mention_regex(title: &str) -> Option<Regex> {
+    Regex::new(&format!(r"(?i)\b{}\b", regex::escape(title))).ok()
+}
+
+// Primera aparición del título fuera de un [[enlace]], en bytes de la línea
+fn find_mention(re: &Regex, line: &str) -> Option<(usize, usize)> {
+    re.find_iter(line)
+        .find(|m| {
+            let before = &line[..m.start()];
+            before.matches("[[").count() <= before.matches("]]").count()
+        })
+        .map(|m| (m.start(), m.end()))
+}
+
+/// Turns the first unlinked mention in `line` of `path` into a wikilink.
+fn link_mention(path: &str, line: usize, title: &str) -> bool {
+    let Ok(content) = fs::read_to_string(path) else {
+        return false;
+    };
+    let Some(new_content) = link_mention_in(&content, line, title) else {
+        return false;
+    };
+    if let Err(e) = fs::write(path, new_content) {
+        println!("Error enlazando {}: {}", path, e);
+        return false;
+    }
+    true
+}
+
+/// `content` with the first unlinked mention of `title` in `line` turned
+/// into a wikilink, or `None` if there is none.
+fn link_mention_in(content: &str, line: usize, title: &str) -> Option<String> {
+    let re = mention_regex(title)?;
+    let mut changed = false;
+    let new_content: String = content
+        .split_inclusive('\n')
+        .enumerate()
+        .map(|(n, l)| {
+            if n != line {
+                return l.to_string();
+            }
+            match find_mention(&re, l) {
+                Some((start, end)) => {
+                    changed = true;
+                    let found = &l[start..end];
+                    let link = if found == title {
+                        format!("[[{}]]", title)
+                    } else {
+                        format!("[[{}|{}]]", title, found)
+                    };
+                    format!("{}{}{}", &l[..start], link, &l[end..])
+                }
+                None => l.to_string(),
+            }
+        })
+        .collect();
+    changed.then_some(new_content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn links_resolve_to_the_right_note() {
+        let files = ["/v/a/Note.md", "/v/b/Note.md", "/v/xa/Note.md", "/v/b/ref.md", "/v/index.md"];
+        let mut by_name: HashMap<String, Vec<String>> = HashMap::new();
+        for file in files {
+            let name = Path::new(file).file_name().unwrap().to_str().unwrap().to_lowercase();
+            by_name.entry(name).or_default().push(file.to_string());
+        }
+        let resolve = |source: &str, target: &str| {
+            crate::vault_index::resolve_link(
+                "/v",
+                source,
+                target,
+                &|p| files.contains(&p.to_str().unwrap_or("")),
+                &|name| by_name.get(name).cloned().unwrap_or_default(),
+            )
+        };
+        let points = |source: &str, link: &str| link_points_to(link, source, "/v/a/Note.md", &resolve);
+
+        assert!(points("/v/index.md", "a/Note#Plan"));
+        assert!(points("/v/b/ref.md", "/a/Note.md"));
+        assert!(points("/v/a/Other.md", "Note^block"));
+        // Desde b/ el nombre suelto es la nota de al lado
+        assert!(!points("/v/b/ref.md", "Note"));
+        assert!(!points("/v/index.md", "b/Note"));
+        assert!(!points("/v/index.md", "#Plan"));
+        // `xa/Note` no es `a/Note`
+        let xa = |link: &str| link_points_to(link, "/v/index.md", "/v/xa/Note.md", &resolve);
+        assert!(xa("xa/Note"));
+        assert!(!xa("a/Note"));
+        assert!(points("/v/b/ref.md", "a/Note"));
+        assert!(!link_points_to("a/Note", "/v/b/ref.md", "/v/xa/Note.md", &resolve));
+    }
+
+    #[test]
+    fn mentions_outside_links() {
+        let re = mention_regex("Big Plan").unwrap();
+        assert_eq!(find_mention(&re, "The big plan is here"), Some((4, 12)));
+        assert_eq!(find_mention(&re, "See [[Big Plan]] and the Big Plan"), Some((25, 33)));
+        assert_eq!(find_mention(&re, "Bigger Planet"), None);
+        assert_eq!(find_mention(&re, "Only [[Big Plan|the big plan]]"), None);
+    }
+
+    #[test]
+    fn linking_a_mention() {
+        let content = "# Notes\nThe big plan, and Big Plan again\n";
+        assert_eq!(
+            link_mention_in(content, 1, "Big Plan").unwrap(),
+            "# Notes\nThe [[Big Plan|big plan]], and Big Plan again\n"
+        );
+        let linked = "Read [[Big Plan]] first, then Big Plan";
+        assert_eq!(link_mention_in(linked, 0, "Big Plan").unwrap(), "Read [[Big Plan]] first, then [[Big Plan]]");
+        assert_eq!(link_mention_in(content, 0, "Big Plan"), None);
+    }
+}
@@ -0,0 +1,233 @@
+use crate::bookmarks::{Bookmark, Bookmarks};
+
+use egui::collapsing_header::CollapsingState;
+use egui::{Id, Label, RichText, ScrollArea, Sense};
+
+// Segundos que se reutiliza la lista antes de volver a leer el archivo
+const REFRESH_SECS: f64 = 2.0;
+
+/// What to do after clicking a bookmark.
+pub enum BookmarkAction {
+    /// Absolute path of the file and its `#heading` or `#^block`.
+    Open(String, Option<String>),
+    RevealFolder(String),
+    Search(String),
+    Graph,
+    Url(String),
+}
+
+#[derive(Default)]
+pub struct BookmarksPane {
+    bookmarks: Bookmarks,
+    loaded: Option<(String, f64)>,
+    /// Item being renamed, its new title and whether the field has the focus.
+    renaming: Option<(Vec<usize>, String, bool)>,
+}
+
+enum Edit {
+    Remove(Vec<usize>),
+    Rename(Vec<usize>, String),
+    Move(Vec<usize>, Vec<usize>, bool),
+}
+
+impl BookmarksPane {
+    pub fn ui(&mut self, ui: &mut egui::Ui, vault: &str, current_file: &str) -> Option<BookmarkAction> {
+        let now = ui.input(|i| i.time);
+        let stale = match &self.loaded {
+            Some((loaded_vault, time)) => loaded_vault != vault || (now - time >= REFRESH_SECS && self.renaming.is_none()),
+            None => true,
+        };
+        if stale {
+            self.bookmarks = Bookmarks::load(vault);
+            self.loaded = Some((vault.to_string(), now));
+        }
+
+        let mut edits = vec![];
+        ui.horizontal(|ui| {
+            if ui.small_button("＋ Group").clicked() {
+                self.bookmarks.items.push(Bookmark::group("New group"));
+                let at = vec![self.bookmarks.items.len() - 1];
+                self.renaming = Some((at, "New group".to_string(), false));
+                self.bookmarks.save(vault);
+            }
+            if !current_file.is_empty() && ui.small_button("＋ Current file").clicked() {
+                crate::bookmarks::add(vault, Bookmark::file(vault, current_file, None));
+                self.loaded = None;
+            }
+        });
+        ui.separator();
+
+        if self.bookmarks.broken {
+            ui.label(RichText::new("bookmarks.json couldn't be read; it won't be changed until it's fixed").weak().small());
+        }
+        if self.bookmarks.items.is_empty() {
+            ui.label("No bookmarks yet");
+            return None;
+        }
+
+        let mut action = None;
+        let items = self.bookmarks.items.clone();
+        ScrollArea::vertical().show(ui, |ui| {
+            self.list_ui(ui, vault, current_file, &items, &[], &mut action, &mut edits);
+            // Soltar debajo de la lista lo lleva al final
+            let (_, dropped) = ui.dnd_drop_zone::<Vec<usize>, ()>(egui::Frame::NONE, |ui| {
+                ui.set_min_size(egui::vec2(ui.available_width(), 40.0));
+            });
+            if let Some(from) = dropped {
+                edits.push(Edit::Move((*from).clone(), vec![self.bookmarks.items.len()], false));
+            }
+        });
+
+        if !edits.is_empty() {
+            for edit in edits {
+                match edit {
+                    Edit::Remove(at) => {
+                        self.bookmarks.take(&at);
+                    }
+                    Edit::Rename(at, title) => {
+                        if let Some(item) = self.bookmarks.get_mut(&at) {
+                            item.set_title(&title);
+                        }
+                    }
+                    Edit::Move(from, to, into_group) => self.bookmarks.move_item(&from, &to, into_group),
+                }
+            }
+            self.bookmarks.save(vault);
+        }
+        action
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn list_ui(
+        &mut self,
+        ui: &mut egui::Ui,
+        vault: &str,
+        current_file: &str,
+        items: &[Bookmark],
+        parent: &[usize],
+        action: &mut Option<BookmarkAction>,
+        edits: &mut Vec<Edit>,
+    ) {
+        for (i, item) in items.iter().enumerate() {
+            let mut at = parent.to_vec();
+            at.push(i);
+            if let Bookmark::Group { items: inner, .. } = item {
+                let id = ui.make_persistent_id(("bookmark_group", &at));
+                CollapsingState::load_with_default_open(ui.ctx(), id, true)
+                    .show_header(ui, |ui| {
+                        self.item_row(ui, vault, current_file, item, &at, action, edits);
+                    })
+                    .body(|ui| {
+                        self.list_ui(ui, vault, current_file, inner, &at, action, edits);
+                    });
+            } else {
+                self.item_row(ui, vault, current_file, item, &at, action, edits);
+            }
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn item_row(
+        &mut self,
+        ui: &mut egui::Ui,
+        vault: &str,
+        current_file: &str,
+        item: &Bookmark,
+        at: &[usize],
+        action: &mut Option<BookmarkAction>,
+        edits: &mut Vec<Edit>,
+    ) {
+        if let Some((renaming_at, title, focused)) = &mut self.renaming {
+            if renaming_at.as_slice() == at {
+                let response = ui.add(egui::TextEdit::singleline(title).desired_width(140.0));
+                if !*focused {
+                    response.request_focus();
+                    *focused = true;
+                }
+                if response.lost_focus() {
+                    if !ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+                        edits.push(Edit::Rename(at.to_vec(), title.clone()));
+                    }
+                    self.renaming = None;
+                }
+                return;
+            }
+        }
+
+        let is_current = matches!(item, Bookmark::File { path, subpath: None, .. }
+            if format!("{}/{}", vault, path) == current_file);
+        let text = RichText::new(format!("{} {}", item.icon(), item.title()));
+        let text = if is_current { text.color(ui.visuals().selection.stroke.color) } else { text };
+        let is_group = matches!(item, Bookmark::Group { .. });
+
+        let dnd = ui.dnd_drag_source(Id::new("dnd_bookmark").with(at), at.to_vec(), |ui| {
+            ui.add(Label::new(text).selectable(false).truncate());
+        });
+        let response = dnd.response.interact(Sense::click());
+
+        // Soltar sobre un elemento lo coloca delante, sobre un grupo lo mete dentro
+        if response.dnd_hover_payload::<Vec<usize>>().is_some() {
+            let stroke = ui.visuals().selection.stroke;
+            if is_group {
+                ui.painter().rect_stroke(response.rect, 2.0, stroke, egui::StrokeKind::Inside);
+            } else {
+                ui.painter().hline(response.rect.x_range(), response.rect.top(), stroke);
+            }
+        }
+        if let Some(from) = response.dnd_release_payload::<Vec<usize>>() {
+            if *from != at {
+                edits.push(Edit::Move((*from).clone(), at.to_vec(), is_group));
+            }
+        }
+
+        if response.clicked() {
+            *action = match item {
+                Bookmark::File { path, subpath, .. } => Some(BookmarkAction::Open(format!("{}/{}", vault, path), subpath.clone())),
+                Bookmark::Folder { path, .. } => Some(BookmarkAction::RevealFolder(format!("{}/{}", vault, path))),
+                Bookmark::Search { query, .. } => Some(BookmarkAction::Search(query.clone())),
+                Bookmark::Graph { .. } => Some(BookmarkAction::Graph),
+                Bookmark::Url { url, .. } => Some(BookmarkAction::Url(url.clone())),
+                Bookmark::Group { .. } | Bookmark::Unknown(_) => None,
+            };
+        }
+
+        response.context_menu(|ui| {
+            let rename = if is_group { "Rename group" } else { "Edit title" };
+            if ui.button(rename).clicked() {
+                self.renaming = Some((at.to_vec(), item.title(), false));
+                ui.close();
+            }
+            if !is_group {
+                ui.menu_button("Move to group", |ui| {
+                    let groups: Vec<(usize, String)> = self
+                        .bookmarks
+                        .items
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, b)| matches!(b, Bookmark::Group { .. }))
+                        .map(|(i, b)| (i, b.title()))
+                        .collect();
+                    if groups.is_empty() {
+                        ui.weak("No groups");
+                    }
+                    for (i, title) in groups {
+                        if ui.button(title).clicked() {
+                            edits.push(Edit::Move(at.to_vec(), vec![i], true));
+                            ui.close();
+                        }
+                    }
+                    if at.len() > 1 && ui.button("Top level").clicked() {
+                        edits.push(Edit::Move(at.to_vec(), vec![self.bookmarks.items.len()], false));
+                        ui.close();
+                    }
+                });
+            }
+            ui.separator();
+            let remove = if is_group { "🗑 Delete group" } else { "Remove bookmark" };
+            if ui.button(remove).clicked() {
+                edits.push(Edit::Remove(at.to_vec()));
+                ui.close();
+            }
+        });
+    }
+}
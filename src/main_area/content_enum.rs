@@ -4,6 +4,7 @@ use serde::{Deserialize, Serialize};
 pub enum Content {
     Edit,
     View,
+    Split,
     NewFile,
     NewTask,
     Graph,
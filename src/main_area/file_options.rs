@@ -15,7 +15,7 @@ pub fn file_options(
 ) {
     ui.label(RichText::new(&*error).color(ui.ctx().style().visuals.error_fg_color));
     let copy = egui::Button::new("Copy file").frame(false);
-    let star = egui::Button::new("Star this file").frame(false);
+    let star = egui::Button::new("Bookmark").frame(false);
     let path_s = Path::new(s).file_name().unwrap();
 
     if ui.add(copy).clicked() {
@@ -90,8 +90,12 @@ pub fn file_options(
     }
 }
 
-pub fn folder_options(ui: &mut egui::Ui, s: &str, _path: &str, error: &mut String) {
+pub fn folder_options(ui: &mut egui::Ui, s: &str, _path: &str, error: &mut String, vault: &str) {
     ui.label(RichText::new(&*error).color(ui.ctx().style().visuals.error_fg_color));
+    if ui.add(egui::Button::new("Bookmark folder").frame(false)).clicked() {
+        crate::bookmarks::add(vault, crate::bookmarks::Bookmark::folder(vault, s));
+        ui.close();
+    }
     let path_s = Path::new(s).file_name().unwrap();
 
     let id = ui.make_persistent_id(format!("del_dir_{}", s));
@@ -48,6 +48,11 @@ impl Default for FileTree {
 }
 
 impl FileTree {
+    /// Forces the next render to read the folders again.
+    pub fn invalidate(&mut self) {
+        self.cache.clear();
+    }
+
     pub fn render(
         &mut self,
         ui: &mut egui::Ui,
@@ -228,6 +233,7 @@ impl FileTree {
                                     .map(|p| p.to_string_lossy().replace('\\', "/"))
                                     .unwrap_or_else(|_| new_path.to_string_lossy().to_string());
                                 icon_manager.rename_icon(vault, &old_rel, &new_rel);
+                                crate::links::notify_renamed(ui.ctx(), &file_location, &new_path.to_string_lossy());
 
                                 if *current_file == file_location {
                                     *current_file = new_path.to_str().unwrap().to_string();
@@ -369,6 +375,7 @@ impl FileTree {
                                 &file_location,
                                 path,
                                 &mut self.menu_error,
+                                vault,
                             );
                         });
 
@@ -401,6 +408,7 @@ impl FileTree {
                                     .map(|p| p.to_string_lossy().replace('\\', "/"))
                                     .unwrap_or_else(|_| target_path.to_string_lossy().to_string());
                                 icon_manager.rename_icon(vault, &old_rel, &new_rel);
+                                crate::links::notify_renamed(ui.ctx(), source_str, &target_path.to_string_lossy());
 
                                 if *current_file == source_str {
                                     *current_file = target_path.to_str().unwrap().to_string();
@@ -443,6 +451,7 @@ impl FileTree {
                                 let old_rel = Path::new(&file_location).strip_prefix(vault).map(|p| p.to_string_lossy().replace('\\', "/")).unwrap_or_else(|_| file_location.clone());
                                 let new_rel = new_path.strip_prefix(vault).map(|p| p.to_string_lossy().replace('\\', "/")).unwrap_or_else(|_| new_path.to_string_lossy().to_string());
                                 icon_manager.rename_icon(vault, &old_rel, &new_rel);
+                                crate::links::notify_renamed(ui.ctx(), &file_location, &new_path.to_string_lossy());
 
                                 if *current_file == file_location {
                                     *current_file = new_path.to_str().unwrap().to_string();
@@ -1,25 +1,27 @@
 pub mod enums;
 
 use crate::iconize::{IconManager, IconSelector};
+use crate::periodic::{self, Period};
 use crate::screens;
 use crate::search;
 use crate::MShape;
 
-use chrono::prelude::*;
 use eframe::egui::{
     Align, Button, Context, Frame, Layout, RichText, ScrollArea, SidePanel, Style, TopBottomPanel,
 };
 use egui::Vec2;
-use egui::{text::LayoutJob, TextFormat, Widget};
+use egui::{text::LayoutJob, TextFormat};
 
 use egui::{Id, Popup};
 use std::fs;
-use std::fs::File;
 use std::path::Path;
 
 use self::enums::{LeftTab, SortOrder};
+use crate::main_area::backlinks::Backlinks;
+use crate::main_area::bookmarks_pane::{BookmarkAction, BookmarksPane};
 use crate::main_area::content_enum::Content;
 use crate::main_area::file_tree::FileTree;
+use crate::main_area::tag_pane::TagPane;
 
 pub struct LeftControls {
     pub current_left_tab: LeftTab,
@@ -27,6 +29,10 @@ pub struct LeftControls {
     pub prev_search_string_menu: String,
     pub search_results: Vec<search::MenuItem>,
     pub regex_search: bool,
+    pub search_index: search::SearchIndex,
+    pub backlinks: Backlinks,
+    pub tag_pane: TagPane,
+    pub bookmarks_pane: BookmarksPane,
 
     pub file_tree: FileTree,
 
@@ -43,6 +49,10 @@ impl Default for LeftControls {
             prev_search_string_menu: "".to_owned(),
             search_results: vec![],
             regex_search: false,
+            search_index: search::SearchIndex::default(),
+            backlinks: Backlinks::default(),
+            tag_pane: TagPane::default(),
+            bookmarks_pane: BookmarksPane::default(),
             file_tree: FileTree::default(),
             icon_manager: IconManager::new(),
             last_vault_path: String::new(),
@@ -134,6 +144,7 @@ impl LeftControls {
                     .clicked()
                 {
                     self.current_left_tab = LeftTab::Search;
+                    self.search_index.open(vault);
                 }
                 if ui
                     .add_sized(
@@ -144,10 +155,41 @@ impl LeftControls {
                                 .tint(color),
                         ),
                     )
+                    .on_hover_text("Bookmarks")
                     .clicked()
                 {
                     self.current_left_tab = LeftTab::Starred;
                 }
+                if ui
+                    .add_sized(
+                        btn_size,
+                        Button::image(
+                            egui::Image::new(egui::include_image!("../../resources/link.svg"))
+                                .fit_to_exact_size(btn_size)
+                                .tint(color),
+                        ),
+                    )
+                    .on_hover_text("Backlinks")
+                    .clicked()
+                {
+                    self.current_left_tab = LeftTab::Backlinks;
+                    self.search_index.open(vault);
+                    self.backlinks.for_file.clear();
+                }
+                if ui
+                    .add_sized(
+                        btn_size,
+                        Button::image(
+                            egui::Image::new(egui::include_image!("../../resources/tag.svg"))
+                                .fit_to_exact_size(btn_size)
+                                .tint(color),
+                        ),
+                    )
+                    .on_hover_text("Tags")
+                    .clicked()
+                {
+                    self.current_left_tab = LeftTab::Tags;
+                }
 
                 ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
                     let sort_btn_response = ui
@@ -329,6 +371,7 @@ impl LeftControls {
                                 let old_rel = Path::new(source_str).strip_prefix(path).map(|p| p.to_string_lossy().replace('\\', "/")).unwrap_or_else(|_| source_str.to_string());
                                 let new_rel = target_path.strip_prefix(path).map(|p| p.to_string_lossy().replace('\\', "/")).unwrap_or_else(|_| target_path.to_string_lossy().to_string());
                                 self.icon_manager.rename_icon(path, &old_rel, &new_rel);
+                                crate::links::notify_renamed(ui.ctx(), source_str, &target_path.to_string_lossy());
 
                                 if *current_file == source_str {
                                     *current_file = target_path.to_str().unwrap().to_string();
@@ -339,11 +382,30 @@ impl LeftControls {
                 }
             });
         } else if self.current_left_tab == LeftTab::Search {
-            ui.text_edit_singleline(&mut self.search_string_menu);
-            ui.checkbox(&mut self.regex_search, "regex");
-            if self.search_string_menu != self.prev_search_string_menu {
-                self.search_results =
-                    search::search_dir(path, &self.search_string_menu, self.regex_search);
+            if self.search_index.vault() != path {
+                self.search_index.open(path);
+            }
+            ui.add(
+                egui::TextEdit::singleline(&mut self.search_string_menu)
+                    .hint_text("tag:#work path:projects/ \"phrase\" -draft"),
+            )
+            .on_hover_text(
+                "Operators: tag: path: file: line: section:\nCombine with OR, AND, NOT, -term and (groups)",
+            );
+            let regex_changed = ui
+                .horizontal(|ui| {
+                    let changed = ui.checkbox(&mut self.regex_search, "regex").changed();
+                    let query = self.search_string_menu.trim();
+                    if ui.add_enabled(!query.is_empty(), Button::new("Bookmark search").small()).clicked() {
+                        crate::bookmarks::add(path, crate::bookmarks::Bookmark::search(query));
+                    }
+                    changed
+                })
+                .inner;
+            if self.search_string_menu != self.prev_search_string_menu || regex_changed {
+                self.search_results = self
+                    .search_index
+                    .search(&self.search_string_menu, self.regex_search);
                 self.prev_search_string_menu = self.search_string_menu.to_string();
             }
             let style_frame = Style::default();
@@ -357,7 +419,7 @@ impl LeftControls {
                     frame.show(ui, |ui| {
                         let mut title = LayoutJob::default();
                         title.append(
-                            &i.path.strip_prefix(&path).unwrap(),
+                            i.path.strip_prefix(&path).unwrap_or(&i.path),
                             0.0,
                             TextFormat {
                                 color: ui.ctx().style().visuals.selection.stroke.color,
@@ -365,35 +427,53 @@ impl LeftControls {
                             },
                         );
                         ui.label(title);
-                        ui.label(&i.text);
+                        for m in &i.matches {
+                            ui.horizontal_wrapped(|ui| {
+                                ui.label(RichText::new(format!("{}:", m.line)).weak().small());
+                                ui.label(&m.text);
+                            });
+                        }
                         if ui.button("open file").clicked() {
                             *current_file = String::from(&i.path);
                         };
                     });
                 }
             });
+        } else if self.current_left_tab == LeftTab::Backlinks {
+            if self.search_index.vault() != path {
+                self.search_index.open(path);
+            }
+            self.backlinks.ui(ui, &mut self.search_index, current_file);
+        } else if self.current_left_tab == LeftTab::Tags {
+            if self.search_index.vault() != path {
+                self.search_index.open(path);
+            }
+            if let Some(search) = self.tag_pane.ui(ui, path, &mut self.search_index) {
+                self.search_string_menu = search;
+                self.current_left_tab = LeftTab::Search;
+            }
         } else if self.current_left_tab == LeftTab::Starred {
-            let contents = match fs::read_to_string(format!("{}/.obsidian/starred.json", path)) {
-                Ok(x) => x.as_str().to_owned(),
-                _ => {
-                    ui.label("No starred file found!");
-                    return;
+            match self.bookmarks_pane.ui(ui, path, current_file) {
+                Some(BookmarkAction::Open(file, None)) => *current_file = file,
+                Some(BookmarkAction::Open(file, Some(subpath))) => {
+                    crate::anchors::follow_link(ui.ctx(), path, &file, &subpath);
                 }
-            };
-            let parsed = json::parse(&contents).unwrap();
-            for (_key, value) in parsed.entries() {
-                for i in 0..value.len() {
-                    let text = parsed["items"][i]["path"].as_str().unwrap();
-                    let full_path = format!("{}/{}", path, text);
-                    if full_path == current_file.as_str() {
-                        ui.label(RichText::new(text).color(ui.style().visuals.selection.bg_fill));
-                    } else {
-                        let btn = Button::new(text).frame(false);
-                        if btn.ui(ui).clicked() {
-                            *current_file = Path::new(&full_path).to_str().unwrap().to_owned();
-                        }
+                Some(BookmarkAction::RevealFolder(folder)) => {
+                    self.file_tree.reveal_path = Some(folder);
+                    self.current_left_tab = LeftTab::Files;
+                }
+                Some(BookmarkAction::Search(query)) => {
+                    if self.search_index.vault() != path {
+                        self.search_index.open(path);
                     }
+                    self.search_string_menu = query;
+                    self.current_left_tab = LeftTab::Search;
+                }
+                Some(BookmarkAction::Graph) => {
+                    ui.ctx().data_mut(|d| d.insert_temp(Id::new("open_graph_signal"), Some(true)));
                 }
+                Some(BookmarkAction::Url(url)) => ui.ctx().open_url(egui::OpenUrl::new_tab(url)),
+                None => {}
             }
         }
     }
@@ -470,7 +550,7 @@ impl LeftControls {
                     println!("canvas")
                 }
                 ui.add_space(space);
-                if ui
+                let daily = ui
                     .add(egui::Button::image(
                         egui::Image::new(egui::include_image!(
                             "../../resources/calendar-check.svg"
@@ -478,11 +558,36 @@ impl LeftControls {
                         .fit_to_exact_size(btn_size)
                         .tint(color),
                     ))
-                    .on_hover_text("Daily note")
-                    .clicked()
-                {
-                    Self::create_date_file(vault, current_file);
+                    .on_hover_text("Daily note");
+                if daily.clicked() {
+                    if let Some(path) = periodic::open_today(ui.ctx(), vault, Period::Daily) {
+                        *current_file = path;
+                    }
                 }
+                daily.context_menu(|ui| {
+                    let settings = periodic::settings(ui.ctx(), vault);
+                    for period in Period::ALL {
+                        let enabled = period == Period::Daily || settings.get(period).enabled;
+                        if ui
+                            .add_enabled(enabled, egui::Button::new(format!("{} note", period.name())))
+                            .clicked()
+                        {
+                            if let Some(path) = periodic::open_today(ui.ctx(), vault, period) {
+                                *current_file = path;
+                            }
+                            ui.close();
+                        }
+                    }
+                    ui.separator();
+                    if ui.button("Open calendar").clicked() {
+                        tabs.add_calendar_tab();
+                        ui.close();
+                    }
+                    if ui.button("Open tasks").clicked() {
+                        tabs.add_tasks_tab();
+                        ui.close();
+                    }
+                });
                 ui.add_space(space);
                 if ui
                     .add(egui::Button::image(
@@ -554,15 +659,4 @@ impl LeftControls {
             });
         });
     }
-
-    fn create_date_file(path: &String, current_file: &mut String) {
-        let date = Local::now().format("%Y-%m-%d").to_string();
-        let file_name = format!("{}/{}.md", path, date);
-        if Path::new(&file_name).exists() {
-            *current_file = file_name.to_string();
-        } else {
-            File::create(&file_name).expect("Unable to create file");
-            *current_file = file_name.to_string();
-        }
-    }
-}
\ No newline at end of file
+}
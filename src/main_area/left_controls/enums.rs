@@ -15,4 +15,6 @@ pub enum LeftTab {
     Files,
     Starred,
     Search,
+    Backlinks,
+    Tags,
 }
\ No newline at end of file
@@ -1,85 +1,39 @@
-use egui::{text::LayoutJob, Color32, TextFormat};
-use yaml_rust::{YamlEmitter, YamlLoader};
+use egui::{Color32, RichText};
 
-pub fn create_metadata(metadata: String, ui: &mut egui::Ui) {
-    let result = YamlLoader::load_from_str(&metadata);
-    let Ok(docs) = result else {
-        egui::Frame::group(ui.style())
-            .fill(ui.visuals().faint_bg_color)
-            .corner_radius(5.0)
-            .inner_margin(10.0)
-            .stroke(egui::Stroke::new(1.0, ui.visuals().error_fg_color))
-            .show(ui, |ui| {
-                ui.set_width(ui.available_width());
-                ui.label("Bad Formatting! :(");
-            });
-        ui.add_space(5.0);
-        return;
-    };
+use crate::properties::{self, Property, PropertyType, Value};
 
-    if docs.is_empty() {
-        return;
+/// Properties panel shown above the note. Returns the new content of the
+/// note when a property was edited.
+pub fn properties_editor(ui: &mut egui::Ui, vault: &str, path: &str, content: &str) -> Option<String> {
+    let adding_id = egui::Id::new("property_adding").with(path);
+    let adding: bool = ui.ctx().data(|d| d.get_temp(adding_id).unwrap_or(false));
+    if !properties::has_frontmatter(content) && !adding {
+        return None;
     }
 
-    let metadata_parsed = &docs[0];
-    let mut job = LayoutJob::default();
-
-    let mut out_str = String::new();
-    let mut emitter = YamlEmitter::new(&mut out_str);
-    emitter.dump(metadata_parsed).unwrap();
-    out_str.split("\n").skip(1).for_each(|s| {
-        if s.is_empty() {
-            return;
+    let props = match properties::read(content) {
+        Ok(props) => props,
+        Err(error) => {
+            // YAML inválido: se enseña tal cual para arreglarlo en el editor
+            egui::Frame::group(ui.style())
+                .fill(ui.visuals().faint_bg_color)
+                .corner_radius(5.0)
+                .inner_margin(10.0)
+                .stroke(egui::Stroke::new(1.0, ui.visuals().error_fg_color))
+                .show(ui, |ui| {
+                    ui.set_width(ui.available_width());
+                    ui.colored_label(ui.visuals().error_fg_color, format!("Invalid properties: {}", error));
+                    if let (Some(frontmatter), _) = crate::vault_index::split_frontmatter(content) {
+                        ui.label(RichText::new(frontmatter.trim_end()).monospace());
+                    }
+                });
+            ui.add_space(5.0);
+            return None;
         }
-        if s.ends_with(':') {
-            job.append(
-                &(s.to_owned() + "\n"),
-                0.0,
-                TextFormat {
-                    color: ui.ctx().style().visuals.widgets.inactive.fg_stroke.color,
-                    ..Default::default()
-                },
-            )
-        } else if s.starts_with(' ') {
-            job.append(
-                &(s.to_owned() + "\n"),
-                0.0,
-                TextFormat {
-                    color: ui.ctx().style().visuals.override_text_color.unwrap_or(Color32::WHITE),
-                    ..Default::default()
-                },
-            )
-        } else {
-            let mut splitted = s.split(" ");
-            let mut content: &str;
-            let mut text = splitted.next();
-            match text {
-                Some(x) => content = x,
-                None => content = "Error parsing",
-            }
-            job.append(
-                content,
-                0.0,
-                TextFormat {
-                    color: ui.ctx().style().visuals.widgets.inactive.fg_stroke.color,
-                    ..Default::default()
-                },
-            );
-            text = splitted.next();
-            match text {
-                Some(x) => content = x,
-                None => content = "Error parsing",
-            }
-            job.append(
-                &format!("{}\n", content),
-                0.0,
-                TextFormat {
-                    color: ui.ctx().style().visuals.override_text_color.unwrap_or(Color32::WHITE),
-                    ..Default::default()
-                },
-            );
-        }
-    });
+    };
+
+    let types = properties::types(ui.ctx(), vault);
+    let mut updated: Option<String> = None;
     egui::Frame::group(ui.style())
         .fill(ui.visuals().faint_bg_color)
         .corner_radius(5.0)
@@ -90,6 +44,306 @@ pub fn create_metadata(metadata: String, ui: &mut egui::Ui) {
         ))
         .show(ui, |ui| {
             ui.set_width(ui.available_width());
-            ui.label(job);
+            egui::Grid::new(egui::Id::new("properties_grid").with(path))
+                .num_columns(2)
+                .spacing([12.0, 6.0])
+                .show(ui, |ui| {
+                    for property in &props {
+                        let property_type = types.type_of(property);
+                        if let Some(new) = key_cell(ui, vault, content, property, property_type) {
+                            updated = Some(new);
+                        }
+                        let id = egui::Id::new("property_value").with(path).with(&property.key);
+                        if let Some(value) = value_cell(ui, id, vault, path, property, property_type) {
+                            updated = Some(properties::set(content, &property.key, &value));
+                        }
+                        ui.end_row();
+                    }
+                });
+
+            if adding {
+                if let Some(new) = add_row(ui, vault, path, content) {
+                    updated = Some(new);
+                }
+            } else if ui.small_button("＋ Add property").clicked() {
+                ui.ctx().data_mut(|d| d.insert_temp(adding_id, true));
+            }
+        });
+    ui.add_space(5.0);
+    updated
+}
+
+/// Name of the property with its menu: type, rename and remove.
+fn key_cell(ui: &mut egui::Ui, vault: &str, content: &str, property: &Property, property_type: PropertyType) -> Option<String> {
+    let mut updated = None;
+    let label = RichText::new(format!("{} {}", property_type.icon(), property.key))
+        .color(ui.visuals().widgets.inactive.fg_stroke.color);
+    ui.menu_button(label, |ui| {
+        ui.label(RichText::new("Property type").strong());
+        for t in PropertyType::ALL {
+            if ui.selectable_label(t == property_type, format!("{} {}", t.icon(), t.name())).clicked() {
+                properties::set_type(ui.ctx(), vault, &property.key, t);
+                ui.close();
+            }
+        }
+        ui.separator();
+
+        let rename_id = egui::Id::new("property_rename").with(&property.key);
+        let mut new_key: String = ui.ctx().data(|d| d.get_temp(rename_id)).unwrap_or_else(|| property.key.clone());
+        ui.horizontal(|ui| {
+            let response = ui.add(egui::TextEdit::singleline(&mut new_key).desired_width(140.0));
+            let enter = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+            let exists = new_key.trim() != property.key
+                && properties::read(content)
+                    .map(|props| props.iter().any(|p| p.key == new_key.trim()))
+                    .unwrap_or(false);
+            let valid = !new_key.trim().is_empty() && new_key.trim() != property.key && !exists;
+            if (ui.add_enabled(valid, egui::Button::new("Rename")).clicked() || enter) && valid {
+                updated = Some(properties::rename(content, &property.key, new_key.trim()));
+                ui.close();
+            }
+            if exists {
+                ui.colored_label(ui.visuals().warn_fg_color, "Already exists");
+            }
         });
-}
\ No newline at end of file
+        ui.ctx().data_mut(|d| d.insert_temp(rename_id, new_key));
+        ui.separator();
+
+        if ui.button("🗑 Remove property").clicked() {
+            updated = Some(properties::remove(content, &property.key));
+            ui.close();
+        }
+    });
+    updated
+}
+
+/// Widget for the value of the property. Returns the new value once an
+/// edit is finished.
+pub fn value_cell(
+    ui: &mut egui::Ui,
+    id: egui::Id,
+    vault: &str,
+    path: &str,
+    property: &Property,
+    property_type: PropertyType,
+) -> Option<Value> {
+    if let Value::Other(raw) = &property.value {
+        ui.label(RichText::new(raw).monospace().weak());
+        return None;
+    }
+    match property_type {
+        PropertyType::Checkbox => {
+            let mut checked = match &property.value {
+                Value::Checkbox(checked) => *checked,
+                other => other.as_text().trim() == "true",
+            };
+            ui.checkbox(&mut checked, "").changed().then_some(Value::Checkbox(checked))
+        }
+        PropertyType::Number => {
+            let current = property.value.as_text();
+            ui.horizontal(|ui| {
+                let text = text_field(ui, id, &current, "0", |t| t.trim().is_empty() || t.trim().parse::<f64>().is_ok())?;
+                Some(Value::Number(text))
+            })
+            .inner
+        }
+        PropertyType::Date => {
+            let current = property.value.as_text();
+            ui.horizontal(|ui| {
+                let edited = text_field(ui, id, &current, "YYYY-MM-DD", |t| t.trim().is_empty() || properties::is_date(t));
+                if ui.small_button("Today").clicked() {
+                    return Some(Value::Text(chrono::Local::now().format("%Y-%m-%d").to_string()));
+                }
+                edited.map(Value::Text)
+            })
+            .inner
+        }
+        PropertyType::Text => {
+            let current = property.value.as_text();
+            ui.horizontal(|ui| {
+                let mut edited = text_field(ui, id, &current, "Empty", |_| true);
+                if let Some(picked) = suggestions_menu(ui, id, vault, &property.key, &[]) {
+                    edited = Some(picked);
+                }
+                edited.map(Value::Text)
+            })
+            .inner
+        }
+        PropertyType::List | PropertyType::Links => {
+            let items = property.value.as_list();
+            ui.horizontal_wrapped(|ui| {
+                let mut new_items = items.clone();
+                for (i, item) in items.iter().enumerate() {
+                    let chip = if property_type == PropertyType::Links && properties::is_link(item) {
+                        let target = item.trim().trim_start_matches("[[").trim_end_matches("]]");
+                        let name = target.split('|').next_back().unwrap_or(target);
+                        if ui.link(name).clicked() {
+                            crate::anchors::follow_link(ui.ctx(), vault, path, target);
+                        }
+                        ui.small_button("✕")
+                    } else {
+                        ui.small_button(format!("{} ✕", item))
+                    };
+                    if chip.on_hover_text("Remove").clicked() {
+                        new_items.remove(i);
+                        return Some(Value::List(new_items));
+                    }
+                }
+
+                let hint = if property_type == PropertyType::Links { "Add link" } else { "Add item" };
+                let mut added = text_field(ui, id, "", hint, |_| true);
+                if let Some(picked) = suggestions_menu(ui, id, vault, &property.key, &items) {
+                    added = Some(picked);
+                }
+                let added = added.map(|a| a.trim().to_string()).filter(|a| !a.is_empty())?;
+                let added = if property_type == PropertyType::Links && !properties::is_link(&added) {
+                    format!("[[{}]]", added)
+                } else {
+                    added
+                };
+                new_items.push(added);
+                // Un solo enlace escrito como texto sigue siendo texto
+                if matches!(property.value, Value::Text(_)) && new_items.len() == 1 {
+                    return new_items.pop().map(Value::Text);
+                }
+                Some(Value::List(new_items))
+            })
+            .inner
+        }
+    }
+}
+
+/// Single line editor that commits when it loses the focus. Invalid text
+/// is shown in red and not committed.
+fn text_field(ui: &mut egui::Ui, id: egui::Id, current: &str, hint: &str, valid: impl Fn(&str) -> bool) -> Option<String> {
+    let edit_id = id.with("edit");
+    let focused = ui.memory(|m| m.has_focus(edit_id));
+    let mut buffer: String = if focused {
+        ui.ctx().data(|d| d.get_temp(edit_id)).unwrap_or_else(|| current.to_string())
+    } else {
+        current.to_string()
+    };
+    let is_valid = valid(&buffer);
+    let mut edit = egui::TextEdit::singleline(&mut buffer)
+        .id(edit_id)
+        .hint_text(hint)
+        .desired_width(ui.available_width().clamp(60.0, 220.0));
+    if !is_valid {
+        edit = edit.text_color(Color32::RED);
+    }
+    let response = ui.add(edit);
+    ui.ctx().data_mut(|d| d.insert_temp(edit_id, buffer.clone()));
+    (response.lost_focus() && is_valid && buffer != current).then_some(buffer)
+}
+
+/// "▾" menu with the values other notes use for `key`.
+fn suggestions_menu(ui: &mut egui::Ui, id: egui::Id, vault: &str, key: &str, exclude: &[String]) -> Option<String> {
+    let mut picked = None;
+    let menu = ui.menu_button("▾", |ui| {
+        // Se calculan al abrir el menú y se guardan mientras siga abierto
+        let cache_id = id.with("suggestions");
+        let values: Vec<String> = match ui.ctx().data(|d| d.get_temp(cache_id)) {
+            Some(values) => values,
+            None => {
+                let values = properties::vault_values(vault, key);
+                ui.ctx().data_mut(|d| d.insert_temp(cache_id, values.clone()));
+                values
+            }
+        };
+        let values: Vec<&String> = values.iter().filter(|v| !exclude.contains(v)).take(30).collect();
+        if values.is_empty() {
+            ui.weak("No values in other notes");
+        }
+        egui::ScrollArea::vertical().max_height(240.0).show(ui, |ui| {
+            for value in values {
+                if ui.button(value).clicked() {
+                    picked = Some(value.clone());
+                    ui.close();
+                }
+            }
+        });
+    });
+    if picked.is_some() || menu.inner.is_none() {
+        ui.ctx().data_mut(|d| d.remove::<Vec<String>>(id.with("suggestions")));
+    }
+    picked
+}
+
+/// Row to add a new property: name, type and "Add".
+fn add_row(ui: &mut egui::Ui, vault: &str, path: &str, content: &str) -> Option<String> {
+    let adding_id = egui::Id::new("property_adding").with(path);
+    let key_id = adding_id.with("key");
+    let type_id = adding_id.with("type");
+    let mut key: String = ui.ctx().data(|d| d.get_temp(key_id)).unwrap_or_default();
+    let mut property_type: Option<PropertyType> = ui.ctx().data(|d| d.get_temp(type_id));
+    let mut updated = None;
+    ui.separator();
+    ui.horizontal(|ui| {
+        let response = ui.add(egui::TextEdit::singleline(&mut key).hint_text("Property name").desired_width(160.0));
+        ui.menu_button("▾", |ui| {
+            let cache_id = adding_id.with("keys");
+            let keys: Vec<String> = match ui.ctx().data(|d| d.get_temp(cache_id)) {
+                Some(keys) => keys,
+                None => {
+                    let keys = properties::vault_keys(vault);
+                    ui.ctx().data_mut(|d| d.insert_temp(cache_id, keys.clone()));
+                    keys
+                }
+            };
+            egui::ScrollArea::vertical().max_height(240.0).show(ui, |ui| {
+                for k in keys.iter().filter(|k| k.to_lowercase().contains(&key.to_lowercase())).take(30) {
+                    if ui.button(k).clicked() {
+                        key = k.clone();
+                        ui.close();
+                    }
+                }
+            });
+        });
+
+        // Sin elegir tipo se usa el registrado en el vault
+        let registered = properties::types(ui.ctx(), vault).get(key.trim());
+        let shown = property_type.or(registered).unwrap_or(PropertyType::Text);
+        egui::ComboBox::from_id_salt(type_id)
+            .selected_text(format!("{} {}", shown.icon(), shown.name()))
+            .show_ui(ui, |ui| {
+                for t in PropertyType::ALL {
+                    if ui.selectable_label(t == shown, format!("{} {}", t.icon(), t.name())).clicked() {
+                        property_type = Some(t);
+                    }
+                }
+            });
+
+        let exists = properties::read(content)
+            .map(|props| props.iter().any(|p| p.key == key.trim()))
+            .unwrap_or(false);
+        let valid = !key.trim().is_empty() && !exists;
+        let enter = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+        if (ui.add_enabled(valid, egui::Button::new("Add")).clicked() || enter) && valid {
+            let key = key.trim();
+            if registered.is_none() || property_type.is_some() {
+                properties::set_type(ui.ctx(), vault, key, shown);
+            }
+            updated = Some(properties::set(content, key, &shown.empty_value()));
+        }
+        if exists {
+            ui.colored_label(ui.visuals().warn_fg_color, "Already exists");
+        }
+        if ui.small_button("Cancel").clicked() || updated.is_some() {
+            ui.ctx().data_mut(|d| {
+                d.remove::<bool>(adding_id);
+                d.remove::<String>(key_id);
+                d.remove::<PropertyType>(type_id);
+                d.remove::<Vec<String>>(adding_id.with("keys"));
+            });
+        }
+    });
+    if updated.is_none() && ui.ctx().data(|d| d.get_temp::<bool>(adding_id)).unwrap_or(false) {
+        ui.ctx().data_mut(|d| {
+            d.insert_temp(key_id, key);
+            if let Some(t) = property_type {
+                d.insert_temp(type_id, t);
+            }
+        });
+    }
+    updated
+}
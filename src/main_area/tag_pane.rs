@@ -0,0 +1,141 @@
+use crate::search::SearchIndex;
+use crate::tags::{self, TagNode};
+
+use egui::collapsing_header::CollapsingState;
+use egui::{Align, Button, Layout, RichText, ScrollArea};
+
+// Segundos que se reutiliza el árbol antes de volver a leer el índice
+const REFRESH_SECS: f64 = 2.0;
+
+#[derive(Default)]
+pub struct TagPane {
+    pub filter: String,
+    tree: Vec<TagNode>,
+    loaded: Option<(String, f64)>,
+    /// Tag being renamed, its new name and whether the field has the focus.
+    renaming: Option<(String, String, bool)>,
+    /// Rename confirmed with Enter, applied after drawing the tree.
+    confirmed: Option<(String, String)>,
+    message: String,
+}
+
+impl TagPane {
+    /// Shows the tag tree. Returns the search to run when a tag is clicked.
+    pub fn ui(&mut self, ui: &mut egui::Ui, vault: &str, search_index: &mut SearchIndex) -> Option<String> {
+        let now = ui.input(|i| i.time);
+        let stale = match &self.loaded {
+            Some((loaded_vault, time)) => loaded_vault != vault || now - time >= REFRESH_SECS,
+            None => true,
+        };
+        if stale {
+            self.tree = tags::tag_tree(vault);
+            self.loaded = Some((vault.to_string(), now));
+        }
+
+        ui.add(egui::TextEdit::singleline(&mut self.filter).hint_text("Filter tags"));
+        if !self.message.is_empty() {
+            ui.label(RichText::new(&self.message).weak().small());
+        }
+        if self.tree.is_empty() {
+            ui.label("No tags in this vault");
+            return None;
+        }
+
+        let mut search = None;
+        let filter = self.filter.trim().trim_start_matches('#').to_lowercase();
+        let tree = std::mem::take(&mut self.tree);
+        ScrollArea::vertical().show(ui, |ui| {
+            for node in &tree {
+                if let Some(s) = self.node_ui(ui, node, &filter) {
+                    search = Some(s);
+                }
+            }
+        });
+        self.tree = tree;
+
+        if let Some((old, new)) = self.confirmed.take() {
+            let written = tags::rename_tag(vault, &old, &new);
+            for path in &written {
+                search_index.update_file(path);
+            }
+            self.message = format!("#{} → #{} in {} notes", old, new, written.len());
+            self.loaded = None;
+        }
+        search
+    }
+
+    fn node_ui(&mut self, ui: &mut egui::Ui, node: &TagNode, filter: &str) -> Option<String> {
+        if !filter.is_empty() && !matches_filter(node, filter) {
+            return None;
+        }
+        let mut search = None;
+        if node.children.is_empty() {
+            search = self.tag_row(ui, node);
+        } else {
+            let id = ui.make_persistent_id(("tag_node", &node.full));
+            // Al filtrar se abren las ramas para ver lo encontrado
+            let mut state = CollapsingState::load_with_default_open(ui.ctx(), id, false);
+            if !filter.is_empty() {
+                state.set_open(true);
+            }
+            state
+                .show_header(ui, |ui| {
+                    search = self.tag_row(ui, node);
+                })
+                .body(|ui| {
+                    for child in &node.children {
+                        if let Some(s) = self.node_ui(ui, child, filter) {
+                            search = Some(s);
+                        }
+                    }
+                });
+        }
+        search
+    }
+
+    fn tag_row(&mut self, ui: &mut egui::Ui, node: &TagNode) -> Option<String> {
+        let mut search = None;
+        ui.horizontal(|ui| {
+            if let Some((old, new, focused)) = &mut self.renaming {
+                if *old == node.full {
+                    let response = ui.add(egui::TextEdit::singleline(new).desired_width(120.0));
+                    if !*focused {
+                        response.request_focus();
+                        *focused = true;
+                    }
+                    if response.lost_focus() {
+                        let enter = ui.input(|i| i.key_pressed(egui::Key::Enter));
+                        let new = new.trim().trim_start_matches('#').to_string();
+                        if enter && !new.is_empty() && new != *old {
+                            self.confirmed = Some((old.clone(), new));
+                        }
+                        self.renaming = None;
+                    }
+                    return;
+                }
+            }
+            let response = ui.add(Button::new(format!("#{}", node.name)).frame(false));
+            if response.clicked() {
+                search = Some(format!("tag:#{}", node.full));
+            }
+            response.on_hover_text(format!("#{}", node.full)).context_menu(|ui| {
+                if ui.button("Search notes with this tag").clicked() {
+                    search = Some(format!("tag:#{}", node.full));
+                    ui.close();
+                }
+                if ui.button("Rename tag").clicked() {
+                    self.renaming = Some((node.full.clone(), node.full.clone(), false));
+                    ui.close();
+                }
+            });
+            ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                ui.label(RichText::new(node.count.to_string()).weak());
+            });
+        });
+        search
+    }
+}
+
+fn matches_filter(node: &TagNode, filter: &str) -> bool {
+    node.full.to_lowercase().contains(filter) || node.children.iter().any(|c| matches_filter(c, filter))
+}
@@ -0,0 +1,408 @@
+use chrono::prelude::*;
+use chrono::Duration;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::templates;
+use crate::vault_index;
+
+// Configuración propia de Marmol (los formatos son de chrono, no de moment.js)
+const SETTINGS_FILE: &str = ".obsidian/marmol-periodic-notes.json";
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub enum Period {
+    Daily,
+    Weekly,
+    Monthly,
+}
+
+impl Period {
+    pub const ALL: [Period; 3] = [Period::Daily, Period::Weekly, Period::Monthly];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Period::Daily => "Daily",
+            Period::Weekly => "Weekly",
+            Period::Monthly => "Monthly",
+        }
+    }
+
+    /// First day of the period containing `date`.
+    pub fn start(&self, date: NaiveDate) -> NaiveDate {
+        match self {
+            Period::Daily => date,
+            Period::Weekly => date - Duration::days(date.weekday().num_days_from_monday() as i64),
+            Period::Monthly => date.with_day(1).unwrap_or(date),
+        }
+    }
+
+    pub fn shift(&self, date: NaiveDate, n: i32) -> NaiveDate {
+        match self {
+            Period::Daily => date + Duration::days(n as i64),
+            Period::Weekly => date + Duration::weeks(n as i64),
+            Period::Monthly => {
+                let months = date.year() * 12 + date.month0() as i32 + n;
+                NaiveDate::from_ymd_opt(months.div_euclid(12), months.rem_euclid(12) as u32 + 1, 1)
+                    .unwrap_or(date)
+            }
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct PeriodConfig {
+    pub enabled: bool,
+    pub folder: String,
+    pub format: String,
+    pub template: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct PeriodicSettings {
+    pub daily: PeriodConfig,
+    pub weekly: PeriodConfig,
+    pub monthly: PeriodConfig,
+}
+
+impl Default for PeriodicSettings {
+    fn default() -> Self {
+        let config = |enabled: bool, format: &str| PeriodConfig {
+            enabled,
+            folder: String::new(),
+            format: format.to_string(),
+            template: String::new(),
+        };
+        Self {
+            daily: config(true, "%Y-%m-%d"),
+            weekly: config(false, "%G-W%V"),
+            monthly: config(false, "%Y-%m"),
+        }
+    }
+}
+
+impl PeriodicSettings {
+    pub fn load(vault: &str) -> Self {
+        match fs::read_to_string(Path::new(vault).join(SETTINGS_FILE)) {
+            Ok(data) => serde_json::from_str(&data).unwrap_or_default(),
+            Err(_) => Self::from_obsidian(vault).unwrap_or_default(),
+        }
+    }
+
+    pub fn save(&self, vault: &str) {
+        let path = Path::new(vault).join(SETTINGS_FILE);
+        if let Some(parent) = path.parent() {
+            let _ = fs::create_dir_all(parent);
+        }
+        match serde_json::to_string_pretty(self) {
+            Ok(data) => {
+                if let Err(e) = fs::write(&path, data) {
+                    println!("Error guardando la configuración de notas periódicas: {}", e);
+                }
+            }
+            Err(e) => println!("Error serializando la configuración de notas periódicas: {}", e),
+        }
+    }
+
+    // Reutiliza la configuración del plugin "Daily notes" de Obsidian si existe
+    fn from_obsidian(vault: &str) -> Option<Self> {
+        let data = fs::read_to_string(Path::new(vault).join(".obsidian/daily-notes.json")).ok()?;
+        let json: serde_json::Value = serde_json::from_str(&data).ok()?;
+        let mut settings = Self::default();
+        if let Some(folder) = json.get("folder").and_then(|f| f.as_str()) {
+            settings.daily.folder = folder.trim_matches('/').to_string();
+        }
+        if let Some(format) = json.get("format").and_then(|f| f.as_str()).filter(|f| !f.is_empty()) {
+            match moment_to_chrono(format) {
+                Some(format) => settings.daily.format = format,
+                None => println!("Formato de notas diarias no compatible, se usa el predeterminado: {}", format),
+            }
+        }
+        if let Some(template) = json.get("template").and_then(|t| t.as_str()) {
+            settings.daily.template = template.to_string();
+        }
+        Some(settings)
+    }
+
+    pub fn get(&self, period: Period) -> &PeriodConfig {
+        match period {
+            Period::Daily => &self.daily,
+            Period::Weekly => &self.weekly,
+            Period::Monthly => &self.monthly,
+        }
+    }
+
+    pub fn get_mut(&mut self, period: Period) -> &mut PeriodConfig {
+        match period {
+            Period::Daily => &mut self.daily,
+            Period::Weekly => &mut self.weekly,
+            Period::Monthly => &mut self.monthly,
+        }
+    }
+
+    pub fn note_path(&self, vault: &str, period: Period, date: NaiveDate) -> PathBuf {
+        let config = self.get(period);
+        let name = format_date(period.start(date), &config.format)
+            .unwrap_or_else(|| period.start(date).format("%Y-%m-%d").to_string());
+        let mut path = Path::new(vault).to_path_buf();
+        if !config.folder.trim().is_empty() {
+            path.push(config.folder.trim().trim_matches('/'));
+        }
+        path.push(format!("{}.md", name));
+        path
+    }
+
+    /// Opens the note of the period containing `date`, creating it (and its
+    /// folders) from the configured template when it doesn't exist.
+    pub fn open_or_create(&self, vault: &str, period: Period, date: NaiveDate) -> Option<String> {
+        let path = self.note_path(vault, period, date);
+        if !path.exists() {
+            if let Some(parent) = path.parent() {
+                if let Err(e) = fs::create_dir_all(parent) {
+                    println!("Error creando {}: {}", parent.display(), e);
+                    return None;
+                }
+            }
+            let title = path.file_stem()?.to_str()?.to_string();
+            let content = self.template_content(vault, period, date, &title);
+            if let Err(e) = fs::write(&path, content) {
+                println!("Error creando {}: {}", path.display(), e);
+                return None;
+            }
+        }
+        path.to_str().map(|s| s.to_string())
+    }
+
+    fn template_content(&self, vault: &str, period: Period, date: NaiveDate, title: &str) -> String {
+        let template = self.get(period).template.trim();
+        if template.is_empty() {
+            return String::new();
+        }
+        let mut path = Path::new(vault).join(template);
+        if path.extension().is_none() {
+            path.set_extension("md");
+        }
+        let content = fs::read_to_string(&path).unwrap_or_default();
+        let vars = templates::TemplateVars {
+            title: title.to_string(),
+            date,
+            prompts: Default::default(),
+        };
+        // {{cursor}} no aplica aquí: la nota se abre en modo lectura
+        templates::render(&content, &templates::TemplateSettings::load(vault), &vars).text
+    }
+
+    /// Period and date of a periodic note, if `path` is one.
+    pub fn note_date(&self, vault: &str, path: &str) -> Option<(Period, NaiveDate)> {
+        let file = Path::new(path);
+        let stem = file.file_stem()?.to_str()?;
+        if file.extension()?.to_str()? != "md" {
+            return None;
+        }
+        let parent = file.parent()?;
+        Period::ALL.into_iter().find_map(|period| {
+            let config = self.get(period);
+            if period != Period::Daily && !config.enabled {
+                return None;
+            }
+            let folder = Path::new(vault).join(config.folder.trim().trim_matches('/'));
+            if parent != folder.as_path() {
+                return None;
+            }
+            parse_date(stem, &config.format, period).map(|d| (period, d))
+        })
+    }
+
+    /// The closest existing note of the same period before or after `date`.
+    pub fn adjacent(&self, vault: &str, period: Period, date: NaiveDate, forward: bool) -> Option<String> {
+        let config = self.get(period);
+        let folder = Path::new(vault).join(config.folder.trim().trim_matches('/'));
+        let entries = fs::read_dir(&folder).ok()?;
+        entries
+            .filter_map(|e| e.ok())
+            .filter_map(|e| {
+                let path = e.path();
+                if path.extension()?.to_str()? != "md" {
+                    return None;
+                }
+                let d = parse_date(path.file_stem()?.to_str()?, &config.format, period)?;
+                Some((d, path.to_str()?.to_string()))
+            })
+            .filter(|(d, _)| if forward { *d > date } else { *d < date })
+            .min_by_key(|(d, _)| if forward { (*d - date).num_days() } else { (date - *d).num_days() })
+            .map(|(_, p)| p)
+    }
+}
+
+fn parse_date(stem: &str, format: &str, period: Period) -> Option<NaiveDate> {
+    if let Ok(d) = NaiveDate::parse_from_str(stem, format) {
+        return Some(d);
+    }
+    // Formatos sin día (semanas y meses): se completa con el primer día del periodo
+    let date = match period {
+        Period::Daily => None,
+        Period::Weekly => NaiveDate::parse_from_str(&format!("{} 1", stem), &format!("{} %u", format)).ok(),
+        Period::Monthly => NaiveDate::parse_from_str(&format!("{} 01", stem), &format!("{} %d", format)).ok(),
+    }?;
+    // Evita que otro nombre cualquiera "parezca" una fecha
+    (format_date(date, format)? == stem).then_some(date)
+}
+
+/// Formats `date`, returning None for invalid format strings instead of panicking.
+pub fn format_date(date: NaiveDate, format: &str) -> Option<String> {
+    use std::fmt::Write;
+    let mut out = String::new();
+    write!(out, "{}", date.format(format)).ok()?;
+    Some(out)
+}
+
+// Traducción de los tokens más comunes de moment.js. None si usa ordinales
+// ("Do" es "1st"), que chrono no sabe escribir ni leer
+pub(crate) fn moment_to_chrono(format: &str) -> Option<String> {
+    let tokens = [
+        ("YYYY", "%Y"),
+        ("GGGG", "%G"),
+        ("gggg", "%G"),
+        ("YY", "%y"),
+        ("MMMM", "%B"),
+        ("MMM", "%b"),
+        ("MM", "%m"),
+        ("DDDD", "%j"),
+        ("DD", "%d"),
+        ("dddd", "%A"),
+        ("ddd", "%a"),
+        ("WW", "%V"),
+        ("ww", "%V"),
+        ("D", "%-d"),
+        ("M", "%-m"),
+        ("HH", "%H"),
+        ("H", "%-H"),
+        ("mm", "%M"),
+        ("ss", "%S"),
+        ("A", "%p"),
+    ];
+    let mut out = String::new();
+    let mut rest = format;
+    'outer: while !rest.is_empty() {
+        // Texto entre corchetes se copia tal cual
+        if let Some(stripped) = rest.strip_prefix('[') {
+            if let Some(end) = stripped.find(']') {
+                out.push_str(&stripped[..end].replace('%', "%%"));
+                rest = &stripped[end + 1..];
+                continue;
+            }
+        }
+        if rest.starts_with("Do") {
+            return None;
+        }
+        for (moment, chrono) in tokens {
+            if let Some(stripped) = rest.strip_prefix(moment) {
+                out.push_str(chrono);
+                rest = stripped;
+                continue 'outer;
+            }
+        }
+        let c = rest.chars().next().unwrap();
+        if c == '%' {
+            out.push('%');
+        }
+        out.push(c);
+        rest = &rest[c.len_utf8()..];
+    }
+    Some(out)
+}
+
+/// Settings of `vault`, cached in the context so tabs can check every frame
+/// whether they show a periodic note.
+pub fn settings(ctx: &egui::Context, vault: &str) -> PeriodicSettings {
+    let id = egui::Id::new("periodic_settings");
+    let cached: Option<(String, PeriodicSettings)> = ctx.data(|d| d.get_temp(id));
+    if let Some((cached_vault, settings)) = cached {
+        if cached_vault == vault {
+            return settings;
+        }
+    }
+    let settings = PeriodicSettings::load(vault);
+    ctx.data_mut(|d| d.insert_temp(id, (vault.to_string(), settings.clone())));
+    settings
+}
+
+/// Previous and next notes of the periodic note at `path`, cached per path
+/// until the vault index changes (a note created, renamed or deleted).
+pub fn adjacent_notes(
+    ctx: &egui::Context,
+    vault: &str,
+    path: &str,
+    period: Period,
+    date: NaiveDate,
+) -> (Option<String>, Option<String>) {
+    let settings = settings(ctx, vault);
+    let config = settings.get(period);
+    let id = egui::Id::new("periodic_adjacent").with(path).with((&config.folder, &config.format));
+    let generation = vault_index::generation();
+    let cached: Option<(u64, Option<String>, Option<String>)> = ctx.data(|d| d.get_temp(id));
+    if let Some((cached_generation, prev, next)) = cached {
+        if cached_generation == generation {
+            return (prev, next);
+        }
+    }
+    let prev = settings.adjacent(vault, period, date, false);
+    let next = settings.adjacent(vault, period, date, true);
+    ctx.data_mut(|d| d.insert_temp(id, (generation, prev.clone(), next.clone())));
+    (prev, next)
+}
+
+/// Opens (creating it if needed) the note of `period` for today.
+pub fn open_today(ctx: &egui::Context, vault: &str, period: Period) -> Option<String> {
+    settings(ctx, vault).open_or_create(vault, period, Local::now().date_naive())
+}
+
+/// Settings section shown in the configuration screen.
+pub fn settings_ui(ui: &mut egui::Ui, vault: &str) {
+    let mut current = settings(ui.ctx(), vault);
+    let settings = &mut current;
+    let old = settings.clone();
+    egui::CollapsingHeader::new(egui::RichText::new("Daily notes").strong()).show(ui, |ui| {
+        for period in Period::ALL {
+            let config = settings.get_mut(period);
+            ui.group(|ui| {
+                if period == Period::Daily {
+                    ui.label(egui::RichText::new(period.name()).strong());
+                } else {
+                    ui.checkbox(&mut config.enabled, format!("{} notes", period.name()));
+                }
+                egui::Grid::new(("periodic_settings", period.name()))
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        ui.label("Folder:");
+                        ui.add(egui::TextEdit::singleline(&mut config.folder).hint_text("Vault root"));
+                        ui.end_row();
+                        ui.label("Format:");
+                        ui.text_edit_singleline(&mut config.format).on_hover_text(
+                            "chrono format, e.g. %Y-%m-%d, %G-W%V or %Y-%m",
+                        );
+                        ui.end_row();
+                        ui.label("Template:");
+                        ui.add(
+                            egui::TextEdit::singleline(&mut config.template)
+                                .hint_text("templates/daily.md"),
+                        );
+                        ui.end_row();
+                    });
+                let today = Local::now().date_naive();
+                match format_date(period.start(today), &config.format) {
+                    Some(name) => ui.weak(format!("Today: {}.md", name)),
+                    None => ui.colored_label(ui.visuals().error_fg_color, "Invalid format"),
+                };
+            });
+        }
+    });
+    if *settings != old && !vault.is_empty() {
+        settings.save(vault);
+        ui.ctx().data_mut(|d| {
+            d.insert_temp(egui::Id::new("periodic_settings"), (vault.to_string(), settings.clone()))
+        });
+    }
+}
@@ -0,0 +1,613 @@
+//! Frontmatter properties. Keys are edited one at a time, rewriting only
+//! their own lines, so the order and comments of the YAML stay as written.
+
+use chrono::{NaiveDate, NaiveDateTime};
+use std::collections::HashMap;
+use std::fs;
+use std::ops::Range;
+use std::path::Path;
+use yaml_rust::{Yaml, YamlEmitter, YamlLoader};
+
+use crate::vault_index::{self, split_frontmatter};
+
+// El mismo archivo donde Obsidian guarda los tipos de las propiedades
+const TYPES_FILE: &str = ".obsidian/types.json";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PropertyType {
+    Text,
+    Number,
+    Date,
+    Checkbox,
+    List,
+    Links,
+}
+
+impl PropertyType {
+    pub const ALL: [PropertyType; 6] = [
+        PropertyType::Text,
+        PropertyType::Number,
+        PropertyType::Date,
+        PropertyType::Checkbox,
+        PropertyType::List,
+        PropertyType::Links,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            PropertyType::Text => "Text",
+            PropertyType::Number => "Number",
+            PropertyType::Date => "Date",
+            PropertyType::Checkbox => "Checkbox",
+            PropertyType::List => "List",
+            PropertyType::Links => "Links",
+        }
+    }
+
+    pub fn icon(self) -> &'static str {
+        match self {
+            PropertyType::Text => "🗛",
+            PropertyType::Number => "#",
+            PropertyType::Date => "📅",
+            PropertyType::Checkbox => "☑",
+            PropertyType::List => "☰",
+            PropertyType::Links => "🔗",
+        }
+    }
+
+    fn key(self) -> &'static str {
+        match self {
+            PropertyType::Text => "text",
+            PropertyType::Number => "number",
+            PropertyType::Date => "date",
+            PropertyType::Checkbox => "checkbox",
+            PropertyType::List => "multitext",
+            PropertyType::Links => "links",
+        }
+    }
+
+    fn from_key(key: &str) -> Option<Self> {
+        match key {
+            "text" => Some(PropertyType::Text),
+            "number" => Some(PropertyType::Number),
+            "date" | "datetime" => Some(PropertyType::Date),
+            "checkbox" => Some(PropertyType::Checkbox),
+            "multitext" | "tags" | "aliases" => Some(PropertyType::List),
+            "links" => Some(PropertyType::Links),
+            _ => None,
+        }
+    }
+
+    /// Empty value used when a property of this type is added.
+    pub fn empty_value(self) -> Value {
+        match self {
+            PropertyType::Checkbox => Value::Checkbox(false),
+            PropertyType::List | PropertyType::Links => Value::List(vec![]),
+            PropertyType::Number => Value::Number(String::new()),
+            _ => Value::Text(String::new()),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Text(String),
+    Number(String),
+    Checkbox(bool),
+    List(Vec<String>),
+    /// Maps and other YAML that isn't edited, as written.
+    Other(String),
+}
+
+impl Value {
+    /// Type guessed from the value when the vault has none registered.
+    pub fn guess_type(&self) -> PropertyType {
+        match self {
+            Value::Checkbox(_) => PropertyType::Checkbox,
+            Value::Number(_) => PropertyType::Number,
+            Value::List(items) if !items.is_empty() && items.iter().all(|i| is_link(i)) => PropertyType::Links,
+            Value::List(_) => PropertyType::List,
+            Value::Text(text) if is_date(text) => PropertyType::Date,
+            Value::Text(text) if is_link(text) => PropertyType::Links,
+            Value::Text(_) | Value::Other(_) => PropertyType::Text,
+        }
+    }
+
+    /// The value as a single line of text.
+    pub fn as_text(&self) -> String {
+        match self {
+            Value::Text(text) | Value::Number(text) | Value::Other(text) => text.clone(),
+            Value::Checkbox(checked) => checked.to_string(),
+            Value::List(items) => items.join(", "),
+        }
+    }
+
+    /// The value as list items; a text is a list of one.
+    pub fn as_list(&self) -> Vec<String> {
+        match self {
+            Value::List(items) => items.clone(),
+            Value::Text(text) | Value::Number(text) if !text.is_empty() => vec![text.clone()],
+            _ => vec![],
+        }
+    }
+}
+
+pub fn is_link(text: &str) -> bool {
+    let text = text.trim();
+    text.starts_with("[[") && text.ends_with("]]")
+}
+
+/// `YYYY-MM-DD`, optionally with a time as Obsidian writes it.
+pub fn is_date(text: &str) -> bool {
+    let text = text.trim();
+    NaiveDate::parse_from_str(text, "%Y-%m-%d").is_ok()
+        || NaiveDateTime::parse_from_str(text, "%Y-%m-%dT%H:%M").is_ok()
+        || NaiveDateTime::parse_from_str(text, "%Y-%m-%dT%H:%M:%S").is_ok()
+}
+
+#[derive(Clone, Debug)]
+pub struct Property {
+    pub key: String,
+    pub value: Value,
+}
+
+fn scalar_text(yaml: &Yaml) -> Option<String> {
+    match yaml {
+        Yaml::String(s) => Some(s.clone()),
+        Yaml::Integer(n) => Some(n.to_string()),
+        Yaml::Real(r) => Some(r.clone()),
+        Yaml::Boolean(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+fn from_yaml(yaml: &Yaml) -> Value {
+    match yaml {
+        Yaml::String(s) => Value::Text(s.clone()),
+        Yaml::Integer(n) => Value::Number(n.to_string()),
+        Yaml::Real(r) => Value::Number(r.clone()),
+        Yaml::Boolean(b) => Value::Checkbox(*b),
+        Yaml::Null => Value::Text(String::new()),
+        Yaml::Array(items) => match items.iter().map(scalar_text).collect::<Option<Vec<_>>>() {
+            Some(items) => Value::List(items),
+            None => Value::Other(dump(yaml)),
+        },
+        _ => Value::Other(dump(yaml)),
+    }
+}
+
+fn dump(yaml: &Yaml) -> String {
+    let mut out = String::new();
+    let _ = YamlEmitter::new(&mut out).dump(yaml);
+    out.trim_start_matches("---").trim().to_string()
+}
+
+/// Properties of the note in the order they are written. `Err` has the
+/// parse error of a frontmatter that isn't valid YAML.
+pub fn read(content: &str) -> Result<Vec<Property>, String> {
+    let (Some(frontmatter), _) = split_frontmatter(content) else {
+        return Ok(vec![]);
+    };
+    let docs = YamlLoader::load_from_str(frontmatter).map_err(|e| e.to_string())?;
+    match docs.into_iter().next() {
+        None | Some(Yaml::Null) => Ok(vec![]),
+        Some(Yaml::Hash(hash)) => Ok(hash
+            .iter()
+            .filter_map(|(key, value)| {
+                Some(Property {
+                    key: scalar_text(key)?,
+                    value: from_yaml(value),
+                })
+            })
+            .collect()),
+        Some(_) => Err("The frontmatter is not a list of properties".to_string()),
+    }
+}
+
+/// Whether the note starts with a frontmatter block.
+pub fn has_frontmatter(content: &str) -> bool {
+    split_frontmatter(content).0.is_some()
+}
+
+/// Key of a top-level `key: value` line and the byte where the key ends.
+fn line_key(line: &str) -> Option<(String, usize)> {
+    let first = line.chars().next()?;
+    if first.is_whitespace() || first == '#' || first == '-' {
+        return None;
+    }
+    if first == '"' || first == '\'' {
+        let end = line[1..].find(first)? + 1;
+        return line[end + 1..]
+            .trim_start()
+            .starts_with(':')
+            .then(|| (line[1..end].to_string(), end + 1));
+    }
+    let trimmed = line.trim_end();
+    let end = trimmed.find(": ").or_else(|| trimmed.strip_suffix(':').map(|k| k.len()))?;
+    Some((line[..end].trim_end().to_string(), end))
+}
+
+/// Byte ranges of the lines of each top-level key, including its indented
+/// or list lines. Blank lines and comments between keys are left out; the
+/// comments among the value lines fall inside, see [`keep_comments`].
+fn entries(frontmatter: &str) -> Vec<(String, Range<usize>)> {
+    let mut result: Vec<(String, Range<usize>)> = vec![];
+    let mut offset = 0;
+    for line in frontmatter.split_inclusive('\n') {
+        let end = offset + line.len();
+        if let Some((key, _)) = line_key(line) {
+            result.push((key, offset..end));
+        } else if !line.trim().is_empty() && (line.starts_with(char::is_whitespace) || line.starts_with('-')) {
+            // Las líneas en blanco intermedias solo cuentan si el valor sigue después
+            if let Some((_, range)) = result.last_mut() {
+                range.end = end;
+            }
+        }
+        offset = end;
+    }
+    result
+}
+
+/// Trailing `# comment` of a `key: value` line, with the spaces before it.
+fn inline_comment(line: &str) -> Option<&str> {
+    let (_, key_end) = line_key(line)?;
+    let line = line.trim_end_matches(['\n', '\r']);
+    let value_start = key_end + line[key_end..].find(':')? + 1;
+    let value = &line[value_start..];
+    let mut from = value.len() - value.trim_start().len();
+    // Dentro de un valor entre comillas un # no es un comentario
+    let mut chars = value[from..].char_indices();
+    if let Some((_, quote @ ('"' | '\''))) = chars.next() {
+        let mut closed = false;
+        while let Some((i, c)) = chars.next() {
+            if quote == '"' && c == '\\' {
+                chars.next();
+            } else if c == quote {
+                if quote == '\'' && value[from + i + 1..].starts_with('\'') {
+                    chars.next();
+                    continue;
+                }
+                from += i + 1;
+                closed = true;
+                break;
+            }
+        }
+        if !closed {
+            return None;
+        }
+    }
+    // Un # solo empieza un comentario al principio o tras un espacio
+    let hash = value[from..]
+        .char_indices()
+        .find(|&(i, c)| c == '#' && (from + i == 0 || value[..from + i].ends_with([' ', '\t'])))
+        .map(|(i, _)| from + i)?;
+    Some(&line[value_start + value[..hash].trim_end().len()..])
+}
+
+/// `entry` with the comments of `old`, the lines it replaces: the one at the
+/// end of the key line and the comment lines among the values.
+fn keep_comments(old: &str, entry: &str) -> String {
+    let mut lines = old.split_inclusive('\n');
+    let first = lines.next().unwrap_or("");
+    let comment_lines: String = lines.filter(|l| l.trim_start().starts_with('#')).collect();
+    let (head, rest) = entry.split_at(entry.find('\n').map_or(entry.len(), |i| i + 1));
+    let head = match inline_comment(first) {
+        Some(comment) => format!("{}{}\n", head.trim_end_matches('\n'), comment),
+        None => head.to_string(),
+    };
+    format!("{}{}{}", head, comment_lines, rest)
+}
+
+/// Replaces the frontmatter of `content` with what `edit` returns, creating
+/// the block if the note has none.
+fn edit_frontmatter(content: &str, edit: impl FnOnce(&str) -> String) -> String {
+    match split_frontmatter(content) {
+        (Some(frontmatter), _) => {
+            let start = frontmatter.as_ptr() as usize - content.as_ptr() as usize;
+            let end = start + frontmatter.len();
+            format!("{}{}{}", &content[..start], edit(frontmatter), &content[end..])
+        }
+        (None, _) => {
+            let frontmatter = edit("");
+            if frontmatter.is_empty() {
+                content.to_string()
+            } else {
+                format!("---\n{}---\n{}", frontmatter, content)
+            }
+        }
+    }
+}
+
+fn yaml_key(key: &str) -> String {
+    let plain = !key.is_empty()
+        && !key.contains(": ")
+        && !key.ends_with(':')
+        && !key.starts_with(|c: char| c.is_whitespace() || "#-\"'[{&*!|>%@`".contains(c));
+    if plain {
+        key.to_string()
+    } else {
+        format!("\"{}\"", key.replace('\\', "\\\\").replace('"', "\\\""))
+    }
+}
+
+fn yaml_scalar(text: &str) -> String {
+    match serde_yaml::to_string(text) {
+        Ok(yaml) => yaml.trim_end().to_string(),
+        Err(_) => format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\"")),
+    }
+}
+
+/// Lines of `key: value`, ending with a newline.
+fn render_entry(key: &str, value: &Value) -> String {
+    let key = yaml_key(key);
+    match value {
+        Value::Text(text) | Value::Number(text) if text.trim().is_empty() => format!("{}:\n", key),
+        Value::Number(text) if text.trim().parse::<f64>().is_ok() => format!("{}: {}\n", key, text.trim()),
+        Value::Text(text) | Value::Number(text) => format!("{}: {}\n", key, yaml_scalar(text)),
+        Value::Checkbox(checked) => format!("{}: {}\n", key, checked),
+        Value::List(items) if items.is_empty() => format!("{}: []\n", key),
+        Value::List(items) => {
+            let mut lines = format!("{}:\n", key);
+            for item in items {
+                lines.push_str(&format!("  - {}\n", yaml_scalar(item)));
+            }
+            lines
+        }
+        Value::Other(raw) => format!("{}:\n  {}\n", key, raw.replace('\n', "\n  ")),
+    }
+}
+
+/// Sets `key` to `value`, adding it at the end if the note doesn't have it.
+pub fn set(content: &str, key: &str, value: &Value) -> String {
+    edit_frontmatter(content, |frontmatter| {
+        let entry = render_entry(key, value);
+        match entries(frontmatter).into_iter().find(|(k, _)| k == key) {
+            Some((_, range)) => format!(
+                "{}{}{}",
+                &frontmatter[..range.start],
+                keep_comments(&frontmatter[range.clone()], &entry),
+                &frontmatter[range.end..]
+            ),
+            None if frontmatter.is_empty() || frontmatter.ends_with('\n') => format!("{}{}", frontmatter, entry),
+            None => format!("{}\n{}", frontmatter, entry),
+        }
+    })
+}
+
+/// Removes `key` and its value, keeping the comment lines among them.
+pub fn remove(content: &str, key: &str) -> String {
+    edit_frontmatter(content, |frontmatter| match entries(frontmatter).into_iter().find(|(k, _)| k == key) {
+        Some((_, range)) => {
+            let comments: String = frontmatter[range.clone()]
+                .split_inclusive('\n')
+                .skip(1)
+                .filter(|l| l.trim_start().starts_with('#'))
+                .collect();
+            format!("{}{}{}", &frontmatter[..range.start], comments, &frontmatter[range.end..])
+        }
+        None => frontmatter.to_string(),
+    })
+}
+
+/// Renames `old` to `new` keeping its value and comments. Nothing changes
+/// if `new` already exists.
+pub fn rename(content: &str, old: &str, new: &str) -> String {
+    edit_frontmatter(content, |frontmatter| {
+        let entries = entries(frontmatter);
+        // No se pisa una propiedad que ya existe
+        if entries.iter().any(|(k, _)| k == new) {
+            return frontmatter.to_string();
+        }
+        let Some((_, range)) = entries.into_iter().find(|(k, _)| k == old) else {
+            return frontmatter.to_string();
+        };
+        let line = &frontmatter[range.clone()];
+        let Some((_, key_end)) = line_key(line) else {
+            return frontmatter.to_string();
+        };
+        format!(
+            "{}{}{}",
+            &frontmatter[..range.start],
+            yaml_key(new),
+            &frontmatter[range.start + key_end..]
+        )
+    })
+}
+
+/// Property types of the vault, in `.obsidian/types.json` like Obsidian.
+#[derive(Clone, Default)]
+pub struct PropertyTypes {
+    types: HashMap<String, String>,
+}
+
+impl PropertyTypes {
+    pub fn load(vault: &str) -> Self {
+        let json: Option<serde_json::Value> = fs::read_to_string(Path::new(vault).join(TYPES_FILE))
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok());
+        let types = json
+            .as_ref()
+            .and_then(|json| json.get("types"))
+            .and_then(|types| types.as_object())
+            .map(|types| {
+                types
+                    .iter()
+                    .filter_map(|(key, value)| Some((key.clone(), value.as_str()?.to_string())))
+                    .collect()
+            })
+            .unwrap_or_default();
+        PropertyTypes { types }
+    }
+
+    pub fn get(&self, key: &str) -> Option<PropertyType> {
+        self.types.get(key).and_then(|t| PropertyType::from_key(t))
+    }
+
+    /// Registered type of `key`, or the one guessed from its value.
+    pub fn type_of(&self, property: &Property) -> PropertyType {
+        self.get(&property.key).unwrap_or_else(|| property.value.guess_type())
+    }
+}
+
+/// Types of `vault`, cached in the context since the panel asks every frame.
+pub fn types(ctx: &egui::Context, vault: &str) -> PropertyTypes {
+    let id = egui::Id::new("property_types");
+    let cached: Option<(String, PropertyTypes)> = ctx.data(|d| d.get_temp(id));
+    if let Some((cached_vault, types)) = cached {
+        if cached_vault == vault {
+            return types;
+        }
+    }
+    let types = PropertyTypes::load(vault);
+    ctx.data_mut(|d| d.insert_temp(id, (vault.to_string(), types.clone())));
+    types
+}
+
+/// Registers the type of `key` for the whole vault.
+pub fn set_type(ctx: &egui::Context, vault: &str, key: &str, property_type: PropertyType) {
+    let path = Path::new(vault).join(TYPES_FILE);
+    // Se conservan las demás claves que Obsidian pueda haber guardado
+    let mut json: serde_json::Value = fs::read_to_string(&path)
+        .ok()
+        .and_then(|d| serde_json::from_str(&d).ok())
+        .unwrap_or_else(|| serde_json::json!({}));
+    if !json.get("types").map(|t| t.is_object()).unwrap_or(false) {
+        json["types"] = serde_json::json!({});
+    }
+    json["types"][key] = serde_json::Value::String(property_type.key().to_string());
+    if let Some(parent) = path.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+    match serde_json::to_string_pretty(&json) {
+        Ok(data) => {
+            if let Err(e) = fs::write(&path, data) {
+                println!("Error guardando {}: {}", path.display(), e);
+            }
+        }
+        Err(e) => println!("Error guardando {}: {}", path.display(), e),
+    }
+    ctx.data_mut(|d| d.insert_temp(egui::Id::new("property_types"), (vault.to_string(), PropertyTypes::load(vault))));
+}
+
+fn vault_properties(vault: &str) -> Vec<Vec<Property>> {
+    vault_index::with_index(vault, |index| {
+        index
+            .notes()
+            .filter(|(_, note)| !note.frontmatter.trim().is_empty())
+            .filter_map(|(_, note)| read(&format!("---\n{}---\n", note.frontmatter)).ok())
+            .collect()
+    })
+}
+
+/// Keys used in the notes of the vault, most used first.
+pub fn vault_keys(vault: &str) -> Vec<String> {
+    let mut count: HashMap<String, usize> = HashMap::new();
+    for property in vault_properties(vault).into_iter().flatten() {
+        *count.entry(property.key).or_default() += 1;
+    }
+    sorted_by_count(count)
+}
+
+/// Values given to `key` in the notes of the vault, most used first.
+pub fn vault_values(vault: &str, key: &str) -> Vec<String> {
+    let mut count: HashMap<String, usize> = HashMap::new();
+    for property in vault_properties(vault).into_iter().flatten().filter(|p| p.key == key) {
+        for value in property.value.as_list() {
+            *count.entry(value).or_default() += 1;
+        }
+    }
+    sorted_by_count(count)
+}
+
+fn sorted_by_count(count: HashMap<String, usize>) -> Vec<String> {
+    let mut items: Vec<(String, usize)> = count.into_iter().collect();
+    items.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    items.into_iter().map(|(item, _)| item).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOTE: &str = "---\ntitle: \"Issue #4\" # from the tracker\ntags:\n# picked by hand\n  - a\n  - b\nstatus: draft # not yet\n\n# kept\nurl: http://example.com/#top\n---\nBody #tag\n";
+
+    fn list(items: &[&str]) -> Value {
+        Value::List(items.iter().map(|i| i.to_string()).collect())
+    }
+
+    #[test]
+    fn keys_and_entries() {
+        assert_eq!(line_key("status: draft\n"), Some(("status".to_string(), 6)));
+        assert_eq!(line_key("\"my: key\": 1"), Some(("my: key".to_string(), 9)));
+        assert_eq!(line_key("tags:\n"), Some(("tags".to_string(), 4)));
+        assert_eq!(line_key("url: http://x"), Some(("url".to_string(), 3)));
+        assert_eq!(line_key("  - a"), None);
+        assert_eq!(line_key("# comment"), None);
+        assert_eq!(line_key("- item"), None);
+
+        let frontmatter = split_frontmatter(NOTE).0.unwrap();
+        let keys: Vec<(String, &str)> =
+            entries(frontmatter).into_iter().map(|(k, r)| (k, &frontmatter[r])).collect();
+        assert_eq!(
+            keys,
+            [
+                ("title".to_string(), "title: \"Issue #4\" # from the tracker\n"),
+                ("tags".to_string(), "tags:\n# picked by hand\n  - a\n  - b\n"),
+                ("status".to_string(), "status: draft # not yet\n"),
+                ("url".to_string(), "url: http://example.com/#top\n"),
+            ]
+        );
+    }
+
+    #[test]
+    fn inline_comments() {
+        assert_eq!(inline_comment("status: draft # not yet\n"), Some(" # not yet"));
+        assert_eq!(inline_comment("title: \"Issue #4\" # from the tracker"), Some(" # from the tracker"));
+        assert_eq!(inline_comment("title: 'It''s #1'"), None);
+        assert_eq!(inline_comment("tags: # by hand"), Some(" # by hand"));
+        assert_eq!(inline_comment("url: http://example.com/#top"), None);
+        assert_eq!(inline_comment("  - a # item"), None);
+    }
+
+    #[test]
+    fn unchanged_values_round_trip() {
+        let mut content = NOTE.to_string();
+        for property in read(NOTE).unwrap().iter().filter(|p| p.key != "title") {
+            content = set(&content, &property.key, &property.value);
+        }
+        assert_eq!(content, NOTE);
+    }
+
+    #[test]
+    fn set_keeps_comments_and_order() {
+        let content = set(NOTE, "status", &Value::Text("done".to_string()));
+        assert_eq!(content, NOTE.replace("status: draft # not yet", "status: done # not yet"));
+
+        let content = set(NOTE, "tags", &list(&["x"]));
+        assert_eq!(content, NOTE.replace("  - a\n  - b\n", "  - x\n"));
+
+        let content = set(NOTE, "tags", &Value::Text("none".to_string()));
+        assert!(content.contains("tags: none\n# picked by hand\nstatus:"));
+
+        let content = set(NOTE, "priority", &Value::Number("2".to_string()));
+        assert!(content.ends_with("url: http://example.com/#top\npriority: 2\n---\nBody #tag\n"));
+        assert_eq!(set("Body\n", "done", &Value::Checkbox(true)), "---\ndone: true\n---\nBody\n");
+    }
+
+    #[test]
+    fn remove_keeps_comment_lines() {
+        let content = remove(NOTE, "tags");
+        assert_eq!(content, NOTE.replace("tags:\n# picked by hand\n  - a\n  - b\n", "# picked by hand\n"));
+        assert_eq!(remove(NOTE, "missing"), NOTE);
+    }
+
+    #[test]
+    fn rename_keeps_the_value_and_never_overwrites() {
+        let content = rename(NOTE, "status", "state");
+        assert_eq!(content, NOTE.replace("status: draft", "state: draft"));
+        assert_eq!(rename(NOTE, "status", "my key: x"), NOTE.replace("status: draft", "\"my key: x\": draft"));
+        // Ya existe: no se pierde ninguna de las dos
+        assert_eq!(rename(NOTE, "status", "url"), NOTE);
+        assert_eq!(rename(NOTE, "missing", "other"), NOTE);
+    }
+}
@@ -0,0 +1,1080 @@
+//! ` ```query ` code blocks: `LIST`, `TABLE` and `TASK` queries over the
+//! notes of the vault, in the style of Dataview.
+//!
+//! ```query
+//! TABLE status, due AS "Due date"
+//! FROM #project and -"Archive"
+//! WHERE status != "done" and due <= date(today)
+//! SORT due ASC
+//! LIMIT 20
+//! ```
+
+use chrono::{Local, NaiveDate, NaiveDateTime, TimeZone};
+use regex::Regex;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use crate::properties::{self, Property};
+use crate::vault_index;
+use crate::vault_tasks;
+
+#[derive(Clone, Debug, PartialEq)]
+enum Kind {
+    List(Option<Expr>),
+    Table(Vec<(Expr, String)>),
+    Task,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Source {
+    Tag(String),
+    Folder(String),
+    Not(Box<Source>),
+    And(Box<Source>, Box<Source>),
+    Or(Box<Source>, Box<Source>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Expr {
+    Literal(QValue),
+    Field(String),
+    Call(String, Vec<Expr>),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Compare(Box<Expr>, String, Box<Expr>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Query {
+    kind: Kind,
+    from: Option<Source>,
+    filter: Option<Expr>,
+    sort: Vec<(Expr, bool)>,
+    limit: Option<usize>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum QValue {
+    Null,
+    Bool(bool),
+    Number(f64),
+    Text(String),
+    Date(NaiveDateTime),
+    /// Note path and the text shown.
+    Link(String, String),
+    List(Vec<QValue>),
+}
+
+impl QValue {
+    fn truthy(&self) -> bool {
+        match self {
+            QValue::Null => false,
+            QValue::Bool(b) => *b,
+            QValue::Number(n) => *n != 0.0,
+            QValue::Text(t) => !t.is_empty(),
+            QValue::List(items) => !items.is_empty(),
+            QValue::Date(_) | QValue::Link(..) => true,
+        }
+    }
+
+    fn text(&self) -> String {
+        match self {
+            QValue::Null => String::new(),
+            QValue::Bool(b) => b.to_string(),
+            QValue::Number(n) => format_number(*n),
+            QValue::Text(t) => t.clone(),
+            QValue::Date(d) if d.time() == chrono::NaiveTime::MIN => d.format("%Y-%m-%d").to_string(),
+            QValue::Date(d) => d.format("%Y-%m-%d %H:%M").to_string(),
+            QValue::Link(_, name) => name.clone(),
+            QValue::List(items) => items.iter().map(|i| i.text()).collect::<Vec<_>>().join(", "),
+        }
+    }
+
+    fn compare(&self, other: &QValue) -> Option<Ordering> {
+        match (self, other) {
+            (QValue::Null, QValue::Null) => Some(Ordering::Equal),
+            (QValue::Number(a), QValue::Number(b)) => a.partial_cmp(b),
+            (QValue::Date(a), QValue::Date(b)) => Some(a.cmp(b)),
+            (QValue::Bool(a), QValue::Bool(b)) => Some(a.cmp(b)),
+            (QValue::Null, _) | (_, QValue::Null) => None,
+            (QValue::Date(a), b) | (b, QValue::Date(a)) if parse_date(&b.text()).is_some() => {
+                let b = parse_date(&b.text())?;
+                let ordering = a.cmp(&b);
+                Some(if matches!(self, QValue::Date(_)) { ordering } else { ordering.reverse() })
+            }
+            (QValue::Number(a), b) | (b, QValue::Number(a)) if b.text().trim().parse::<f64>().is_ok() => {
+                let b: f64 = b.text().trim().parse().ok()?;
+                let ordering = a.partial_cmp(&b)?;
+                Some(if matches!(self, QValue::Number(_)) { ordering } else { ordering.reverse() })
+            }
+            (a, b) => Some(a.text().to_lowercase().cmp(&b.text().to_lowercase())),
+        }
+    }
+}
+
+fn format_number(n: f64) -> String {
+    if n.fract() == 0.0 && n.abs() < 1e15 {
+        format!("{}", n as i64)
+    } else {
+        n.to_string()
+    }
+}
+
+fn parse_date(text: &str) -> Option<NaiveDateTime> {
+    let text = text.trim();
+    if let Ok(date) = NaiveDate::parse_from_str(text, "%Y-%m-%d") {
+        return date.and_hms_opt(0, 0, 0);
+    }
+    ["%Y-%m-%dT%H:%M:%S", "%Y-%m-%dT%H:%M", "%Y-%m-%d %H:%M"]
+        .iter()
+        .find_map(|f| NaiveDateTime::parse_from_str(text, f).ok())
+}
+
+// ---------------------------------------------------------------------------
+// Análisis de la consulta
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Word(String),
+    Str(String),
+    Tag(String),
+    Op(String),
+    Minus,
+    Bang,
+    Comma,
+    Open,
+    Close,
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = vec![];
+    let mut i = 0;
+    let is_word = |c: char| c.is_alphanumeric() || "_.-:/".contains(c);
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c == '"' || c == '\'' {
+            let end = (i + 1..chars.len()).find(|&j| chars[j] == c).ok_or("Unclosed string")?;
+            tokens.push(Token::Str(chars[i + 1..end].iter().collect()));
+            i = end + 1;
+        } else if c == '#' {
+            let end = (i + 1..chars.len()).find(|&j| !is_word(chars[j])).unwrap_or(chars.len());
+            tokens.push(Token::Tag(chars[i + 1..end].iter().collect()));
+            i = end;
+        } else if c == '(' || c == ')' || c == ',' {
+            tokens.push(match c {
+                '(' => Token::Open,
+                ')' => Token::Close,
+                _ => Token::Comma,
+            });
+            i += 1;
+        } else if "=<>!".contains(c) {
+            if chars.get(i + 1) == Some(&'=') {
+                tokens.push(Token::Op(format!("{}=", c)));
+                i += 2;
+            } else if c == '!' {
+                tokens.push(Token::Bang);
+                i += 1;
+            } else {
+                tokens.push(Token::Op(c.to_string()));
+                i += 1;
+            }
+        } else if c == '-' && !chars.get(i + 1).map(|n| n.is_alphanumeric()).unwrap_or(false) {
+            tokens.push(Token::Minus);
+            i += 1;
+        } else if is_word(c) {
+            let end = (i..chars.len()).find(|&j| !is_word(chars[j])).unwrap_or(chars.len());
+            tokens.push(Token::Word(chars[i..end].iter().collect()));
+            i = end;
+        } else {
+            return Err(format!("Unexpected character '{}'", c));
+        }
+    }
+    Ok(tokens)
+}
+
+const CLAUSES: [&str; 4] = ["FROM", "WHERE", "SORT", "LIMIT"];
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn keyword(&self) -> Option<String> {
+        match self.peek() {
+            Some(Token::Word(w)) => Some(w.to_uppercase()),
+            _ => None,
+        }
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        if self.keyword().as_deref() == Some(keyword) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn at_clause(&self) -> bool {
+        self.peek().is_none() || self.keyword().map(|k| CLAUSES.contains(&k.as_str())).unwrap_or(false)
+    }
+
+    fn query(&mut self) -> Result<Query, String> {
+        let kind = match self.keyword().as_deref() {
+            Some("LIST") => {
+                self.pos += 1;
+                Kind::List(if self.at_clause() { None } else { Some(self.expr()?) })
+            }
+            Some("TABLE") => {
+                self.pos += 1;
+                let mut columns = vec![];
+                while !self.at_clause() {
+                    let start = self.pos;
+                    let expr = self.expr()?;
+                    let label = if self.eat_keyword("AS") {
+                        match self.next() {
+                            Some(Token::Str(s)) | Some(Token::Word(s)) => s,
+                            _ => return Err("Expected a column name after AS".to_string()),
+                        }
+                    } else {
+                        self.tokens[start..self.pos].iter().map(token_text).collect::<Vec<_>>().join("")
+                    };
+                    columns.push((expr, label));
+                    if self.peek() == Some(&Token::Comma) {
+                        self.pos += 1;
+                    }
+                }
+                Kind::Table(columns)
+            }
+            Some("TASK") => {
+                self.pos += 1;
+                Kind::Task
+            }
+            _ => return Err("A query starts with LIST, TABLE or TASK".to_string()),
+        };
+
+        let mut query = Query {
+            kind,
+            from: None,
+            filter: None,
+            sort: vec![],
+            limit: None,
+        };
+        while let Some(keyword) = self.keyword() {
+            self.pos += 1;
+            match keyword.as_str() {
+                "FROM" => query.from = Some(self.source_or()?),
+                "WHERE" => {
+                    let filter = self.expr()?;
+                    // Varios WHERE se combinan con "and"
+                    query.filter = Some(match query.filter.take() {
+                        Some(previous) => Expr::And(Box::new(previous), Box::new(filter)),
+                        None => filter,
+                    });
+                }
+                "SORT" => loop {
+                    let expr = self.expr()?;
+                    let descending = if self.eat_keyword("DESC") {
+                        true
+                    } else {
+                        self.eat_keyword("ASC");
+                        false
+                    };
+                    query.sort.push((expr, descending));
+                    if self.peek() != Some(&Token::Comma) {
+                        break;
+                    }
+                    self.pos += 1;
+                },
+                "LIMIT" => match self.next() {
+                    Some(Token::Word(n)) => query.limit = Some(n.parse().map_err(|_| format!("Invalid LIMIT {}", n))?),
+                    _ => return Err("Expected a number after LIMIT".to_string()),
+                },
+                other => return Err(format!("Unexpected '{}'", other)),
+            }
+        }
+        if let Some(token) = self.peek() {
+            return Err(format!("Unexpected '{}'", token_text(token)));
+        }
+        Ok(query)
+    }
+
+    fn source_or(&mut self) -> Result<Source, String> {
+        let mut left = self.source_and()?;
+        while self.eat_keyword("OR") {
+            left = Source::Or(Box::new(left), Box::new(self.source_and()?));
+        }
+        Ok(left)
+    }
+
+    fn source_and(&mut self) -> Result<Source, String> {
+        let mut left = self.source_unary()?;
+        while self.eat_keyword("AND") {
+            left = Source::And(Box::new(left), Box::new(self.source_unary()?));
+        }
+        Ok(left)
+    }
+
+    fn source_unary(&mut self) -> Result<Source, String> {
+        match self.next() {
+            Some(Token::Minus) | Some(Token::Bang) => Ok(Source::Not(Box::new(self.source_unary()?))),
+            Some(Token::Tag(tag)) => Ok(Source::Tag(tag)),
+            Some(Token::Str(folder)) => Ok(Source::Folder(folder.trim_matches('/').to_string())),
+            Some(Token::Open) => {
+                let source = self.source_or()?;
+                match self.next() {
+                    Some(Token::Close) => Ok(source),
+                    _ => Err("Expected ')' in FROM".to_string()),
+                }
+            }
+            _ => Err("FROM takes #tags and \"folders\"".to_string()),
+        }
+    }
+
+    fn expr(&mut self) -> Result<Expr, String> {
+        let mut left = self.and_expr()?;
+        while self.eat_keyword("OR") {
+            left = Expr::Or(Box::new(left), Box::new(self.and_expr()?));
+        }
+        Ok(left)
+    }
+
+    fn and_expr(&mut self) -> Result<Expr, String> {
+        let mut left = self.not_expr()?;
+        while self.eat_keyword("AND") {
+            left = Expr::And(Box::new(left), Box::new(self.not_expr()?));
+        }
+        Ok(left)
+    }
+
+    fn not_expr(&mut self) -> Result<Expr, String> {
+        if self.peek() == Some(&Token::Bang) || self.eat_keyword("NOT") {
+            if self.peek() == Some(&Token::Bang) {
+                self.pos += 1;
+            }
+            return Ok(Expr::Not(Box::new(self.not_expr()?)));
+        }
+        let left = self.primary()?;
+        if let Some(Token::Op(op)) = self.peek().cloned() {
+            self.pos += 1;
+            let right = self.primary()?;
+            return Ok(Expr::Compare(Box::new(left), op, Box::new(right)));
+        }
+        Ok(left)
+    }
+
+    fn primary(&mut self) -> Result<Expr, String> {
+        match self.next() {
+            Some(Token::Str(s)) => Ok(Expr::Literal(QValue::Text(s))),
+            Some(Token::Tag(tag)) => Ok(Expr::Literal(QValue::Text(format!("#{}", tag)))),
+            Some(Token::Open) => {
+                let expr = self.expr()?;
+                match self.next() {
+                    Some(Token::Close) => Ok(expr),
+                    _ => Err("Expected ')'".to_string()),
+                }
+            }
+            Some(Token::Word(word)) => {
+                if self.peek() == Some(&Token::Open) {
+                    self.pos += 1;
+                    let mut args = vec![];
+                    while self.peek() != Some(&Token::Close) {
+                        // date(2024-01-01) y date(today) sin comillas
+                        match self.peek().cloned() {
+                            Some(Token::Word(w)) if word.eq_ignore_ascii_case("date") => {
+                                self.pos += 1;
+                                args.push(Expr::Literal(QValue::Text(w)));
+                            }
+                            _ => args.push(self.expr()?),
+                        }
+                        match self.peek() {
+                            Some(Token::Comma) => self.pos += 1,
+                            Some(Token::Close) => {}
+                            _ => return Err(format!("Expected ')' after the arguments of {}", word)),
+                        }
+                    }
+                    self.pos += 1;
+                    return Ok(Expr::Call(word.to_lowercase(), args));
+                }
+                Ok(match word.to_lowercase().as_str() {
+                    "true" => Expr::Literal(QValue::Bool(true)),
+                    "false" => Expr::Literal(QValue::Bool(false)),
+                    "null" => Expr::Literal(QValue::Null),
+                    _ => match word.parse::<f64>() {
+                        Ok(n) => Expr::Literal(QValue::Number(n)),
+                        Err(_) => match parse_date(&word) {
+                            Some(date) => Expr::Literal(QValue::Date(date)),
+                            None => Expr::Field(word),
+                        },
+                    },
+                })
+            }
+            Some(token) => Err(format!("Unexpected '{}'", token_text(&token))),
+            None => Err("The query ends too soon".to_string()),
+        }
+    }
+}
+
+fn token_text(token: &Token) -> String {
+    match token {
+        Token::Word(w) => w.clone(),
+        Token::Str(s) => format!("\"{}\"", s),
+        Token::Tag(t) => format!("#{}", t),
+        Token::Op(op) => format!(" {} ", op),
+        Token::Minus => "-".to_string(),
+        Token::Bang => "!".to_string(),
+        Token::Comma => ", ".to_string(),
+        Token::Open => "(".to_string(),
+        Token::Close => ")".to_string(),
+    }
+}
+
+fn parse(text: &str) -> Result<Query, String> {
+    let text: String = text
+        .lines()
+        .filter(|l| !l.trim_start().starts_with("//"))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let mut parser = Parser {
+        tokens: tokenize(&text)?,
+        pos: 0,
+    };
+    parser.query()
+}
+
+// ---------------------------------------------------------------------------
+// Datos del vault
+
+#[derive(Clone, Debug)]
+struct Note {
+    path: String,
+    rel_path: String,
+    name: String,
+    modified: u64,
+    tags: Vec<String>,
+    properties: Vec<Property>,
+}
+
+#[derive(Clone, Debug)]
+struct Task {
+    text: String,
+    completed: bool,
+    /// Line of the task in the note, starting at 0.
+    line: usize,
+    due: Option<NaiveDate>,
+}
+
+/// A note, or one of its tasks for `TASK` queries.
+struct Row<'a> {
+    note: &'a Note,
+    task: Option<&'a Task>,
+}
+
+fn load_notes(vault: &str) -> Vec<Note> {
+    vault_index::with_index(vault, |index| {
+        index
+            .notes()
+            .map(|(file, meta)| Note {
+                path: file.path.clone(),
+                rel_path: file.rel_path.clone(),
+                name: file.name.clone(),
+                modified: file.modified,
+                tags: meta.tags(),
+                properties: if meta.frontmatter.trim().is_empty() {
+                    vec![]
+                } else {
+                    properties::read(&format!("---\n{}---\n", meta.frontmatter)).unwrap_or_default()
+                },
+            })
+            .collect()
+    })
+}
+
+/// Tasks of every note of the vault, by path. They come from the cache of
+/// `vault_tasks`, which only rereads the notes that changed.
+fn load_tasks(vault: &str) -> HashMap<String, Vec<Task>> {
+    vault_tasks::vault_task_notes(vault)
+        .into_iter()
+        .filter_map(|(_, tasks)| {
+            let path = tasks.first()?.path.clone();
+            let tasks = tasks
+                .into_iter()
+                .map(|task| Task {
+                    completed: task.completed(),
+                    due: task.due,
+                    text: task.text,
+                    line: task.line,
+                })
+                .collect();
+            Some((path, tasks))
+        })
+        .collect()
+}
+
+fn matches_source(note: &Note, source: &Source) -> bool {
+    match source {
+        Source::Tag(tag) => {
+            let tag = tag.to_lowercase();
+            // Un tag incluye sus subtags: #project encuentra #project/web
+            note.tags.iter().any(|t| {
+                let t = t.to_lowercase();
+                t == tag || t.starts_with(&format!("{}/", tag))
+            })
+        }
+        Source::Folder(folder) => folder.is_empty() || note.rel_path.starts_with(&format!("{}/", folder)) || note.rel_path == format!("{}.md", folder),
+        Source::Not(inner) => !matches_source(note, inner),
+        Source::And(a, b) => matches_source(note, a) && matches_source(note, b),
+        Source::Or(a, b) => matches_source(note, a) || matches_source(note, b),
+    }
+}
+
+fn property_value(value: &properties::Value) -> QValue {
+    let text_value = |text: &str| {
+        if properties::is_link(text) {
+            let target = text.trim().trim_start_matches("[[").trim_end_matches("]]");
+            let name = target.split('|').next_back().unwrap_or(target).to_string();
+            QValue::Link(target.split('|').next().unwrap_or(target).to_string(), name)
+        } else if let Some(date) = parse_date(text) {
+            QValue::Date(date)
+        } else {
+            QValue::Text(text.to_string())
+        }
+    };
+    match value {
+        properties::Value::Text(t) if t.is_empty() => QValue::Null,
+        properties::Value::Text(t) | properties::Value::Other(t) => text_value(t),
+        properties::Value::Number(n) => n.parse().map(QValue::Number).unwrap_or(QValue::Text(n.clone())),
+        properties::Value::Checkbox(b) => QValue::Bool(*b),
+        properties::Value::List(items) => QValue::List(items.iter().map(|i| text_value(i)).collect()),
+    }
+}
+
+fn timestamp(millis: u64) -> QValue {
+    match Local.timestamp_millis_opt(millis as i64) {
+        chrono::LocalResult::Single(time) => QValue::Date(time.naive_local()),
+        _ => QValue::Null,
+    }
+}
+
+fn field(row: &Row, name: &str) -> QValue {
+    let note = row.note;
+    let lower = name.to_lowercase();
+    match lower.as_str() {
+        "file.name" => return QValue::Text(note.name.clone()),
+        "file.path" => return QValue::Text(note.rel_path.clone()),
+        "file.folder" => {
+            let folder = Path::new(&note.rel_path).parent().and_then(|p| p.to_str()).unwrap_or("");
+            return QValue::Text(folder.to_string());
+        }
+        "file.link" => return QValue::Link(note.path.clone(), note.name.clone()),
+        "file.mtime" => return timestamp(note.modified),
+        "file.tags" => return QValue::List(note.tags.iter().map(|t| QValue::Text(format!("#{}", t))).collect()),
+        "file.day" => {
+            static DAY: OnceLock<Regex> = OnceLock::new();
+            let day = DAY.get_or_init(|| Regex::new(r"\d{4}-\d{2}-\d{2}").unwrap());
+            return day
+                .find(&note.name)
+                .and_then(|m| parse_date(m.as_str()))
+                .map(QValue::Date)
+                .unwrap_or(QValue::Null);
+        }
+        _ => {}
+    }
+    if let Some(task) = row.task {
+        match lower.as_str() {
+            "text" => return QValue::Text(task.text.clone()),
+            "completed" | "checked" => return QValue::Bool(task.completed),
+            "line" => return QValue::Number(task.line as f64 + 1.0),
+            "due" => {
+                if let Some(due) = task.due.and_then(|d| d.and_hms_opt(0, 0, 0)) {
+                    return QValue::Date(due);
+                }
+            }
+            _ => {}
+        }
+    }
+    note.properties
+        .iter()
+        .find(|p| p.key.to_lowercase() == lower)
+        .map(|p| property_value(&p.value))
+        .unwrap_or(QValue::Null)
+}
+
+fn eval(expr: &Expr, row: &Row) -> QValue {
+    match expr {
+        Expr::Literal(value) => value.clone(),
+        Expr::Field(name) => field(row, name),
+        Expr::Not(inner) => QValue::Bool(!eval(inner, row).truthy()),
+        Expr::And(a, b) => QValue::Bool(eval(a, row).truthy() && eval(b, row).truthy()),
+        Expr::Or(a, b) => QValue::Bool(eval(a, row).truthy() || eval(b, row).truthy()),
+        Expr::Compare(a, op, b) => {
+            let (a, b) = (eval(a, row), eval(b, row));
+            // Con una lista, "=" comprueba si contiene el valor
+            if let (QValue::List(items), "=" | "!=") = (&a, op.as_str()) {
+                let found = items.iter().any(|i| i.compare(&b) == Some(Ordering::Equal));
+                return QValue::Bool(found == (op == "="));
+            }
+            let ordering = a.compare(&b);
+            QValue::Bool(match op.as_str() {
+                "=" => ordering == Some(Ordering::Equal),
+                "!=" => ordering != Some(Ordering::Equal),
+                "<" => ordering == Some(Ordering::Less),
+                ">" => ordering == Some(Ordering::Greater),
+                "<=" => matches!(ordering, Some(Ordering::Less | Ordering::Equal)),
+                ">=" => matches!(ordering, Some(Ordering::Greater | Ordering::Equal)),
+                _ => false,
+            })
+        }
+        Expr::Call(name, args) => {
+            let args: Vec<QValue> = args.iter().map(|a| eval(a, row)).collect();
+            call(name, &args)
+        }
+    }
+}
+
+fn call(name: &str, args: &[QValue]) -> QValue {
+    let arg = |i: usize| args.get(i).cloned().unwrap_or(QValue::Null);
+    match name {
+        "contains" => {
+            let needle = arg(1);
+            QValue::Bool(match arg(0) {
+                QValue::List(items) => items.iter().any(|i| {
+                    i.compare(&needle) == Some(Ordering::Equal)
+                        || i.text().to_lowercase().contains(&needle.text().to_lowercase())
+                }),
+                QValue::Null => false,
+                other => other.text().to_lowercase().contains(&needle.text().to_lowercase()),
+            })
+        }
+        "date" => {
+            let text = arg(0).text();
+            let today = Local::now().date_naive();
+            let date = match text.to_lowercase().as_str() {
+                "today" => Some(today),
+                "tomorrow" => today.succ_opt(),
+                "yesterday" => today.pred_opt(),
+                _ => None,
+            };
+            match date {
+                Some(date) => date.and_hms_opt(0, 0, 0).map(QValue::Date).unwrap_or(QValue::Null),
+                None => match arg(0) {
+                    QValue::Date(d) => QValue::Date(d),
+                    _ => parse_date(&text).map(QValue::Date).unwrap_or(QValue::Null),
+                },
+            }
+        }
+        "length" => match arg(0) {
+            QValue::List(items) => QValue::Number(items.len() as f64),
+            QValue::Null => QValue::Number(0.0),
+            other => QValue::Number(other.text().chars().count() as f64),
+        },
+        "lower" => QValue::Text(arg(0).text().to_lowercase()),
+        "upper" => QValue::Text(arg(0).text().to_uppercase()),
+        "default" => match arg(0) {
+            QValue::Null => arg(1),
+            value => value,
+        },
+        _ => QValue::Null,
+    }
+}
+
+#[derive(Clone)]
+enum Output {
+    /// Note path, name and the extra value of `LIST expr`.
+    List(Vec<(String, String, Option<QValue>)>),
+    Table(Vec<String>, Vec<(String, String, Vec<QValue>)>),
+    /// Note path, name and its tasks.
+    Tasks(Vec<(String, String, Vec<Task>)>),
+}
+
+fn run(vault: &str, query: &Query) -> Output {
+    let notes: Vec<Note> = load_notes(vault)
+        .into_iter()
+        .filter(|n| query.from.as_ref().map(|s| matches_source(n, s)).unwrap_or(true))
+        .collect();
+
+    let sort_rows = |rows: &mut Vec<Row>| {
+        if query.sort.is_empty() {
+            return;
+        }
+        rows.sort_by(|a, b| {
+            for (expr, descending) in &query.sort {
+                let (va, vb) = (eval(expr, a), eval(expr, b));
+                // Los valores vacíos van al final en los dos sentidos
+                let ordering = match (&va, &vb) {
+                    (QValue::Null, QValue::Null) => Ordering::Equal,
+                    (QValue::Null, _) => return Ordering::Greater,
+                    (_, QValue::Null) => return Ordering::Less,
+                    _ => va.compare(&vb).unwrap_or(Ordering::Equal),
+                };
+                let ordering = if *descending { ordering.reverse() } else { ordering };
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+            Ordering::Equal
+        });
+    };
+    let passes = |row: &Row| query.filter.as_ref().map(|f| eval(f, row).truthy()).unwrap_or(true);
+    let limit = query.limit.unwrap_or(usize::MAX);
+
+    let note_rows = || {
+        let mut rows: Vec<Row> = notes.iter().map(|note| Row { note, task: None }).filter(|row| passes(row)).collect();
+        sort_rows(&mut rows);
+        rows.truncate(limit);
+        rows
+    };
+
+    match &query.kind {
+        Kind::Task => {
+            let tasks = load_tasks(vault);
+            let mut rows: Vec<Row> = notes
+                .iter()
+                .filter_map(|note| tasks.get(&note.path).map(|tasks| (note, tasks)))
+                .flat_map(|(note, tasks)| tasks.iter().map(move |t| Row { note, task: Some(t) }))
+                .filter(|row| passes(row))
+                .collect();
+            sort_rows(&mut rows);
+            rows.truncate(limit);
+            // Agrupadas por nota, en el orden en que aparece cada una
+            let mut groups: Vec<(String, String, Vec<Task>)> = vec![];
+            for row in rows {
+                let Some(task) = row.task.cloned() else {
+                    continue;
+                };
+                match groups.iter_mut().find(|g| g.0 == row.note.path) {
+                    Some(group) => group.2.push(task),
+                    None => groups.push((row.note.path.clone(), row.note.name.clone(), vec![task])),
+                }
+            }
+            Output::Tasks(groups)
+        }
+        Kind::Table(columns) => Output::Table(
+            columns.iter().map(|(_, label)| label.clone()).collect(),
+            note_rows()
+                .iter()
+                .map(|row| {
+                    let values = columns.iter().map(|(expr, _)| eval(expr, row)).collect();
+                    (row.note.path.clone(), row.note.name.clone(), values)
+                })
+                .collect(),
+        ),
+        Kind::List(expr) => Output::List(
+            note_rows()
+                .iter()
+                .map(|row| (row.note.path.clone(), row.note.name.clone(), expr.as_ref().map(|e| eval(e, row))))
+                .collect(),
+        ),
+    }
+}
+
+/// Paths of the notes matching a `FROM` source and a `WHERE` filter, both
+/// written as in a query block. Empty parts match every note.
+pub fn matching_notes(vault: &str, source: &str, filter: &str) -> Result<Vec<String>, String> {
+    let mut text = String::from("LIST");
+    if !source.trim().is_empty() {
+        text.push_str(&format!("\nFROM {}", source));
+    }
+    if !filter.trim().is_empty() {
+        text.push_str(&format!("\nWHERE {}", filter));
+    }
+    match run(vault, &parse(&text)?) {
+        Output::List(items) => Ok(items.into_iter().map(|(path, _, _)| path).collect()),
+        _ => Ok(vec![]),
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Interfaz
+
+fn open_note(ctx: &egui::Context, path: &str) {
+    ctx.data_mut(|d| d.insert_temp(egui::Id::new("global_nav_request"), Some(path.to_string())));
+}
+
+fn value_ui(ui: &mut egui::Ui, vault: &str, current_path: &str, value: &QValue) {
+    match value {
+        QValue::Null => {
+            ui.weak("—");
+        }
+        QValue::Bool(b) => {
+            ui.label(if *b { "✔" } else { "✘" });
+        }
+        QValue::Link(target, name) => {
+            if ui.link(name).clicked() {
+                if Path::new(target).is_absolute() {
+                    open_note(ui.ctx(), target);
+                } else {
+                    crate::anchors::follow_link(ui.ctx(), vault, current_path, target);
+                }
+            }
+        }
+        QValue::List(items) => {
+            ui.horizontal_wrapped(|ui| {
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        ui.label(",");
+                    }
+                    value_ui(ui, vault, current_path, item);
+                }
+            });
+        }
+        other => {
+            ui.label(other.text());
+        }
+    }
+}
+
+/// Renders the result of a ` ```query ` block of the note at `current_path`.
+pub fn render(ui: &mut egui::Ui, vault: &str, current_path: &str, text: &str) {
+    let id = egui::Id::new("query_block").with(vault).with(text);
+    // Se vuelve a consultar cuando cambia el índice (guardados y cambios
+    // externos) o el día, para `date(today)`
+    let key = (vault_index::generation(), Local::now().date_naive());
+    let cached: Option<((u64, NaiveDate), Result<Output, String>)> = ui.ctx().data(|d| d.get_temp(id));
+    let result = match cached {
+        Some((cached_key, result)) if cached_key == key => result,
+        _ => {
+            let result = parse(text).map(|query| run(vault, &query));
+            ui.ctx().data_mut(|d| d.insert_temp(id, (key, result.clone())));
+            result
+        }
+    };
+
+    egui::Frame::group(ui.style())
+        .fill(ui.visuals().faint_bg_color)
+        .corner_radius(5.0)
+        .inner_margin(10.0)
+        .show(ui, |ui| {
+            ui.set_width(ui.available_width());
+            match &result {
+                Err(error) => {
+                    ui.colored_label(ui.visuals().error_fg_color, format!("Query error: {}", error));
+                }
+                Ok(Output::List(items)) if items.is_empty() => {
+                    ui.weak("No results");
+                }
+                Ok(Output::List(items)) => {
+                    for (path, name, value) in items {
+                        ui.horizontal_wrapped(|ui| {
+                            ui.label("•");
+                            if ui.link(name).clicked() {
+                                open_note(ui.ctx(), path);
+                            }
+                            if let Some(value) = value {
+                                ui.label(":");
+                                value_ui(ui, vault, current_path, value);
+                            }
+                        });
+                    }
+                }
+                Ok(Output::Table(columns, rows)) => {
+                    egui::ScrollArea::horizontal().id_salt(id.with("scroll")).show(ui, |ui| {
+                        egui::Grid::new(id.with("table")).striped(true).num_columns(columns.len() + 1).show(ui, |ui| {
+                            ui.label(egui::RichText::new(format!("File ({})", rows.len())).strong());
+                            for column in columns {
+                                ui.label(egui::RichText::new(column).strong());
+                            }
+                            ui.end_row();
+                            for (path, name, values) in rows {
+                                if ui.link(name).clicked() {
+                                    open_note(ui.ctx(), path);
+                                }
+                                for value in values {
+                                    value_ui(ui, vault, current_path, value);
+                                }
+                                ui.end_row();
+                            }
+                        });
+                    });
+                }
+                Ok(Output::Tasks(groups)) if groups.is_empty() => {
+                    ui.weak("No tasks");
+                }
+                Ok(Output::Tasks(groups)) => {
+                    for (path, name, tasks) in groups {
+                        if ui.link(egui::RichText::new(name).strong()).clicked() {
+                            open_note(ui.ctx(), path);
+                        }
+                        for task in tasks {
+                            let mut completed = task.completed;
+                            if ui.checkbox(&mut completed, &task.text).changed() {
+                                vault_tasks::toggle(ui.ctx(), vault, path, task.line, &task.text);
+                                ui.ctx().data_mut(|d| d.remove::<((u64, NaiveDate), Result<Output, String>)>(id));
+                            }
+                        }
+                    }
+                }
+            }
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn note() -> Note {
+        let property = |key: &str, value: properties::Value| Property { key: key.to_string(), value };
+        Note {
+            path: "/vault/Projects/Plan.md".to_string(),
+            rel_path: "Projects/Plan.md".to_string(),
+            name: "Plan".to_string(),
+            modified: 0,
+            tags: vec!["project/web".to_string()],
+            properties: vec![
+                property("status", properties::Value::Text("done".to_string())),
+                property("rating", properties::Value::Number("4".to_string())),
+                property("due", properties::Value::Text("2024-03-01".to_string())),
+                property("aliases", properties::Value::List(vec!["a".to_string(), "b".to_string()])),
+                property("draft", properties::Value::Checkbox(false)),
+            ],
+        }
+    }
+
+    fn holds(note: &Note, task: Option<&Task>, filter: &str) -> bool {
+        let query = parse(&format!("LIST WHERE {}", filter)).unwrap();
+        eval(&query.filter.unwrap(), &Row { note, task }).truthy()
+    }
+
+    #[test]
+    fn parse_table_with_every_clause() {
+        let query = parse(
+            "TABLE status, due AS \"Due date\"\n\
+             FROM #project and -\"Archive\"\n\
+             WHERE status != \"done\" and due <= date(today)\n\
+             SORT due DESC, file.name\n\
+             LIMIT 20",
+        )
+        .unwrap();
+        let field = |name: &str| Expr::Field(name.to_string());
+        assert_eq!(
+            query.kind,
+            Kind::Table(vec![(field("status"), "status".to_string()), (field("due"), "Due date".to_string())])
+        );
+        assert_eq!(
+            query.from,
+            Some(Source::And(
+                Box::new(Source::Tag("project".to_string())),
+                Box::new(Source::Not(Box::new(Source::Folder("Archive".to_string())))),
+            ))
+        );
+        assert_eq!(
+            query.filter,
+            Some(Expr::And(
+                Box::new(Expr::Compare(
+                    Box::new(field("status")),
+                    "!=".to_string(),
+                    Box::new(Expr::Literal(QValue::Text("done".to_string()))),
+                )),
+                Box::new(Expr::Compare(
+                    Box::new(field("due")),
+                    "<=".to_string(),
+                    Box::new(Expr::Call("date".to_string(), vec![Expr::Literal(QValue::Text("today".to_string()))])),
+                )),
+            ))
+        );
+        assert_eq!(query.sort, vec![(field("due"), true), (field("file.name"), false)]);
+        assert_eq!(query.limit, Some(20));
+    }
+
+    #[test]
+    fn parse_list_and_task() {
+        let query = parse("list file.mtime\n// a comment\nfrom \"Projects/\" or #a").unwrap();
+        assert_eq!(query.kind, Kind::List(Some(Expr::Field("file.mtime".to_string()))));
+        assert_eq!(
+            query.from,
+            Some(Source::Or(
+                Box::new(Source::Folder("Projects".to_string())),
+                Box::new(Source::Tag("a".to_string())),
+            ))
+        );
+
+        let query = parse("TASK WHERE !completed WHERE due").unwrap();
+        assert_eq!(query.kind, Kind::Task);
+        assert_eq!(
+            query.filter,
+            Some(Expr::And(
+                Box::new(Expr::Not(Box::new(Expr::Field("completed".to_string())))),
+                Box::new(Expr::Field("due".to_string())),
+            ))
+        );
+    }
+
+    #[test]
+    fn parse_errors() {
+        for text in [
+            "",
+            "SELECT name",
+            "LIST LIMIT ten",
+            "TABLE status AS",
+            "LIST WHERE (status",
+            "LIST FROM status",
+            "LIST WHERE status = \"done",
+            "LIST WHERE status ; 1",
+            "LIST FROM #a extra",
+        ] {
+            assert!(parse(text).is_err(), "{:?} should not parse", text);
+        }
+    }
+
+    #[test]
+    fn eval_fields_and_comparisons() {
+        let note = note();
+        assert!(holds(&note, None, "status = \"DONE\""));
+        assert!(holds(&note, None, "rating >= 3 and rating < 5"));
+        assert!(holds(&note, None, "due < 2024-04-01 and due >= date(2024-03-01)"));
+        assert!(holds(&note, None, "aliases = \"b\" and aliases != \"c\""));
+        assert!(holds(&note, None, "contains(aliases, \"a\") and length(aliases) = 2"));
+        assert!(holds(&note, None, "!draft and missing = null"));
+        assert!(holds(&note, None, "default(missing, \"x\") = \"x\""));
+        assert!(holds(&note, None, "file.name = \"Plan\" and file.folder = \"Projects\""));
+        assert!(holds(&note, None, "contains(file.tags, \"#project/web\")"));
+        assert!(!holds(&note, None, "status = \"todo\" or rating > 4"));
+        assert!(!holds(&note, None, "missing"));
+    }
+
+    #[test]
+    fn eval_task_fields() {
+        let note = note();
+        let task = Task {
+            text: "Call Ana".to_string(),
+            completed: false,
+            line: 2,
+            due: NaiveDate::from_ymd_opt(2024, 5, 1),
+        };
+        assert!(holds(&note, Some(&task), "!completed and line = 3"));
+        assert!(holds(&note, Some(&task), "contains(text, \"ana\")"));
+        // La fecha de la tarea tiene prioridad sobre la propiedad de la nota
+        assert!(holds(&note, Some(&task), "due = 2024-05-01"));
+        assert!(holds(&note, None, "due = 2024-03-01"));
+    }
+
+    #[test]
+    fn sources_match_subtags_and_folders() {
+        let note = note();
+        let tag = |t: &str| Source::Tag(t.to_string());
+        assert!(matches_source(&note, &tag("project")));
+        assert!(matches_source(&note, &tag("Project/Web")));
+        assert!(!matches_source(&note, &tag("proj")));
+        assert!(!matches_source(&note, &tag("project/web/api")));
+        assert!(matches_source(&note, &Source::Folder("Projects".to_string())));
+        assert!(!matches_source(&note, &Source::Folder("Project".to_string())));
+        assert!(matches_source(&note, &Source::Not(Box::new(Source::Folder("Archive".to_string())))));
+    }
+}
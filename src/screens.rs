@@ -393,6 +393,11 @@ pub fn configuracion(
                 }
             }
 
+            ui.add_space(10.0);
+            crate::periodic::settings_ui(ui, vault);
+            crate::templates::settings_ui(ui, vault);
+            crate::vault_tasks::settings_ui(ui, vault);
+
             server_settings(ui, current_window, button_size);
 
             ui.add_space(30.0);
@@ -797,7 +802,27 @@ fn server_settings(ui: &mut egui::Ui, current_window: &mut Screen, button_size:
     }
 }
 
-pub fn set_server(_ctx: &egui::Context) {}
+pub fn set_server(
+    ctx: &egui::Context,
+    current_window: &mut Screen,
+    vault: &str,
+    backups: &mut crate::server::BackupManager,
+    sync: &mut crate::server::sync::SyncClient,
+) {
+    backups.tick(vault);
+    sync.tick(vault);
+    CentralPanel::default().show(ctx, |ui| {
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            backups.ui(ui);
+            ui.separator();
+            sync.ui(ui);
+            ui.add_space(20.0);
+            if ui.button("Return").clicked() {
+                *current_window = Screen::Configuracion;
+            }
+        });
+    });
+}
 
 
 
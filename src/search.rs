@@ -1,68 +1,581 @@
 extern crate regex;
-extern crate walkdir;
 
+pub mod query;
+
+use crate::configuraciones;
+use crate::graph::MarmolPoint;
 use regex::Regex;
-use walkdir::WalkDir;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::cell::OnceCell;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+use crate::vault_index;
+
+// Extensiones que se indexan como texto
+const INDEXED_EXTENSIONS: [&str; 4] = ["md", "txt", "canvas", "csv"];
+// Archivos más grandes que esto no se indexan (dibujos, exportaciones, etc.)
+const MAX_FILE_SIZE: u64 = 2 * 1024 * 1024;
+// Cambiar cuando cambie lo que se guarda por archivo para forzar reindexar
+const INDEX_VERSION: u32 = 5;
+
+pub struct LineMatch {
+    pub line: usize,
+    pub text: String,
+}
 
 pub struct MenuItem {
     pub path: String,
-    pub text: String,
+    pub score: f32,
+    pub matches: Vec<LineMatch>,
 }
 
-impl MenuItem {
-    fn new() -> Self {
-        MenuItem {
-            path: "".to_owned(),
-            text: "".to_owned(),
-        }
+#[derive(Serialize, Deserialize, Default)]
+struct IndexedFile {
+    modified: u64,
+    #[serde(default)]
+    tags: Vec<String>,
+    /// Lines (starting at 0) where each token appears, once per appearance.
+    positions: HashMap<String, Vec<u32>>,
+}
+
+impl IndexedFile {
+    fn point(&self, vault: &str, path: &str) -> MarmolPoint {
+        let name = Path::new(path)
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("Unknown");
+        MarmolPoint::new(
+            name,
+            self.tags.clone(),
+            vec![],
+            path.replace(vault, ""),
+            path.to_string(),
+            false,
+            false,
+            true,
+        )
     }
 }
 
-pub fn search_dir(path: &str, query: &str, use_regex: bool) -> Vec<MenuItem> {
-    let walkdir = WalkDir::new(path);
-    let files = walkdir
-        .into_iter()
-        .filter_map(|e| e.ok())
-        .filter(|e| e.metadata().unwrap().is_file());
+/// Persistent inverted index of the text files of a vault.
+///
+/// Only the token positions of every file are stored on disk under the config
+/// dir, the postings are rebuilt in memory when the index is loaded. Searches
+/// use them to pick the candidates and read only those files.
+#[derive(Serialize, Deserialize, Default)]
+pub struct SearchIndex {
+    #[serde(default)]
+    version: u32,
+    vault: String,
+    files: HashMap<String, IndexedFile>,
+    // token -> (archivo -> apariciones)
+    #[serde(skip)]
+    postings: BTreeMap<String, HashMap<String, u32>>,
+    #[serde(skip)]
+    dirty: bool,
+}
 
-    let regex = if use_regex {
-        Regex::new(query).ok()
-    } else {
-        None
-    };
+impl SearchIndex {
+    /// Loads the stored index of `vault` (if any) and brings it up to date.
+    pub fn load(vault: &str) -> Self {
+        let mut index = fs::read_to_string(index_path(vault))
+            .ok()
+            .and_then(|data| serde_json::from_str::<SearchIndex>(&data).ok())
+            .filter(|index| index.vault == vault && index.version == INDEX_VERSION)
+            .unwrap_or_else(|| SearchIndex {
+                version: INDEX_VERSION,
+                vault: vault.to_string(),
+                ..Default::default()
+            });
 
-    files
-        .filter_map(|file| {
-            if fstream::contains(file.path(), query).unwrap_or(false) {
-                let mut result = MenuItem::new();
-                result.path = file.path().to_str().unwrap().to_string();
-                
-                if let Some(lines) = fstream::read_lines(file.path()) {
-                    for line in lines {
-                        let matched = if let Some(ref re) = regex {
-                            re.is_match(&line)
-                        } else {
-                            line.contains(query)
-                        };
-
-                        if matched {
-                            result.text = line.trim().chars().take(200).collect();
-                            break;
-                        }
+        index.rebuild_postings();
+        index.refresh();
+        index
+    }
+
+    pub fn vault(&self) -> &str {
+        &self.vault
+    }
+
+    /// Indexed files containing every word of `text`, also inside longer
+    /// words (`Note` is found in `My%20Note`).
+    pub fn files_mentioning(&self, text: &str) -> Vec<String> {
+        let words: Vec<String> = tokenize(text).collect();
+        self.containing(&words).into_iter().collect()
+    }
+
+    /// Loads the index if the vault changed, otherwise only reindexes the
+    /// files modified since the last refresh.
+    pub fn open(&mut self, vault: &str) {
+        if self.vault != vault {
+            *self = SearchIndex::load(vault);
+        } else {
+            self.refresh();
+        }
+    }
+
+    /// Compares the modification time of every file of the vault index (see
+    /// `vault_index`, which scans the disk) against this index and reindexes
+    /// only what changed. Call it after refreshing the vault index.
+    pub fn refresh(&mut self) {
+        if self.vault.is_empty() || !Path::new(&self.vault).exists() {
+            return;
+        }
+        let seen: HashMap<String, u64> = vault_index::with_index(&self.vault, |index| {
+            index
+                .files()
+                .filter(|f| has_indexed_extension(Path::new(&f.path)))
+                .map(|f| (f.path.clone(), f.modified))
+                .collect()
+        });
+
+        let removed: Vec<String> = self
+            .files
+            .keys()
+            .filter(|path| !seen.contains_key(*path))
+            .cloned()
+            .collect();
+        for path in removed {
+            self.remove_file(&path);
+        }
+
+        for (path, modified) in seen {
+            let up_to_date = self
+                .files
+                .get(&path)
+                .map(|f| f.modified == modified)
+                .unwrap_or(false);
+            if up_to_date {
+                continue;
+            }
+            if is_indexable(Path::new(&path)) {
+                self.index_file(&path, modified);
+            } else {
+                self.remove_file(&path);
+            }
+        }
+
+        if self.dirty {
+            self.save();
+        }
+    }
+
+    /// Reindexes a single file, used when the app itself writes, renames or
+    /// deletes a file.
+    pub fn update_file(&mut self, path: &str) {
+        if self.vault.is_empty() || !path.starts_with(&self.vault) {
+            return;
+        }
+        let file_path = Path::new(path);
+        if file_path.is_file() && is_indexable(file_path) {
+            self.index_file(path, modified_time(file_path));
+        } else {
+            self.remove_file(path);
+        }
+    }
+
+    pub fn save(&mut self) {
+        if self.vault.is_empty() || !self.dirty {
+            return;
+        }
+        let index_file = index_path(&self.vault);
+        if let Some(parent) = Path::new(&index_file).parent() {
+            if let Err(e) = fs::create_dir_all(parent) {
+                println!("Error creando la carpeta del índice de búsqueda: {}", e);
+                return;
+            }
+        }
+        match serde_json::to_string(&*self) {
+            Ok(data) => match fs::write(&index_file, data) {
+                Ok(_) => self.dirty = false,
+                Err(e) => println!("Error guardando el índice de búsqueda {}: {}", index_file, e),
+            },
+            Err(e) => println!("Error serializando el índice de búsqueda: {}", e),
+        }
+    }
+
+    /// Returns every file matching `query`, best matches first, with all the
+    /// lines that matched.
+    pub fn search(&self, query: &str, use_regex: bool) -> Vec<MenuItem> {
+        if query.trim().is_empty() {
+            return vec![];
+        }
+
+        let mut results: Vec<MenuItem> = if use_regex {
+            let Ok(re) = Regex::new(query) else {
+                return vec![];
+            };
+            self.containing(&regex_literals(query))
+                .into_iter()
+                .filter_map(|path| {
+                    let content = fs::read_to_string(&path).ok()?;
+                    let matches = matching_lines(&content, |l| re.is_match(l));
+                    if matches.is_empty() {
+                        None
+                    } else {
+                        Some(MenuItem {
+                            path,
+                            score: matches.len() as f32,
+                            matches,
+                        })
                     }
-                }
-                Some(result)
+                })
+                .collect()
+        } else {
+            let Some(parsed) = query::parse(query) else {
+                return vec![];
+            };
+            let highlight = parsed.highlight_terms();
+            self.query_candidates(&parsed.required_terms())
+                .into_iter()
+                .filter_map(|(path, mut score)| {
+                    let file = self.files.get(&path)?;
+                    let point = file.point(&self.vault, &path);
+                    // Solo se lee el archivo si la consulta o las líneas lo necesitan
+                    let content = OnceCell::new();
+                    let read = || content.get_or_init(|| fs::read_to_string(&path).ok()).clone();
+                    if !parsed.matches(&point, &read) {
+                        return None;
+                    }
+                    let matches = if highlight.is_empty() {
+                        vec![]
+                    } else {
+                        matching_lines(&read().unwrap_or_default(), |l| {
+                            let lower = l.to_lowercase();
+                            highlight.iter().any(|t| lower.contains(t.as_str()))
+                        })
+                    };
+                    let file_name = point.text.to_lowercase();
+                    if highlight.iter().any(|t| file_name.contains(t.as_str())) {
+                        score *= 2.0;
+                    }
+                    Some(MenuItem {
+                        path,
+                        score: score + matches.len() as f32 * 0.01,
+                        matches,
+                    })
+                })
+                .collect()
+        };
+
+        results.sort_by(|a, b| {
+            b.score
+                .partial_cmp(&a.score)
+                .unwrap_or(Ordering::Equal)
+                .then_with(|| a.path.cmp(&b.path))
+        });
+        results
+    }
+
+    // Candidatos para una consulta: archivos cuyo nombre o contenido tiene
+    // todos los términos obligatorios. Sin términos se evalúa todo el vault.
+    fn query_candidates(&self, required: &[String]) -> HashMap<String, f32> {
+        if required.is_empty() {
+            return self.files.keys().map(|p| (p.clone(), 1.0)).collect();
+        }
+        let mut candidates: Option<HashMap<String, f32>> = None;
+        for term in required {
+            let tokens: Vec<String> = tokenize(term).collect();
+            let mut scores = if tokens.is_empty() {
+                HashMap::new()
             } else {
-                None
+                self.rank_candidates(&tokens)
+            };
+            for path in self.files.keys() {
+                let name = Path::new(path)
+                    .file_stem()
+                    .and_then(|s| s.to_str())
+                    .unwrap_or("")
+                    .to_lowercase();
+                if name.contains(term.as_str()) {
+                    *scores.entry(path.clone()).or_insert(0.0) += 1.0;
+                }
+            }
+            candidates = Some(match candidates {
+                None => scores,
+                Some(prev) => prev
+                    .into_iter()
+                    .filter_map(|(path, score)| scores.get(&path).map(|s| (path, score + s)))
+                    .collect(),
+            });
+        }
+        candidates.unwrap_or_default()
+    }
+
+    // Archivos que contienen todos los términos, con su puntuación tf-idf.
+    // Como al evaluar la consulta, un término vale dentro de cualquier token
+    // ("line" encuentra "deadline"); las coincidencias exactas y los
+    // prefijos puntúan más.
+    fn rank_candidates(&self, terms: &[String]) -> HashMap<String, f32> {
+        let total_files = self.files.len().max(1) as f32;
+        let mut candidates: Option<HashMap<String, f32>> = None;
+
+        for term in terms {
+            let mut scores: HashMap<String, f32> = HashMap::new();
+            let matching = self.postings.iter().filter(|(token, _)| token.contains(term.as_str()));
+            for (token, docs) in matching {
+                let idf = (total_files / docs.len() as f32).ln() + 1.0;
+                let closeness = if token == term {
+                    2.0
+                } else if token.starts_with(term.as_str()) {
+                    1.5
+                } else {
+                    1.0
+                };
+                for (path, count) in docs {
+                    *scores.entry(path.clone()).or_insert(0.0) +=
+                        (*count as f32).sqrt() * idf * closeness;
+                }
             }
+            candidates = Some(match candidates {
+                None => scores,
+                Some(prev) => prev
+                    .into_iter()
+                    .filter_map(|(path, score)| scores.get(&path).map(|s| (path, score + s)))
+                    .collect(),
+            });
+        }
+        candidates.unwrap_or_default()
+    }
+
+    // Archivos con algún token que contiene cada palabra. Recorre el
+    // vocabulario, no el texto; sin palabras devuelve todos los archivos.
+    fn containing(&self, words: &[String]) -> HashSet<String> {
+        let mut candidates: Option<HashSet<String>> = None;
+        for word in words {
+            let found: HashSet<String> = self
+                .postings
+                .iter()
+                .filter(|(token, _)| token.contains(word.as_str()))
+                .flat_map(|(_, docs)| docs.keys().cloned())
+                .collect();
+            candidates = Some(match candidates {
+                None => found,
+                Some(prev) => prev.intersection(&found).cloned().collect(),
+            });
+        }
+        candidates.unwrap_or_else(|| self.files.keys().cloned().collect())
+    }
+
+    fn index_file(&mut self, path: &str, modified: u64) {
+        self.remove_file(path);
+        let Ok(content) = fs::read_to_string(path) else {
+            return;
+        };
+        let mut positions: HashMap<String, Vec<u32>> = HashMap::new();
+        for (n, line) in content.lines().enumerate() {
+            for token in tokenize(line) {
+                positions.entry(token).or_default().push(n as u32);
+            }
+        }
+        let file = IndexedFile {
+            modified,
+            tags: crate::vault_index::parse_note(&content).tags(),
+            positions,
+        };
+        add_postings(&mut self.postings, path, &file);
+        self.files.insert(path.to_string(), file);
+        self.dirty = true;
+    }
+
+    fn remove_file(&mut self, path: &str) {
+        let Some(old) = self.files.remove(path) else {
+            return;
+        };
+        for token in old.positions.keys() {
+            if let Some(docs) = self.postings.get_mut(token) {
+                docs.remove(path);
+                if docs.is_empty() {
+                    self.postings.remove(token);
+                }
+            }
+        }
+        self.dirty = true;
+    }
+
+    fn rebuild_postings(&mut self) {
+        self.postings.clear();
+        for (path, file) in &self.files {
+            add_postings(&mut self.postings, path, file);
+        }
+    }
+}
+
+fn add_postings(postings: &mut BTreeMap<String, HashMap<String, u32>>, path: &str, file: &IndexedFile) {
+    for (token, lines) in &file.positions {
+        postings
+            .entry(token.clone())
+            .or_default()
+            .insert(path.to_string(), lines.len() as u32);
+    }
+}
+
+fn matching_lines<F: Fn(&str) -> bool>(content: &str, is_match: F) -> Vec<LineMatch> {
+    content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| is_match(line))
+        .map(|(n, line)| LineMatch {
+            line: n + 1,
+            text: line.trim().chars().take(200).collect(),
         })
         .collect()
 }
 
+/// Runs of letters and digits every match of `pattern` must contain, used to
+/// narrow a regex search with the index. Anything inside groups, classes or
+/// before an optional quantifier is skipped, and an alternation at the top
+/// level means there's nothing required.
+fn regex_literals(pattern: &str) -> Vec<String> {
+    let chars: Vec<char> = pattern.chars().collect();
+    let mut literals = vec![];
+    let mut current = String::new();
+    let mut depth = 0;
+    let mut i = 0;
+    let mut flush = |current: &mut String| {
+        if !current.is_empty() {
+            literals.push(current.to_lowercase());
+            current.clear();
+        }
+    };
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            '\\' => {
+                flush(&mut current);
+                // \d, \w, \p{L}... no son texto literal
+                i += 1;
+                if chars.get(i + 1) == Some(&'{') {
+                    while i < chars.len() && chars[i] != '}' {
+                        i += 1;
+                    }
+                }
+            }
+            '[' => {
+                flush(&mut current);
+                i += 1;
+                while i < chars.len() && chars[i] != ']' {
+                    i += if chars[i] == '\\' { 2 } else { 1 };
+                }
+            }
+            '(' => {
+                flush(&mut current);
+                depth += 1;
+            }
+            ')' => depth -= 1,
+            '|' if depth == 0 => return vec![],
+            '?' | '*' | '{' => {
+                // El último carácter puede no aparecer
+                current.pop();
+                flush(&mut current);
+                if c == '{' {
+                    while i < chars.len() && chars[i] != '}' {
+                        i += 1;
+                    }
+                }
+            }
+            c if depth == 0 && (c.is_alphanumeric() || c == '_') => current.push(c),
+            _ => flush(&mut current),
+        }
+        i += 1;
+    }
+    flush(&mut current);
+    literals
+}
+
+fn tokenize(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .filter(|t| !t.is_empty())
+        .map(|t| t.to_lowercase())
+}
+
+fn has_indexed_extension(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| INDEXED_EXTENSIONS.contains(&e.to_lowercase().as_str()))
+        .unwrap_or(false)
+}
+
+fn is_indexable(path: &Path) -> bool {
+    has_indexed_extension(path)
+        && fs::metadata(path)
+            .map(|m| m.len() <= MAX_FILE_SIZE)
+            .unwrap_or(false)
+}
+
+// En milisegundos, como en `vault_index`
+fn modified_time(path: &Path) -> u64 {
+    fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+fn index_path(vault: &str) -> String {
+    let mut hasher = DefaultHasher::new();
+    vault.hash(&mut hasher);
+    format!(
+        "{}/search_index/{:x}.json",
+        configuraciones::get_config_dir(),
+        hasher.finish()
+    )
+}
+
 pub fn check_dir_regex(path: &str, query: &str) -> Vec<MenuItem> {
-    search_dir(path, query, true)
+    SearchIndex::load(path).search(query, true)
 }
 
 pub fn check_dir(path: &str, query: &str) -> Vec<MenuItem> {
-    search_dir(path, query, false)
+    SearchIndex::load(path).search(query, false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn required_regex_literals() {
+        let literals = |p: &str| regex_literals(p);
+        assert_eq!(literals("dead.*Line"), vec!["dead", "line"]);
+        assert_eq!(literals(r"\bfoo\d+bar_baz"), vec!["foo", "bar_baz"]);
+        assert_eq!(literals("colou?r"), vec!["colo", "r"]);
+        assert_eq!(literals("ab{2,3}c"), vec!["a", "c"]);
+        assert_eq!(literals("(?i)(draft|wip) note[s0-9]"), vec!["note"]);
+        assert_eq!(literals(r"\p{Lu}x"), vec!["x"]);
+        assert!(literals("foo|bar").is_empty());
+        assert!(literals(".*").is_empty());
+    }
+
+    #[test]
+    fn candidates_match_inside_words() {
+        let mut index = SearchIndex::default();
+        for (path, text) in [("/v/a.md", "The deadline is near"), ("/v/b.md", "One line only"), ("/v/c.md", "Nothing here")] {
+            let mut positions: HashMap<String, Vec<u32>> = HashMap::new();
+            for token in tokenize(text) {
+                positions.entry(token).or_default().push(0);
+            }
+            let file = IndexedFile {
+                modified: 0,
+                tags: vec![],
+                positions,
+            };
+            add_postings(&mut index.postings, path, &file);
+            index.files.insert(path.to_string(), file);
+        }
+        let candidates = index.query_candidates(&["line".to_string()]);
+        let mut paths: Vec<&str> = candidates.keys().map(String::as_str).collect();
+        paths.sort();
+        assert_eq!(paths, ["/v/a.md", "/v/b.md"]);
+        // La palabra exacta puntúa más que la encontrada dentro de otra
+        assert!(candidates["/v/b.md"] > candidates["/v/a.md"]);
+        assert!(index.query_candidates(&["eadl".to_string()]).contains_key("/v/a.md"));
+    }
 }
@@ -0,0 +1,372 @@
+use crate::graph::data::{MarmolPoint, MatchType};
+use crate::graph::logic::match_point;
+
+/// Where a search term has to be found. Plain terms match the file name or
+/// the contents, operators reuse the graph's `MatchType`.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Field {
+    Any,
+    Match(MatchType),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum QueryNode {
+    Term { field: Field, value: String },
+    And(Vec<QueryNode>),
+    Or(Vec<QueryNode>),
+    Not(Box<QueryNode>),
+    /// `line:(a b)`: some line of the file matches the whole group.
+    Line(Box<QueryNode>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    LParen,
+    RParen,
+    Not,
+    And,
+    Or,
+    Line,
+    Term { field: Field, value: String },
+}
+
+/// Parses queries like `tag:#work path:projects/ "deadline" -draft`.
+///
+/// Terms next to each other are joined with AND, `OR` binds weaker than
+/// AND, `-term` and `NOT term` negate, parentheses group and double quotes
+/// keep phrases together (also after an operator: `path:"my folder"`).
+/// `line:(a b)` needs every term of the group on the same line.
+pub fn parse(query: &str) -> Option<QueryNode> {
+    let tokens = lex(query);
+    if tokens.is_empty() {
+        return None;
+    }
+    let mut pos = 0;
+    parse_or(&tokens, &mut pos)
+}
+
+impl QueryNode {
+    /// Evaluates the query against a file. `content` is only called for the
+    /// terms that need the file contents.
+    pub fn matches(&self, point: &MarmolPoint, content: &dyn Fn() -> Option<String>) -> bool {
+        match self {
+            QueryNode::Term { field, value } => match field {
+                Field::Any => {
+                    match_point(&MatchType::Filename, value, point, || None)
+                        || match_point(&MatchType::Content, value, point, content)
+                }
+                Field::Match(m_type) => match_point(m_type, value, point, content),
+            },
+            QueryNode::And(nodes) => nodes.iter().all(|n| n.matches(point, content)),
+            QueryNode::Or(nodes) => nodes.iter().any(|n| n.matches(point, content)),
+            QueryNode::Not(node) => !node.matches(point, content),
+            QueryNode::Line(node) => {
+                if point.is_attachment || point.is_tag {
+                    return false;
+                }
+                content()
+                    .map(|c| c.lines().any(|line| node.matches_line(&line.to_lowercase())))
+                    .unwrap_or(false)
+            }
+        }
+    }
+
+    /// Evaluates a `line:` group against a single lowercase line. Every term
+    /// is looked for as text, tags with their `#`.
+    fn matches_line(&self, line: &str) -> bool {
+        match self {
+            QueryNode::Term {
+                field: Field::Match(MatchType::Tag),
+                value,
+            } => line.contains(&format!("#{}", value)),
+            QueryNode::Term { value, .. } => line.contains(value.as_str()),
+            QueryNode::And(nodes) => nodes.iter().all(|n| n.matches_line(line)),
+            QueryNode::Or(nodes) => nodes.iter().any(|n| n.matches_line(line)),
+            QueryNode::Not(node) => !node.matches_line(line),
+            QueryNode::Line(node) => node.matches_line(line),
+        }
+    }
+
+    /// Text terms every matching file must contain (in its name or contents),
+    /// used to narrow the candidates with the inverted index before
+    /// evaluating the query.
+    pub fn required_terms(&self) -> Vec<String> {
+        match self {
+            QueryNode::Term {
+                field: Field::Any | Field::Match(MatchType::Content),
+                value,
+            } => vec![value.clone()],
+            QueryNode::And(nodes) => nodes.iter().flat_map(|n| n.required_terms()).collect(),
+            QueryNode::Line(node) => node.required_terms(),
+            _ => vec![],
+        }
+    }
+
+    /// Non negated text terms, used to pick the lines shown in the results.
+    pub fn highlight_terms(&self) -> Vec<String> {
+        match self {
+            QueryNode::Term {
+                field: Field::Any | Field::Match(MatchType::Content),
+                value,
+            } => vec![value.clone()],
+            QueryNode::Term { .. } | QueryNode::Not(_) => vec![],
+            QueryNode::And(nodes) | QueryNode::Or(nodes) => {
+                nodes.iter().flat_map(|n| n.highlight_terms()).collect()
+            }
+            QueryNode::Line(node) => node.highlight_terms(),
+        }
+    }
+}
+
+fn parse_or(tokens: &[Token], pos: &mut usize) -> Option<QueryNode> {
+    let mut nodes = vec![];
+    if let Some(node) = parse_and(tokens, pos) {
+        nodes.push(node);
+    }
+    while *pos < tokens.len() && tokens[*pos] == Token::Or {
+        *pos += 1;
+        if let Some(node) = parse_and(tokens, pos) {
+            nodes.push(node);
+        }
+    }
+    match nodes.len() {
+        0 => None,
+        1 => nodes.pop(),
+        _ => Some(QueryNode::Or(nodes)),
+    }
+}
+
+fn parse_and(tokens: &[Token], pos: &mut usize) -> Option<QueryNode> {
+    let mut nodes = vec![];
+    while *pos < tokens.len() {
+        match tokens[*pos] {
+            Token::Or | Token::RParen => break,
+            Token::And => {
+                *pos += 1;
+                continue;
+            }
+            _ => {}
+        }
+        if let Some(node) = parse_unary(tokens, pos) {
+            nodes.push(node);
+        }
+    }
+    match nodes.len() {
+        0 => None,
+        1 => nodes.pop(),
+        _ => Some(QueryNode::And(nodes)),
+    }
+}
+
+fn parse_unary(tokens: &[Token], pos: &mut usize) -> Option<QueryNode> {
+    let token = tokens.get(*pos)?.clone();
+    *pos += 1;
+    match token {
+        Token::Not => parse_unary(tokens, pos).map(|n| QueryNode::Not(Box::new(n))),
+        Token::Line => parse_unary(tokens, pos).map(|n| QueryNode::Line(Box::new(n))),
+        Token::LParen => {
+            let node = parse_or(tokens, pos);
+            if tokens.get(*pos) == Some(&Token::RParen) {
+                *pos += 1;
+            }
+            node
+        }
+        Token::Term { field, value } => Some(QueryNode::Term { field, value }),
+        // Paréntesis sin abrir u operadores sueltos se ignoran
+        Token::RParen | Token::And | Token::Or => None,
+    }
+}
+
+fn lex(query: &str) -> Vec<Token> {
+    let chars: Vec<char> = query.chars().collect();
+    let mut tokens = vec![];
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c == '(' {
+            tokens.push(Token::LParen);
+            i += 1;
+        } else if c == ')' {
+            tokens.push(Token::RParen);
+            i += 1;
+        } else if c == '-' {
+            tokens.push(Token::Not);
+            i += 1;
+        } else if c == '"' {
+            let (phrase, next) = read_quoted(&chars, i + 1);
+            i = next;
+            if !phrase.is_empty() {
+                tokens.push(Token::Term {
+                    field: Field::Match(MatchType::Content),
+                    value: phrase.to_lowercase(),
+                });
+            }
+        } else {
+            let mut word = String::new();
+            while i < chars.len() && !chars[i].is_whitespace() && chars[i] != '(' && chars[i] != ')' {
+                if chars[i] == '"' && word.ends_with(':') {
+                    let (phrase, next) = read_quoted(&chars, i + 1);
+                    word.push_str(&phrase);
+                    i = next;
+                    break;
+                }
+                word.push(chars[i]);
+                i += 1;
+            }
+            // "line:(" abre un grupo que se evalúa línea a línea
+            if word.eq_ignore_ascii_case("line:") && chars.get(i) == Some(&'(') {
+                tokens.push(Token::Line);
+            } else {
+                tokens.push(word_token(&word));
+            }
+        }
+    }
+    tokens
+}
+
+fn read_quoted(chars: &[char], start: usize) -> (String, usize) {
+    let mut i = start;
+    let mut phrase = String::new();
+    while i < chars.len() && chars[i] != '"' {
+        phrase.push(chars[i]);
+        i += 1;
+    }
+    // Saltar la comilla de cierre
+    (phrase, i + 1)
+}
+
+fn word_token(word: &str) -> Token {
+    match word {
+        "OR" => return Token::Or,
+        "AND" => return Token::And,
+        "NOT" => return Token::Not,
+        _ => {}
+    }
+
+    if let Some((op, value)) = word.split_once(':') {
+        let m_type = match op.to_lowercase().as_str() {
+            "tag" => Some(MatchType::Tag),
+            "path" => Some(MatchType::Path),
+            "file" => Some(MatchType::Filename),
+            "line" | "content" => Some(MatchType::Content),
+            "section" => Some(MatchType::Section),
+            _ => None,
+        };
+        if let Some(m_type) = m_type {
+            let value = if m_type == MatchType::Tag {
+                value.trim_start_matches('#')
+            } else {
+                value
+            };
+            return Token::Term {
+                field: Field::Match(m_type),
+                value: value.to_lowercase(),
+            };
+        }
+    }
+
+    Token::Term {
+        field: Field::Any,
+        value: word.to_lowercase(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn term(field: Field, value: &str) -> QueryNode {
+        QueryNode::Term {
+            field,
+            value: value.to_string(),
+        }
+    }
+
+    fn any(value: &str) -> QueryNode {
+        term(Field::Any, value)
+    }
+
+    fn point(name: &str, tags: &[&str]) -> MarmolPoint {
+        MarmolPoint::new(
+            name,
+            tags.iter().map(|t| t.to_string()).collect(),
+            vec![],
+            format!("notes/{}.md", name),
+            format!("/vault/notes/{}.md", name),
+            false,
+            false,
+            true,
+        )
+    }
+
+    #[test]
+    fn terms_and_or_not() {
+        assert_eq!(parse("  "), None);
+        assert_eq!(parse("Foo bar"), Some(QueryNode::And(vec![any("foo"), any("bar")])));
+        assert_eq!(parse("foo AND bar"), parse("foo bar"));
+        assert_eq!(
+            parse("a b OR c"),
+            Some(QueryNode::Or(vec![QueryNode::And(vec![any("a"), any("b")]), any("c")]))
+        );
+        assert_eq!(
+            parse("(a OR b) c"),
+            Some(QueryNode::And(vec![QueryNode::Or(vec![any("a"), any("b")]), any("c")]))
+        );
+        assert_eq!(parse("-draft"), Some(QueryNode::Not(Box::new(any("draft")))));
+        assert_eq!(parse("NOT draft"), parse("-draft"));
+        assert_eq!(parse("a OR"), Some(any("a")));
+    }
+
+    #[test]
+    fn operators_and_phrases() {
+        assert_eq!(
+            parse("tag:#Work path:\"My Folder\" file:Plan section:goals content:x \"due soon\""),
+            Some(QueryNode::And(vec![
+                term(Field::Match(MatchType::Tag), "work"),
+                term(Field::Match(MatchType::Path), "my folder"),
+                term(Field::Match(MatchType::Filename), "plan"),
+                term(Field::Match(MatchType::Section), "goals"),
+                term(Field::Match(MatchType::Content), "x"),
+                term(Field::Match(MatchType::Content), "due soon"),
+            ]))
+        );
+        assert_eq!(parse("unknown:x"), Some(any("unknown:x")));
+    }
+
+    #[test]
+    fn line_groups() {
+        let content = term(Field::Match(MatchType::Content), "foo");
+        assert_eq!(parse("line:foo"), Some(content));
+        assert_eq!(
+            parse("line:(foo -bar) baz"),
+            Some(QueryNode::And(vec![
+                QueryNode::Line(Box::new(QueryNode::And(vec![
+                    any("foo"),
+                    QueryNode::Not(Box::new(any("bar"))),
+                ]))),
+                any("baz"),
+            ]))
+        );
+
+        let query = parse("line:(meeting #work)").unwrap();
+        assert_eq!(query.required_terms(), vec!["meeting".to_string(), "#work".to_string()]);
+        let note = point("log", &[]);
+        let matches = |text: &str| query.matches(&note, &|| Some(text.to_string()));
+        assert!(matches("Notes\nMeeting about #work\n"));
+        assert!(!matches("Meeting\n#work"));
+        assert!(!query.matches(&note, &|| None));
+    }
+
+    #[test]
+    fn tag_operator_matches_subtags_only() {
+        let note = point("plan", &["project/web"]);
+        let matches = |q: &str| parse(q).unwrap().matches(&note, &|| None);
+        assert!(matches("tag:#project"));
+        assert!(matches("tag:project/web"));
+        assert!(!matches("tag:proj"));
+        assert!(!matches("tag:web"));
+    }
+}
@@ -1,24 +1,528 @@
 use chrono::prelude::*;
-use std::process::Command;
-
-use crate::files;
-//Reads when the last backup where made
-fn read_date() {
-    let date = files::read_file("file");
-    let _paserd_date = match NaiveDate::parse_from_str(&date, "") {
-        Ok(some) => some,
-        Err(_) => NaiveDate::MIN,
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver};
+use std::time::SystemTime;
+use walkdir::WalkDir;
+
+use crate::configuraciones;
+
+pub mod sync;
+pub mod sync_server;
+
+// Nombre de cada snapshot: carpeta `2024-01-31_18-05-00` o `2024-01-31_18-05-00.zip`
+const SNAPSHOT_FORMAT: &str = "%Y-%m-%d_%H-%M-%S";
+// Minutos de espera antes de reintentar un respaldo programado que falló
+const RETRY_MINUTES: i64 = 30;
+
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct BackupSettings {
+    pub enabled: bool,
+    pub target: String,
+    pub zip: bool,
+    pub interval_hours: u32,
+    pub keep_last: usize,
+    pub keep_daily: usize,
+    pub keep_weekly: usize,
+    pub last_backup: Option<String>,
+}
+
+impl Default for BackupSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            target: String::new(),
+            zip: false,
+            interval_hours: 24,
+            keep_last: 10,
+            keep_daily: 7,
+            keep_weekly: 4,
+            last_backup: None,
+        }
+    }
+}
+
+pub struct Snapshot {
+    pub name: String,
+    pub path: PathBuf,
+    pub time: NaiveDateTime,
+    pub zipped: bool,
+}
+
+/// Scheduled snapshots of the current vault, configured on `Screen::Server`.
+#[derive(Default)]
+pub struct BackupManager {
+    vault: String,
+    pub settings: BackupSettings,
+    pub snapshots: Vec<Snapshot>,
+    pub status: String,
+    running: Option<Receiver<Result<String, String>>>,
+    restore_confirm: Option<usize>,
+    /// When the last backup was started, so a failing one isn't retried
+    /// every frame.
+    last_attempt: Option<NaiveDateTime>,
+    last_error: Option<String>,
+}
+
+impl BackupManager {
+    pub fn load(&mut self, vault: &str) {
+        if self.vault == vault {
+            return;
+        }
+        self.vault = vault.to_string();
+        self.settings = read_settings().remove(vault).unwrap_or_default();
+        self.restore_confirm = None;
+        self.status.clear();
+        self.last_attempt = None;
+        self.last_error = None;
+        self.refresh_snapshots();
+    }
+
+    pub fn save_settings(&self) {
+        if self.vault.is_empty() {
+            return;
+        }
+        let mut all = read_settings();
+        all.insert(self.vault.clone(), self.settings.clone());
+        let path = settings_path();
+        if let Some(parent) = Path::new(&path).parent() {
+            let _ = fs::create_dir_all(parent);
+        }
+        match serde_json::to_string_pretty(&all) {
+            Ok(data) => {
+                if let Err(e) = fs::write(&path, data) {
+                    println!("Error guardando la configuración de copias {}: {}", path, e);
+                }
+            }
+            Err(e) => println!("Error serializando la configuración de copias: {}", e),
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running.is_some()
+    }
+
+    pub fn last_backup(&self) -> Option<NaiveDateTime> {
+        self.settings
+            .last_backup
+            .as_ref()
+            .and_then(|d| NaiveDateTime::parse_from_str(d, SNAPSHOT_FORMAT).ok())
+    }
+
+    /// Polls a running backup and starts a new one when the interval expired.
+    /// Called every frame from the main screen.
+    pub fn tick(&mut self, vault: &str) {
+        self.load(vault);
+
+        if let Some(rx) = &self.running {
+            if let Ok(result) = rx.try_recv() {
+                self.running = None;
+                match result {
+                    Ok(name) => {
+                        self.settings.last_backup = Some(name.trim_end_matches(".zip").to_string());
+                        self.status = format!("Backup {} finished", name);
+                        self.last_error = None;
+                        self.save_settings();
+                    }
+                    Err(e) => {
+                        // El mismo error en cada reintento solo se muestra una vez
+                        if self.last_error.as_ref() != Some(&e) {
+                            println!("Error en el respaldo: {}", e);
+                        }
+                        self.status = format!("Backup failed: {} (retrying in {} minutes)", e, RETRY_MINUTES);
+                        self.last_error = Some(e);
+                    }
+                }
+                self.refresh_snapshots();
+            }
+            return;
+        }
+
+        if !self.settings.enabled || self.settings.target.is_empty() {
+            return;
+        }
+        let now = Local::now().naive_local();
+        if self.last_attempt.is_some_and(|at| now - at < chrono::Duration::minutes(RETRY_MINUTES)) {
+            return;
+        }
+        let due = match self.last_backup() {
+            Some(last) => {
+                Local::now().naive_local() - last
+                    >= chrono::Duration::hours(self.settings.interval_hours.max(1) as i64)
+            }
+            None => true,
+        };
+        if due {
+            self.start_backup();
+        }
+    }
+
+    pub fn start_backup(&mut self) {
+        if self.running.is_some() || self.vault.is_empty() || self.settings.target.is_empty() {
+            return;
+        }
+        self.last_attempt = Some(Local::now().naive_local());
+        if target_in_vault(&self.vault, &self.settings.target) {
+            self.status = "Backup skipped: the target folder is inside the vault".to_string();
+            return;
+        }
+        let vault = self.vault.clone();
+        let settings = self.settings.clone();
+        let (tx, rx) = channel();
+        std::thread::spawn(move || {
+            let _ = tx.send(run_backup(&vault, &settings));
+        });
+        self.running = Some(rx);
+        self.status = "Backing up...".to_string();
+    }
+
+    pub fn refresh_snapshots(&mut self) {
+        self.snapshots = list_snapshots(&snapshots_dir(&self.vault, &self.settings.target));
+    }
+
+    pub fn restore(&mut self, idx: usize) {
+        let Some(snapshot) = self.snapshots.get(idx) else {
+            return;
+        };
+        self.status = match restore_snapshot(snapshot, &self.vault) {
+            Ok(n) => format!("Restored {} files from {}", n, snapshot.name),
+            Err(e) => {
+                println!("Error restaurando {}: {}", snapshot.name, e);
+                format!("Restore failed: {}", e)
+            }
+        };
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        let old_settings = self.settings.clone();
+
+        ui.heading("Backups");
+        ui.label(egui::RichText::new(&self.vault).weak());
+        ui.add_space(10.0);
+
+        ui.checkbox(&mut self.settings.enabled, "Scheduled backups");
+        ui.horizontal(|ui| {
+            ui.label("Target folder:");
+            ui.add(
+                egui::TextEdit::singleline(&mut self.settings.target)
+                    .hint_text("/path/to/backups")
+                    .desired_width(300.0),
+            );
+            #[cfg(not(target_os = "android"))]
+            if ui.button("📂").clicked() {
+                if let Some(x) = rfd::FileDialog::new()
+                    .set_title("Select Backup Folder")
+                    .pick_folder()
+                {
+                    self.settings.target = x.to_string_lossy().to_string();
+                }
+            }
+        });
+        let inside_vault = target_in_vault(&self.vault, &self.settings.target);
+        if inside_vault {
+            ui.label(
+                egui::RichText::new("The target folder can't be inside the vault")
+                    .color(ui.visuals().warn_fg_color),
+            );
+        }
+        ui.horizontal(|ui| {
+            ui.radio_value(&mut self.settings.zip, false, "Folder (incremental)")
+                .on_hover_text("Unchanged files are hard linked to the previous snapshot");
+            ui.radio_value(&mut self.settings.zip, true, "Zip archive");
+        });
+        ui.add(
+            egui::Slider::new(&mut self.settings.interval_hours, 1..=168)
+                .text("Hours between backups"),
+        );
+
+        ui.collapsing("Retention", |ui| {
+            ui.add(egui::Slider::new(&mut self.settings.keep_last, 1..=100).text("Keep last"));
+            ui.add(egui::Slider::new(&mut self.settings.keep_daily, 0..=60).text("Keep daily"));
+            ui.add(egui::Slider::new(&mut self.settings.keep_weekly, 0..=52).text("Keep weekly"));
+        });
+
+        if self.settings != old_settings {
+            if self.settings.target != old_settings.target {
+                self.refresh_snapshots();
+            }
+            self.save_settings();
+        }
+
+        ui.separator();
+        ui.horizontal(|ui| {
+            let label = match self.last_backup() {
+                Some(d) => format!("Last backup: {}", d.format("%Y-%m-%d %H:%M")),
+                None => "No backups yet".to_string(),
+            };
+            ui.label(label);
+            let can_back_up = !self.is_running() && !self.settings.target.is_empty() && !inside_vault;
+            ui.add_enabled_ui(can_back_up, |ui| {
+                if ui.button("Back up now").clicked() {
+                    self.start_backup();
+                }
+            });
+            if self.is_running() {
+                ui.spinner();
+                ui.ctx().request_repaint();
+            }
+        });
+        if !self.status.is_empty() {
+            ui.label(egui::RichText::new(&self.status).small());
+        }
+
+        ui.separator();
+        ui.label(egui::RichText::new(format!("Snapshots ({})", self.snapshots.len())).strong());
+        let mut restore = None;
+        egui::ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+            for (i, snapshot) in self.snapshots.iter().enumerate() {
+                ui.horizontal(|ui| {
+                    let icon = if snapshot.zipped { "🗜" } else { "📁" };
+                    ui.label(format!("{} {}", icon, snapshot.time.format("%Y-%m-%d %H:%M:%S")));
+                    if self.restore_confirm == Some(i) {
+                        ui.label(
+                            egui::RichText::new("Overwrite vault files?")
+                                .color(ui.visuals().warn_fg_color),
+                        );
+                        if ui.button("Yes, restore").clicked() {
+                            restore = Some(i);
+                        }
+                        if ui.button("Cancel").clicked() {
+                            self.restore_confirm = None;
+                        }
+                    } else if ui.button("Restore").clicked() {
+                        self.restore_confirm = Some(i);
+                    }
+                });
+            }
+        });
+        if let Some(i) = restore {
+            self.restore_confirm = None;
+            self.restore(i);
+        }
+    }
+}
+
+fn settings_path() -> String {
+    format!("{}/backup.json", configuraciones::get_config_dir())
+}
+
+fn read_settings() -> HashMap<String, BackupSettings> {
+    fs::read_to_string(settings_path())
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+fn snapshots_dir(vault: &str, target: &str) -> PathBuf {
+    let name = Path::new(vault)
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("vault");
+    Path::new(target).join(name)
+}
+
+// Un destino dentro del vault acabaría indexado y vigilado como parte de las notas
+fn target_in_vault(vault: &str, target: &str) -> bool {
+    let canonical = |p: &str| fs::canonicalize(p).unwrap_or_else(|_| PathBuf::from(p));
+    !vault.is_empty() && !target.is_empty() && canonical(target).starts_with(canonical(vault))
+}
+
+fn list_snapshots(dir: &Path) -> Vec<Snapshot> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return vec![];
     };
+    let mut snapshots: Vec<Snapshot> = entries
+        .filter_map(|e| e.ok())
+        .filter_map(|e| {
+            let name = e.file_name().to_str()?.to_string();
+            let zipped = name.ends_with(".zip");
+            let time =
+                NaiveDateTime::parse_from_str(name.trim_end_matches(".zip"), SNAPSHOT_FORMAT).ok()?;
+            Some(Snapshot {
+                name,
+                path: e.path(),
+                time,
+                zipped,
+            })
+        })
+        .collect();
+    // El más reciente primero
+    snapshots.sort_by(|a, b| b.time.cmp(&a.time));
+    snapshots
+}
+
+fn vault_files<'a>(vault: &'a str, exclude: &'a Path) -> impl Iterator<Item = (PathBuf, PathBuf)> + 'a {
+    WalkDir::new(vault)
+        .into_iter()
+        .filter_entry(move |e| !e.path().starts_with(exclude))
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .filter_map(move |e| {
+            let rel = e.path().strip_prefix(vault).ok()?.to_path_buf();
+            Some((e.path().to_path_buf(), rel))
+        })
+}
+
+fn run_backup(vault: &str, settings: &BackupSettings) -> Result<String, String> {
+    let dir = snapshots_dir(vault, &settings.target);
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    remove_partials(&dir);
+    let now = Local::now().naive_local();
+    let name = now.format(SNAPSHOT_FORMAT).to_string();
+    // Ya hay un respaldo de este mismo segundo
+    for existing in [name.clone(), format!("{}.zip", name)] {
+        if dir.join(&existing).exists() {
+            return Ok(existing);
+        }
+    }
+
+    let name = if settings.zip {
+        let name = format!("{}.zip", name);
+        zip_snapshot(vault, &dir, &dir.join(&name)).map_err(|e| e.to_string())?;
+        name
+    } else {
+        let previous = list_snapshots(&dir).into_iter().find(|s| !s.zipped);
+        folder_snapshot(vault, &dir, &dir.join(&name), previous.as_ref())
+            .map_err(|e| e.to_string())?;
+        name
+    };
+
+    apply_retention(&dir, settings, now);
+    Ok(name)
 }
 
-//uses Rsync to sync uses Rsync to sync
-fn update(source: &str, destination: &str) {
-    let out = Command::new("rsync")
-        .arg("--archive")
-        .arg(source)
-        .arg(destination)
-        .output()
-        .expect("Failed to execute");
+// Restos de un respaldo interrumpido: nunca aparecen en la lista de snapshots
+fn remove_partials(dir: &Path) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.filter_map(|e| e.ok()) {
+        let path = entry.path();
+        if path.extension().and_then(|e| e.to_str()) != Some("partial") {
+            continue;
+        }
+        let result = if path.is_dir() {
+            fs::remove_dir_all(&path)
+        } else {
+            fs::remove_file(&path)
+        };
+        if let Err(e) = result {
+            println!("Error borrando el respaldo incompleto {}: {}", path.display(), e);
+        }
+    }
+}
+
+// Copia incremental: los archivos sin cambios desde el snapshot anterior se enlazan (hard link)
+fn folder_snapshot(vault: &str, exclude: &Path, dest: &Path, previous: Option<&Snapshot>) -> io::Result<()> {
+    let tmp = dest.with_extension("partial");
+    for (src, rel) in vault_files(vault, exclude) {
+        let out = tmp.join(&rel);
+        if let Some(parent) = out.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let linked = previous
+            .map(|prev| {
+                let old = prev.path.join(&rel);
+                unchanged_since(&src, &old, prev.time) && fs::hard_link(&old, &out).is_ok()
+            })
+            .unwrap_or(false);
+        if !linked {
+            fs::copy(&src, &out)?;
+        }
+    }
+    // Solo aparece en la lista cuando terminó de copiarse
+    fs::rename(&tmp, dest)
+}
+
+fn unchanged_since(src: &Path, old: &Path, since: NaiveDateTime) -> bool {
+    let (Ok(src_meta), Ok(old_meta)) = (fs::metadata(src), fs::metadata(old)) else {
+        return false;
+    };
+    if src_meta.len() != old_meta.len() {
+        return false;
+    }
+    let modified: DateTime<Local> = src_meta.modified().unwrap_or(SystemTime::now()).into();
+    modified.naive_local() < since
+}
+
+fn zip_snapshot(vault: &str, exclude: &Path, dest: &Path) -> zip::result::ZipResult<()> {
+    let tmp = dest.with_extension("partial");
+    let mut writer = zip::ZipWriter::new(fs::File::create(&tmp)?);
+    let options = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated);
+    for (src, rel) in vault_files(vault, exclude) {
+        writer.start_file(rel.to_string_lossy().replace('\\', "/"), options)?;
+        io::copy(&mut fs::File::open(&src)?, &mut writer)?;
+    }
+    writer.finish()?;
+    fs::rename(&tmp, dest)?;
+    Ok(())
+}
+
+fn restore_snapshot(snapshot: &Snapshot, vault: &str) -> Result<usize, String> {
+    if snapshot.zipped {
+        let file = fs::File::open(&snapshot.path).map_err(|e| e.to_string())?;
+        let mut archive = zip::ZipArchive::new(file).map_err(|e| e.to_string())?;
+        let count = archive.len();
+        archive.extract(vault).map_err(|e| e.to_string())?;
+        return Ok(count);
+    }
+
+    let mut count = 0;
+    for entry in WalkDir::new(&snapshot.path)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+    {
+        let Ok(rel) = entry.path().strip_prefix(&snapshot.path) else {
+            continue;
+        };
+        let out = Path::new(vault).join(rel);
+        if let Some(parent) = out.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        // Quitar el archivo antes de copiar para no escribir a través de un hard link
+        let _ = fs::remove_file(&out);
+        fs::copy(entry.path(), &out).map_err(|e| e.to_string())?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+/// Keeps the last `keep_last` snapshots plus the newest one of each of the
+/// last `keep_daily` days and `keep_weekly` weeks; deletes the rest.
+fn apply_retention(dir: &Path, settings: &BackupSettings, now: NaiveDateTime) {
+    let snapshots = list_snapshots(dir);
+    let mut keep: HashSet<usize> = (0..settings.keep_last.max(1).min(snapshots.len())).collect();
+
+    let mut days = HashSet::new();
+    let mut weeks = HashSet::new();
+    for (i, s) in snapshots.iter().enumerate() {
+        let age = (now.date() - s.time.date()).num_days();
+        if age < settings.keep_daily as i64 && days.insert(s.time.date()) {
+            keep.insert(i);
+        }
+        let week = s.time.date().iso_week();
+        if age < settings.keep_weekly as i64 * 7 && weeks.insert((week.year(), week.week())) {
+            keep.insert(i);
+        }
+    }
 
-    println!("{:?}", out.stdout);
+    for (i, s) in snapshots.iter().enumerate() {
+        if keep.contains(&i) {
+            continue;
+        }
+        let result = if s.zipped {
+            fs::remove_file(&s.path)
+        } else {
+            fs::remove_dir_all(&s.path)
+        };
+        if let Err(e) = result {
+            println!("Error borrando el respaldo {}: {}", s.name, e);
+        }
+    }
 }
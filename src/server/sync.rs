@@ -0,0 +1,683 @@
+use chrono::prelude::*;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::{Component, Path};
+use std::sync::mpsc::{channel, Receiver};
+use walkdir::WalkDir;
+
+use crate::configuraciones;
+
+/// Edit counter per device. A file version happened before another one when
+/// every counter is lower or equal.
+pub type VersionVector = BTreeMap<String, u64>;
+
+// Estado de la última sincronización, guardado dentro del vault
+const STATE_FILE: &str = ".obsidian/marmol-sync.json";
+const SYNC_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+#[derive(Serialize, Deserialize, Clone, Default, PartialEq, Debug)]
+pub struct FileRecord {
+    pub hash: String,
+    pub version: VersionVector,
+    #[serde(default)]
+    pub deleted: bool,
+}
+
+#[derive(PartialEq, Debug)]
+pub enum Causality {
+    Equal,
+    Before,
+    After,
+    Concurrent,
+}
+
+/// Compares `a` against `b`: `Before` means `b` already contains every edit of `a`.
+pub fn compare(a: &VersionVector, b: &VersionVector) -> Causality {
+    let mut less = false;
+    let mut greater = false;
+    for key in a.keys().chain(b.keys()) {
+        let x = a.get(key).copied().unwrap_or(0);
+        let y = b.get(key).copied().unwrap_or(0);
+        less |= x < y;
+        greater |= x > y;
+    }
+    match (less, greater) {
+        (false, false) => Causality::Equal,
+        (true, false) => Causality::Before,
+        (false, true) => Causality::After,
+        (true, true) => Causality::Concurrent,
+    }
+}
+
+/// What to do with a file known both locally and on the server.
+#[derive(PartialEq, Debug)]
+enum Resolution {
+    Nothing,
+    Download,
+    Upload,
+    /// Same content on both sides: only the versions are combined.
+    MergeVersions,
+    /// Deleted on the server but edited here: the edit is uploaded again.
+    Restore,
+    /// Concurrent edits: the local version is kept as a conflict copy.
+    ConflictCopy,
+}
+
+fn resolve(local: &FileRecord, remote: &FileRecord) -> Resolution {
+    match compare(&local.version, &remote.version) {
+        Causality::Equal => Resolution::Nothing,
+        Causality::Before => Resolution::Download,
+        Causality::After => Resolution::Upload,
+        Causality::Concurrent => {
+            if local.deleted == remote.deleted && local.hash == remote.hash {
+                Resolution::MergeVersions
+            } else if local.deleted {
+                // Una edición gana sobre un borrado
+                Resolution::Download
+            } else if remote.deleted {
+                Resolution::Restore
+            } else {
+                Resolution::ConflictCopy
+            }
+        }
+    }
+}
+
+pub fn merge(a: &VersionVector, b: &VersionVector) -> VersionVector {
+    let mut out = a.clone();
+    for (key, value) in b {
+        let entry = out.entry(key.clone()).or_insert(0);
+        *entry = (*entry).max(*value);
+    }
+    out
+}
+
+pub fn content_hash(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Relative paths only, without `..`, so a peer can't write outside the vault.
+pub fn is_safe_path(rel: &str) -> bool {
+    !rel.is_empty()
+        && Path::new(rel)
+            .components()
+            .all(|c| matches!(c, Component::Normal(_)))
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct SyncSettings {
+    pub enabled: bool,
+    pub url: String,
+    pub token: String,
+    pub device: String,
+    pub interval_minutes: u32,
+    pub last_sync: Option<String>,
+}
+
+impl Default for SyncSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            url: "http://127.0.0.1:8484".to_string(),
+            token: String::new(),
+            device: format!("{:x}", rand::random::<u64>()),
+            interval_minutes: 5,
+            last_sync: None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct SyncState {
+    files: HashMap<String, FileRecord>,
+}
+
+#[derive(Default)]
+pub struct SyncReport {
+    pub uploaded: usize,
+    pub downloaded: usize,
+    pub deleted: usize,
+    pub conflicts: Vec<String>,
+}
+
+/// Client side of the sync protocol served by the `marmol-sync-server` binary.
+#[derive(Default)]
+pub struct SyncClient {
+    vault: String,
+    pub settings: SyncSettings,
+    pub status: String,
+    pub conflicts: Vec<String>,
+    running: Option<Receiver<Result<SyncReport, String>>>,
+}
+
+impl SyncClient {
+    pub fn load(&mut self, vault: &str) {
+        if self.vault == vault {
+            return;
+        }
+        self.vault = vault.to_string();
+        self.settings = read_settings().remove(vault).unwrap_or_default();
+        self.status.clear();
+        self.conflicts.clear();
+    }
+
+    pub fn save_settings(&self) {
+        if self.vault.is_empty() {
+            return;
+        }
+        let mut all = read_settings();
+        all.insert(self.vault.clone(), self.settings.clone());
+        match serde_json::to_string_pretty(&all) {
+            Ok(data) => {
+                let _ = fs::create_dir_all(configuraciones::get_config_dir());
+                if let Err(e) = fs::write(settings_path(), data) {
+                    println!("Error guardando la configuración de sincronización: {}", e);
+                }
+            }
+            Err(e) => println!("Error serializando la configuración de sincronización: {}", e),
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running.is_some()
+    }
+
+    /// Polls a running sync and starts the next scheduled one. Returns true
+    /// when a sync finished, so the caller can reload what changed on disk.
+    pub fn tick(&mut self, vault: &str) -> bool {
+        self.load(vault);
+
+        if let Some(rx) = &self.running {
+            let Ok(result) = rx.try_recv() else {
+                return false;
+            };
+            self.running = None;
+            self.settings.last_sync = Some(Local::now().format(SYNC_FORMAT).to_string());
+            match result {
+                Ok(report) => {
+                    self.status = format!(
+                        "↑ {}  ↓ {}  ✕ {}  conflicts: {}",
+                        report.uploaded,
+                        report.downloaded,
+                        report.deleted,
+                        report.conflicts.len()
+                    );
+                    self.conflicts.extend(report.conflicts);
+                }
+                Err(e) => {
+                    println!("Error sincronizando: {}", e);
+                    self.status = format!("Sync failed: {}", e);
+                }
+            }
+            self.save_settings();
+            return true;
+        }
+
+        if !self.settings.enabled || self.settings.url.is_empty() {
+            return false;
+        }
+        let due = match self
+            .settings
+            .last_sync
+            .as_ref()
+            .and_then(|d| NaiveDateTime::parse_from_str(d, SYNC_FORMAT).ok())
+        {
+            Some(last) => {
+                Local::now().naive_local() - last
+                    >= chrono::Duration::minutes(self.settings.interval_minutes.max(1) as i64)
+            }
+            None => true,
+        };
+        if due {
+            self.start_sync();
+        }
+        false
+    }
+
+    pub fn start_sync(&mut self) {
+        if self.running.is_some() || self.vault.is_empty() || self.settings.url.is_empty() {
+            return;
+        }
+        let vault = self.vault.clone();
+        let settings = self.settings.clone();
+        let (tx, rx) = channel();
+        std::thread::spawn(move || {
+            let _ = tx.send(run_sync(&vault, &settings));
+        });
+        self.running = Some(rx);
+        self.status = "Syncing...".to_string();
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        let old_settings = self.settings.clone();
+
+        ui.heading("Sync");
+        ui.checkbox(&mut self.settings.enabled, "Sync automatically");
+        egui::Grid::new("sync_settings").num_columns(2).show(ui, |ui| {
+            ui.label("Server:");
+            ui.text_edit_singleline(&mut self.settings.url);
+            ui.end_row();
+            ui.label("Token:");
+            ui.add(egui::TextEdit::singleline(&mut self.settings.token).password(true));
+            ui.end_row();
+            ui.label("Device id:");
+            ui.text_edit_singleline(&mut self.settings.device)
+                .on_hover_text("Must be different on every device syncing this vault");
+            ui.end_row();
+        });
+        ui.add(
+            egui::Slider::new(&mut self.settings.interval_minutes, 1..=120)
+                .text("Minutes between syncs"),
+        );
+
+        if self.settings != old_settings {
+            self.save_settings();
+        }
+
+        ui.horizontal(|ui| {
+            match &self.settings.last_sync {
+                Some(d) => ui.label(format!("Last sync: {}", d)),
+                None => ui.label("Never synced"),
+            };
+            ui.add_enabled_ui(!self.is_running(), |ui| {
+                if ui.button("Sync now").clicked() {
+                    self.start_sync();
+                }
+            });
+            if self.is_running() {
+                ui.spinner();
+                ui.ctx().request_repaint();
+            }
+        });
+        if !self.status.is_empty() {
+            ui.label(egui::RichText::new(&self.status).small());
+        }
+
+        if !self.conflicts.is_empty() {
+            ui.add_space(5.0);
+            ui.label(
+                egui::RichText::new("Conflict copies (both versions were kept)")
+                    .color(ui.visuals().warn_fg_color),
+            );
+            for conflict in &self.conflicts {
+                ui.label(egui::RichText::new(conflict).small());
+            }
+            if ui.small_button("Clear").clicked() {
+                self.conflicts.clear();
+            }
+        }
+    }
+}
+
+fn settings_path() -> String {
+    format!("{}/sync.json", configuraciones::get_config_dir())
+}
+
+fn read_settings() -> HashMap<String, SyncSettings> {
+    fs::read_to_string(settings_path())
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+struct Remote {
+    client: reqwest::blocking::Client,
+    url: String,
+    token: String,
+}
+
+impl Remote {
+    fn request(&self, method: reqwest::Method, endpoint: &str) -> reqwest::blocking::RequestBuilder {
+        let req = self
+            .client
+            .request(method, format!("{}/{}", self.url.trim_end_matches('/'), endpoint));
+        if self.token.is_empty() {
+            req
+        } else {
+            req.bearer_auth(&self.token)
+        }
+    }
+
+    fn index(&self) -> Result<HashMap<String, FileRecord>, String> {
+        let resp = self
+            .request(reqwest::Method::GET, "index")
+            .send()
+            .map_err(|e| e.to_string())?;
+        if !resp.status().is_success() {
+            return Err(format!("server answered {}", resp.status()));
+        }
+        let text = resp.text().map_err(|e| e.to_string())?;
+        serde_json::from_str(&text).map_err(|e| e.to_string())
+    }
+
+    fn download(&self, rel: &str) -> Result<Vec<u8>, String> {
+        let resp = self
+            .request(reqwest::Method::GET, "file")
+            .query(&[("path", rel)])
+            .send()
+            .map_err(|e| e.to_string())?;
+        if !resp.status().is_success() {
+            return Err(format!("{}: server answered {}", rel, resp.status()));
+        }
+        Ok(resp.bytes().map_err(|e| e.to_string())?.to_vec())
+    }
+
+    /// Uploads (or deletes, for tombstones) a file. Returns false when the
+    /// server already has a newer or concurrent version.
+    fn push(&self, rel: &str, record: &FileRecord, body: Vec<u8>) -> Result<bool, String> {
+        let method = if record.deleted {
+            reqwest::Method::DELETE
+        } else {
+            reqwest::Method::PUT
+        };
+        let header = serde_json::to_string(record).map_err(|e| e.to_string())?;
+        let resp = self
+            .request(method, "file")
+            .query(&[("path", rel)])
+            .header("X-Marmol-Record", header)
+            .body(body)
+            .send()
+            .map_err(|e| e.to_string())?;
+        match resp.status().as_u16() {
+            200..=299 => Ok(true),
+            409 => Ok(false),
+            s => Err(format!("{}: server answered {}", rel, s)),
+        }
+    }
+}
+
+fn scan_local(vault: &str) -> HashMap<String, String> {
+    WalkDir::new(vault)
+        .into_iter()
+        .filter_entry(|e| {
+            e.depth() == 0 || !e.file_name().to_str().map(|n| n.starts_with('.')).unwrap_or(false)
+        })
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .filter_map(|e| {
+            let rel = e.path().strip_prefix(vault).ok()?.to_str()?.replace('\\', "/");
+            let bytes = fs::read(e.path()).ok()?;
+            Some((rel, content_hash(&bytes)))
+        })
+        .collect()
+}
+
+fn bump(version: &mut VersionVector, device: &str) {
+    *version.entry(device.to_string()).or_insert(0) += 1;
+}
+
+fn conflict_name(rel: &str, device: &str) -> String {
+    let path = Path::new(rel);
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or(rel);
+    let suffix = format!(
+        "{} (conflict {} {})",
+        stem,
+        device,
+        Local::now().format("%Y-%m-%d %H%M")
+    );
+    let name = match path.extension().and_then(|e| e.to_str()) {
+        Some(ext) => format!("{}.{}", suffix, ext),
+        None => suffix,
+    };
+    match path.parent().and_then(|p| p.to_str()).filter(|p| !p.is_empty()) {
+        Some(parent) => format!("{}/{}", parent, name),
+        None => name,
+    }
+}
+
+fn run_sync(vault: &str, settings: &SyncSettings) -> Result<SyncReport, String> {
+    let remote = Remote {
+        client: reqwest::blocking::Client::new(),
+        url: settings.url.clone(),
+        token: settings.token.clone(),
+    };
+    let device = settings.device.as_str();
+    let vault_path = Path::new(vault);
+    let state_path = vault_path.join(STATE_FILE);
+    let mut state: SyncState = fs::read_to_string(&state_path)
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default();
+    let mut report = SyncReport::default();
+
+    // 1. Cambios locales desde la última sincronización: se incrementa el contador de este dispositivo
+    let local = scan_local(vault);
+    for (rel, hash) in &local {
+        let unchanged = state
+            .files
+            .get(rel)
+            .map(|r| !r.deleted && &r.hash == hash)
+            .unwrap_or(false);
+        if !unchanged {
+            let mut version = state.files.get(rel).map(|r| r.version.clone()).unwrap_or_default();
+            bump(&mut version, device);
+            state.files.insert(
+                rel.clone(),
+                FileRecord {
+                    hash: hash.clone(),
+                    version,
+                    deleted: false,
+                },
+            );
+        }
+    }
+    for (rel, record) in state.files.iter_mut() {
+        if !record.deleted && !local.contains_key(rel) {
+            bump(&mut record.version, device);
+            record.deleted = true;
+            record.hash.clear();
+        }
+    }
+
+    // 2. Comparar con el servidor
+    let remote_index = remote.index()?;
+    let mut paths: Vec<String> = state
+        .files
+        .keys()
+        .chain(remote_index.keys())
+        .cloned()
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+    paths.sort();
+
+    let read_local = |rel: &str| fs::read(vault_path.join(rel)).unwrap_or_default();
+    let apply_remote = |rel: &str, record: &FileRecord, report: &mut SyncReport| -> Result<(), String> {
+        let path = vault_path.join(rel);
+        if record.deleted {
+            if path.exists() {
+                fs::remove_file(&path).map_err(|e| e.to_string())?;
+                report.deleted += 1;
+            }
+            return Ok(());
+        }
+        let bytes = remote.download(rel)?;
+        if content_hash(&bytes) != record.hash {
+            return Err(format!("{}: downloaded content does not match its hash", rel));
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        fs::write(&path, bytes).map_err(|e| e.to_string())?;
+        report.downloaded += 1;
+        Ok(())
+    };
+
+    // El estado se guarda también si algo falla a mitad, para no perder lo ya descargado
+    let sync_paths = || -> Result<(), String> {
+        for rel in paths {
+            if !is_safe_path(&rel) {
+                println!("Ruta insegura ignorada: {}", rel);
+                continue;
+            }
+            let local_record = state.files.get(&rel).cloned();
+            let remote_record = remote_index.get(&rel).cloned();
+
+            match (local_record, remote_record) {
+                (Some(l), None) => {
+                    // El servidor no conoce el archivo: se sube aunque no haya cambiado desde la última vez
+                    if !l.deleted {
+                        if remote.push(&rel, &l, read_local(&rel))? {
+                            report.uploaded += 1;
+                        } else {
+                            report.conflicts.push(format!("{} (changed on the server, sync again)", rel));
+                        }
+                    }
+                }
+                (None, Some(r)) => {
+                    apply_remote(&rel, &r, &mut report)?;
+                    state.files.insert(rel, r);
+                }
+                (Some(l), Some(r)) => match resolve(&l, &r) {
+                    Resolution::Nothing => {}
+                    Resolution::Download => {
+                        apply_remote(&rel, &r, &mut report)?;
+                        state.files.insert(rel, r);
+                    }
+                    Resolution::Upload => {
+                        if remote.push(&rel, &l, read_local(&rel))? {
+                            report.uploaded += 1;
+                        } else {
+                            report.conflicts.push(format!("{} (changed on the server, sync again)", rel));
+                        }
+                    }
+                    Resolution::MergeVersions => {
+                        let record = FileRecord {
+                            version: merge(&l.version, &r.version),
+                            ..l
+                        };
+                        if remote.push(&rel, &record, read_local(&rel))? {
+                            state.files.insert(rel, record);
+                        } else {
+                            report.conflicts.push(format!("{} (changed on the server, sync again)", rel));
+                        }
+                    }
+                    Resolution::Restore => {
+                        let mut version = merge(&l.version, &r.version);
+                        bump(&mut version, device);
+                        let record = FileRecord { version, ..l };
+                        if remote.push(&rel, &record, read_local(&rel))? {
+                            report.uploaded += 1;
+                            state.files.insert(rel, record);
+                        } else {
+                            report.conflicts.push(format!("{} (changed on the server, sync again)", rel));
+                        }
+                    }
+                    Resolution::ConflictCopy => {
+                        let copy = conflict_name(&rel, device);
+                        let bytes = read_local(&rel);
+                        fs::write(vault_path.join(&copy), &bytes).map_err(|e| e.to_string())?;
+                        apply_remote(&rel, &r, &mut report)?;
+                        state.files.insert(rel, r);
+
+                        let mut version = VersionVector::new();
+                        bump(&mut version, device);
+                        let record = FileRecord {
+                            hash: content_hash(&bytes),
+                            version,
+                            deleted: false,
+                        };
+                        if remote.push(&copy, &record, bytes)? {
+                            report.uploaded += 1;
+                        }
+                        state.files.insert(copy.clone(), record);
+                        report.conflicts.push(copy);
+                    }
+                },
+                (None, None) => {}
+            }
+        }
+        Ok(())
+    };
+    let result = sync_paths();
+
+    if let Some(parent) = state_path.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+    let data = serde_json::to_string(&state).map_err(|e| e.to_string())?;
+    fs::write(&state_path, data).map_err(|e| e.to_string())?;
+    result.map(|_| report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn version(counters: &[(&str, u64)]) -> VersionVector {
+        counters.iter().map(|(d, n)| (d.to_string(), *n)).collect()
+    }
+
+    fn record(hash: &str, counters: &[(&str, u64)], deleted: bool) -> FileRecord {
+        FileRecord {
+            hash: hash.to_string(),
+            version: version(counters),
+            deleted,
+        }
+    }
+
+    #[test]
+    fn compare_version_vectors() {
+        let a = version(&[("laptop", 1)]);
+        let b = version(&[("laptop", 2)]);
+        let c = version(&[("laptop", 1), ("phone", 1)]);
+        assert_eq!(compare(&a, &a), Causality::Equal);
+        assert_eq!(compare(&a, &b), Causality::Before);
+        assert_eq!(compare(&b, &a), Causality::After);
+        assert_eq!(compare(&b, &c), Causality::Concurrent);
+        // Un contador que falta vale 0
+        assert_eq!(compare(&VersionVector::new(), &a), Causality::Before);
+        assert_eq!(compare(&version(&[("phone", 0)]), &VersionVector::new()), Causality::Equal);
+    }
+
+    #[test]
+    fn merge_takes_the_highest_counters() {
+        let merged = merge(&version(&[("laptop", 3), ("phone", 1)]), &version(&[("phone", 2), ("tablet", 1)]));
+        assert_eq!(merged, version(&[("laptop", 3), ("phone", 2), ("tablet", 1)]));
+    }
+
+    #[test]
+    fn resolve_ordered_versions() {
+        let old = record("a", &[("laptop", 1)], false);
+        let new = record("b", &[("laptop", 2)], false);
+        assert_eq!(resolve(&old, &old), Resolution::Nothing);
+        assert_eq!(resolve(&old, &new), Resolution::Download);
+        assert_eq!(resolve(&new, &old), Resolution::Upload);
+    }
+
+    #[test]
+    fn resolve_concurrent_versions() {
+        let laptop = record("a", &[("laptop", 2), ("phone", 1)], false);
+        let phone = record("b", &[("laptop", 1), ("phone", 2)], false);
+        let phone_same = record("a", &[("laptop", 1), ("phone", 2)], false);
+        let phone_deleted = record("", &[("laptop", 1), ("phone", 2)], true);
+        let laptop_deleted = record("", &[("laptop", 2), ("phone", 1)], true);
+        assert_eq!(resolve(&laptop, &phone), Resolution::ConflictCopy);
+        assert_eq!(resolve(&laptop, &phone_same), Resolution::MergeVersions);
+        assert_eq!(resolve(&laptop, &phone_deleted), Resolution::Restore);
+        assert_eq!(resolve(&laptop_deleted, &phone), Resolution::Download);
+        assert_eq!(resolve(&laptop_deleted, &phone_deleted), Resolution::MergeVersions);
+    }
+
+    #[test]
+    fn conflict_copies_keep_folder_and_extension() {
+        let name = conflict_name("notes/idea.md", "phone");
+        assert!(name.starts_with("notes/idea (conflict phone "), "{}", name);
+        assert!(name.ends_with(").md"), "{}", name);
+        assert!(conflict_name("README", "laptop").starts_with("README (conflict laptop "));
+    }
+
+    #[test]
+    fn unsafe_paths_are_rejected() {
+        assert!(is_safe_path("notes/idea.md"));
+        assert!(!is_safe_path("../outside.md"));
+        assert!(!is_safe_path("/etc/passwd"));
+        assert!(!is_safe_path("notes/../../x"));
+        assert!(!is_safe_path(""));
+    }
+}
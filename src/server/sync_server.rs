@@ -0,0 +1,255 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use super::sync::{compare, content_hash, is_safe_path, Causality, FileRecord};
+
+// Límite para el cuerpo de una petición
+const MAX_BODY: usize = 256 * 1024 * 1024;
+
+struct ServerState {
+    root: PathBuf,
+    token: Option<String>,
+    index: Mutex<HashMap<String, FileRecord>>,
+}
+
+impl ServerState {
+    fn index_path(&self) -> PathBuf {
+        self.root.join("index.json")
+    }
+
+    fn file_path(&self, rel: &str) -> PathBuf {
+        self.root.join("files").join(rel)
+    }
+
+    fn save_index(&self, index: &HashMap<String, FileRecord>) -> io::Result<()> {
+        let data = serde_json::to_string(index)?;
+        let tmp = self.root.join("index.json.tmp");
+        fs::write(&tmp, data)?;
+        fs::rename(tmp, self.index_path())
+    }
+}
+
+struct Request {
+    method: String,
+    path: String,
+    query: HashMap<String, String>,
+    headers: HashMap<String, String>,
+    body: Vec<u8>,
+}
+
+/// Whether every address `addr` resolves to is a loopback one.
+pub fn is_loopback(addr: &str) -> bool {
+    addr.to_socket_addrs()
+        .map(|mut addrs| addrs.all(|a| a.ip().is_loopback()))
+        .unwrap_or(false)
+}
+
+/// Serves the sync protocol used by `sync::SyncClient`:
+///
+/// * `GET /index` returns the `FileRecord` of every file as JSON.
+/// * `GET /file?path=` returns the contents of a file.
+/// * `PUT /file?path=` stores the body; `DELETE /file?path=` leaves a tombstone.
+///   Both need the new record in the `X-Marmol-Record` header and answer
+///   `409 Conflict` (with the current record) unless its version vector
+///   descends from the stored one.
+pub fn run(root: &str, addr: &str, token: Option<String>) -> io::Result<()> {
+    // Sin token solo se atiende a este equipo
+    let token = token.filter(|t| !t.is_empty());
+    if token.is_none() && !is_loopback(addr) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} is reachable from other machines: a --token is required", addr),
+        ));
+    }
+    let root = PathBuf::from(root);
+    fs::create_dir_all(root.join("files"))?;
+    let index = fs::read_to_string(root.join("index.json"))
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default();
+    let state = Arc::new(ServerState {
+        root,
+        token,
+        index: Mutex::new(index),
+    });
+
+    let listener = TcpListener::bind(addr)?;
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                let state = Arc::clone(&state);
+                std::thread::spawn(move || {
+                    if let Err(e) = handle(stream, &state) {
+                        println!("Error en la conexión: {}", e);
+                    }
+                });
+            }
+            Err(e) => println!("Error aceptando la conexión: {}", e),
+        }
+    }
+    Ok(())
+}
+
+fn handle(mut stream: TcpStream, state: &ServerState) -> io::Result<()> {
+    let request = match read_request(&mut stream)? {
+        Some(r) => r,
+        None => return respond(&mut stream, 400, "text/plain", b"bad request"),
+    };
+
+    if let Some(token) = &state.token {
+        let expected = format!("Bearer {}", token);
+        if request.headers.get("authorization") != Some(&expected) {
+            return respond(&mut stream, 401, "text/plain", b"unauthorized");
+        }
+    }
+
+    match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/index") => {
+            let index = state.index.lock().unwrap();
+            let body = serde_json::to_vec(&*index)?;
+            respond(&mut stream, 200, "application/json", &body)
+        }
+        ("GET", "/file") => {
+            let Some(rel) = request.query.get("path").filter(|p| is_safe_path(p)) else {
+                return respond(&mut stream, 400, "text/plain", b"invalid path");
+            };
+            match fs::read(state.file_path(rel)) {
+                Ok(bytes) => respond(&mut stream, 200, "application/octet-stream", &bytes),
+                Err(_) => respond(&mut stream, 404, "text/plain", b"not found"),
+            }
+        }
+        ("PUT", "/file") | ("DELETE", "/file") => store(&mut stream, state, &request),
+        _ => respond(&mut stream, 404, "text/plain", b"not found"),
+    }
+}
+
+fn store(stream: &mut TcpStream, state: &ServerState, request: &Request) -> io::Result<()> {
+    let Some(rel) = request.query.get("path").filter(|p| is_safe_path(p)) else {
+        return respond(stream, 400, "text/plain", b"invalid path");
+    };
+    let Some(mut record) = request
+        .headers
+        .get("x-marmol-record")
+        .and_then(|h| serde_json::from_str::<FileRecord>(h).ok())
+    else {
+        return respond(stream, 400, "text/plain", b"missing X-Marmol-Record");
+    };
+    record.deleted = request.method == "DELETE";
+    if !record.deleted && content_hash(&request.body) != record.hash {
+        return respond(stream, 400, "text/plain", b"hash mismatch");
+    }
+
+    // El índice queda bloqueado mientras se escribe, así las subidas del mismo archivo no se pisan
+    let mut index = state.index.lock().unwrap();
+    if let Some(current) = index.get(rel) {
+        if !matches!(compare(&record.version, &current.version), Causality::After | Causality::Equal) {
+            let body = serde_json::to_vec(current)?;
+            return respond(stream, 409, "application/json", &body);
+        }
+    }
+
+    let path = state.file_path(rel);
+    if record.deleted {
+        record.hash.clear();
+        let _ = fs::remove_file(&path);
+    } else {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let tmp = path.with_extension("marmol-upload");
+        fs::write(&tmp, &request.body)?;
+        fs::rename(&tmp, &path)?;
+    }
+    index.insert(rel.clone(), record.clone());
+    state.save_index(&index)?;
+    let body = serde_json::to_vec(&record)?;
+    respond(stream, 200, "application/json", &body)
+}
+
+fn read_request(stream: &mut TcpStream) -> io::Result<Option<Request>> {
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        return Ok(None);
+    };
+    let method = method.to_string();
+    let (path, query) = match target.split_once('?') {
+        Some((p, q)) => (p.to_string(), parse_query(q)),
+        None => (target.to_string(), HashMap::new()),
+    };
+
+    let mut headers = HashMap::new();
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 {
+            break;
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((key, value)) = header.split_once(':') {
+            headers.insert(key.trim().to_lowercase(), value.trim().to_string());
+        }
+    }
+
+    let length: usize = headers
+        .get("content-length")
+        .and_then(|l| l.parse().ok())
+        .unwrap_or(0);
+    if length > MAX_BODY {
+        return Ok(None);
+    }
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+
+    Ok(Some(Request {
+        method,
+        path,
+        query,
+        headers,
+        body,
+    }))
+}
+
+fn parse_query(query: &str) -> HashMap<String, String> {
+    query
+        .split('&')
+        .filter_map(|pair| {
+            let (key, value) = pair.split_once('=')?;
+            let decode = |s: &str| {
+                percent_encoding::percent_decode_str(&s.replace('+', " "))
+                    .decode_utf8_lossy()
+                    .to_string()
+            };
+            Some((decode(key), decode(value)))
+        })
+        .collect()
+}
+
+fn respond(stream: &mut TcpStream, status: u16, content_type: &str, body: &[u8]) -> io::Result<()> {
+    let reason = match status {
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        404 => "Not Found",
+        409 => "Conflict",
+        _ => "Error",
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status,
+        reason,
+        content_type,
+        body.len()
+    )?;
+    stream.write_all(body)?;
+    stream.flush()
+}
@@ -37,28 +37,22 @@ impl QuickSwitcher {
         self.initialized = true;
     }
 
+    /// Rescans the vault if the switcher is showing results.
+    pub fn refresh(&mut self, vault_path: &str) {
+        if self.is_open {
+            self.scan_dir(vault_path);
+            self.update_filter();
+        }
+    }
+
     pub fn close(&mut self) {
         self.is_open = false;
     }
 
     fn scan_dir(&mut self, vault_path: &str) {
-        use walkdir::WalkDir;
-        self.all_files = WalkDir::new(vault_path)
-            .into_iter()
-            .filter_map(|e| e.ok())
-            .filter(|e| {
-                let path = e.path();
-                if path.is_dir() {
-                    return false;
-                }
-                // Skip .obsidian and .git folders
-                !path.components().any(|c| {
-                    let s = c.as_os_str().to_str().unwrap_or("");
-                    s == ".obsidian" || s == ".git"
-                })
-            })
-            .filter_map(|e| e.path().to_str().map(|s| s.to_string()))
-            .collect();
+        self.all_files = crate::vault_index::with_index(vault_path, |index| {
+            index.files().map(|f| f.path.clone()).collect()
+        });
     }
 
     fn update_filter(&mut self) {
@@ -1,5 +1,10 @@
+use crate::anchors;
+use crate::calendar;
+use crate::vault_index;
+use crate::vault_tasks;
 use crate::excalidraw;
 use crate::canvas;
+use crate::database;
 use crate::easy_mark;
 use crate::files;
 use crate::iconize::{IconManager, IconSource};
@@ -7,7 +12,7 @@ use crate::income;
 use crate::kanban;
 
 use crate::main_area::content_enum::Content;
-use crate::main_area::metadata_renderer::create_metadata;
+use crate::main_area::metadata_renderer::properties_editor;
 use crate::tasks;
 use egui::Image;
 use egui::{Frame, Sense, Ui, WidgetText};
@@ -15,8 +20,11 @@ use crate::egui_commonmark::*;
 use crate::egui_dock::{DockArea, DockState, NodeIndex, Style, SurfaceIndex, TabViewer, Split, Node};
 use egui_extras::{Size, StripBuilder};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::io::Write;
 use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+use std::time::SystemTime;
 
 #[derive(Serialize, Deserialize)]
 pub enum TabContent {
@@ -52,6 +60,19 @@ pub enum TabContent {
         #[serde(skip, default)]
         gui: canvas::CanvasGui,
     },
+    Calendar {
+        #[serde(skip, default)]
+        gui: calendar::CalendarGui,
+    },
+    VaultTasks {
+        #[serde(skip, default)]
+        gui: vault_tasks::VaultTasksGui,
+    },
+    Database {
+        path: String,
+        #[serde(skip, default)]
+        gui: database::DatabaseGui,
+    },
     Markdown {
         #[serde(skip, default)]
         editor: easy_mark::EasyMarkEditor,
@@ -117,6 +138,20 @@ impl Clone for TabContent {
                     gui,
                 }
             }
+            TabContent::Calendar { .. } => TabContent::Calendar {
+                gui: calendar::CalendarGui::default(),
+            },
+            TabContent::VaultTasks { .. } => TabContent::VaultTasks {
+                gui: vault_tasks::VaultTasksGui::default(),
+            },
+            TabContent::Database { path, .. } => {
+                let mut gui = database::DatabaseGui::default();
+                gui.set_path(path);
+                TabContent::Database {
+                    path: path.clone(),
+                    gui,
+                }
+            }
             TabContent::Markdown { scroll_offset, .. } => TabContent::Markdown {
                 editor: easy_mark::EasyMarkEditor::default(),
                 cache: CommonMarkCache::default(),
@@ -186,6 +221,13 @@ impl Tabe {
                 path: path.clone(),
                 gui,
             }
+        } else if path.ends_with(".table") {
+            let mut gui = database::DatabaseGui::default();
+            gui.set_path(&path);
+            TabContent::Database {
+                path: path.clone(),
+                gui,
+            }
         } else if path.ends_with(".graph") {
             let mut gui = tasks::TasksGui::default();
             gui.set_path(&path);
@@ -241,6 +283,40 @@ impl Tabe {
             rename_buffer: String::new(),
         }
     }
+
+    pub fn new_calendar(n: usize) -> Self {
+        Self {
+            id: n,
+            ctype: Content::View,
+            title: "Calendar".to_string(),
+            path: String::new(),
+            content: TabContent::Calendar {
+                gui: calendar::CalendarGui::default(),
+            },
+            history: vec![String::new()],
+            history_index: 0,
+            is_renaming: false,
+            just_started_renaming: false,
+            rename_buffer: String::new(),
+        }
+    }
+
+    pub fn new_tasks(n: usize) -> Self {
+        Self {
+            id: n,
+            ctype: Content::View,
+            title: "Tasks".to_string(),
+            path: String::new(),
+            content: TabContent::VaultTasks {
+                gui: vault_tasks::VaultTasksGui::default(),
+            },
+            history: vec![String::new()],
+            history_index: 0,
+            is_renaming: false,
+            just_started_renaming: false,
+            rename_buffer: String::new(),
+        }
+    }
 }
 
 struct MTabViewer<'a> {
@@ -306,7 +382,7 @@ impl TabViewer for MTabViewer<'_> {
 
     fn scroll_bars(&self, tab: &Self::Tab) -> [bool; 2] {
         match tab.content {
-            TabContent::Excalidraw { .. } | TabContent::Canvas { .. } | TabContent::Graph { .. } => [false, false],
+            TabContent::Excalidraw { .. } | TabContent::Canvas { .. } | TabContent::Graph { .. } | TabContent::Database { .. } => [false, false],
             _ => [true, true],
         }
     }
@@ -346,6 +422,7 @@ impl TabViewer for MTabViewer<'_> {
                             let new_rel = new_rel.strip_prefix('/').unwrap_or(new_rel);
 
                             self.icon_manager.rename_icon(self.vault, old_rel, new_rel);
+                            crate::links::notify_renamed(ui.ctx(), &tab.path, &new_path_str);
 
                             tab.path = new_path_str.clone();
                             tab.title = tab.rename_buffer.clone();
@@ -392,6 +469,22 @@ impl TabViewer for MTabViewer<'_> {
                         };
                     }
                 });
+            } else if matches!(tab.content, TabContent::Markdown { .. })
+                && matches!(tab.ctype, Content::View | Content::Edit | Content::Split)
+            {
+                // Selector de modo: lectura, edición o ambos lado a lado
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    for (ctype, label) in [(Content::Split, "◫ Split"), (Content::Edit, "✏ Edit"), (Content::View, "📖 Read")] {
+                        if ui.selectable_label(tab.ctype == ctype, label).clicked() && tab.ctype != ctype {
+                            if let TabContent::Markdown { editor, .. } = &mut tab.content {
+                                if tab.ctype == Content::View {
+                                    editor.code = files::read_file(&tab.path);
+                                }
+                            }
+                            tab.ctype = ctype;
+                        }
+                    }
+                });
             }
 
             // Menú de opciones (Tres puntos)
@@ -482,8 +575,10 @@ impl TabViewer for MTabViewer<'_> {
                             todo!();
                         }
 
-                        if ui.button("Bookmark").clicked() {
-                            todo!();
+                        let bookmarked = crate::bookmarks::is_bookmarked(self.vault, &tab.path);
+                        if ui.button(if bookmarked { "Remove bookmark" } else { "Bookmark" }).clicked() {
+                            crate::bookmarks::toggle(self.vault, crate::bookmarks::Bookmark::file(self.vault, &tab.path, None));
+                            ui.close();
                         }
 
                         if ui.button("Rename").clicked() {
@@ -534,8 +629,17 @@ impl TabViewer for MTabViewer<'_> {
                             ui.close();
                         }
 
+                        if ui.button("Add property").clicked() {
+                            ui.ctx().data_mut(|d| d.insert_temp(egui::Id::new("property_adding").with(&tab.path), true));
+                            if tab.ctype == Content::Edit {
+                                tab.ctype = Content::View;
+                            }
+                            ui.close();
+                        }
+
                         if ui.button("Mostrar backlinks").clicked() {
-                            todo!();
+                            ui.ctx().data_mut(|d| d.insert_temp(egui::Id::new("show_backlinks_signal"), Some(tab.path.clone())));
+                            ui.close();
                         }
                     });
                 });
@@ -569,6 +673,16 @@ impl TabViewer for MTabViewer<'_> {
                     gui.set_path(path);
                     gui.show(ui, self.vault, seed_id);
                 }
+                TabContent::Calendar { gui } => {
+                    if let Some(path) = gui.show(ui, self.vault) {
+                        *self.current_file = path;
+                    }
+                }
+                TabContent::VaultTasks { gui } => {
+                    if let Some(path) = gui.show(ui, self.vault, seed_id) {
+                        *self.current_file = path;
+                    }
+                }
                 TabContent::Image(image_path) => {
                     egui::ScrollArea::vertical()
                         .id_salt(seed_id.with("img_scroll"))
@@ -589,6 +703,10 @@ impl TabViewer for MTabViewer<'_> {
                     gui.set_path(path);
                     gui.show(ui, self.vault);
                 }
+                TabContent::Database { path, gui } => {
+                    gui.set_path(path);
+                    gui.show(ui, self.vault, seed_id);
+                }
                 TabContent::Kanban { path, gui } => {
                     gui.set_path(path);
                     if let Some(new_path) = gui.show(ui, self.vault, seed_id) {
@@ -601,12 +719,60 @@ impl TabViewer for MTabViewer<'_> {
                     if editor.code.is_empty() && !tab.path.is_empty() {
                         editor.code = files::read_file(&tab.path);
                     }
+
+                    // Plantillas: insertar en el cursor o colocar el cursor de una nota recién creada
+                    let insert_req: Option<(String, crate::templates::Rendered)> =
+                        ui.ctx().data(|d| d.get_temp(egui::Id::new("insert_template_signal")).flatten());
+                    if let Some((path, rendered)) = insert_req.filter(|(p, _)| *p == tab.path) {
+                        if tab.ctype != Content::Edit && tab.ctype != Content::Split {
+                            editor.code = files::read_file(&path);
+                            tab.ctype = Content::Edit;
+                        }
+                        editor.pending_insert = Some(rendered);
+                        ui.ctx().data_mut(|d| d.insert_temp(egui::Id::new("insert_template_signal"), None::<(String, crate::templates::Rendered)>));
+                    }
+                    let cursor_req: Option<(String, usize)> =
+                        ui.ctx().data(|d| d.get_temp(egui::Id::new("template_cursor_signal")).flatten());
+                    if let Some((_, cursor)) = cursor_req.filter(|(p, _)| *p == tab.path) {
+                        editor.code = files::read_file(&tab.path);
+                        editor.pending_cursor = Some(cursor);
+                        if tab.ctype != Content::Split {
+                            tab.ctype = Content::Edit;
+                        }
+                        ui.ctx().data_mut(|d| d.insert_temp(egui::Id::new("template_cursor_signal"), None::<(String, usize)>));
+                    }
+
                     let width = ui.available_width();
                     let height = ui.available_height();
                     let margin_ratio = (0.15 * (width / 1500.0).min(height / 1000.0)).clamp(0.005, 0.15);
                     let content_ratio = 1.0 - 2.0 * margin_ratio;
                     let tab_id = tab.id;
 
+                    // Encabezado o bloque al que apuntaba el enlace abierto
+                    let anchor_id = egui::Id::new("link_anchor").with(tab_id);
+                    let now = ui.input(|i| i.time);
+                    let link_anchor: Option<LinkAnchor> = ui
+                        .ctx()
+                        .data(|d| d.get_temp::<Option<LinkAnchor>>(anchor_id).flatten())
+                        .filter(|a| a.path == tab.path && now - a.since < ANCHOR_HIGHLIGHT_SECS);
+                    let anchor = link_anchor.as_ref().map(|a| (a.anchor.clone(), !a.scrolled));
+                    if let Some((target, true)) = &anchor {
+                        if tab.ctype != Content::View {
+                            editor.pending_selection = anchors::find_char_range(&editor.code, target);
+                        }
+                    }
+                    ui.ctx().data_mut(|d| {
+                        d.insert_temp(anchor_id, link_anchor.map(|a| LinkAnchor { scrolled: true, ..a }))
+                    });
+                    if anchor.is_some() {
+                        ui.ctx().request_repaint_after(std::time::Duration::from_millis(200));
+                    }
+
+                    let save_error: Option<String> = ui.ctx().data(|d| d.get_temp(save_error_id(&tab.path)).flatten());
+                    if let Some(error) = save_error {
+                        ui.colored_label(ui.visuals().error_fg_color, format!("⚠ Couldn't save the note: {}", error));
+                    }
+
                     if tab.ctype == Content::View {
                         let cont = StripBuilder::new(ui)
                             .size(Size::relative(margin_ratio))
@@ -628,7 +794,7 @@ impl TabViewer for MTabViewer<'_> {
                                     let frame =
                                         Frame::NONE.inner_margin(egui::Margin::symmetric(30, 10));
                                     let inner_response = frame.show(ui, |ui| {
-                                        let (markdown_content, metadata) = files::contents(&editor.code);
+                                        let markdown_content = vault_index::split_frontmatter(&editor.code).1.to_string();
                                         
                                         if self.icon_manager.settings.icon_in_title_enabled {
                                             let relative_path = if tab.path.starts_with(self.vault) {
@@ -660,56 +826,46 @@ impl TabViewer for MTabViewer<'_> {
                                             ui.heading(&tab.title);
                                         }
 
-                                        if !metadata.is_empty() {
-                                            create_metadata(metadata, ui);
+                                        let periodic = crate::periodic::settings(ui.ctx(), self.vault);
+                                        if let Some((period, date)) = periodic.note_date(self.vault, &tab.path) {
+                                            ui.horizontal(|ui| {
+                                                let (prev, next) = crate::periodic::adjacent_notes(ui.ctx(), self.vault, &tab.path, period, date);
+                                                let mut target = None;
+                                                if ui.add_enabled(prev.is_some(), egui::Button::new("◀").small()).on_hover_text("Previous note").clicked() {
+                                                    target = prev;
+                                                }
+                                                if ui.small_button("Today").clicked() {
+                                                    target = crate::periodic::open_today(ui.ctx(), self.vault, period);
+                                                }
+                                                if ui.add_enabled(next.is_some(), egui::Button::new("▶").small()).on_hover_text("Next note").clicked() {
+                                                    target = next;
+                                                }
+                                                ui.weak(format!("{} note · {}", period.name(), date.format("%A %d %B %Y")));
+                                                if let Some(path) = target {
+                                                    ui.ctx().data_mut(|d| d.insert_temp(egui::Id::new("global_nav_request"), Some(path)));
+                                                }
+                                            });
+                                        }
+
+                                        if let Some(updated) = properties_editor(ui, self.vault, &tab.path, &editor.code) {
+                                            save_editor(ui.ctx(), &tab.path, &updated);
                                         }
                                         
-                                        // Store context for the 'static closure
-                                        let ctx = ui.ctx().clone();
-                                        ctx.data_mut(|d| {
-                                            d.insert_temp(egui::Id::new("nav_vault"), self.vault.to_string());
-                                            d.insert_temp(egui::Id::new("nav_current_path"), tab.path.clone());
+                                        let highlight = anchor.as_ref().and_then(|(target, scroll)| {
+                                            anchors::find_range(&markdown_content, target).map(|r| (r, *scroll))
                                         });
-
-                                        CommonMarkViewer::new()
-                                            .process_link(Some(&|ui, url, layout| {
-                                                let response = ui.link(layout);
-                                                if response.clicked() {
-                                                    let ctx = ui.ctx();
-                                                    let vault: String = ctx.data(|d| d.get_temp(egui::Id::new("nav_vault")).unwrap_or_default());
-                                                    let current_path: String = ctx.data(|d| d.get_temp(egui::Id::new("nav_current_path")).unwrap_or_default());
-                                                    
-                                                    let decoded_url = percent_encoding::percent_decode_str(url).decode_utf8_lossy().to_string();
-                                                    let clean_url = decoded_url.split('|').next().unwrap_or(&decoded_url).trim();
-                                                    
-                                                    let resolved = crate::files::resolve_path(&vault, &current_path, clean_url);
-
-                                                    if let Some(path) = resolved {
-                                                        ctx.data_mut(|d| d.insert_temp(egui::Id::new("global_nav_request"), Some(path)));
-                                                    }
-                                                }
-                                                true 
-                                            }))
-                                            .render_excalidraw_fn(Some(&|ui, filename, size| {
-                                                let ctx = ui.ctx();
-                                                let vault: String = ctx.data(|d| d.get_temp(egui::Id::new("nav_vault")).unwrap_or_default());
-                                                let current_path: String = ctx.data(|d| d.get_temp(egui::Id::new("nav_current_path")).unwrap_or_default());
-                                                
-                                                if let Some(path) = crate::files::resolve_path(&vault, &current_path, filename) {
-                                                    let mut gui = crate::excalidraw::ExcalidrawGui::default();
-                                                    gui.set_path(&path);
-                                                    gui.render_static(ui, size);
-                                                } else {
-                                                    ui.colored_label(egui::Color32::RED, format!("Could not find drawing: {}", filename));
-                                                }
-                                            }))
-                                            .show(ui, cache, &markdown_content);
+                                        let action = markdown_preview(ui, cache, self.vault, &tab.path, &markdown_content, highlight);
+                                        if let Some(updated) = preview_action(ui.ctx(), self.vault, &tab.path, &editor.code, &markdown_content, action) {
+                                            save_editor(ui.ctx(), &tab.path, &updated);
+                                        }
                                         
                                         ui.allocate_space(ui.available_size());
                                     });
 
                                     if ui.input(|i| i.pointer.button_double_clicked(egui::PointerButton::Primary)) {
-                                        if ui.rect_contains_pointer(inner_response.response.rect) {
+                                        // Con un campo de propiedades enfocado el doble clic es para el campo
+                                        let editing_field = ui.memory(|m| m.focused().is_some());
+                                        if ui.rect_contains_pointer(inner_response.response.rect) && !editing_field {
                                             tab.ctype = Content::Edit;
                                         }
                                     }
@@ -738,18 +894,16 @@ impl TabViewer for MTabViewer<'_> {
                                 let output = area.show(ui, |ui| {
                                     let frame =
                                         Frame::NONE.inner_margin(egui::Margin::symmetric(30, 10));
+                                    if editor.vault != self.vault || editor.path != tab.path {
+                                        editor.vault = self.vault.to_string();
+                                        editor.path = tab.path.clone();
+                                    }
                                     let response = frame.show(ui, |ui| {
                                         editor.ui(ui)
                                     }).inner;
 
                                     if response.changed() {
-                                        let mut f = std::fs::OpenOptions::new()
-                                            .write(true)
-                                            .truncate(true)
-                                            .open(&tab.path)
-                                            .unwrap();
-                                        f.write_all(editor.code.as_bytes()).unwrap();
-                                        f.flush().unwrap();
+                                        save_editor(ui.ctx(), &tab.path, &editor.code);
                                     }
                                     if ui.input(|i| i.key_pressed(egui::Key::Escape)) {
                                         tab.ctype = Content::View;
@@ -760,6 +914,86 @@ impl TabViewer for MTabViewer<'_> {
                                 *scroll_offset = output.state.offset.y;
                             });
                         });
+                    } else if tab.ctype == Content::Split {
+                        if editor.vault != self.vault || editor.path != tab.path {
+                            editor.vault = self.vault.to_string();
+                            editor.path = tab.path.clone();
+                        }
+                        let sync_id = egui::Id::new("split_scroll").with(tab_id);
+                        let mut sync: SplitScroll = ui.ctx().data(|d| d.get_temp(sync_id)).unwrap_or_default();
+                        if !sync.restored {
+                            sync.restored = true;
+                            sync.editor_target = Some(*scroll_offset).filter(|o| *o > 0.0);
+                        }
+
+                        ui.columns(2, |columns| {
+                            let mut area = egui::ScrollArea::vertical().id_salt("split_editor");
+                            if let Some(offset) = sync.editor_target.take() {
+                                area = area.vertical_scroll_offset(offset);
+                            }
+                            let editor_out = area.show(&mut columns[0], |ui| {
+                                let response = Frame::NONE
+                                    .inner_margin(egui::Margin::symmetric(10, 10))
+                                    .show(ui, |ui| editor.ui(ui))
+                                    .inner;
+                                if response.changed() {
+                                    save_editor(ui.ctx(), &tab.path, &editor.code);
+                                }
+                                if ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+                                    tab.ctype = Content::View;
+                                }
+                                ui.add_space(height * 0.5);
+                            });
+
+                            // La vista previa se genera del texto del editor, sin esperar al disco
+                            let mut area = egui::ScrollArea::vertical().id_salt("split_preview");
+                            if let Some(offset) = sync.preview_target.take() {
+                                area = area.vertical_scroll_offset(offset);
+                            }
+                            let preview_out = area.show(&mut columns[1], |ui| {
+                                Frame::NONE.inner_margin(egui::Margin::symmetric(10, 10)).show(ui, |ui| {
+                                    if let Some(updated) = properties_editor(ui, self.vault, &tab.path, &editor.code) {
+                                        editor.code = updated;
+                                        save_editor(ui.ctx(), &tab.path, &editor.code);
+                                    }
+                                    let markdown_content = vault_index::split_frontmatter(&editor.code).1.to_string();
+                                    let highlight = anchor.as_ref().and_then(|(target, scroll)| {
+                                        anchors::find_range(&markdown_content, target).map(|r| (r, *scroll))
+                                    });
+                                    let action = markdown_preview(ui, cache, self.vault, &tab.path, &markdown_content, highlight);
+                                    if let Some(updated) = preview_action(ui.ctx(), self.vault, &tab.path, &editor.code, &markdown_content, action) {
+                                        editor.code = updated;
+                                        save_editor(ui.ctx(), &tab.path, &editor.code);
+                                    }
+                                });
+                                ui.add_space(height * 0.5);
+                            });
+
+                            let editor_max = (editor_out.content_size.y - editor_out.inner_rect.height()).max(1.0);
+                            let preview_max = (preview_out.content_size.y - preview_out.inner_rect.height()).max(1.0);
+                            let editor_offset = editor_out.state.offset.y;
+                            let preview_offset = preview_out.state.offset.y;
+                            let cursor_line = editor.cursor_line();
+
+                            // Lo que se movió desde el cuadro anterior arrastra al otro lado
+                            if cursor_line.is_some() && cursor_line != sync.cursor_line {
+                                let lines = editor.code.lines().count().max(1) as f32;
+                                let ratio = cursor_line.unwrap_or(0) as f32 / lines;
+                                sync.preview_target = Some(ratio * preview_max);
+                            } else if (editor_offset - sync.editor_offset).abs() > 0.5 {
+                                sync.preview_target = Some(editor_offset / editor_max * preview_max);
+                            } else if (preview_offset - sync.preview_offset).abs() > 0.5 {
+                                sync.editor_target = Some(preview_offset / preview_max * editor_max);
+                            }
+                            sync.cursor_line = cursor_line;
+                            sync.editor_offset = sync.editor_target.unwrap_or(editor_offset);
+                            sync.preview_offset = sync.preview_target.unwrap_or(preview_offset);
+                            *scroll_offset = editor_offset;
+                        });
+                        if sync.editor_target.is_some() || sync.preview_target.is_some() {
+                            ui.ctx().request_repaint();
+                        }
+                        ui.ctx().data_mut(|d| d.insert_temp(sync_id, sync));
                     }
                 }
                 TabContent::Empty => {
@@ -771,6 +1005,18 @@ impl TabViewer for MTabViewer<'_> {
         let nav_req: Option<String> = ui.ctx().data_mut(|d| d.get_temp(egui::Id::new("global_nav_request")).flatten());
         if let Some(path) = nav_req {
              ui.ctx().data_mut(|d| d.insert_temp(egui::Id::new("global_nav_request"), None::<String>));
+             let anchor_req: Option<(String, anchors::Anchor)> =
+                 ui.ctx().data_mut(|d| d.get_temp(egui::Id::new("nav_anchor_request")).flatten());
+             if let Some((anchor_path, anchor)) = anchor_req {
+                 let since = ui.input(|i| i.time);
+                 ui.ctx().data_mut(|d| {
+                     d.insert_temp(egui::Id::new("nav_anchor_request"), None::<(String, anchors::Anchor)>);
+                     d.insert_temp(
+                         egui::Id::new("link_anchor").with(tab.id),
+                         Some(LinkAnchor { path: anchor_path, anchor, since, scrolled: false }),
+                     );
+                 });
+             }
              *self.current_file = path.clone();
              update_tab_content(tab, &path, false);
         }
@@ -779,6 +1025,332 @@ impl TabViewer for MTabViewer<'_> {
         self.added_nodes.push((surface, node))
     }
 }
+
+// Segundos que queda resaltado el destino de un enlace
+const ANCHOR_HIGHLIGHT_SECS: f64 = 2.0;
+
+/// Heading or block a link pointed to, highlighted for a moment after
+/// opening it.
+#[derive(Clone)]
+struct LinkAnchor {
+    path: String,
+    anchor: anchors::Anchor,
+    since: f64,
+    scrolled: bool,
+}
+
+/// Scroll positions of a split tab, to move each side when the other one moves.
+#[derive(Clone, Default)]
+struct SplitScroll {
+    restored: bool,
+    editor_offset: f32,
+    preview_offset: f32,
+    cursor_line: Option<usize>,
+    editor_target: Option<f32>,
+    preview_target: Option<f32>,
+}
+
+/// Autosave of the markdown editor.
+fn save_editor(ctx: &egui::Context, path: &str, code: &str) {
+    // Con un cambio externo pendiente no se guarda hasta resolver el conflicto
+    let external_conflict: bool = ctx.data(|d| {
+        d.get_temp(egui::Id::new("external_conflict").with(path)).unwrap_or(false)
+    });
+    if external_conflict {
+        return;
+    }
+    let written = std::fs::OpenOptions::new()
+        .write(true)
+        .truncate(true)
+        .open(path)
+        .and_then(|mut f| {
+            f.write_all(code.as_bytes())?;
+            f.flush()
+        });
+    if let Err(e) = written {
+        println!("Error guardando {}: {}", path, e);
+        ctx.data_mut(|d| d.insert_temp(save_error_id(path), Some(e.to_string())));
+        return;
+    }
+    ctx.data_mut(|d| {
+        d.insert_temp(egui::Id::new("file_saved_signal"), Some(path.to_string()));
+        d.insert_temp(saved_hash_id(path), crate::watcher::content_hash(code.as_bytes()));
+        d.insert_temp::<Option<String>>(save_error_id(path), None);
+    });
+}
+
+fn saved_hash_id(path: &str) -> egui::Id {
+    egui::Id::new("saved_hash").with(path)
+}
+
+/// Last error of `save_editor` for the file, shown above the note.
+fn save_error_id(path: &str) -> egui::Id {
+    egui::Id::new("save_error").with(path)
+}
+
+/// Whether the editor holds text that `save_editor` couldn't write yet.
+fn has_unsaved_edits(ctx: &egui::Context, path: &str, code: &str) -> bool {
+    let saved: Option<u64> = ctx.data(|d| d.get_temp(saved_hash_id(path)));
+    saved.is_some_and(|hash| hash != crate::watcher::content_hash(code.as_bytes()))
+}
+
+// Profundidad máxima de notas incrustadas unas dentro de otras
+const MAX_EMBED_DEPTH: usize = 4;
+
+/// What was clicked in the rendered note, with offsets into the markdown.
+enum PreviewAction {
+    /// "Copy link" (or "Bookmark" when true) of the block at the offset.
+    BlockLink(usize, bool),
+    /// Checkbox of a task at the offset.
+    Checkbox(usize),
+}
+
+/// Renders markdown with the vault link handling (wikilinks, embedded notes
+/// and drawings). Returns the block link or checkbox that was clicked.
+fn markdown_preview(
+    ui: &mut Ui,
+    cache: &mut CommonMarkCache,
+    vault: &str,
+    path: &str,
+    markdown: &str,
+    highlight: Option<(std::ops::Range<usize>, bool)>,
+) -> Option<PreviewAction> {
+    let stack = [(path.to_string(), None)];
+    // Clic derecho: copiar el enlace al bloque bajo el puntero
+    let mut checkbox = None;
+    let response = ui
+        .scope_builder(egui::UiBuilder::new().sense(egui::Sense::click()), |ui| {
+            checkbox = render_markdown(ui, cache, vault, &stack, markdown, highlight).pop();
+        })
+        .response;
+    if let Some((span, _)) = checkbox {
+        return Some(PreviewAction::Checkbox(span.start));
+    }
+    let menu_id = egui::Id::new("preview_menu_block").with(path);
+    if response.secondary_clicked() {
+        if let Some(pos) = response.interact_pointer_pos() {
+            let block = cache.block_tops.iter().rev().find(|(_, top)| *top <= pos.y).map(|(offset, _)| *offset);
+            ui.ctx().data_mut(|d| d.insert_temp(menu_id, block));
+        }
+    }
+    let mut block_action = None;
+    response.context_menu(|ui| {
+        let block: Option<usize> = ui.ctx().data(|d| d.get_temp(menu_id).flatten());
+        if ui.add_enabled(block.is_some(), egui::Button::new("Copy link to heading/block")).clicked() {
+            block_action = block.map(|b| PreviewAction::BlockLink(b, false));
+            ui.close();
+        }
+        if ui.add_enabled(block.is_some(), egui::Button::new("Bookmark heading/block")).clicked() {
+            block_action = block.map(|b| PreviewAction::BlockLink(b, true));
+            ui.close();
+        }
+    });
+    block_action
+}
+
+/// Applies `action` of the preview of `markdown` (the body of `content`).
+/// Returns the new content of the note if it has to be saved.
+fn preview_action(
+    ctx: &egui::Context,
+    vault: &str,
+    path: &str,
+    content: &str,
+    markdown: &str,
+    action: Option<PreviewAction>,
+) -> Option<String> {
+    match action? {
+        PreviewAction::BlockLink(offset, bookmark) => block_link(ctx, vault, content, markdown, offset, path, bookmark),
+        PreviewAction::Checkbox(offset) => toggle_checkbox(ctx, vault, content, markdown, offset),
+    }
+}
+
+/// Ticks or unticks the task whose `[ ]` is at `offset` of `markdown` (the
+/// body of `content`). Returns the new content.
+fn toggle_checkbox(ctx: &egui::Context, vault: &str, content: &str, markdown: &str, offset: usize) -> Option<String> {
+    let offset = content.len().saturating_sub(markdown.len()) + offset;
+    // Sin una casilla en ese punto el desplazamiento no es fiable y no se toca la nota
+    let flipped = flip_checkbox(content, offset)?;
+    let line = content.get(..offset)?.matches('\n').count();
+    // Las tareas que no empiezan la línea, como las de las citas, solo cambian la casilla
+    Some(crate::vault_tasks::toggle_line(ctx, vault, content, line).unwrap_or(flipped))
+}
+
+/// Flips the `[ ]`, `[x]` or `[X]` at `offset` of `content`, if there is one.
+fn flip_checkbox(content: &str, offset: usize) -> Option<String> {
+    let flipped = match content.get(offset..offset + 3)? {
+        "[ ]" => "[x]",
+        "[x]" | "[X]" => "[ ]",
+        _ => return None,
+    };
+    let mut updated = content.to_string();
+    updated.replace_range(offset..offset + 3, flipped);
+    Some(updated)
+}
+
+/// Copies the link to the block at `offset` of `markdown` (the body of
+/// `content`), or bookmarks it with `bookmark`. Returns the new content if
+/// a block id had to be added.
+fn block_link(
+    ctx: &egui::Context,
+    vault: &str,
+    content: &str,
+    markdown: &str,
+    offset: usize,
+    path: &str,
+    bookmark: bool,
+) -> Option<String> {
+    let offset = content.len().saturating_sub(markdown.len()) + offset;
+    let line = content.get(..offset)?.matches('\n').count();
+    let (link, updated) = anchors::link_to_line(content, line, path)?;
+    if bookmark {
+        let target = link.trim_start_matches("[[").trim_end_matches("]]");
+        let subpath = target.find('#').map(|i| target[i..].to_string());
+        crate::bookmarks::add(vault, crate::bookmarks::Bookmark::file(vault, path, subpath));
+    } else {
+        ctx.copy_text(link);
+    }
+    updated
+}
+
+/// Shows `markdown` of the last note of `stack`, which holds the notes (and
+/// the section of each, if only one is embedded) being rendered with the
+/// outermost first. Returns the checkboxes clicked, which
+/// only the outermost note has.
+fn render_markdown(
+    ui: &mut Ui,
+    cache: &mut CommonMarkCache,
+    vault: &str,
+    stack: &[(String, Option<anchors::Anchor>)],
+    markdown: &str,
+    highlight: Option<(std::ops::Range<usize>, bool)>,
+) -> Vec<(std::ops::Range<usize>, bool)> {
+    let path = stack.last().map(|(path, _)| path.as_str()).unwrap_or("");
+    let process_link = |ui: &mut Ui, url: &str, layout: egui::text::LayoutJob| {
+        let response = ui.link(layout);
+        if response.clicked() {
+            let decoded_url = percent_encoding::percent_decode_str(url).decode_utf8_lossy().to_string();
+            anchors::follow_link(ui.ctx(), vault, path, &decoded_url);
+        }
+        true 
+    };
+    let render_excalidraw = |ui: &mut Ui, filename: &str, size: Option<f32>| {
+        if let Some(path) = crate::files::resolve_path(vault, path, filename) {
+            let mut gui = crate::excalidraw::ExcalidrawGui::default();
+            gui.set_path(&path);
+            gui.render_static(ui, size);
+        } else {
+            ui.colored_label(egui::Color32::RED, format!("Could not find drawing: {}", filename));
+        }
+    };
+    let render_embed = |ui: &mut Ui, cache: &mut CommonMarkCache, target: &str| {
+        embed_note(ui, cache, vault, stack, target)
+    };
+    let render_query = |ui: &mut Ui, query: &str| crate::query::render(ui, vault, path, query);
+    let viewer = CommonMarkViewer::new()
+        .process_link(Some(&process_link))
+        .render_excalidraw_fn(Some(&render_excalidraw))
+        .render_embed_fn(Some(&render_embed))
+        .render_query_fn(Some(&render_query))
+        .highlight(highlight);
+    if stack.len() == 1 {
+        viewer.show_checkboxes(ui, cache, markdown).1
+    } else {
+        viewer.show(ui, cache, markdown);
+        vec![]
+    }
+}
+
+/// Renders the `![[target]]` embed of a note (or one of its sections) in a
+/// box with a link to it. Returns false for files that aren't notes so the
+/// viewer shows them as images.
+fn embed_note(
+    ui: &mut Ui,
+    cache: &mut CommonMarkCache,
+    vault: &str,
+    stack: &[(String, Option<anchors::Anchor>)],
+    target: &str,
+) -> bool {
+    let target = target.split('|').next().unwrap_or(target).trim();
+    let current = stack.last().map(|(path, _)| path.clone()).unwrap_or_default();
+    let (path, anchor) = match anchors::resolve_target(vault, &current, target) {
+        Some((path, anchor)) if path.to_lowercase().ends_with(".md") => (path, anchor),
+        Some(_) => return false,
+        None => {
+            // Con otra extensión se deja al visor de imágenes
+            let (note, _) = anchors::split_target(target);
+            let extension = Path::new(note).extension().and_then(|e| e.to_str()).unwrap_or("md");
+            if !extension.eq_ignore_ascii_case("md") {
+                return false;
+            }
+            ui.end_row();
+            ui.colored_label(egui::Color32::RED, format!("Could not find note: {}", target));
+            ui.end_row();
+            return true;
+        }
+    };
+
+    ui.end_row();
+    let width = ui.available_width();
+    ui.allocate_ui_with_layout(egui::vec2(width, 0.0), egui::Layout::top_down(egui::Align::LEFT), |ui| {
+        Frame::NONE
+            .inner_margin(egui::Margin::symmetric(10, 6))
+            .stroke(ui.visuals().widgets.noninteractive.bg_stroke)
+            .corner_radius(4.0)
+            .show(ui, |ui| {
+                ui.set_width(ui.available_width());
+                if ui.link(egui::RichText::new(format!("📄 {}", target)).small()).clicked() {
+                    anchors::follow_link(ui.ctx(), vault, &current, target);
+                }
+                // Una sección de la misma nota (![[#Sección]]) no es un ciclo
+                let embed = (path.clone(), anchor.clone());
+                if stack.contains(&embed) {
+                    ui.weak("This note is already embedded above.");
+                } else if stack.len() >= MAX_EMBED_DEPTH {
+                    ui.weak("Too many nested embeds.");
+                } else {
+                    let body = embed_body(&path);
+                    let text = match &anchor {
+                        Some(anchor) => anchors::section(&body, anchor),
+                        None => Some(body.as_str()),
+                    };
+                    match text {
+                        Some(text) => {
+                            let mut nested = stack.to_vec();
+                            nested.push(embed);
+                            // Las posiciones de bloques son las de la nota exterior
+                            let block_tops = std::mem::take(&mut cache.block_tops);
+                            render_markdown(ui, cache, vault, &nested, text, None);
+                            cache.block_tops = block_tops;
+                        }
+                        None => {
+                            ui.colored_label(egui::Color32::RED, "Section not found.");
+                        }
+                    }
+                }
+            });
+    });
+    ui.end_row();
+    true
+}
+
+/// Body (without the frontmatter) of an embedded note, reread only when the
+/// file's modification time changes.
+fn embed_body(path: &str) -> String {
+    static EMBEDS: OnceLock<Mutex<HashMap<String, (SystemTime, String)>>> = OnceLock::new();
+    let modified = std::fs::metadata(path).and_then(|m| m.modified()).ok();
+    let mut embeds = EMBEDS.get_or_init(Default::default).lock().unwrap_or_else(|e| e.into_inner());
+    if let (Some(modified), Some((cached, body))) = (modified, embeds.get(path)) {
+        if *cached == modified {
+            return body.clone();
+        }
+    }
+    let body = vault_index::split_frontmatter(&files::read_file(path)).1.to_string();
+    if let Some(modified) = modified {
+        embeds.insert(path.to_string(), (modified, body.clone()));
+    }
+    body
+}
+
 // Free function to update tab content
 fn update_tab_content(tab: &mut Tabe, path: &String, is_history_nav: bool) {
     if !is_history_nav {
@@ -830,6 +1402,13 @@ fn update_tab_content(tab: &mut Tabe, path: &String, is_history_nav: bool) {
             path: path.clone(),
             gui,
         }
+    } else if path.ends_with(".table") {
+        let mut gui = database::DatabaseGui::default();
+        gui.set_path(path);
+        TabContent::Database {
+            path: path.clone(),
+            gui,
+        }
     } else if path.ends_with(".graph") {
         let mut gui = tasks::TasksGui::default();
         gui.set_path(path);
@@ -855,10 +1434,17 @@ fn update_tab_content(tab: &mut Tabe, path: &String, is_history_nav: bool) {
     };
 }
 
+/// A tab being edited whose file was changed by another program.
+pub struct ExternalConflict {
+    pub path: String,
+    pub disk: String,
+}
+
 pub struct Tabs {
     //dock_state: DockState<Tabe>,
     tree: DockState<Tabe>,
     counter: usize,
+    conflicts: Vec<ExternalConflict>,
 }
 
 impl Tabs {
@@ -872,6 +1458,7 @@ impl Tabs {
         Self {
             tree: dock_state,
             counter,
+            conflicts: Vec::new(),
         }
     }
 
@@ -879,6 +1466,7 @@ impl Tabs {
         Self {
             tree: DockState::new(vec![]),
             counter: 0,
+            conflicts: Vec::new(),
         }
     }
 
@@ -938,6 +1526,29 @@ impl Tabs {
         }
     }
 
+    /// Points the tabs (and their history) of a renamed file or folder to the new location.
+    pub fn rename_tab_path(&mut self, old_path: &str, new_path: &str) {
+        let moved = |p: &str| -> Option<String> {
+            if p == old_path {
+                Some(new_path.to_string())
+            } else {
+                p.strip_prefix(old_path)
+                    .filter(|rest| rest.starts_with('/'))
+                    .map(|rest| format!("{}{}", new_path, rest))
+            }
+        };
+        for (_, tab) in self.tree.iter_all_tabs_mut() {
+            for entry in tab.history.iter_mut() {
+                if let Some(p) = moved(entry) {
+                    *entry = p;
+                }
+            }
+            if let Some(p) = moved(&tab.path) {
+                update_tab_content(tab, &p, true);
+            }
+        }
+    }
+
     pub fn duplicate_current_tab(&mut self) {
         if let Some((_, tab)) = self.tree.find_active_focused() {
             let mut new_tab = tab.clone();
@@ -1027,7 +1638,18 @@ impl Tabs {
             return;
         }
 
-        if let Some((_, tab)) = self.tree.find_active_focused() {
+        let calendar_focused = matches!(
+            self.tree.find_active_focused(),
+            Some((_, Tabe { content: TabContent::Calendar { .. } | TabContent::VaultTasks { .. }, .. }))
+        );
+        if calendar_focused {
+            // El calendario y las tareas se quedan abiertos, la nota va a una pestaña nueva
+            self.counter += 1;
+            while self.tree.iter_all_tabs().any(|(_, t)| t.id == self.counter) {
+                 self.counter += 1;
+            }
+            self.tree.push_to_focused_leaf(Tabe::new(self.counter, path.to_string()));
+        } else if let Some((_, tab)) = self.tree.find_active_focused() {
             update_tab_content(tab, &path.to_string(), false);
         } else {
             self.counter += 1;
@@ -1039,6 +1661,161 @@ impl Tabs {
         }
     }
 
+    /// Reloads the tabs showing files changed outside the app. Markdown tabs
+    /// in edit mode with unsaved edits are not touched, a conflict is queued
+    /// for `conflicts_ui` instead.
+    pub fn reload_changed(&mut self, ctx: &egui::Context, paths: &[String], vault: &str) {
+        let notes_changed = paths.iter().any(|p| p.ends_with(".md"));
+        for (_, tab) in self.tree.iter_all_tabs_mut() {
+            if let TabContent::Graph { state: Some(graph), .. } = &mut tab.content {
+                if notes_changed {
+                    graph.update_vault(Path::new(vault));
+                }
+                continue;
+            }
+            if let TabContent::Calendar { gui } = &mut tab.content {
+                gui.invalidate();
+                continue;
+            }
+            if let TabContent::VaultTasks { gui } = &mut tab.content {
+                gui.invalidate();
+                continue;
+            }
+            if !paths.contains(&tab.path) {
+                continue;
+            }
+            if !Path::new(&tab.path).exists() {
+                // Borrado desde fuera: se deja la pestaña, se recarga si vuelve a aparecer
+                continue;
+            }
+            match &mut tab.content {
+                TabContent::Markdown { editor, cache, .. } => {
+                    // A medio escribir (Syncthing, git) puede no leerse: se espera al siguiente cambio
+                    let Ok(disk) = std::fs::read_to_string(&tab.path) else {
+                        continue;
+                    };
+                    if disk == editor.code {
+                        continue;
+                    }
+                    let editing = tab.ctype == Content::Edit || tab.ctype == Content::Split;
+                    if editing && has_unsaved_edits(ctx, &tab.path, &editor.code) {
+                        if let Some(c) = self.conflicts.iter_mut().find(|c| c.path == tab.path) {
+                            c.disk = disk;
+                        } else {
+                            ctx.data_mut(|d| d.insert_temp(egui::Id::new("external_conflict").with(&tab.path), true));
+                            self.conflicts.push(ExternalConflict {
+                                path: tab.path.clone(),
+                                disk,
+                            });
+                        }
+                    } else {
+                        editor.code = disk;
+                        *cache = CommonMarkCache::default();
+                        ctx.data_mut(|d| d.insert_temp(saved_hash_id(&tab.path), crate::watcher::content_hash(editor.code.as_bytes())));
+                    }
+                }
+                // Clonar el contenido vuelve a cargar el archivo desde el disco
+                content => *content = content.clone(),
+            }
+        }
+    }
+
+    /// Prompt for the files changed on disk while their tab was being edited.
+    pub fn conflicts_ui(&mut self, ctx: &egui::Context) {
+        let Some(conflict) = self.conflicts.first() else {
+            return;
+        };
+        let path = conflict.path.clone();
+        let disk = conflict.disk.clone();
+        let mine = self
+            .tree
+            .iter_all_tabs()
+            .find_map(|(_, tab)| match &tab.content {
+                TabContent::Markdown { editor, .. } if tab.path == path => Some(editor.code.clone()),
+                _ => None,
+            });
+        let Some(mine) = mine else {
+            // La pestaña se cerró mientras tanto
+            self.resolve_conflict(ctx, &path, None);
+            return;
+        };
+
+        let disk_lines: std::collections::HashSet<&str> = disk.lines().collect();
+        let mine_lines: std::collections::HashSet<&str> = mine.lines().collect();
+        let mut resolution = None;
+
+        egui::Window::new("File changed on disk")
+            .collapsible(false)
+            .resizable(true)
+            .default_width(600.0)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.label(egui::RichText::new(&path).strong());
+                ui.label("Another program modified this file while you were editing it.");
+                ui.separator();
+                ui.columns(2, |cols| {
+                    cols[0].label(egui::RichText::new("Only in your version").strong());
+                    cols[1].label(egui::RichText::new("Only on disk").strong());
+                    egui::ScrollArea::vertical().id_salt("conflict_mine").max_height(250.0).show(&mut cols[0], |ui| {
+                        for line in mine.lines().filter(|l| !disk_lines.contains(l)) {
+                            ui.label(egui::RichText::new(line).small().color(ui.visuals().error_fg_color));
+                        }
+                    });
+                    egui::ScrollArea::vertical().id_salt("conflict_disk").max_height(250.0).show(&mut cols[1], |ui| {
+                        for line in disk.lines().filter(|l| !mine_lines.contains(l)) {
+                            ui.label(egui::RichText::new(line).small().color(ui.visuals().selection.stroke.color));
+                        }
+                    });
+                });
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if ui.button("Reload from disk").clicked() {
+                        resolution = Some(disk.clone());
+                    }
+                    if ui.button("Keep mine").on_hover_text("Overwrite the file with your version").clicked() {
+                        match std::fs::write(&path, &mine) {
+                            Ok(_) => resolution = Some(mine.clone()),
+                            Err(e) => println!("Error guardando {}: {}", path, e),
+                        }
+                    }
+                    if ui.button("Keep both").on_hover_text("Save your version as a copy and reload").clicked() {
+                        let file = Path::new(&path);
+                        let stem = file.file_stem().and_then(|s| s.to_str()).unwrap_or("note");
+                        let mut copy = file.with_file_name(format!("{} (local copy).md", stem));
+                        let mut count = 2;
+                        while copy.exists() {
+                            copy = file.with_file_name(format!("{} (local copy {}).md", stem, count));
+                            count += 1;
+                        }
+                        match std::fs::write(&copy, &mine) {
+                            Ok(_) => resolution = Some(disk.clone()),
+                            Err(e) => println!("Error guardando {}: {}", copy.display(), e),
+                        }
+                    }
+                });
+            });
+
+        if let Some(code) = resolution {
+            self.resolve_conflict(ctx, &path, Some(code));
+        }
+    }
+
+    fn resolve_conflict(&mut self, ctx: &egui::Context, path: &str, code: Option<String>) {
+        if let Some(code) = code {
+            // Tras resolver, el disco y la pestaña tienen el mismo texto
+            ctx.data_mut(|d| d.insert_temp(saved_hash_id(path), crate::watcher::content_hash(code.as_bytes())));
+            for (_, tab) in self.tree.iter_all_tabs_mut() {
+                if let TabContent::Markdown { editor, .. } = &mut tab.content {
+                    if tab.path == path {
+                        editor.code = code.clone();
+                    }
+                }
+            }
+        }
+        self.conflicts.retain(|c| c.path != path);
+        ctx.data_mut(|d| d.insert_temp(egui::Id::new("external_conflict").with(path), false));
+    }
+
     pub fn add_tab(&mut self, tab: Tabe) {
         self.tree.push_to_focused_leaf(tab);
     }
@@ -1051,7 +1828,49 @@ impl Tabs {
         self.tree.push_to_focused_leaf(Tabe::new_graph(self.counter, vault));
     }
 
+    pub fn add_tasks_tab(&mut self) {
+        self.counter += 1;
+        while self.tree.iter_all_tabs().any(|(_, t)| t.id == self.counter) {
+             self.counter += 1;
+        }
+        self.tree.push_to_focused_leaf(Tabe::new_tasks(self.counter));
+    }
+
+    pub fn add_calendar_tab(&mut self) {
+        self.counter += 1;
+        while self.tree.iter_all_tabs().any(|(_, t)| t.id == self.counter) {
+             self.counter += 1;
+        }
+        self.tree.push_to_focused_leaf(Tabe::new_calendar(self.counter));
+    }
+
     pub fn dock_state(&self) -> &DockState<Tabe> {
         &self.tree
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checkboxes_in_quotes_flip() {
+        let content = "> - [ ] Quoted task\n> - [X] Done";
+        let offset = content.find('[').unwrap();
+        let ticked = flip_checkbox(content, offset).unwrap();
+        assert_eq!(ticked, "> - [x] Quoted task\n> - [X] Done");
+        assert_eq!(flip_checkbox(&ticked, offset).unwrap(), content);
+        let done = content.rfind('[').unwrap();
+        assert_eq!(flip_checkbox(content, done).unwrap(), "> - [ ] Quoted task\n> - [ ] Done");
+    }
+
+    #[test]
+    fn wrong_offsets_leave_the_note_alone() {
+        let content = "- [ ] Task ✅\n";
+        assert_eq!(flip_checkbox(content, 0), None);
+        assert_eq!(flip_checkbox(content, 3), None);
+        // En medio de un carácter de varios bytes o fuera del texto
+        assert_eq!(flip_checkbox(content, content.find('✅').unwrap() + 1), None);
+        assert_eq!(flip_checkbox(content, content.len()), None);
+    }
+}
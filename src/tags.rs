@@ -0,0 +1,236 @@
+//! Tags of the vault, from the frontmatter `tags:` and the inline `#tags`
+//! of the body. `#parent/child` tags are nested under their parent.
+
+use regex::{Captures, Regex};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::sync::OnceLock;
+
+use crate::properties::{self, Value};
+use crate::vault_index;
+
+/// A tag with the tags nested under it.
+#[derive(Clone, Debug)]
+pub struct TagNode {
+    /// Last part of the tag, `child` for `parent/child`.
+    pub name: String,
+    /// Whole tag without `#`.
+    pub full: String,
+    /// Notes with the tag or with one nested under it.
+    pub count: usize,
+    pub children: Vec<TagNode>,
+}
+
+/// Regex of the inline `#tags` of a line: group 1 is what precedes the
+/// `#` and group 2 the tag. It also matches numeric tags like `#123`, which
+/// aren't tags; check the match with [`is_tag`] or use [`inline_tags`].
+pub fn inline_tag_regex() -> &'static Regex {
+    static TAG: OnceLock<Regex> = OnceLock::new();
+    TAG.get_or_init(|| Regex::new(r"(^|\s)#([\p{L}\p{N}_/-]+)").unwrap())
+}
+
+/// Whether `tag` (without `#`) is a tag: Obsidian needs at least one
+/// character that isn't a digit.
+pub fn is_tag(tag: &str) -> bool {
+    tag.trim_matches('/').chars().any(|c| !c.is_ascii_digit())
+}
+
+/// Inline tags of `text`, as captures of [`inline_tag_regex`].
+pub fn inline_tags(text: &str) -> impl Iterator<Item = Captures<'_>> {
+    inline_tag_regex().captures_iter(text).filter(|c| is_tag(&c[2]))
+}
+
+/// Tree of every tag of the vault, sorted by name.
+pub fn tag_tree(vault: &str) -> Vec<TagNode> {
+    // Notas de cada tag y de sus padres, sin distinguir mayúsculas
+    let mut notes: BTreeMap<String, (String, HashSet<String>)> = BTreeMap::new();
+    vault_index::with_index(vault, |index| {
+        for (file, meta) in index.notes() {
+            for tag in meta.tags() {
+                let tag = tag.trim_matches('/');
+                let mut end = 0;
+                for part in tag.split('/') {
+                    end += part.len();
+                    let prefix = &tag[..end];
+                    notes
+                        .entry(prefix.to_lowercase())
+                        .or_insert_with(|| (prefix.to_string(), HashSet::new()))
+                        .1
+                        .insert(file.path.clone());
+                    end += 1;
+                }
+            }
+        }
+    });
+
+    fn children(notes: &BTreeMap<String, (String, HashSet<String>)>, parent: Option<&str>) -> Vec<TagNode> {
+        notes
+            .iter()
+            .filter(|(key, _)| match parent {
+                Some(parent) => key
+                    .strip_prefix(parent)
+                    .and_then(|rest| rest.strip_prefix('/'))
+                    .map(|rest| !rest.is_empty() && !rest.contains('/'))
+                    .unwrap_or(false),
+                None => !key.contains('/'),
+            })
+            .map(|(key, (full, paths))| TagNode {
+                name: full.rsplit('/').next().unwrap_or(full).to_string(),
+                full: full.clone(),
+                count: paths.len(),
+                children: children(notes, Some(key)),
+            })
+            .collect()
+    }
+    children(&notes, None)
+}
+
+/// New name of `tag` when `old` is renamed to `new`, also for the tags
+/// nested under it. `None` if the tag isn't affected.
+fn renamed(tag: &str, old: &str, new: &str) -> Option<String> {
+    if tag.to_lowercase() == old.to_lowercase() {
+        return Some(new.to_string());
+    }
+    let prefix = tag.get(..old.len())?;
+    let rest = &tag[old.len()..];
+    (prefix.to_lowercase() == old.to_lowercase() && rest.starts_with('/')).then(|| format!("{}{}", new, rest))
+}
+
+/// Content of a note with the tag `old` (and the ones nested under it)
+/// renamed to `new`, both in the frontmatter and in the body.
+pub fn rename_in_content(content: &str, old: &str, new: &str) -> String {
+    let (_, body) = vault_index::split_frontmatter(content);
+    let header = &content[..content.len() - body.len()];
+
+    let mut new_body = String::with_capacity(body.len());
+    let mut in_code = false;
+    for line in body.split_inclusive('\n') {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_code = !in_code;
+        }
+        if in_code || trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            new_body.push_str(line);
+            continue;
+        }
+        let replaced = inline_tag_regex().replace_all(line, |caps: &Captures| {
+            match renamed(&caps[2], old, new).filter(|_| is_tag(&caps[2])) {
+                Some(tag) => format!("{}#{}", &caps[1], tag),
+                None => caps[0].to_string(),
+            }
+        });
+        new_body.push_str(&replaced);
+    }
+    let mut updated = format!("{}{}", header, new_body);
+
+    // Los tags del frontmatter pueden ir como lista o como texto
+    let Ok(props) = properties::read(content) else {
+        return updated;
+    };
+    for property in props.iter().filter(|p| ["tags", "tag"].contains(&p.key.to_lowercase().as_str())) {
+        let rename_item = |item: &str| {
+            let hash = if item.starts_with('#') { "#" } else { "" };
+            renamed(item.trim_start_matches('#'), old, new).map(|t| format!("{}{}", hash, t))
+        };
+        let value = match &property.value {
+            Value::List(items) => {
+                let new_items: Vec<String> = items
+                    .iter()
+                    .map(|i| rename_item(i).unwrap_or_else(|| i.clone()))
+                    .collect();
+                (new_items != *items).then_some(Value::List(new_items))
+            }
+            Value::Text(text) => {
+                static WORD: OnceLock<Regex> = OnceLock::new();
+                let word = WORD.get_or_init(|| Regex::new(r"#?[\p{L}\p{N}_/-]+").unwrap());
+                let new_text = word.replace_all(text, |caps: &Captures| rename_item(&caps[0]).unwrap_or_else(|| caps[0].to_string()));
+                (new_text != *text).then(|| Value::Text(new_text.to_string()))
+            }
+            _ => None,
+        };
+        if let Some(value) = value {
+            updated = properties::set(&updated, &property.key, &value);
+        }
+    }
+    updated
+}
+
+/// Renames the tag `old` to `new` in every note of the vault. Returns the
+/// paths of the notes that were written.
+pub fn rename_tag(vault: &str, old: &str, new: &str) -> Vec<String> {
+    let old = old.trim().trim_start_matches('#').trim_matches('/');
+    let new = new.trim().trim_start_matches('#').trim_matches('/');
+    if old.is_empty() || new.is_empty() || old == new {
+        return vec![];
+    }
+    let paths: Vec<String> = vault_index::with_index(vault, |index| {
+        index
+            .notes()
+            .filter(|(_, meta)| meta.tags().iter().any(|t| renamed(t, old, new).is_some()))
+            .map(|(file, _)| file.path.clone())
+            .collect()
+    });
+
+    let mut written = vec![];
+    for path in paths {
+        let Ok(content) = fs::read_to_string(&path) else {
+            continue;
+        };
+        let updated = rename_in_content(&content, old, new);
+        if updated == content {
+            continue;
+        }
+        match fs::write(&path, updated) {
+            Ok(_) => {
+                vault_index::update_file(vault, &path);
+                written.push(path);
+            }
+            Err(e) => println!("Error renombrando el tag en {}: {}", path, e),
+        }
+    }
+    written
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn numeric_tags_are_not_tags() {
+        let found: Vec<String> = inline_tags("#123 #2024 #v2 #a/1 text#no").map(|c| c[2].to_string()).collect();
+        assert_eq!(found, vec!["v2", "a/1"]);
+    }
+
+    #[test]
+    fn renames_inline_and_nested_tags() {
+        let content = "#project and #Project/web, not #projects or #123\n";
+        assert_eq!(
+            rename_in_content(content, "project", "work"),
+            "#work and #work/web, not #projects or #123\n"
+        );
+    }
+
+    #[test]
+    fn skips_code_fences() {
+        let content = "#project\n```\n#project\n```\n~~~\n#project\n~~~\n#project\n";
+        assert_eq!(
+            rename_in_content(content, "project", "work"),
+            "#work\n```\n#project\n```\n~~~\n#project\n~~~\n#work\n"
+        );
+    }
+
+    #[test]
+    fn renames_frontmatter_tags() {
+        let list = "---\ntags:\n  - project/web\n  - other\n---\n#project\n";
+        assert_eq!(
+            rename_in_content(list, "project", "work"),
+            "---\ntags:\n  - work/web\n  - other\n---\n#work\n"
+        );
+        let text = "---\ntags: \"#project, other\"\n---\nbody\n";
+        let renamed = rename_in_content(text, "project", "work");
+        assert!(renamed.contains("#work, other"), "{}", renamed);
+        assert!(renamed.ends_with("---\nbody\n"));
+        let untouched = "---\ntags: [other]\n---\n";
+        assert_eq!(rename_in_content(untouched, "project", "work"), untouched);
+    }
+}
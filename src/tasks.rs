@@ -1,4 +1,4 @@
-use chrono::Local;
+use chrono::{Datelike, Duration, Local, NaiveDate};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
@@ -24,11 +24,53 @@ enum Edit {
     Null,
 }
 
+/// Days a habit has to be done on. Missing a day it isn't scheduled for
+/// doesn't break its streak.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Schedule {
+    #[default]
+    Daily,
+    /// Only these days of the week, 0 is Monday.
+    Weekdays { days: Vec<u8> },
+    /// Any N days of each week, the streak counts weeks.
+    TimesPerWeek { times: u8 },
+}
+
+impl Schedule {
+    fn is_due(&self, date: NaiveDate) -> bool {
+        match self {
+            Schedule::Weekdays { days } => days.contains(&(date.weekday().num_days_from_monday() as u8)),
+            _ => true,
+        }
+    }
+
+    fn name(&self) -> String {
+        match self {
+            Schedule::Daily => "Every day".to_string(),
+            Schedule::Weekdays { days } => {
+                let names: Vec<&str> = WEEKDAYS
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, _)| days.contains(&(*i as u8)))
+                    .map(|(_, n)| *n)
+                    .collect();
+                if names.is_empty() { "No days".to_string() } else { names.join(" ") }
+            }
+            Schedule::TimesPerWeek { times } => format!("{}× a week", times),
+        }
+    }
+}
+
+const WEEKDAYS: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+
 #[derive(Serialize, Deserialize, Debug)]
 struct TaskToDo {
     id: u32,
     name: String,
     description: Option<String>,
+    #[serde(default)]
+    schedule: Schedule,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -74,6 +116,152 @@ pub struct TasksFile {
     top_id: u32,
 }
 
+impl TasksFile {
+    /// Number of completed tasks for each day, with the date as written in the file.
+    pub fn completed_by_date(&self) -> Vec<(String, usize)> {
+        self.days
+            .iter()
+            .map(|day| (day.date.clone(), day.tasks.iter().filter(|t| t.completed).count()))
+            .collect()
+    }
+
+    /// Adds `day` keeping the days sorted with the most recent first.
+    fn insert_day(&mut self, day: Day) {
+        let index = match parse_day(&day.date) {
+            Some(date) => self
+                .days
+                .iter()
+                .position(|d| parse_day(&d.date).is_some_and(|other| other < date))
+                .unwrap_or(self.days.len()),
+            None => 0,
+        };
+        self.days.insert(index, day);
+    }
+
+    /// Marks the habit `id` as done or not done on `date`, adding the day if
+    /// it wasn't tracked yet.
+    fn toggle_day(&mut self, id: u32, date: NaiveDate) {
+        let name = date.format("%Y-%m-%d").to_string();
+        match self.days.iter_mut().find(|d| parse_day(&d.date) == Some(date)) {
+            Some(day) => match day.tasks.iter_mut().find(|t| t.id == id) {
+                Some(task) => task.completed = !task.completed,
+                None => day.tasks.push(TaskCompleted { id, completed: true }),
+            },
+            None => {
+                let tasks = self
+                    .tasks
+                    .iter()
+                    .map(|t| TaskCompleted { id: t.id, completed: t.id == id })
+                    .collect();
+                self.insert_day(Day::new(&name, tasks));
+            }
+        }
+    }
+
+    /// History of the days, oldest first, with one column per habit.
+    fn to_csv(&self) -> String {
+        let mut csv = String::from("date");
+        for habit in &self.tasks {
+            csv.push(',');
+            csv.push_str(&csv_field(&habit.name));
+        }
+        csv.push_str(",notes\n");
+
+        let mut days: Vec<&Day> = self.days.iter().collect();
+        days.sort_by(|a, b| a.date.cmp(&b.date));
+        for day in days {
+            csv.push_str(&csv_field(&day.date));
+            for habit in &self.tasks {
+                csv.push(',');
+                if let Some(task) = day.tasks.iter().find(|t| t.id == habit.id) {
+                    csv.push_str(if task.completed { "1" } else { "0" });
+                }
+            }
+            csv.push(',');
+            csv.push_str(&csv_field(day.notes.as_deref().unwrap_or("")));
+            csv.push('\n');
+        }
+        csv
+    }
+}
+
+fn parse_day(date: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d").ok()
+}
+
+/// Streaks and history of one habit.
+#[derive(Default)]
+struct HabitStats {
+    current: u32,
+    longest: u32,
+    /// Whether the streaks count weeks instead of days.
+    weekly: bool,
+    /// First day the habit was tracked.
+    first: Option<NaiveDate>,
+    /// Whether it was done on each tracked day.
+    history: HashMap<NaiveDate, bool>,
+}
+
+fn habit_stats(file: &TasksFile, habit: &TaskToDo, today: NaiveDate) -> HabitStats {
+    let mut stats = HabitStats::default();
+    for day in &file.days {
+        let Some(date) = parse_day(&day.date) else {
+            continue;
+        };
+        if let Some(task) = day.tasks.iter().find(|t| t.id == habit.id) {
+            *stats.history.entry(date).or_insert(false) |= task.completed;
+        }
+    }
+    stats.first = stats.history.keys().min().copied();
+    let Some(first) = stats.first else {
+        return stats;
+    };
+    let done = |date: NaiveDate| stats.history.get(&date).copied().unwrap_or(false);
+
+    let (mut current, mut longest) = (0, 0);
+    match &habit.schedule {
+        Schedule::TimesPerWeek { times } => {
+            let mut week = first - Duration::days(first.weekday().num_days_from_monday() as i64);
+            while week <= today {
+                let count = (0..7).filter(|i| done(week + Duration::days(*i))).count();
+                if count >= *times as usize {
+                    current += 1;
+                    longest = longest.max(current);
+                } else if week + Duration::days(7) <= today {
+                    // La semana en curso todavía puede cumplirse
+                    current = 0;
+                }
+                week += Duration::days(7);
+            }
+            stats.weekly = true;
+        }
+        schedule => {
+            let mut date = first;
+            while date <= today {
+                if done(date) {
+                    current += 1;
+                    longest = longest.max(current);
+                } else if schedule.is_due(date) && date < today {
+                    current = 0;
+                }
+                date += Duration::days(1);
+            }
+        }
+    }
+    stats.current = current;
+    stats.longest = longest;
+    stats
+}
+
+/// Quotes a CSV field when it needs it.
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
 impl Default for TasksFile {
     fn default() -> Self {
         TasksFile {
@@ -116,6 +304,8 @@ pub struct TasksGui {
     add_entry: bool,
     new_entry_tittle: String,
     tasks_hash: HashMap<u32, String>,
+    habit_stats: HashMap<u32, HabitStats>,
+    export_message: String,
     update_graph: bool,
     save_file: bool,
 }
@@ -141,6 +331,8 @@ impl Default for TasksGui {
             new_task_desc: String::new(),
             new_task_update: false,
             tasks_hash: HashMap::new(),
+            habit_stats: HashMap::new(),
+            export_message: String::new(),
             prom: 0.0,
         }
     }
@@ -198,6 +390,14 @@ impl TasksGui {
         } else {
             self.prom = 0.0;
         }
+
+        let today = Local::now().date_naive();
+        self.habit_stats = self
+            .json_content
+            .tasks
+            .iter()
+            .map(|habit| (habit.id, habit_stats(&self.json_content, habit, today)))
+            .collect();
     }
 
     pub fn show(&mut self, ui: &mut egui::Ui, seed_id: Id) {
@@ -283,8 +483,10 @@ impl TasksGui {
 
                     let mut del = None;
                     let mut id_to_del = 0;
+                    let mut backfill = None;
+                    let today = Local::now().date_naive();
 
-                    for (rem_indx, element) in self.json_content.tasks.iter().enumerate() {
+                    for (rem_indx, element) in self.json_content.tasks.iter_mut().enumerate() {
                         ui.horizontal(|ui| {
                             ui.strong(&element.name);
                             if let Some(desc) = &element.description {
@@ -303,10 +505,36 @@ impl TasksGui {
                                         ui.close(); // CORREGIDO
                                     }
                                 });
+                                if schedule_menu(ui, &mut element.schedule) {
+                                    self.update_graph = true;
+                                    self.save_file = true;
+                                }
+                                if let Some(stats) = self.habit_stats.get(&element.id) {
+                                    let unit = if stats.weekly { "weeks" } else { "days" };
+                                    ui.label(
+                                        RichText::new(format!("🔥 {} {} · best {}", stats.current, unit, stats.longest))
+                                            .small(),
+                                    );
+                                }
                             });
                         });
+                        if let Some(stats) = self.habit_stats.get(&element.id) {
+                            let id = ui.make_persistent_id(("habit_heatmap", element.id));
+                            egui::collapsing_header::CollapsingState::load_with_default_open(ui.ctx(), id, false)
+                                .show_header(ui, |ui| {
+                                    ui.label(RichText::new("Last year").weak().small());
+                                })
+                                .body(|ui| {
+                                    if let Some(date) = heatmap(ui, stats, &element.schedule, today) {
+                                        backfill = Some((element.id, date));
+                                    }
+                                });
+                        }
                     }
 
+                    if let Some((id, date)) = backfill {
+                        self.toggle_day(id, date);
+                    }
                     if let Some(index) = del {
                         self.remove_task_global(index, id_to_del);
                     }
@@ -330,10 +558,22 @@ impl TasksGui {
                     });
                 });
             } else {
-                if ui.button("📅 Add New Day Entry").clicked() {
-                    self.add_entry = true;
-                    self.new_entry_tittle = Local::now().format("%Y-%m-%d").to_string();
-                }
+                ui.horizontal(|ui| {
+                    if ui.button("📅 Add New Day Entry").clicked() {
+                        self.add_entry = true;
+                        self.new_entry_tittle = Local::now().format("%Y-%m-%d").to_string();
+                    }
+                    if ui.button("⬇ Export CSV").clicked() {
+                        match self.export_csv() {
+                            Ok(Some(path)) => self.export_message = format!("Saved {}", path),
+                            Ok(None) => {}
+                            Err(e) => self.export_message = format!("Export failed: {}", e),
+                        }
+                    }
+                    if !self.export_message.is_empty() {
+                        ui.label(RichText::new(&self.export_message).weak().small());
+                    }
+                });
             }
 
             ui.add_space(10.0);
@@ -486,6 +726,7 @@ impl TasksGui {
             name: self.new_task.clone(),
             description: desc,
             id: new_id,
+            schedule: Schedule::default(),
         });
 
         self.tasks_hash.insert(new_id, self.new_task.clone());
@@ -530,12 +771,49 @@ impl TasksGui {
             .map(|t| TaskCompleted::new(t.id))
             .collect();
 
-        self.json_content
-            .days
-            .insert(0, Day::new(&self.new_entry_tittle, tasks));
+        if !self.json_content.days.iter().any(|d| d.date == self.new_entry_tittle) {
+            self.json_content.insert_day(Day::new(&self.new_entry_tittle, tasks));
+        }
         self.add_entry = false;
     }
 
+    fn toggle_day(&mut self, id: u32, date: NaiveDate) {
+        self.json_content.toggle_day(id, date);
+        self.save_file = true;
+        self.update_graph = true;
+    }
+
+    /// Asks where to save the history of the days as CSV. Returns the path
+    /// written, `None` if the dialog was cancelled.
+    fn export_csv(&self) -> Result<Option<String>, String> {
+        let csv = self.json_content.to_csv();
+        let default = Path::new(&self.path).with_extension("csv");
+        #[cfg(not(target_os = "android"))]
+        let path = {
+            let mut dialog = rfd::FileDialog::new().add_filter("CSV", &["csv"]);
+            if let Some(name) = default.file_name().and_then(|n| n.to_str()) {
+                dialog = dialog.set_file_name(name);
+            }
+            if let Some(dir) = default.parent() {
+                dialog = dialog.set_directory(dir);
+            }
+            match dialog.save_file() {
+                Some(path) => path,
+                None => return Ok(None),
+            }
+        };
+        // Sin diálogo para elegir otro nombre no se sobrescribe nada
+        #[cfg(target_os = "android")]
+        let path = {
+            if default.exists() {
+                return Err(format!("{} already exists", default.display()));
+            }
+            default
+        };
+        fs::write(&path, csv).map_err(|e| e.to_string())?;
+        Ok(Some(path.to_string_lossy().to_string()))
+    }
+
     pub fn save_tasks(&self) {
         if let Ok(mut file) = fs::File::create(&self.path) {
             if let Ok(conts) = serde_json::to_string_pretty(&self.json_content) {
@@ -544,3 +822,192 @@ impl TasksGui {
         }
     }
 }
+
+/// Button with the schedule of a habit and a menu to change it. Returns
+/// true if it changed.
+fn schedule_menu(ui: &mut egui::Ui, schedule: &mut Schedule) -> bool {
+    let old = schedule.clone();
+    ui.menu_button(RichText::new(format!("🗓 {}", schedule.name())).small(), |ui| {
+        if ui.radio(*schedule == Schedule::Daily, "Every day").clicked() {
+            *schedule = Schedule::Daily;
+        }
+        let weekdays = matches!(schedule, Schedule::Weekdays { .. });
+        if ui.radio(weekdays, "Days of the week").clicked() && !weekdays {
+            *schedule = Schedule::Weekdays { days: vec![0, 1, 2, 3, 4] };
+        }
+        if let Schedule::Weekdays { days } = schedule {
+            ui.horizontal(|ui| {
+                for (i, name) in WEEKDAYS.iter().enumerate() {
+                    let i = i as u8;
+                    if ui.selectable_label(days.contains(&i), *name).clicked() {
+                        if days.contains(&i) {
+                            days.retain(|d| *d != i);
+                        } else {
+                            days.push(i);
+                            days.sort();
+                        }
+                    }
+                }
+            });
+        }
+        let weekly = matches!(schedule, Schedule::TimesPerWeek { .. });
+        if ui.radio(weekly, "Times per week").clicked() && !weekly {
+            *schedule = Schedule::TimesPerWeek { times: 3 };
+        }
+        if let Schedule::TimesPerWeek { times } = schedule {
+            ui.add(egui::DragValue::new(times).range(1..=7).suffix("× a week"));
+        }
+    });
+    *schedule != old
+}
+
+/// GitHub style grid of the last year, a column per week. Returns the day
+/// clicked, to mark it as done or not done.
+fn heatmap(ui: &mut egui::Ui, stats: &HabitStats, schedule: &Schedule, today: NaiveDate) -> Option<NaiveDate> {
+    let cell = 10.0;
+    let step = cell + 2.0;
+    let end = today + Duration::days(6 - today.weekday().num_days_from_monday() as i64);
+    let start = end - Duration::weeks(53) + Duration::days(1);
+    let (rect, response) = ui.allocate_exact_size(vec2(53.0 * step, 7.0 * step), Sense::click());
+
+    let done_color = Color32::from_rgb(64, 196, 99);
+    let missed_color = ui.visuals().widgets.inactive.bg_fill;
+    let empty_color = ui.visuals().extreme_bg_color;
+    let painter = ui.painter_at(rect);
+    let mut date = start;
+    while date <= today {
+        let offset = (date - start).num_days();
+        let pos = rect.min + vec2((offset / 7) as f32 * step, (offset % 7) as f32 * step);
+        let color = match stats.history.get(&date) {
+            Some(true) => done_color,
+            _ if stats.first.is_some_and(|first| date >= first) && schedule.is_due(date) => missed_color,
+            _ => empty_color,
+        };
+        painter.rect_filled(Rect::from_min_size(pos, vec2(cell, cell)), 2.0, color);
+        date += Duration::days(1);
+    }
+
+    let hovered = response.hover_pos().and_then(|pos| {
+        let column = ((pos.x - rect.min.x) / step) as i64;
+        let row = ((pos.y - rect.min.y) / step) as i64;
+        let date = start + Duration::days(column * 7 + row.min(6));
+        (date <= today).then_some(date)
+    });
+    let clicked = if response.clicked() { hovered } else { None };
+    if let Some(date) = hovered {
+        let status = match stats.history.get(&date) {
+            Some(true) => "done",
+            Some(false) => "not done",
+            None => "not tracked",
+        };
+        response.on_hover_text_at_pointer(format!("{}: {} (click to toggle)", date.format("%Y-%m-%d"), status));
+    }
+    clicked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(text: &str) -> NaiveDate {
+        parse_day(text).unwrap()
+    }
+
+    fn habit(id: u32, name: &str, schedule: Schedule) -> TaskToDo {
+        TaskToDo {
+            id,
+            name: name.to_string(),
+            description: None,
+            schedule,
+        }
+    }
+
+    /// A file with one habit (id 1) done on `done` and missed on `missed`.
+    fn history(schedule: Schedule, done: &[&str], missed: &[&str]) -> TasksFile {
+        let mut file = TasksFile {
+            tasks: vec![habit(1, "Run", schedule)],
+            days: vec![],
+            top_id: 1,
+        };
+        for (days, completed) in [(done, true), (missed, false)] {
+            for day in days {
+                file.insert_day(Day::new(day, vec![TaskCompleted { id: 1, completed }]));
+            }
+        }
+        file
+    }
+
+    fn streaks(file: &TasksFile, today: &str) -> (u32, u32, bool) {
+        let stats = habit_stats(file, &file.tasks[0], date(today));
+        (stats.current, stats.longest, stats.weekly)
+    }
+
+    #[test]
+    fn daily_streaks() {
+        let file = history(Schedule::Daily, &["2024-01-01", "2024-01-02", "2024-01-04", "2024-01-05"], &["2024-01-03"]);
+        assert_eq!(streaks(&file, "2024-01-05"), (2, 2, false));
+        // Hoy todavía puede hacerse
+        assert_eq!(streaks(&file, "2024-01-06"), (2, 2, false));
+        assert_eq!(streaks(&file, "2024-01-07"), (0, 2, false));
+    }
+
+    #[test]
+    fn weekday_streaks_skip_days_off() {
+        // 2024-01-01 es lunes: lunes, miércoles y viernes
+        let schedule = Schedule::Weekdays { days: vec![0, 2, 4] };
+        let file = history(schedule, &["2024-01-01", "2024-01-03", "2024-01-05", "2024-01-08"], &[]);
+        assert_eq!(streaks(&file, "2024-01-08"), (4, 4, false));
+        assert_eq!(streaks(&file, "2024-01-10"), (4, 4, false));
+        assert_eq!(streaks(&file, "2024-01-11"), (0, 4, false));
+    }
+
+    #[test]
+    fn times_per_week_streaks_count_weeks() {
+        let schedule = Schedule::TimesPerWeek { times: 2 };
+        let file = history(schedule, &["2024-01-01", "2024-01-03", "2024-01-09"], &["2024-01-02"]);
+        // La semana del 8 todavía no ha terminado
+        assert_eq!(streaks(&file, "2024-01-10"), (1, 1, true));
+        assert_eq!(streaks(&file, "2024-01-15"), (0, 1, true));
+        assert_eq!(streaks(&history(Schedule::Daily, &[], &[]), "2024-01-15"), (0, 0, false));
+    }
+
+    #[test]
+    fn toggle_day_backfills_missing_days() {
+        let mut file = TasksFile {
+            tasks: vec![habit(1, "Run", Schedule::Daily), habit(2, "Read, daily", Schedule::Daily)],
+            days: vec![
+                Day::new("2024-01-05", vec![TaskCompleted { id: 1, completed: true }, TaskCompleted { id: 2, completed: false }]),
+                Day::new("2024-01-01", vec![TaskCompleted { id: 1, completed: false }]),
+            ],
+            top_id: 2,
+        };
+        let dates = |file: &TasksFile| file.days.iter().map(|d| d.date.clone()).collect::<Vec<_>>();
+        let done = |file: &TasksFile, day: &str, id: u32| {
+            let day = file.days.iter().find(|d| d.date == day).unwrap();
+            day.tasks.iter().find(|t| t.id == id).map(|t| t.completed)
+        };
+
+        file.toggle_day(2, date("2024-01-03"));
+        assert_eq!(dates(&file), ["2024-01-05", "2024-01-03", "2024-01-01"]);
+        assert_eq!((done(&file, "2024-01-03", 1), done(&file, "2024-01-03", 2)), (Some(false), Some(true)));
+
+        file.toggle_day(2, date("2024-01-03"));
+        assert_eq!(done(&file, "2024-01-03", 2), Some(false));
+
+        file.toggle_day(1, date("2024-01-10"));
+        assert_eq!(dates(&file)[0], "2024-01-10");
+
+        // Un hábito creado después del día se añade a ese día
+        file.toggle_day(2, date("2024-01-01"));
+        assert_eq!(done(&file, "2024-01-01", 2), Some(true));
+
+        assert_eq!(
+            file.to_csv(),
+            "date,Run,\"Read, daily\",notes\n\
+             2024-01-01,0,1,\n\
+             2024-01-03,0,0,\n\
+             2024-01-05,1,0,\n\
+             2024-01-10,1,0,\n"
+        );
+    }
+}
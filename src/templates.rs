@@ -0,0 +1,449 @@
+use chrono::prelude::*;
+use regex::{Captures, Regex};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::OnceLock;
+use walkdir::WalkDir;
+use yaml_rust::{Yaml, YamlEmitter, YamlLoader};
+
+use crate::periodic::moment_to_chrono;
+use crate::vault_index::split_frontmatter;
+
+// Misma configuración que el plugin "Templates" de Obsidian (formatos de moment.js)
+const SETTINGS_FILE: &str = ".obsidian/templates.json";
+
+#[derive(Clone, PartialEq)]
+pub struct TemplateSettings {
+    pub folder: String,
+    pub date_format: String,
+    pub time_format: String,
+}
+
+impl Default for TemplateSettings {
+    fn default() -> Self {
+        Self {
+            folder: "Templates".to_string(),
+            date_format: "YYYY-MM-DD".to_string(),
+            time_format: "HH:mm".to_string(),
+        }
+    }
+}
+
+impl TemplateSettings {
+    pub fn load(vault: &str) -> Self {
+        let mut settings = Self::default();
+        let Ok(data) = fs::read_to_string(Path::new(vault).join(SETTINGS_FILE)) else {
+            return settings;
+        };
+        let Ok(json) = serde_json::from_str::<serde_json::Value>(&data) else {
+            return settings;
+        };
+        let get = |key: &str| json.get(key).and_then(|v| v.as_str()).filter(|v| !v.is_empty());
+        if let Some(folder) = get("folder") {
+            settings.folder = folder.trim_matches('/').to_string();
+        }
+        if let Some(format) = get("dateFormat") {
+            settings.date_format = format.to_string();
+        }
+        if let Some(format) = get("timeFormat") {
+            settings.time_format = format.to_string();
+        }
+        settings
+    }
+
+    pub fn save(&self, vault: &str) {
+        let path = Path::new(vault).join(SETTINGS_FILE);
+        // Se conservan las demás claves que Obsidian pueda haber guardado
+        let mut json: serde_json::Value = fs::read_to_string(&path)
+            .ok()
+            .and_then(|d| serde_json::from_str(&d).ok())
+            .unwrap_or_else(|| serde_json::json!({}));
+        json["folder"] = serde_json::Value::String(self.folder.clone());
+        json["dateFormat"] = serde_json::Value::String(self.date_format.clone());
+        json["timeFormat"] = serde_json::Value::String(self.time_format.clone());
+        if let Some(parent) = path.parent() {
+            let _ = fs::create_dir_all(parent);
+        }
+        match serde_json::to_string_pretty(&json) {
+            Ok(data) => {
+                if let Err(e) = fs::write(&path, data) {
+                    println!("Error guardando la configuración de plantillas: {}", e);
+                }
+            }
+            Err(e) => println!("Error guardando la configuración de plantillas: {}", e),
+        }
+    }
+
+    /// Templates whose file name ends with one of `extensions`, sorted by
+    /// their path relative to the templates folder.
+    pub fn list(&self, vault: &str, extensions: &[&str]) -> Vec<(String, String)> {
+        let folder = Path::new(vault).join(&self.folder);
+        let mut templates: Vec<(String, String)> = WalkDir::new(&folder)
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file())
+            .filter_map(|e| {
+                let path = e.path().to_str()?.to_string();
+                let lower = path.to_lowercase();
+                if !extensions.iter().any(|ext| lower.ends_with(ext)) {
+                    return None;
+                }
+                let name = e.path().strip_prefix(&folder).ok()?.to_str()?.to_string();
+                Some((name, path))
+            })
+            .collect();
+        templates.sort();
+        templates
+    }
+}
+
+/// Values available to `{{...}}` variables.
+pub struct TemplateVars {
+    pub title: String,
+    pub date: NaiveDate,
+    pub prompts: HashMap<String, String>,
+}
+
+/// A rendered template. `cursor` is the char index of `{{cursor}}`, if any.
+#[derive(Clone, Default)]
+pub struct Rendered {
+    pub text: String,
+    pub cursor: Option<usize>,
+}
+
+fn variable_regex() -> &'static Regex {
+    static VARIABLE: OnceLock<Regex> = OnceLock::new();
+    VARIABLE.get_or_init(|| Regex::new(r"\{\{\s*(\w+)(?::([^}]*))?\s*\}\}").unwrap())
+}
+
+fn chrono_format(format: &str) -> Option<String> {
+    if format.contains('%') {
+        Some(format.to_string())
+    } else {
+        moment_to_chrono(format)
+    }
+}
+
+/// Custom fields asked before rendering: `{{prompt:Label}}` or
+/// `{{prompt:Label:default value}}`.
+pub fn prompts(content: &str) -> Vec<(String, String)> {
+    let mut out: Vec<(String, String)> = vec![];
+    for caps in variable_regex().captures_iter(content) {
+        if &caps[1] != "prompt" {
+            continue;
+        }
+        let arg = caps.get(2).map_or("", |m| m.as_str());
+        let (label, default) = arg.split_once(':').unwrap_or((arg, ""));
+        let label = label.trim().to_string();
+        if !label.is_empty() && !out.iter().any(|(l, _)| *l == label) {
+            out.push((label, default.trim().to_string()));
+        }
+    }
+    out
+}
+
+/// Replaces `{{title}}`, `{{date}}`, `{{date:FORMAT}}`, `{{time}}`,
+/// `{{time:FORMAT}}`, `{{prompt:...}}` and `{{cursor}}`. Formats can use
+/// chrono (`%Y-%m-%d`) or moment.js (`YYYY-MM-DD`) syntax.
+pub fn render(content: &str, settings: &TemplateSettings, vars: &TemplateVars) -> Rendered {
+    let now = Local::now();
+    let date = vars.date.and_time(now.time());
+    let format = |value: NaiveDateTime, format: &str| {
+        use std::fmt::Write;
+        let Some(chrono) = chrono_format(format) else {
+            return format.to_string();
+        };
+        let mut out = String::new();
+        match write!(out, "{}", value.format(&chrono)) {
+            Ok(_) => out,
+            Err(_) => format.to_string(),
+        }
+    };
+
+    let marker = "\u{0}cursor\u{0}";
+    let text = variable_regex().replace_all(content, |caps: &Captures| {
+        let arg = caps.get(2).map(|m| m.as_str().trim());
+        match &caps[1] {
+            "title" => vars.title.clone(),
+            "date" => format(date, arg.unwrap_or(&settings.date_format)),
+            "time" => format(date, arg.unwrap_or(&settings.time_format)),
+            "cursor" => marker.to_string(),
+            "prompt" => {
+                let arg = arg.unwrap_or("");
+                let (label, default) = arg.split_once(':').unwrap_or((arg, ""));
+                vars.prompts
+                    .get(label.trim())
+                    .cloned()
+                    .unwrap_or_else(|| default.trim().to_string())
+            }
+            _ => caps[0].to_string(),
+        }
+    });
+
+    match text.find(marker) {
+        Some(byte) => Rendered {
+            cursor: Some(text[..byte].chars().count()),
+            text: text.replace(marker, ""),
+        },
+        None => Rendered {
+            text: text.to_string(),
+            cursor: None,
+        },
+    }
+}
+
+/// Adds the keys of the template frontmatter missing in the note; lists
+/// like `tags` or `aliases` get the values they don't have yet.
+pub fn merge_frontmatter(note: &str, template: &str) -> String {
+    let parse = |s: &str| {
+        YamlLoader::load_from_str(s)
+            .ok()
+            .and_then(|docs| docs.into_iter().next())
+    };
+    let note_yaml = if note.trim().is_empty() {
+        Some(Yaml::Hash(Default::default()))
+    } else {
+        parse(note)
+    };
+    let (Some(Yaml::Hash(mut merged)), Some(Yaml::Hash(extra))) = (note_yaml, parse(template)) else {
+        // Si alguno no es un mapa válido se conserva el de la nota
+        return note.to_string();
+    };
+
+    for (key, value) in extra {
+        if !merged.contains_key(&key) {
+            merged.insert(key, value);
+            continue;
+        }
+        if let (Some(Yaml::Array(existing)), Yaml::Array(values)) = (merged.get_mut(&key), value) {
+            for v in values {
+                if !existing.contains(&v) {
+                    existing.push(v);
+                }
+            }
+        }
+    }
+    if merged.is_empty() {
+        return String::new();
+    }
+
+    let mut out = String::new();
+    if YamlEmitter::new(&mut out).dump(&Yaml::Hash(merged)).is_err() {
+        return note.to_string();
+    }
+    let out = out.strip_prefix("---").unwrap_or(&out).trim_start_matches('\n');
+    format!("{}\n", out.trim_end())
+}
+
+/// Inserts a rendered template into `code` at the char index `at`, merging
+/// its frontmatter with the note's one. Returns the new text and the char
+/// index where the cursor should go.
+pub fn insert_into(code: &str, at: usize, rendered: &Rendered) -> (String, usize) {
+    let (template_fm, template_body) = split_frontmatter(&rendered.text);
+    let fm_chars = rendered.text.chars().count() - template_body.chars().count();
+
+    let (mut code, mut at) = (code.to_string(), at);
+    if let Some(template_fm) = template_fm {
+        let (note_fm, note_body) = split_frontmatter(&code);
+        let old_head = code.chars().count() - note_body.chars().count();
+        let head = format!("---\n{}---\n", merge_frontmatter(note_fm.unwrap_or(""), template_fm));
+        let head_chars = head.chars().count();
+        at = if at >= old_head { at - old_head + head_chars } else { head_chars };
+        code = format!("{}{}", head, note_body);
+    }
+
+    let byte = code.char_indices().nth(at).map(|(i, _)| i).unwrap_or(code.len());
+    code.insert_str(byte, template_body);
+    let cursor = match rendered.cursor {
+        Some(c) if c >= fm_chars => at + c - fm_chars,
+        _ => at + template_body.chars().count(),
+    };
+    (code, cursor)
+}
+
+/// Text fields for the `{{prompt:...}}` values of a template.
+pub fn prompt_fields(ui: &mut egui::Ui, values: &mut [(String, String)]) {
+    if values.is_empty() {
+        return;
+    }
+    egui::Grid::new("template_prompts").num_columns(2).show(ui, |ui| {
+        for (label, value) in values.iter_mut() {
+            ui.label(format!("{}:", label));
+            ui.text_edit_singleline(value);
+            ui.end_row();
+        }
+    });
+}
+
+/// Picker opened by the "Insert template" command.
+#[derive(Default)]
+pub struct TemplatePicker {
+    pub is_open: bool,
+    target: String,
+    query: String,
+    templates: Vec<(String, String)>,
+    selected: Option<(String, String)>,
+    values: Vec<(String, String)>,
+}
+
+impl TemplatePicker {
+    pub fn open(&mut self, vault: &str, target: &str) {
+        self.is_open = true;
+        self.target = target.to_string();
+        self.query.clear();
+        self.selected = None;
+        self.values.clear();
+        self.templates = TemplateSettings::load(vault).list(vault, &[".md"]);
+    }
+
+    /// Returns the note to insert into and the rendered template.
+    pub fn ui(&mut self, ctx: &egui::Context, vault: &str) -> Option<(String, Rendered)> {
+        if !self.is_open {
+            return None;
+        }
+        let mut result = None;
+        let mut close = false;
+
+        egui::Window::new("Insert template")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_TOP, [0.0, 100.0])
+            .show(ctx, |ui| {
+                if self.target.is_empty() || !self.target.ends_with(".md") {
+                    ui.label("Open a note to insert a template");
+                } else if let Some((name, path)) = self.selected.clone() {
+                    ui.label(egui::RichText::new(&name).strong());
+                    prompt_fields(ui, &mut self.values);
+                    if ui.button("Insert").clicked()
+                        || ui.input(|i| i.key_pressed(egui::Key::Enter))
+                    {
+                        let settings = TemplateSettings::load(vault);
+                        let content = fs::read_to_string(&path).unwrap_or_default();
+                        let title = Path::new(&self.target)
+                            .file_stem()
+                            .and_then(|s| s.to_str())
+                            .unwrap_or("")
+                            .to_string();
+                        let vars = TemplateVars {
+                            title,
+                            date: Local::now().date_naive(),
+                            prompts: self.values.iter().cloned().collect(),
+                        };
+                        result = Some((self.target.clone(), render(&content, &settings, &vars)));
+                        close = true;
+                    }
+                } else {
+                    let response = ui.add(
+                        egui::TextEdit::singleline(&mut self.query).hint_text("Search templates..."),
+                    );
+                    response.request_focus();
+                    let query = self.query.to_lowercase();
+                    let mut choose = None;
+                    egui::ScrollArea::vertical().max_height(250.0).show(ui, |ui| {
+                        let matching = self
+                            .templates
+                            .iter()
+                            .filter(|(name, _)| name.to_lowercase().contains(&query));
+                        for (i, template) in matching.enumerate() {
+                            let clicked = ui.selectable_label(false, &template.0).clicked();
+                            let enter = i == 0 && ui.input(|inp| inp.key_pressed(egui::Key::Enter));
+                            if clicked || enter {
+                                choose = Some(template.clone());
+                            }
+                        }
+                        if self.templates.is_empty() {
+                            ui.weak("No templates found in the templates folder");
+                        }
+                    });
+                    if let Some((name, path)) = choose {
+                        let content = fs::read_to_string(&path).unwrap_or_default();
+                        self.values = prompts(&content);
+                        self.selected = Some((name, path));
+                    }
+                }
+                if ui.input(|i| i.key_pressed(egui::Key::Escape)) || ui.button("Cancel").clicked() {
+                    close = true;
+                }
+            });
+
+        if close {
+            self.is_open = false;
+        }
+        result
+    }
+}
+
+/// Templates folder and formats, shown in the configuration screen.
+pub fn settings_ui(ui: &mut egui::Ui, vault: &str) {
+    let id = egui::Id::new("template_settings").with(vault);
+    let mut settings: TemplateSettings = ui
+        .ctx()
+        .data(|d| d.get_temp(id))
+        .unwrap_or_else(|| TemplateSettings::load(vault));
+    let old = settings.clone();
+
+    egui::CollapsingHeader::new(egui::RichText::new("Templates").strong()).show(ui, |ui| {
+        egui::Grid::new("template_settings").num_columns(2).show(ui, |ui| {
+            ui.label("Folder:");
+            ui.text_edit_singleline(&mut settings.folder);
+            ui.end_row();
+            ui.label("Date format:");
+            ui.text_edit_singleline(&mut settings.date_format)
+                .on_hover_text("Used by {{date}}, e.g. YYYY-MM-DD or %Y-%m-%d");
+            ui.end_row();
+            ui.label("Time format:");
+            ui.text_edit_singleline(&mut settings.time_format)
+                .on_hover_text("Used by {{time}}, e.g. HH:mm or %H:%M");
+            ui.end_row();
+        });
+        ui.weak("Variables: {{title}} {{date}} {{date:FORMAT}} {{time}} {{prompt:Field}} {{cursor}}");
+    });
+
+    if settings != old && !vault.is_empty() {
+        settings.save(vault);
+    }
+    ui.ctx().data_mut(|d| d.insert_temp(id, settings));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars() -> TemplateVars {
+        TemplateVars {
+            title: "Note".to_string(),
+            date: NaiveDate::from_ymd_opt(2024, 5, 2).unwrap(),
+            prompts: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn default_time_format_renders_hours_and_minutes() {
+        let text = render("{{time}}", &TemplateSettings::default(), &vars()).text;
+        assert!(Regex::new(r"^\d{2}:\d{2}$").unwrap().is_match(&text), "{}", text);
+    }
+
+    #[test]
+    fn moment_time_tokens() {
+        assert_eq!(moment_to_chrono("HH:mm:ss A").as_deref(), Some("%H:%M:%S %p"));
+        assert_eq!(moment_to_chrono("H:mm").as_deref(), Some("%-H:%M"));
+        assert_eq!(moment_to_chrono("YYYY-MM-DD").as_deref(), Some("%Y-%m-%d"));
+    }
+
+    #[test]
+    fn moment_literals_and_ordinals() {
+        assert_eq!(moment_to_chrono("[Week] WW [100%]").as_deref(), Some("Week %V 100%%"));
+        assert_eq!(moment_to_chrono("[Do] YYYY").as_deref(), Some("Do %Y"));
+        // chrono no tiene ordinales: el formato se deja sin traducir
+        assert_eq!(moment_to_chrono("MMMM Do"), None);
+        let text = render("{{date:MMMM Do}}", &TemplateSettings::default(), &vars()).text;
+        assert_eq!(text, "MMMM Do");
+    }
+
+    #[test]
+    fn date_with_format() {
+        let text = render("{{title}} {{date:YYYY-MM-DD}}", &TemplateSettings::default(), &vars()).text;
+        assert_eq!(text, "Note 2024-05-02");
+    }
+}
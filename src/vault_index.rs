@@ -0,0 +1,451 @@
+use regex::Regex;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, MutexGuard, OnceLock};
+use std::time::UNIX_EPOCH;
+use walkdir::WalkDir;
+use yaml_rust::{Yaml, YamlLoader};
+
+use crate::graph::logic::extract_links;
+use crate::tags;
+
+// Notas más grandes que esto solo se indexan por nombre
+const MAX_NOTE_SIZE: u64 = 2 * 1024 * 1024;
+
+/// Metadata of a markdown note.
+#[derive(Default, Clone, Debug)]
+pub struct NoteMeta {
+    /// Raw YAML between the `---` lines, without them.
+    pub frontmatter: String,
+    pub aliases: Vec<String>,
+    /// Tags listed in the frontmatter, without `#`.
+    pub frontmatter_tags: Vec<String>,
+    /// `#tags` written in the body, without `#`.
+    pub inline_tags: Vec<String>,
+    /// (level, text)
+    pub headings: Vec<(usize, String)>,
+    /// `^block-id`s, without `^`.
+    pub block_ids: Vec<String>,
+    /// Targets of the wikilinks, as written.
+    pub links: Vec<String>,
+}
+
+impl NoteMeta {
+    /// Frontmatter and inline tags, without duplicates.
+    pub fn tags(&self) -> Vec<String> {
+        let mut tags = self.frontmatter_tags.clone();
+        for tag in &self.inline_tags {
+            if !tags.contains(tag) {
+                tags.push(tag.clone());
+            }
+        }
+        tags
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct FileEntry {
+    pub path: String,
+    /// Path relative to the vault, without the leading `/`.
+    pub rel_path: String,
+    /// File name without the extension (`note` for `note.md`).
+    pub name: String,
+    pub modified: u64,
+    /// `None` for attachments and other non markdown files.
+    pub note: Option<NoteMeta>,
+}
+
+/// In-memory index of every (non hidden) file of a vault.
+#[derive(Default)]
+pub struct VaultIndex {
+    vault: String,
+    files: BTreeMap<String, FileEntry>,
+    // nombre de archivo en minúsculas -> rutas
+    by_file_name: HashMap<String, Vec<String>>,
+}
+
+// Cambia cada vez que el índice se actualiza
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// Counter bumped on every change of the index, so views built from it
+/// know when to rebuild without polling the disk.
+pub fn generation() -> u64 {
+    GENERATION.load(Ordering::Relaxed)
+}
+
+fn cache() -> MutexGuard<'static, VaultIndex> {
+    static INDEX: OnceLock<Mutex<VaultIndex>> = OnceLock::new();
+    INDEX
+        .get_or_init(|| Mutex::new(VaultIndex::default()))
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+}
+
+/// Runs `f` with the index of `vault`, building it the first time.
+pub fn with_index<R>(vault: &str, f: impl FnOnce(&VaultIndex) -> R) -> R {
+    let mut index = cache();
+    if index.vault != vault {
+        *index = VaultIndex::build(vault);
+        GENERATION.fetch_add(1, Ordering::Relaxed);
+    }
+    f(&index)
+}
+
+/// Rescans the vault, reparsing only the files modified since the last scan.
+pub fn refresh(vault: &str) {
+    let mut index = cache();
+    if index.vault != vault {
+        *index = VaultIndex::build(vault);
+    } else {
+        index.refresh();
+    }
+    GENERATION.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Reindexes (or forgets, if it no longer exists) a single file.
+pub fn update_file(vault: &str, path: &str) {
+    let mut index = cache();
+    if index.vault == vault {
+        index.update_file(path);
+        GENERATION.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+impl VaultIndex {
+    fn build(vault: &str) -> Self {
+        let mut index = VaultIndex {
+            vault: vault.to_string(),
+            ..Default::default()
+        };
+        index.refresh();
+        index
+    }
+
+    pub fn vault(&self) -> &str {
+        &self.vault
+    }
+
+    pub fn files(&self) -> impl Iterator<Item = &FileEntry> {
+        self.files.values()
+    }
+
+    /// Markdown notes with their metadata.
+    pub fn notes(&self) -> impl Iterator<Item = (&FileEntry, &NoteMeta)> {
+        self.files
+            .values()
+            .filter_map(|f| f.note.as_ref().map(|n| (f, n)))
+    }
+
+    pub fn get(&self, path: &str) -> Option<&FileEntry> {
+        self.files.get(path)
+    }
+
+    /// Files named exactly `name` or `name.md`.
+    pub fn find_by_name(&self, name: &str) -> Option<String> {
+        let exact = format!("{}.md", name);
+        [name, exact.as_str()].iter().find_map(|target| {
+            self.by_file_name
+                .get(&target.to_lowercase())?
+                .iter()
+                .find(|p| Path::new(p).file_name().and_then(|n| n.to_str()) == Some(*target))
+                .cloned()
+        })
+    }
+
+    /// Resolves a link target the way Obsidian does: vault absolute path,
+    /// path relative to `current_file`, then any file with that name
+    /// (case-insensitive, `.md` and `.excalidraw.md` optional).
+    pub fn resolve(&self, current_file: &str, target: &str) -> Option<String> {
+        resolve_link(&self.vault, current_file, target, &|p| p.is_file(), &|name| {
+            self.by_file_name.get(name).cloned().unwrap_or_default()
+        })
+    }
+
+    /// Note whose name or one of its aliases is `name` (case-insensitive).
+    pub fn note_by_alias(&self, name: &str) -> Option<&FileEntry> {
+        let name = name.to_lowercase();
+        self.notes()
+            .find(|(_, n)| n.aliases.iter().any(|a| a.to_lowercase() == name))
+            .map(|(f, _)| f)
+    }
+
+    fn refresh(&mut self) {
+        if self.vault.is_empty() || !Path::new(&self.vault).exists() {
+            return;
+        }
+        let mut seen: HashMap<String, u64> = HashMap::new();
+        let vault = self.vault.clone();
+        for entry in WalkDir::new(&vault)
+            .into_iter()
+            .filter_entry(|e| e.depth() == 0 || !is_hidden_name(e.file_name().to_str()))
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file())
+        {
+            if let Some(path) = entry.path().to_str() {
+                seen.insert(path.to_string(), modified_time(entry.path()));
+            }
+        }
+
+        let removed: Vec<String> = self
+            .files
+            .keys()
+            .filter(|p| !seen.contains_key(*p))
+            .cloned()
+            .collect();
+        for path in removed {
+            self.remove(&path);
+        }
+        for (path, modified) in seen {
+            let up_to_date = self.files.get(&path).map(|f| f.modified == modified).unwrap_or(false);
+            if !up_to_date {
+                self.insert(&path, modified);
+            }
+        }
+    }
+
+    fn update_file(&mut self, path: &str) {
+        if !path.starts_with(&self.vault) {
+            return;
+        }
+        let file = Path::new(path);
+        if file.is_dir() {
+            // Carpeta movida o renombrada: más simple volver a recorrer
+            self.refresh();
+        } else if file.is_file() && !is_hidden_path(path, &self.vault) {
+            self.insert(path, modified_time(file));
+        } else {
+            self.remove(path);
+            // Si era una carpeta borrada se quitan también sus archivos
+            let prefix = format!("{}/", path.trim_end_matches('/'));
+            let inside: Vec<String> = self.files.keys().filter(|p| p.starts_with(&prefix)).cloned().collect();
+            for p in inside {
+                self.remove(&p);
+            }
+        }
+    }
+
+    fn insert(&mut self, path: &str, modified: u64) {
+        self.remove(path);
+        let file = Path::new(path);
+        let file_name = file.file_name().and_then(|n| n.to_str()).unwrap_or("").to_string();
+        let is_markdown = file_name.to_lowercase().ends_with(".md");
+        let note = if is_markdown {
+            let small = fs::metadata(file).map(|m| m.len() <= MAX_NOTE_SIZE).unwrap_or(false);
+            let content = if small { fs::read_to_string(file).unwrap_or_default() } else { String::new() };
+            Some(parse_note(&content))
+        } else {
+            None
+        };
+        let rel_path = path.strip_prefix(&self.vault).unwrap_or(path).trim_start_matches('/').to_string();
+        let entry = FileEntry {
+            path: path.to_string(),
+            rel_path,
+            name: file.file_stem().and_then(|s| s.to_str()).unwrap_or("").to_string(),
+            modified,
+            note,
+        };
+        self.by_file_name
+            .entry(file_name.to_lowercase())
+            .or_default()
+            .push(path.to_string());
+        self.files.insert(path.to_string(), entry);
+    }
+
+    fn remove(&mut self, path: &str) {
+        if self.files.remove(path).is_none() {
+            return;
+        }
+        let name = Path::new(path)
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("")
+            .to_lowercase();
+        if let Some(paths) = self.by_file_name.get_mut(&name) {
+            paths.retain(|p| p != path);
+            if paths.is_empty() {
+                self.by_file_name.remove(&name);
+            }
+        }
+    }
+}
+
+/// [`VaultIndex::resolve`] over any set of files, such as the vault as it
+/// was before a rename: `is_file` says whether a path is one of them and
+/// `named` lists the ones with a (lowercase) file name.
+pub fn resolve_link(
+    vault: &str,
+    current_file: &str,
+    target: &str,
+    is_file: &dyn Fn(&Path) -> bool,
+    named: &dyn Fn(&str) -> Vec<String>,
+) -> Option<String> {
+    let clean_target = target.trim();
+    if clean_target.is_empty() {
+        return None;
+    }
+
+    if clean_target.starts_with('/') {
+        let p = format!("{}{}", vault, clean_target);
+        if is_file(Path::new(&p)) {
+            return Some(p);
+        }
+    }
+
+    if let Some(current_dir) = Path::new(current_file).parent() {
+        let joined = current_dir.join(clean_target);
+        if is_file(&joined) {
+            return Some(joined.to_string_lossy().to_string());
+        }
+        for ext in &[".md", ".excalidraw.md"] {
+            if !clean_target.to_lowercase().ends_with(ext) {
+                let joined_ext = current_dir.join(format!("{}{}", clean_target, ext));
+                if is_file(&joined_ext) {
+                    return Some(joined_ext.to_string_lossy().to_string());
+                }
+            }
+        }
+    }
+
+    let lower = clean_target.to_lowercase();
+    let name = Path::new(&lower)
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or(&lower)
+        .to_string();
+    let mut candidates = vec![name.clone()];
+    for ext in [".md", ".excalidraw.md"] {
+        if !name.ends_with(ext) {
+            candidates.push(format!("{}{}", name, ext));
+        }
+    }
+    // Con carpetas en el enlace solo vale un archivo cuya ruta acabe en ellas,
+    // carpeta a carpeta: `foo/note` no es `xfoo/note`
+    let folders = Path::new(&lower)
+        .parent()
+        .and_then(|p| p.to_str())
+        .map(|p| p.trim_start_matches('/'))
+        .filter(|p| !p.is_empty());
+    candidates.iter().find_map(|c| {
+        let paths = named(c);
+        match folders {
+            Some(folders) => {
+                let suffix = format!("/{}/{}", folders, c);
+                paths.iter().find(|p| p.to_lowercase().ends_with(&suffix)).cloned()
+            }
+            None => paths.first().cloned(),
+        }
+    })
+}
+
+/// Extracts the metadata of a note from its content.
+pub fn parse_note(content: &str) -> NoteMeta {
+    static BLOCK_ID: OnceLock<Regex> = OnceLock::new();
+    let block_id = BLOCK_ID.get_or_init(|| Regex::new(r"(?:^|\s)\^([A-Za-z0-9-]+)\s*$").unwrap());
+
+    let mut meta = NoteMeta::default();
+    let (frontmatter, body) = split_frontmatter(content);
+    if let Some(frontmatter) = frontmatter {
+        meta.frontmatter = frontmatter.to_string();
+        if let Some(doc) = YamlLoader::load_from_str(frontmatter).ok().and_then(|d| d.into_iter().next()) {
+            meta.aliases = yaml_list(&doc, &["aliases", "alias"]);
+            meta.frontmatter_tags = yaml_list(&doc, &["tags", "Tags", "tag"])
+                .into_iter()
+                .map(|t| t.trim_start_matches('#').to_string())
+                .collect();
+        }
+    }
+
+    let mut in_code = false;
+    for line in body.lines() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_code = !in_code;
+            continue;
+        }
+        if in_code {
+            continue;
+        }
+        let level = trimmed.chars().take_while(|c| *c == '#').count();
+        // En los títulos los tags se buscan después de los "#" del nivel
+        let mut text = line;
+        if (1..=6).contains(&level) && trimmed[level..].starts_with(' ') {
+            meta.headings.push((level, trimmed[level..].trim().to_string()));
+            text = &trimmed[level..];
+        } else if let Some(caps) = block_id.captures(line) {
+            meta.block_ids.push(caps[1].to_string());
+        }
+        for caps in tags::inline_tags(text) {
+            let name = caps[2].trim_end_matches('/').to_string();
+            if !meta.inline_tags.contains(&name) {
+                meta.inline_tags.push(name);
+            }
+        }
+    }
+    meta.links = extract_links(body);
+    meta
+}
+
+/// Splits the YAML between the `---` lines (without them) from the body.
+pub fn split_frontmatter(content: &str) -> (Option<&str>, &str) {
+    let Some(rest) = content.strip_prefix("---\n").or_else(|| content.strip_prefix("---\r\n")) else {
+        return (None, content);
+    };
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        if line.trim_end() == "---" {
+            return (Some(&rest[..offset]), &rest[offset + line.len()..]);
+        }
+        offset += line.len();
+    }
+    (None, content)
+}
+
+// Acepta listas YAML y también "a, b" o "a b" como texto
+fn yaml_list(doc: &Yaml, keys: &[&str]) -> Vec<String> {
+    for key in keys {
+        match &doc[*key] {
+            Yaml::Array(items) => {
+                return items
+                    .iter()
+                    .filter_map(|i| match i {
+                        Yaml::String(s) => Some(s.clone()),
+                        Yaml::Integer(n) => Some(n.to_string()),
+                        _ => None,
+                    })
+                    .collect();
+            }
+            Yaml::String(s) => {
+                return s
+                    .split([',', ' '])
+                    .map(|s| s.trim())
+                    .filter(|s| !s.is_empty())
+                    .map(|s| s.to_string())
+                    .collect();
+            }
+            _ => {}
+        }
+    }
+    vec![]
+}
+
+fn is_hidden_name(name: Option<&str>) -> bool {
+    name.map(|n| n.starts_with('.')).unwrap_or(false)
+}
+
+fn is_hidden_path(path: &str, vault: &str) -> bool {
+    Path::new(path)
+        .strip_prefix(vault)
+        .map(|rel| rel.components().any(|c| is_hidden_name(c.as_os_str().to_str())))
+        .unwrap_or(true)
+}
+
+fn modified_time(path: &Path) -> u64 {
+    fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
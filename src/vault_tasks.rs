@@ -0,0 +1,990 @@
+//! Checkbox tasks (`- [ ]`) of every note of the vault, with the fields of
+//! the Obsidian Tasks format: 📅 due, ⏳ scheduled, 🛫 start, ✅ done,
+//! 🔁 recurrence and the priority emojis.
+
+use chrono::{Datelike, Duration, Local, Months, NaiveDate, Weekday};
+use egui::collapsing_header::CollapsingState;
+use egui::{Color32, RichText, ScrollArea};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+
+use crate::tags;
+use crate::vault_index;
+
+// Segundos entre comprobaciones de notas modificadas
+const REFRESH_SECS: f64 = 2.0;
+
+// Emojis que abren un campo: lo que va detrás de 🔁 acaba en el siguiente
+const SIGNIFIERS: [&str; 11] = ["📅", "⏳", "🛫", "✅", "➕", "❌", "🔁", "🔺", "⏫", "🔼", "🔽"];
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Priority {
+    Highest,
+    High,
+    Medium,
+    #[default]
+    None,
+    Low,
+    Lowest,
+}
+
+impl Priority {
+    pub const ALL: [Priority; 6] = [
+        Priority::Highest,
+        Priority::High,
+        Priority::Medium,
+        Priority::None,
+        Priority::Low,
+        Priority::Lowest,
+    ];
+
+    pub fn emoji(&self) -> &'static str {
+        match self {
+            Priority::Highest => "🔺",
+            Priority::High => "⏫",
+            Priority::Medium => "🔼",
+            Priority::None => "",
+            Priority::Low => "🔽",
+            Priority::Lowest => "⏬",
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Priority::Highest => "Highest",
+            Priority::High => "High",
+            Priority::Medium => "Medium",
+            Priority::None => "Normal",
+            Priority::Low => "Low",
+            Priority::Lowest => "Lowest",
+        }
+    }
+}
+
+/// A checkbox line of a note.
+#[derive(Clone, Debug, Default)]
+pub struct VaultTask {
+    pub path: String,
+    pub rel_path: String,
+    pub note: String,
+    /// Line of the task in the note, starting at 0.
+    pub line: usize,
+    /// Character between the brackets: ` `, `x`, `-`, `/`...
+    pub status: char,
+    /// Text after the checkbox, as written.
+    pub text: String,
+    /// Text without the dates, recurrence, priority and block id.
+    pub description: String,
+    pub due: Option<NaiveDate>,
+    pub scheduled: Option<NaiveDate>,
+    pub start: Option<NaiveDate>,
+    pub done: Option<NaiveDate>,
+    pub recurrence: Option<String>,
+    pub priority: Priority,
+    pub tags: Vec<String>,
+    /// Heading the task is under.
+    pub heading: Option<String>,
+}
+
+impl VaultTask {
+    /// Done (`x`) or cancelled (`-`).
+    pub fn completed(&self) -> bool {
+        matches!(self.status, 'x' | 'X' | '-')
+    }
+
+    /// Date the task is planned for: due, else scheduled, else start.
+    pub fn date(&self) -> Option<NaiveDate> {
+        self.due.or(self.scheduled).or(self.start)
+    }
+}
+
+fn task_regex() -> &'static Regex {
+    static TASK: OnceLock<Regex> = OnceLock::new();
+    TASK.get_or_init(|| Regex::new(r"^(\s*(?:[-*+]|\d+[.)]) \[)(.)(\] ?)(.*)$").unwrap())
+}
+
+fn date_regex() -> &'static Regex {
+    static DATE: OnceLock<Regex> = OnceLock::new();
+    DATE.get_or_init(|| Regex::new(r"(📅|⏳|🛫|✅|➕|❌)️?\s*(\d{4}-\d{2}-\d{2})").unwrap())
+}
+
+fn done_regex() -> &'static Regex {
+    static DONE: OnceLock<Regex> = OnceLock::new();
+    DONE.get_or_init(|| Regex::new(r"\s*(✅|❌)️?\s*\d{4}-\d{2}-\d{2}").unwrap())
+}
+
+fn block_id_regex() -> &'static Regex {
+    static BLOCK_ID: OnceLock<Regex> = OnceLock::new();
+    BLOCK_ID.get_or_init(|| Regex::new(r"\s\^[\w-]+\s*$").unwrap())
+}
+
+fn inline_field_regex() -> &'static Regex {
+    static FIELD: OnceLock<Regex> = OnceLock::new();
+    FIELD.get_or_init(|| Regex::new(r"\[(due|scheduled|start|completion|repeat)::\s*([^\]]*)\]").unwrap())
+}
+
+fn parse_date(text: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(text.trim(), "%Y-%m-%d").ok()
+}
+
+/// Fields of the text after the checkbox.
+fn parse_body(body: &str) -> VaultTask {
+    let mut task = VaultTask {
+        text: body.to_string(),
+        ..Default::default()
+    };
+    let mut description = body.to_string();
+
+    for caps in date_regex().captures_iter(body) {
+        let date = parse_date(&caps[2]);
+        match &caps[1] {
+            "📅" => task.due = date,
+            "⏳" => task.scheduled = date,
+            "🛫" => task.start = date,
+            "✅" => task.done = date,
+            _ => {}
+        }
+    }
+    description = date_regex().replace_all(&description, "").to_string();
+
+    // Formato de Dataview: [due:: 2024-01-31]
+    for caps in inline_field_regex().captures_iter(body) {
+        let value = caps[2].trim();
+        match &caps[1] {
+            "due" => task.due = task.due.or(parse_date(value)),
+            "scheduled" => task.scheduled = task.scheduled.or(parse_date(value)),
+            "start" => task.start = task.start.or(parse_date(value)),
+            "completion" => task.done = task.done.or(parse_date(value)),
+            _ => task.recurrence = Some(value.to_string()),
+        }
+    }
+    description = inline_field_regex().replace_all(&description, "").to_string();
+
+    if let Some(pos) = description.find("🔁") {
+        let rest = &description[pos + "🔁".len()..];
+        let end = SIGNIFIERS
+            .iter()
+            .chain(["⏬", "^", "#"].iter())
+            .filter_map(|s| rest.find(s))
+            .min()
+            .unwrap_or(rest.len());
+        let rule = rest[..end].trim();
+        if !rule.is_empty() {
+            task.recurrence = Some(rule.to_string());
+        }
+        description = format!("{}{}", &description[..pos], &rest[end..]);
+    }
+
+    for priority in Priority::ALL.iter().filter(|p| **p != Priority::None) {
+        if description.contains(priority.emoji()) {
+            task.priority = *priority;
+            description = description.replace(priority.emoji(), "");
+            break;
+        }
+    }
+
+    description = block_id_regex().replace(&description, "").to_string();
+
+    task.tags = tags::inline_tags(&description).map(|c| c[2].to_string()).collect();
+
+    task.description = description.split_whitespace().collect::<Vec<_>>().join(" ");
+    task
+}
+
+/// Tasks of a note, skipping code blocks.
+pub fn parse_tasks(content: &str) -> Vec<VaultTask> {
+    let mut tasks = vec![];
+    let mut in_code = false;
+    let mut heading = None;
+    for (line, text) in content.split('\n').enumerate() {
+        let trimmed = text.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_code = !in_code;
+            continue;
+        }
+        if in_code {
+            continue;
+        }
+        if trimmed.starts_with('#') {
+            let title = trimmed.trim_start_matches('#');
+            if title.starts_with(' ') {
+                heading = Some(title.trim().to_string());
+                continue;
+            }
+        }
+        let Some(caps) = task_regex().captures(text.trim_end_matches('\r')) else {
+            continue;
+        };
+        let mut task = parse_body(&caps[4]);
+        task.line = line;
+        task.status = caps[2].chars().next().unwrap_or(' ');
+        task.heading = heading.clone();
+        tasks.push(task);
+    }
+    tasks
+}
+
+/// How often a task repeats, from text like `every 2 weeks`,
+/// `every weekday`, `every monday, friday`, `every 2 weeks on monday`,
+/// `every month on the 1st` or `every month when done`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Recurrence {
+    pub interval: u32,
+    pub unit: RecurrenceUnit,
+    /// Only these days of the week, for `every monday` and such.
+    pub weekdays: Vec<Weekday>,
+    /// Day of the month for `every month on the 15th`, `0` for `on the last`.
+    pub month_day: Option<u32>,
+    /// The next date counts from the day it was completed.
+    pub when_done: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RecurrenceUnit {
+    Day,
+    Week,
+    Month,
+    Year,
+}
+
+fn weekday(word: &str) -> Option<Weekday> {
+    let word = word.trim().trim_end_matches('s');
+    match word {
+        "monday" | "mon" => Some(Weekday::Mon),
+        "tuesday" | "tue" => Some(Weekday::Tue),
+        "wednesday" | "wed" => Some(Weekday::Wed),
+        "thursday" | "thu" => Some(Weekday::Thu),
+        "friday" | "fri" => Some(Weekday::Fri),
+        "saturday" | "sat" => Some(Weekday::Sat),
+        "sunday" | "sun" => Some(Weekday::Sun),
+        _ => None,
+    }
+}
+
+fn weekdays(text: &str) -> Option<Vec<Weekday>> {
+    let days: Option<Vec<Weekday>> = text
+        .split([',', ' '])
+        .filter(|w| !w.is_empty() && *w != "and")
+        .map(weekday)
+        .collect();
+    days.filter(|d| !d.is_empty())
+}
+
+// "the 1st", "the 22nd", "the last"
+fn month_day(text: &str) -> Option<u32> {
+    let text = text.trim().strip_prefix("the").unwrap_or(text).trim();
+    if text == "last" {
+        return Some(0);
+    }
+    let digits = text.trim_end_matches(|c: char| c.is_alphabetic());
+    digits.parse().ok().filter(|d| (1..=31).contains(d))
+}
+
+fn last_day_of_month(date: NaiveDate) -> NaiveDate {
+    let first = date.with_day(1).unwrap_or(date);
+    first.checked_add_months(Months::new(1)).map(|d| d - Duration::days(1)).unwrap_or(date)
+}
+
+// El día pedido dentro del mes de `date`, o el último si el mes es más corto
+fn day_in_month(date: NaiveDate, day: u32) -> NaiveDate {
+    let last = last_day_of_month(date);
+    if day == 0 || day >= last.day() {
+        last
+    } else {
+        date.with_day(day).unwrap_or(last)
+    }
+}
+
+impl Recurrence {
+    /// `None` for rules it doesn't understand.
+    pub fn parse(text: &str) -> Option<Recurrence> {
+        let mut text = text.trim().to_lowercase();
+        let when_done = text.ends_with("when done");
+        if when_done {
+            text = text.trim_end_matches("when done").trim().to_string();
+        }
+        let rule = text.strip_prefix("every")?.trim();
+        // "2 weeks on monday", "month on the 1st"
+        let (rule, on) = match rule.split_once(" on ") {
+            Some((rule, on)) => (rule.trim(), Some(on.trim())),
+            None => (rule, None),
+        };
+
+        let mut recurrence = Recurrence {
+            interval: 1,
+            unit: RecurrenceUnit::Day,
+            weekdays: vec![],
+            month_day: None,
+            when_done,
+        };
+        match rule {
+            "day" => {}
+            "week" => recurrence.unit = RecurrenceUnit::Week,
+            "month" => recurrence.unit = RecurrenceUnit::Month,
+            "year" => recurrence.unit = RecurrenceUnit::Year,
+            "weekday" if on.is_none() => {
+                recurrence.unit = RecurrenceUnit::Week;
+                recurrence.weekdays = vec![Weekday::Mon, Weekday::Tue, Weekday::Wed, Weekday::Thu, Weekday::Fri];
+            }
+            _ => {
+                if let Some(days) = weekdays(rule).filter(|_| on.is_none()) {
+                    recurrence.unit = RecurrenceUnit::Week;
+                    recurrence.weekdays = days;
+                } else {
+                    let (number, unit) = rule.split_once(' ')?;
+                    recurrence.interval = number.parse().ok().filter(|n| *n > 0)?;
+                    recurrence.unit = match unit.trim().trim_end_matches('s') {
+                        "day" => RecurrenceUnit::Day,
+                        "week" => RecurrenceUnit::Week,
+                        "month" => RecurrenceUnit::Month,
+                        "year" => RecurrenceUnit::Year,
+                        _ => return None,
+                    };
+                }
+            }
+        }
+        match (on, recurrence.unit) {
+            (None, _) => {}
+            (Some(on), RecurrenceUnit::Week) => recurrence.weekdays = weekdays(on)?,
+            (Some(on), RecurrenceUnit::Month) => recurrence.month_day = Some(month_day(on)?),
+            _ => return None,
+        }
+        Some(recurrence)
+    }
+
+    /// First date of the series after `date`.
+    pub fn next(&self, date: NaiveDate) -> NaiveDate {
+        let n = self.interval;
+        if !self.weekdays.is_empty() {
+            let mut next = date + Duration::days(1);
+            while !self.weekdays.contains(&next.weekday()) {
+                next += Duration::days(1);
+            }
+            // Al pasar a otra semana se saltan las que no tocan
+            if next.week(Weekday::Mon).first_day() != date.week(Weekday::Mon).first_day() {
+                next += Duration::weeks(n as i64 - 1);
+            }
+            return next;
+        }
+        if let Some(day) = self.month_day {
+            let this_month = day_in_month(date, day);
+            if this_month > date {
+                return this_month;
+            }
+            let month = date.with_day(1).unwrap_or(date).checked_add_months(Months::new(n)).unwrap_or(date);
+            return day_in_month(month, day);
+        }
+        match self.unit {
+            RecurrenceUnit::Day => date + Duration::days(n as i64),
+            RecurrenceUnit::Week => date + Duration::weeks(n as i64),
+            RecurrenceUnit::Month => date.checked_add_months(Months::new(n)).unwrap_or(date),
+            RecurrenceUnit::Year => date.checked_add_months(Months::new(n * 12)).unwrap_or(date),
+        }
+    }
+}
+
+/// Text of the next occurrence of a recurring task, with its dates moved
+/// forward. `None` if the task doesn't repeat or has no date to move.
+fn next_occurrence(line: &str, today: NaiveDate) -> Option<String> {
+    let caps = task_regex().captures(line)?;
+    let task = parse_body(&caps[4]);
+    let rule = task.recurrence.as_deref()?;
+    let Some(recurrence) = Recurrence::parse(rule) else {
+        println!("Regla de repetición no soportada: {}", rule);
+        return None;
+    };
+    let reference = task.date()?;
+    let base = if recurrence.when_done { today } else { reference };
+    let shift = recurrence.next(base) - reference;
+
+    let body = done_regex().replace_all(&caps[4], "");
+    let body = date_regex().replace_all(&body, |c: &regex::Captures| match &c[1] {
+        "➕" => format!("➕ {}", today.format("%Y-%m-%d")),
+        signifier => match parse_date(&c[2]) {
+            Some(date) => format!("{} {}", signifier, (date + shift).format("%Y-%m-%d")),
+            None => c[0].to_string(),
+        },
+    });
+    let body = block_id_regex().replace(body.trim_end(), "");
+    Some(format!("{} {}{}", &caps[1], &caps[3], body.trim_end()))
+}
+
+/// Marks the task on `line` of `content` as done or not done, adding the
+/// `✅` date with `completion_date`. Completing a recurring task adds its
+/// next occurrence above it. Returns the new content, or `None` if the line
+/// isn't a task.
+pub fn toggle_in_content(content: &str, line: usize, today: NaiveDate, completion_date: bool) -> Option<String> {
+    let mut lines: Vec<String> = content.split('\n').map(|l| l.to_string()).collect();
+    let text = lines.get(line)?.clone();
+    let cr = if text.ends_with('\r') { "\r" } else { "" };
+    let caps = task_regex().captures(text.trim_end_matches('\r'))?;
+    let body = caps[4].trim_end();
+
+    if matches!(&caps[2], "x" | "X" | "-") {
+        let body = done_regex().replace_all(body, "");
+        lines[line] = format!("{} {}{}{}", &caps[1], &caps[3], body, cr);
+        return Some(lines.join("\n"));
+    }
+
+    let body = if completion_date {
+        let done = format!("✅ {}", today.format("%Y-%m-%d"));
+        match block_id_regex().find(body) {
+            Some(m) => format!("{} {}{}", body[..m.start()].trim_end(), done, m.as_str()),
+            None if body.is_empty() => done,
+            None => format!("{} {}", body, done),
+        }
+    } else {
+        body.to_string()
+    };
+    lines[line] = format!("{}x{}{}{}", &caps[1], &caps[3], body, cr);
+    if let Some(next) = next_occurrence(text.trim_end_matches('\r'), today) {
+        lines.insert(line, format!("{}{}", next, cr));
+    }
+    Some(lines.join("\n"))
+}
+
+/// [`toggle_in_content`] with today's date and the settings of `vault`.
+pub fn toggle_line(ctx: &egui::Context, vault: &str, content: &str, line: usize) -> Option<String> {
+    let completion_date = settings(ctx, vault).completion_date;
+    toggle_in_content(content, line, Local::now().date_naive(), completion_date)
+}
+
+/// [`toggle_in_content`] for a task read earlier, only if `line` still holds
+/// a task with the same `text`.
+fn toggle_task_in_content(content: &str, line: usize, text: &str, today: NaiveDate, completion_date: bool) -> Option<String> {
+    let current = parse_tasks(content).into_iter().find(|t| t.line == line)?;
+    if current.text != text {
+        return None;
+    }
+    toggle_in_content(content, line, today, completion_date)
+}
+
+/// Toggles the task with `text` on `line` of the note at `path` and saves
+/// the note. The watcher picks up the write, so the open tabs of the note
+/// reload it or show the conflict.
+pub fn toggle(ctx: &egui::Context, vault: &str, path: &str, line: usize, text: &str) {
+    let Ok(content) = fs::read_to_string(path) else {
+        return;
+    };
+    let completion_date = settings(ctx, vault).completion_date;
+    let Some(updated) = toggle_task_in_content(&content, line, text, Local::now().date_naive(), completion_date) else {
+        // La nota cambió desde que se leyó la tarea
+        println!("La tarea de {} ha cambiado, no se marca: {}", path, text);
+        return;
+    };
+    if let Err(e) = fs::write(path, updated) {
+        println!("Error guardando {}: {}", path, e);
+    }
+}
+
+const SETTINGS_FILE: &str = ".obsidian/marmol-tasks.json";
+
+#[derive(Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TaskSettings {
+    /// Add `✅ YYYY-MM-DD` to the tasks when they are completed.
+    pub completion_date: bool,
+}
+
+impl TaskSettings {
+    pub fn load(vault: &str) -> Self {
+        fs::read_to_string(Path::new(vault).join(SETTINGS_FILE))
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, vault: &str) {
+        let path = Path::new(vault).join(SETTINGS_FILE);
+        if let Some(parent) = path.parent() {
+            let _ = fs::create_dir_all(parent);
+        }
+        match serde_json::to_string_pretty(self) {
+            Ok(data) => {
+                if let Err(e) = fs::write(&path, data) {
+                    println!("Error guardando la configuración de tareas: {}", e);
+                }
+            }
+            Err(e) => println!("Error serializando la configuración de tareas: {}", e),
+        }
+    }
+}
+
+/// Settings of `vault`, cached in the context since checkboxes read them on
+/// every click.
+pub fn settings(ctx: &egui::Context, vault: &str) -> TaskSettings {
+    let id = egui::Id::new("task_settings");
+    let cached: Option<(String, TaskSettings)> = ctx.data(|d| d.get_temp(id));
+    if let Some((cached_vault, settings)) = cached {
+        if cached_vault == vault {
+            return settings;
+        }
+    }
+    let settings = TaskSettings::load(vault);
+    ctx.data_mut(|d| d.insert_temp(id, (vault.to_string(), settings.clone())));
+    settings
+}
+
+/// Settings section shown in the configuration screen.
+pub fn settings_ui(ui: &mut egui::Ui, vault: &str) {
+    let mut settings = settings(ui.ctx(), vault);
+    let old = settings.clone();
+    egui::CollapsingHeader::new(RichText::new("Tasks").strong()).show(ui, |ui| {
+        ui.checkbox(&mut settings.completion_date, "Add the completion date (✅) to completed tasks");
+    });
+    if settings != old && !vault.is_empty() {
+        settings.save(vault);
+        ui.ctx().data_mut(|d| d.insert_temp(egui::Id::new("task_settings"), (vault.to_string(), settings)));
+    }
+}
+
+#[derive(Default, PartialEq, Clone, Copy)]
+enum StatusFilter {
+    #[default]
+    Todo,
+    Done,
+    All,
+}
+
+#[derive(Default, PartialEq, Clone, Copy)]
+enum DateFilter {
+    #[default]
+    Any,
+    Overdue,
+    Today,
+    Week,
+    NoDate,
+}
+
+#[derive(Default, PartialEq, Clone, Copy)]
+enum GroupBy {
+    None,
+    #[default]
+    Note,
+    Folder,
+    Date,
+    Priority,
+    Tag,
+}
+
+impl GroupBy {
+    const ALL: [GroupBy; 6] = [GroupBy::None, GroupBy::Note, GroupBy::Folder, GroupBy::Date, GroupBy::Priority, GroupBy::Tag];
+
+    fn name(&self) -> &'static str {
+        match self {
+            GroupBy::None => "None",
+            GroupBy::Note => "Note",
+            GroupBy::Folder => "Folder",
+            GroupBy::Date => "Date",
+            GroupBy::Priority => "Priority",
+            GroupBy::Tag => "Tag",
+        }
+    }
+}
+
+/// Tasks of each note of `vault` that has any, with the modification time
+/// they were read at. Only the notes modified since the last call are read
+/// again.
+pub fn vault_task_notes(vault: &str) -> Vec<(u64, Vec<VaultTask>)> {
+    static CACHE: OnceLock<Mutex<HashMap<String, (u64, Vec<VaultTask>)>>> = OnceLock::new();
+    let files: Vec<(String, String, String, u64)> = vault_index::with_index(vault, |index| {
+        index
+            .notes()
+            .map(|(file, _)| (file.path.clone(), file.rel_path.clone(), file.name.clone(), file.modified))
+            .collect()
+    });
+    let mut cache = CACHE.get_or_init(|| Mutex::new(HashMap::new())).lock().unwrap_or_else(|e| e.into_inner());
+    let mut notes = HashMap::new();
+    let mut out = vec![];
+    for (path, rel_path, name, modified) in files {
+        let tasks = match cache.remove(&path) {
+            Some((read, tasks)) if read == modified => tasks,
+            _ => {
+                let content = fs::read_to_string(&path).unwrap_or_default();
+                let mut tasks = parse_tasks(&content);
+                for task in &mut tasks {
+                    task.path = path.clone();
+                    task.rel_path = rel_path.clone();
+                    task.note = name.clone();
+                }
+                tasks
+            }
+        };
+        if !tasks.is_empty() {
+            out.push((modified, tasks.clone()));
+        }
+        notes.insert(path, (modified, tasks));
+    }
+    // Las notas que ya no están en el índice se olvidan
+    *cache = notes;
+    out
+}
+
+/// Tab listing the tasks of the whole vault.
+#[derive(Default)]
+pub struct VaultTasksGui {
+    vault: String,
+    /// Tasks of each note and the modification time they were read at.
+    notes: HashMap<String, (u64, Vec<VaultTask>)>,
+    loaded_at: Option<f64>,
+    search: String,
+    folder: String,
+    status: StatusFilter,
+    date: DateFilter,
+    group_by: GroupBy,
+}
+
+impl VaultTasksGui {
+    /// Forces a reload of every note on the next frame.
+    pub fn invalidate(&mut self) {
+        self.loaded_at = None;
+        self.notes.clear();
+    }
+
+    /// Rereads the notes modified since the last time.
+    fn refresh(&mut self) {
+        let mut notes = HashMap::new();
+        for (modified, tasks) in vault_task_notes(&self.vault) {
+            if let Some(first) = tasks.first() {
+                notes.insert(first.path.clone(), (modified, tasks));
+            }
+        }
+        self.notes = notes;
+    }
+
+    fn passes(&self, task: &VaultTask, today: NaiveDate) -> bool {
+        let status = match self.status {
+            StatusFilter::Todo => !task.completed(),
+            StatusFilter::Done => task.completed(),
+            StatusFilter::All => true,
+        };
+        let date = match self.date {
+            DateFilter::Any => true,
+            DateFilter::Overdue => task.date().is_some_and(|d| d < today),
+            DateFilter::Today => task.date().is_some_and(|d| d <= today),
+            DateFilter::Week => task.date().is_some_and(|d| d < today + Duration::days(7)),
+            DateFilter::NoDate => task.date().is_none(),
+        };
+        let search = self.search.trim().to_lowercase();
+        let folder = self.folder.trim().trim_matches('/');
+        status
+            && date
+            && (search.is_empty() || task.text.to_lowercase().contains(&search))
+            && (folder.is_empty() || task.rel_path.starts_with(&format!("{}/", folder)))
+    }
+
+    /// Groups of the tasks, in the order they are shown.
+    fn group_keys(&self, task: &VaultTask, today: NaiveDate) -> Vec<(String, String)> {
+        // (clave para ordenar, título)
+        match self.group_by {
+            GroupBy::None => vec![(String::new(), "All tasks".to_string())],
+            GroupBy::Note => vec![(task.rel_path.clone(), task.note.clone())],
+            GroupBy::Folder => {
+                let folder = task.rel_path.rsplit_once('/').map(|(f, _)| f).unwrap_or("/").to_string();
+                vec![(folder.clone(), folder)]
+            }
+            GroupBy::Date => {
+                let (order, title) = match task.date() {
+                    Some(d) if d < today => (0, "Overdue"),
+                    Some(d) if d == today => (1, "Today"),
+                    Some(d) if d == today + Duration::days(1) => (2, "Tomorrow"),
+                    Some(d) if d < today + Duration::days(7) => (3, "Next 7 days"),
+                    Some(_) => (4, "Later"),
+                    None => (5, "No date"),
+                };
+                vec![(order.to_string(), title.to_string())]
+            }
+            GroupBy::Priority => {
+                let order = Priority::ALL.iter().position(|p| *p == task.priority).unwrap_or(0);
+                vec![(order.to_string(), format!("{} {}", task.priority.emoji(), task.priority.name()).trim().to_string())]
+            }
+            GroupBy::Tag if task.tags.is_empty() => vec![("~".to_string(), "No tag".to_string())],
+            GroupBy::Tag => task.tags.iter().map(|t| (t.to_lowercase(), format!("#{}", t))).collect(),
+        }
+    }
+
+    /// Returns the note the user wants to open.
+    pub fn show(&mut self, ui: &mut egui::Ui, vault: &str, seed_id: egui::Id) -> Option<String> {
+        let now = ui.input(|i| i.time);
+        if self.vault != vault {
+            self.vault = vault.to_string();
+            self.invalidate();
+        }
+        if self.loaded_at.is_none_or(|t| now - t >= REFRESH_SECS) {
+            self.refresh();
+            self.loaded_at = Some(now);
+        }
+        ui.ctx().request_repaint_after(std::time::Duration::from_secs_f64(REFRESH_SECS));
+
+        ui.horizontal_wrapped(|ui| {
+            ui.add(egui::TextEdit::singleline(&mut self.search).hint_text("Search tasks").desired_width(160.0));
+            ui.add(egui::TextEdit::singleline(&mut self.folder).hint_text("Folder").desired_width(100.0));
+            egui::ComboBox::from_id_salt(seed_id.with("status"))
+                .selected_text(match self.status {
+                    StatusFilter::Todo => "To do",
+                    StatusFilter::Done => "Done",
+                    StatusFilter::All => "All",
+                })
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.status, StatusFilter::Todo, "To do");
+                    ui.selectable_value(&mut self.status, StatusFilter::Done, "Done");
+                    ui.selectable_value(&mut self.status, StatusFilter::All, "All");
+                });
+            let dates = [
+                (DateFilter::Any, "Any date"),
+                (DateFilter::Overdue, "Overdue"),
+                (DateFilter::Today, "Due by today"),
+                (DateFilter::Week, "Next 7 days"),
+                (DateFilter::NoDate, "No date"),
+            ];
+            egui::ComboBox::from_id_salt(seed_id.with("date"))
+                .selected_text(dates.iter().find(|(d, _)| *d == self.date).map(|(_, n)| *n).unwrap_or(""))
+                .show_ui(ui, |ui| {
+                    for (filter, name) in dates {
+                        ui.selectable_value(&mut self.date, filter, name);
+                    }
+                });
+            ui.label("Group by");
+            egui::ComboBox::from_id_salt(seed_id.with("group"))
+                .selected_text(self.group_by.name())
+                .show_ui(ui, |ui| {
+                    for group in GroupBy::ALL {
+                        ui.selectable_value(&mut self.group_by, group, group.name());
+                    }
+                });
+            if ui.button("⟳").on_hover_text("Reload tasks").clicked() {
+                self.invalidate();
+            }
+        });
+        ui.separator();
+
+        let today = Local::now().date_naive();
+        let mut groups: BTreeMap<(String, String), Vec<&VaultTask>> = BTreeMap::new();
+        for task in self.notes.values().flat_map(|(_, tasks)| tasks) {
+            if self.passes(task, today) {
+                for key in self.group_keys(task, today) {
+                    groups.entry(key).or_default().push(task);
+                }
+            }
+        }
+        if groups.is_empty() {
+            ui.weak("No tasks");
+            return None;
+        }
+
+        let mut open = None;
+        let mut toggled = None;
+        ScrollArea::vertical().id_salt(seed_id.with("tasks_scroll")).show(ui, |ui| {
+            for ((_, title), mut tasks) in groups {
+                tasks.sort_by(|a, b| {
+                    (a.date().is_none(), a.date(), a.priority, &a.rel_path, a.line)
+                        .cmp(&(b.date().is_none(), b.date(), b.priority, &b.rel_path, b.line))
+                });
+                let id = seed_id.with(("task_group", &title));
+                CollapsingState::load_with_default_open(ui.ctx(), id, true)
+                    .show_header(ui, |ui| {
+                        ui.strong(&title);
+                        ui.weak(tasks.len().to_string());
+                    })
+                    .body(|ui| {
+                        for task in tasks {
+                            if let Some(action) = task_row(ui, task, today, self.group_by != GroupBy::Note) {
+                                match action {
+                                    RowAction::Toggle => toggled = Some(task.clone()),
+                                    RowAction::Open => open = Some(task.path.clone()),
+                                }
+                            }
+                        }
+                    });
+            }
+        });
+
+        if let Some(task) = toggled {
+            toggle(ui.ctx(), vault, &task.path, task.line, &task.text);
+            // Se vuelve a leer la nota sin esperar al siguiente refresco
+            self.notes.remove(&task.path);
+            self.loaded_at = None;
+        }
+        open
+    }
+}
+
+enum RowAction {
+    Toggle,
+    Open,
+}
+
+fn task_row(ui: &mut egui::Ui, task: &VaultTask, today: NaiveDate, show_note: bool) -> Option<RowAction> {
+    let mut action = None;
+    ui.horizontal_wrapped(|ui| {
+        let mut completed = task.completed();
+        if ui.checkbox(&mut completed, "").changed() {
+            action = Some(RowAction::Toggle);
+        }
+        if task.priority != Priority::None {
+            ui.label(task.priority.emoji()).on_hover_text(format!("{} priority", task.priority.name()));
+        }
+        let text = RichText::new(&task.description);
+        let text = if task.completed() { text.strikethrough().weak() } else { text };
+        let response = ui.add(egui::Label::new(text).sense(egui::Sense::click()));
+        if response.double_clicked() {
+            action = Some(RowAction::Open);
+        }
+        response.context_menu(|ui| {
+            if ui.button("Open note").clicked() {
+                action = Some(RowAction::Open);
+                ui.close();
+            }
+        });
+
+        if let Some(date) = task.date() {
+            let color = if task.completed() {
+                ui.visuals().weak_text_color()
+            } else if date < today {
+                ui.visuals().error_fg_color
+            } else if date == today {
+                ui.visuals().warn_fg_color
+            } else {
+                Color32::GRAY
+            };
+            let icon = if task.due.is_some() { "📅" } else if task.scheduled.is_some() { "⏳" } else { "🛫" };
+            ui.label(RichText::new(format!("{} {}", icon, date.format("%Y-%m-%d"))).small().color(color));
+        }
+        if let Some(recurrence) = &task.recurrence {
+            if Recurrence::parse(recurrence).is_some() {
+                ui.label("🔁").on_hover_text(recurrence);
+            } else {
+                ui.label(RichText::new("🔁⚠").color(ui.visuals().warn_fg_color)).on_hover_text(format!(
+                    "Unsupported repeat rule \"{}\": completing the task won't add the next one",
+                    recurrence
+                ));
+            }
+        }
+        if show_note && ui.small_button(&task.note).on_hover_text(&task.rel_path).clicked() {
+            action = Some(RowAction::Open);
+        }
+    });
+    action
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(text: &str) -> NaiveDate {
+        parse_date(text).unwrap()
+    }
+
+    #[test]
+    fn parse_body_fields() {
+        let task = parse_body("Call Ana #work 🔼 🔁 every week 📅 2024-05-10 ⏳ 2024-05-08 ^abc");
+        assert_eq!(task.description, "Call Ana #work");
+        assert_eq!(task.due, Some(date("2024-05-10")));
+        assert_eq!(task.scheduled, Some(date("2024-05-08")));
+        assert_eq!(task.recurrence.as_deref(), Some("every week"));
+        assert_eq!(task.priority, Priority::Medium);
+        assert_eq!(task.tags, vec!["work".to_string()]);
+
+        let task = parse_body("Pay rent [due:: 2024-06-01] [repeat:: every month] ✅ 2024-05-01");
+        assert_eq!(task.description, "Pay rent");
+        assert_eq!(task.due, Some(date("2024-06-01")));
+        assert_eq!(task.done, Some(date("2024-05-01")));
+        assert_eq!(task.recurrence.as_deref(), Some("every month"));
+        assert_eq!(task.priority, Priority::None);
+    }
+
+    #[test]
+    fn parse_tasks_skips_code_and_tracks_headings() {
+        let content = "# Inbox\n- [ ] one\n```\n- [ ] code\n```\n## Later\n  * [x] two\nnot a task";
+        let tasks = parse_tasks(content);
+        assert_eq!(tasks.len(), 2);
+        assert_eq!((tasks[0].line, tasks[0].status, tasks[0].heading.as_deref()), (1, ' ', Some("Inbox")));
+        assert_eq!((tasks[1].line, tasks[1].completed(), tasks[1].heading.as_deref()), (6, true, Some("Later")));
+    }
+
+    #[test]
+    fn recurrence_rules() {
+        let next = |rule: &str, from: &str| Recurrence::parse(rule).map(|r| r.next(date(from)));
+        assert_eq!(next("every day", "2024-01-31"), Some(date("2024-02-01")));
+        assert_eq!(next("every 3 days", "2024-01-31"), Some(date("2024-02-03")));
+        assert_eq!(next("every 2 weeks", "2024-01-01"), Some(date("2024-01-15")));
+        assert_eq!(next("every month", "2024-01-31"), Some(date("2024-02-29")));
+        assert_eq!(next("every year", "2024-02-29"), Some(date("2025-02-28")));
+        // 2024-01-05 es viernes
+        assert_eq!(next("every weekday", "2024-01-05"), Some(date("2024-01-08")));
+        assert_eq!(next("every Monday, Thursday", "2024-01-05"), Some(date("2024-01-08")));
+        assert_eq!(next("every week on Monday", "2024-01-05"), Some(date("2024-01-08")));
+        assert_eq!(next("every 2 weeks on monday", "2024-01-01"), Some(date("2024-01-15")));
+        assert_eq!(next("every 2 weeks on monday, friday", "2024-01-01"), Some(date("2024-01-05")));
+        assert_eq!(next("every 2 weeks on monday, friday", "2024-01-05"), Some(date("2024-01-15")));
+        assert_eq!(next("every month on the 1st", "2024-01-01"), Some(date("2024-02-01")));
+        assert_eq!(next("every month on the 15th", "2024-01-10"), Some(date("2024-01-15")));
+        assert_eq!(next("every 3 months on the 31st", "2024-01-31"), Some(date("2024-04-30")));
+        assert_eq!(next("every month on the last", "2024-01-31"), Some(date("2024-02-29")));
+        assert!(Recurrence::parse("every month when done").unwrap().when_done);
+
+        for unsupported in ["weekly", "every fortnight", "every 0 days", "every month on the 2nd Wednesday", "every year on May"] {
+            assert_eq!(Recurrence::parse(unsupported), None, "{}", unsupported);
+        }
+    }
+
+    #[test]
+    fn toggle_adds_and_removes_the_done_date() {
+        let today = date("2024-03-10");
+        let content = "# List\n- [ ] Buy milk ^id1\r\n- [x] Old ✅ 2024-03-01";
+        assert_eq!(
+            toggle_in_content(content, 1, today, true).unwrap(),
+            "# List\n- [x] Buy milk ✅ 2024-03-10 ^id1\r\n- [x] Old ✅ 2024-03-01"
+        );
+        assert_eq!(
+            toggle_in_content(content, 1, today, false).unwrap(),
+            "# List\n- [x] Buy milk ^id1\r\n- [x] Old ✅ 2024-03-01"
+        );
+        assert_eq!(
+            toggle_in_content(content, 2, today, true).unwrap(),
+            "# List\n- [ ] Buy milk ^id1\r\n- [ ] Old"
+        );
+        assert_eq!(toggle_in_content(content, 0, today, true), None);
+        assert_eq!(toggle_in_content(content, 9, today, true), None);
+    }
+
+    #[test]
+    fn toggle_recurring_task_adds_the_next_one() {
+        let today = date("2024-03-10");
+        let content = "- [ ] Water plants 🔁 every week 📅 2024-03-08 ^w";
+        assert_eq!(
+            toggle_in_content(content, 0, today, true).unwrap(),
+            "- [ ] Water plants 🔁 every week 📅 2024-03-15\n- [x] Water plants 🔁 every week 📅 2024-03-08 ✅ 2024-03-10 ^w"
+        );
+        let content = "- [ ] Review 🔁 every 2 weeks when done ⏳ 2024-03-01";
+        assert_eq!(
+            toggle_in_content(content, 0, today, false).unwrap(),
+            "- [ ] Review 🔁 every 2 weeks when done ⏳ 2024-03-24\n- [x] Review 🔁 every 2 weeks when done ⏳ 2024-03-01"
+        );
+        // Sin regla conocida solo se marca la tarea
+        let content = "- [ ] Odd 🔁 every blue moon 📅 2024-03-08";
+        assert_eq!(
+            toggle_in_content(content, 0, today, false).unwrap(),
+            "- [x] Odd 🔁 every blue moon 📅 2024-03-08"
+        );
+    }
+
+    #[test]
+    fn stale_tasks_are_not_toggled() {
+        let today = date("2024-03-10");
+        let content = "# Todo\n- [ ] Call Ana\n- [ ] Buy milk";
+        let task = parse_tasks(content).remove(0);
+        assert_eq!(
+            toggle_task_in_content(content, task.line, &task.text, today, false).unwrap(),
+            "# Todo\n- [x] Call Ana\n- [ ] Buy milk"
+        );
+        // Con una línea nueva encima la tarea ya no está en esa línea
+        let edited = "# Todo\n- [ ] Pay rent\n- [ ] Call Ana\n- [ ] Buy milk";
+        assert_eq!(toggle_task_in_content(edited, task.line, &task.text, today, false), None);
+        assert_eq!(toggle_task_in_content("# Todo\nCall Ana", task.line, &task.text, today, false), None);
+    }
+}
@@ -0,0 +1,128 @@
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::path::Path;
+use std::sync::mpsc::{channel, Receiver};
+use std::time::{Duration, Instant};
+
+// Espera a que los eventos se calmen antes de recargar (git checkout, sync, etc.)
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// Watches the vault (inotify on Linux/Android) for changes made outside the app.
+#[derive(Default)]
+pub struct VaultWatcher {
+    vault: String,
+    watcher: Option<RecommendedWatcher>,
+    rx: Option<Receiver<notify::Result<Event>>>,
+    pending: HashSet<String>,
+    last_event: Option<Instant>,
+    /// Hash of what the app itself last wrote to each file.
+    own_writes: HashMap<String, u64>,
+}
+
+pub fn content_hash(bytes: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    bytes.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn file_hash(path: &str) -> Option<u64> {
+    std::fs::read(path).ok().map(|bytes| content_hash(&bytes))
+}
+
+impl VaultWatcher {
+    pub fn watch(&mut self, vault: &str, ctx: &egui::Context) {
+        if self.vault == vault {
+            return;
+        }
+        self.vault = vault.to_string();
+        self.watcher = None;
+        self.rx = None;
+        self.pending.clear();
+        self.own_writes.clear();
+        if vault.is_empty() {
+            return;
+        }
+
+        let (tx, rx) = channel();
+        let ctx = ctx.clone();
+        let watcher = notify::recommended_watcher(move |res| {
+            let _ = tx.send(res);
+            ctx.request_repaint_after(DEBOUNCE);
+        });
+        match watcher {
+            Ok(mut watcher) => match watcher.watch(Path::new(vault), RecursiveMode::Recursive) {
+                Ok(_) => {
+                    self.watcher = Some(watcher);
+                    self.rx = Some(rx);
+                }
+                Err(e) => println!("Error vigilando {}: {}", vault, e),
+            },
+            Err(e) => println!("Error iniciando el vigilante de archivos: {}", e),
+        }
+    }
+
+    /// Marks the current content of `path` as written by the app, so the
+    /// events it causes aren't reported as external changes.
+    pub fn ignore_write(&mut self, path: &str) {
+        if let Some(hash) = file_hash(path) {
+            self.own_writes.insert(path.to_string(), hash);
+        }
+    }
+
+    /// Paths changed on disk since the last call, once no new event arrived
+    /// for `DEBOUNCE`. Files inside hidden folders (`.obsidian`, `.git`) are ignored.
+    pub fn poll(&mut self) -> Vec<String> {
+        let Some(rx) = &self.rx else {
+            return vec![];
+        };
+        for res in rx.try_iter() {
+            let event = match res {
+                Ok(event) => event,
+                Err(e) => {
+                    println!("Error del vigilante de archivos: {}", e);
+                    continue;
+                }
+            };
+            if matches!(event.kind, EventKind::Access(_)) {
+                continue;
+            }
+            for path in event.paths {
+                let hidden = path
+                    .strip_prefix(&self.vault)
+                    .map(|rel| {
+                        rel.components().any(|c| {
+                            c.as_os_str().to_str().map(|s| s.starts_with('.')).unwrap_or(false)
+                        })
+                    })
+                    .unwrap_or(true);
+                if !hidden {
+                    if let Some(p) = path.to_str() {
+                        self.pending.insert(p.to_string());
+                    }
+                }
+            }
+            self.last_event = Some(Instant::now());
+        }
+
+        match self.last_event {
+            Some(last) if last.elapsed() >= DEBOUNCE && !self.pending.is_empty() => {
+                self.last_event = None;
+                let mut changed: Vec<String> = self.pending.drain().collect();
+                // Lo que el propio programa guardó no es un cambio externo
+                changed.retain(|path| match self.own_writes.get(path) {
+                    Some(hash) if file_hash(path) == Some(*hash) => false,
+                    Some(_) => {
+                        self.own_writes.remove(path);
+                        true
+                    }
+                    None => true,
+                });
+                changed.sort();
+                changed
+            }
+            _ => vec![],
+        }
+    }
+}